@@ -0,0 +1,228 @@
+//! Benchmarks of the layouts of the pair liquidities, run with
+//! `cargo bench -p uniswap-routing-core --features test-utils --bench layouts`.
+//!
+//! The same trades run on routers laying the liquidities of the same graph out densely and
+//! sparsely, whose quotes are the same bit for bit; only the time they take and the memory they
+//! hold differ. The memory of every router, as counted by the allocator of the benchmarks, is
+//! printed before its trades run. The trades are fee-free, solved by sweeps over the liquidities
+//! rather than over the individual pools.
+
+use uniswap_routing_core::{
+    UniV2Pool,
+    router::{FeeModel, LiquidityStorage, Router, SolverConfig, SolverKind},
+    test_utils::synthetic_pools,
+};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The system allocator, counting the bytes allocated and not yet freed.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Seed drawing the graphs.
+const SEED: u64 = 279;
+
+/// Input amount of the trades, relative to the total reserve of the input token.
+const SIZE_RATIO: f64 = 1e-3;
+
+/// Tokens and pools of the sparse graph, an average of eight pools per token.
+const SPARSE_TOKENS: usize = 500;
+const SPARSE_EDGES: usize = 2_000;
+
+/// Tokens and pools of the graph of the sweeps, an average of four pools per token.
+const SWEEP_TOKENS: usize = 1_000;
+const SWEEP_EDGES: usize = 2_000;
+
+/// Sweeps of every quote of the graph of the sweeps.
+const SWEEPS: usize = 100;
+
+/// Tokens and pools of the dense graph, 30% of the pairs of tokens sharing a pool.
+const DENSE_TOKENS: usize = 1_000;
+const DENSE_EDGES: usize = 150_000;
+
+/// Tokens and pools of the large graph, an average of five pools per token.
+const LARGE_TOKENS: usize = 2_000;
+const LARGE_EDGES: usize = 5_000;
+
+/// Round trips on a sparse graph, whose dense layout scans 500 liquidities per update, against
+/// the eight of the sparse one.
+fn sparse_graph(c: &mut Criterion) {
+    let graph = (SPARSE_TOKENS, SPARSE_EDGES);
+    compare_layouts(c, "sparse graph", graph, SolverKind::FixedPoint);
+}
+
+/// Denominators of the updates of every token of a dense graph of 1,000 tokens, those of a
+/// sweep, laid out densely: summed over the whole rows, zeros included, as the sweeps do, then
+/// over the neighbors alone, skipping the zeros. Both sums are checked to be the same bit for bit.
+fn dense_rows(c: &mut Criterion) {
+    let pools = synthetic_pools(DENSE_TOKENS, DENSE_EDGES, SEED);
+    let router = Router::builder(pools)
+        .fee_model(FeeModel::FeeFree)
+        .liquidity_storage(LiquidityStorage::Dense)
+        .build()
+        .expect("the synthetic pools are valid");
+    let bits = |denominators: Vec<f64>| {
+        denominators
+            .into_iter()
+            .map(f64::to_bits)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        bits(router.sweep_denominators(true)),
+        bits(router.sweep_denominators(false)),
+        "the full rows change the denominators"
+    );
+
+    let mut group = c.benchmark_group("dense rows");
+    for (full_rows, sum) in [(true, "full rows"), (false, "neighbors")] {
+        group.bench_function(
+            format!("sweep denominators, {DENSE_TOKENS} tokens, {DENSE_EDGES} pools, {sum}"),
+            |b| b.iter(|| router.sweep_denominators(black_box(full_rows))),
+        );
+    }
+    group.finish();
+}
+
+/// Round trips on a large graph, whose dense layout holds four million liquidities, against the
+/// ten thousand of the sparse one. The graph is solved by conjugate gradients, the sweeps taking
+/// tens of thousands of iterations to converge on it.
+fn large_graph(c: &mut Criterion) {
+    let graph = (LARGE_TOKENS, LARGE_EDGES);
+    compare_layouts(c, "large graph", graph, SolverKind::ConjugateGradient);
+}
+
+/// Quotes of [`SWEEPS`] fixed-point sweeps each on a graph of 1,000 tokens, whose dense layout
+/// scans 1,000 liquidities per update, against the four of the sparse one: the time of a quote
+/// over [`SWEEPS`] is that of a sweep. The quotes of both layouts are checked to be the same.
+fn sweeps(c: &mut Criterion) {
+    let pools = synthetic_pools(SWEEP_TOKENS, SWEEP_EDGES, SEED);
+    let amount = SIZE_RATIO * reserve_of(&pools, "T0");
+    let solver = SolverConfig {
+        tolerance: 0.0,
+        max_iterations: SWEEPS,
+        ..SolverConfig::default()
+    };
+    let [dense, sparse] = [LiquidityStorage::Dense, LiquidityStorage::Sparse].map(|storage| {
+        let mut router = Router::builder(pools.clone())
+            .fee_model(FeeModel::FeeFree)
+            .liquidity_storage(storage)
+            .solver(solver)
+            .build()
+            .expect("the synthetic pools are valid");
+        router.settle();
+        router
+    });
+    assert_eq!(
+        dense.quote("T0", "T1", amount),
+        sparse.quote("T0", "T1", amount),
+        "the layouts change the iterates"
+    );
+
+    let mut group = c.benchmark_group("sweeps");
+    group.sample_size(10);
+    for (router, storage) in [(dense, "Dense"), (sparse, "Sparse")] {
+        group.bench_function(
+            format!("{SWEEPS} sweeps, {SWEEP_TOKENS} tokens, {SWEEP_EDGES} pools, {storage}"),
+            |b| b.iter(|| router.quote("T0", "T1", black_box(amount))),
+        );
+    }
+    group.finish();
+}
+
+/// Prints the memory held by routers of a synthetic graph of `tokens` and `edges` in either
+/// layout, solving by `kind`, then benchmarks round trips on them.
+fn compare_layouts(
+    c: &mut Criterion,
+    name: &str,
+    (tokens, edges): (usize, usize),
+    kind: SolverKind,
+) {
+    let pools = synthetic_pools(tokens, edges, SEED);
+    let amount = SIZE_RATIO * reserve_of(&pools, "T0");
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for storage in [LiquidityStorage::Dense, LiquidityStorage::Sparse] {
+        let (mut router, bytes) = build(&pools, storage, kind);
+        println!(
+            "{name}: {tokens} tokens, {edges} pools, {storage:?}: {:.1} MiB",
+            bytes as f64 / f64::from(1 << 20)
+        );
+        group.bench_function(
+            format!("warm round trip, {tokens} tokens, {edges} pools, {storage:?}"),
+            |b| b.iter(|| round_trip(&mut router, "T0", "T1", black_box(amount))),
+        );
+    }
+    group.finish();
+}
+
+/// Returns a router of `pools` laying the liquidities out as `storage` and solving by `kind`, at
+/// equilibrium, and the bytes it holds.
+fn build(
+    pools: &[UniV2Pool],
+    storage: LiquidityStorage,
+    kind: SolverKind,
+) -> (Router<'static>, usize) {
+    let pools = pools.to_vec();
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let mut router = Router::builder(pools)
+        .fee_model(FeeModel::FeeFree)
+        .liquidity_storage(storage)
+        .solver_kind(kind)
+        .build()
+        .expect("the synthetic pools are valid");
+    router.settle();
+    let bytes = ALLOCATED.load(Ordering::Relaxed) - before;
+    (router, bytes)
+}
+
+/// Returns the total reserve of `token` across `pools`.
+fn reserve_of(pools: &[UniV2Pool], token: &str) -> f64 {
+    pools
+        .iter()
+        .map(|pool| match (pool.token0 == token, pool.token1 == token) {
+            (true, _) => pool.reserve0,
+            (_, true) => pool.reserve1,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+/// Sells `amount` of `input_token` for `output_token`, then the output back.
+fn round_trip(router: &mut Router<'_>, input_token: &str, output_token: &str, amount: f64) -> f64 {
+    let output = router
+        .solve(input_token, output_token, amount)
+        .expect("the trade solves");
+    router
+        .solve(output_token, input_token, output)
+        .expect("the trade back solves")
+}
+
+criterion_group!(benches, sparse_graph, sweeps, dense_rows, large_graph);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+//! Benchmarks of the parallel Jacobi sweeps, run with
+//! `cargo bench -p uniswap-routing-core --features test-utils,parallel --bench parallel`.
+//!
+//! The same quote runs on a graph of 5,000 tokens, its Jacobi sweeps computed sequentially, then
+//! in parallel over pools of 1 to 8 threads. The sweeps taking thousands of iterations to converge
+//! on the graph, the solver stops after [`SWEEPS`] of them, unconverged: every iteration runs the
+//! same sweeps, and the sequential and parallel ones end on the same residual, bit for bit.
+
+use uniswap_routing_core::{
+    UniV2Pool,
+    router::{FeeModel, Router, SolverConfig, SweepMode},
+    test_utils::synthetic_pools,
+};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use rayon::ThreadPoolBuilder;
+use std::hint::black_box;
+
+/// Tokens and pools of the graph, an average of eight pools per token, and the seed drawing it.
+const TOKENS: usize = 5_000;
+const EDGES: usize = 20_000;
+const SEED: u64 = 351;
+
+/// Input amount of the quotes, relative to the total reserve of the input token.
+const SIZE_RATIO: f64 = 1e-3;
+
+/// Sweeps of every quote.
+const SWEEPS: usize = 100;
+
+/// Numbers of threads the parallel sweeps are spread over.
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+/// Quotes with sequential sweeps, then with parallel ones over pools of more and more threads.
+fn parallel_scaling(c: &mut Criterion) {
+    let pools = synthetic_pools(TOKENS, EDGES, SEED);
+    let amount = SIZE_RATIO * reserve_of(&pools, "T0");
+    let sequential = build(&pools, usize::MAX);
+    let parallel = build(&pools, 0);
+    assert_eq!(
+        sequential.quote("T0", "T1", amount),
+        parallel.quote("T0", "T1", amount),
+        "parallel sweeps change the iterates"
+    );
+
+    let mut group = c.benchmark_group("parallel scaling");
+    group.sample_size(10);
+    group.bench_function(
+        format!("{SWEEPS} sweeps, {TOKENS} tokens, {EDGES} pools, sequential"),
+        |b| b.iter(|| sequential.quote("T0", "T1", black_box(amount))),
+    );
+    for threads in THREADS {
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("the thread pool builds");
+        group.bench_function(
+            format!("{SWEEPS} sweeps, {TOKENS} tokens, {EDGES} pools, {threads} threads"),
+            |b| thread_pool.install(|| b.iter(|| parallel.quote("T0", "T1", black_box(amount)))),
+        );
+    }
+    group.finish();
+}
+
+/// Returns a router of `pools` trading fee-free by [`SWEEPS`] Jacobi sweeps, in parallel from
+/// `threshold` tokens, settled as far as the sweeps go.
+fn build(pools: &[UniV2Pool], threshold: usize) -> Router<'static> {
+    let mut router = Router::builder(pools.to_vec())
+        .fee_model(FeeModel::FeeFree)
+        .solver(SolverConfig {
+            max_iterations: SWEEPS,
+            ..SolverConfig::default()
+        })
+        .sweep_mode(SweepMode::Jacobi)
+        .parallel_sweep_threshold(threshold)
+        .build()
+        .expect("the synthetic pools are valid");
+    router.settle();
+    router
+}
+
+/// Returns the total reserve of `token` across `pools`.
+fn reserve_of(pools: &[UniV2Pool], token: &str) -> f64 {
+    pools
+        .iter()
+        .map(|pool| match (pool.token0 == token, pool.token1 == token) {
+            (true, _) => pool.reserve0,
+            (_, true) => pool.reserve1,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+criterion_group!(benches, parallel_scaling);
+criterion_main!(benches);
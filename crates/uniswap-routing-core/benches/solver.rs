@@ -0,0 +1,189 @@
+//! Benchmarks of the solver, run with `cargo bench -p uniswap-routing-core --features test-utils`.
+//!
+//! The trades go back and forth between the same tokens, selling the output of each trade back,
+//! so that the state of a router benchmarked over many iterations stays close to where it started.
+//! The synthetic graphs trade fee-free, the fee-charging trades being solved by Newton steps over
+//! the individual pools whatever the solver kind.
+//!
+//! The scale group doubles as the scalability test of the router, run once with
+//! `cargo bench -p uniswap-routing-core --features test-utils --bench solver -- --test scale`:
+//! its setup fails if the graph of mainnet size does not build and quote within
+//! [`SCALE_BUILD_BUDGET`] and [`SCALE_QUOTE_BUDGET`].
+
+use uniswap_routing_core::{
+    UniV2Pool,
+    router::{FeeModel, Router, SolverKind},
+    test_utils::{example_pools, synthetic_pools},
+};
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+/// Tokens and pools of the synthetic graph, and the seed drawing it.
+const SYNTHETIC_TOKENS: usize = 500;
+const SYNTHETIC_EDGES: usize = 2_000;
+const SYNTHETIC_SEED: u64 = 354;
+
+/// Input amount of the trades on the synthetic graph, relative to the total reserve of the input
+/// token.
+const SYNTHETIC_SIZE_RATIO: f64 = 1e-3;
+
+/// Tokens and pools of the graph of the scale group, about those of the V2 pairs of mainnet.
+const SCALE_TOKENS: usize = 10_000;
+const SCALE_EDGES: usize = 30_000;
+
+/// Longest time the graph of the scale group may take to build into a router, about 30 ms on a
+/// single core.
+const SCALE_BUILD_BUDGET: Duration = Duration::from_secs(2);
+
+/// Longest time the first quote of the scale group may take, absorbing the arbitrage of the
+/// pools, about 250 ms on a single core.
+const SCALE_QUOTE_BUDGET: Duration = Duration::from_secs(20);
+
+/// First solve of a freshly built router, which also absorbs the arbitrage of the pools.
+fn cold_solve(c: &mut Criterion) {
+    c.bench_function("cold solve, example", |b| {
+        b.iter_batched(
+            || Router::new(example_pools()),
+            |mut router| {
+                let output = router.solve("ETH", "USDC", black_box(10.0));
+                (router, output)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Round trips on a router already at equilibrium.
+fn warm_solves(c: &mut Criterion) {
+    let mut router = Router::new(example_pools());
+    router
+        .solve("ETH", "USDC", 10.0)
+        .expect("the example solves");
+    c.bench_function("warm round trip, example", |b| {
+        b.iter(|| round_trip(&mut router, "ETH", "USDC", black_box(10.0)))
+    });
+}
+
+/// Round trips on the synthetic graph, once at equilibrium, by fixed point and by active set.
+fn synthetic_solves(c: &mut Criterion) {
+    let pools = synthetic_pools(SYNTHETIC_TOKENS, SYNTHETIC_EDGES, SYNTHETIC_SEED);
+    let amount = SYNTHETIC_SIZE_RATIO * reserve_of(&pools, "T0");
+    let mut router = Router::builder(pools.clone())
+        .fee_model(FeeModel::FeeFree)
+        .build()
+        .expect("the synthetic pools are valid");
+    router
+        .solve("T0", "T1", amount)
+        .expect("the synthetic graph solves");
+    let mut active_set_router = Router::builder(pools)
+        .fee_model(FeeModel::FeeFree)
+        .solver_kind(SolverKind::ActiveSet)
+        .build()
+        .expect("the synthetic pools are valid");
+    active_set_router
+        .solve("T0", "T1", amount)
+        .expect("the synthetic graph solves");
+
+    let mut group = c.benchmark_group("synthetic");
+    group.sample_size(10);
+    group.bench_function("warm round trip, 500 tokens, 2000 pools", |b| {
+        b.iter(|| round_trip(&mut router, "T0", "T1", black_box(amount)))
+    });
+    group.bench_function("warm round trip, active set, 500 tokens, 2000 pools", |b| {
+        b.iter(|| round_trip(&mut active_set_router, "T0", "T1", black_box(amount)))
+    });
+    group.finish();
+}
+
+/// Construction of, and trades on, a graph of mainnet size, solved by conjugate gradients.
+fn scale(c: &mut Criterion) {
+    let pools = synthetic_pools(SCALE_TOKENS, SCALE_EDGES, SYNTHETIC_SEED);
+    let amount = SYNTHETIC_SIZE_RATIO * reserve_of(&pools, "T0");
+    let build = |pools: Vec<UniV2Pool>| {
+        Router::builder(pools)
+            .fee_model(FeeModel::FeeFree)
+            .solver_kind(SolverKind::ConjugateGradient)
+            .build()
+            .expect("the synthetic pools are valid")
+    };
+
+    let start = Instant::now();
+    let mut router = build(pools.clone());
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed <= SCALE_BUILD_BUDGET,
+        "building the router took {elapsed:?}"
+    );
+    let start = Instant::now();
+    router
+        .quote("T0", "T1", amount)
+        .expect("the graph of mainnet size quotes");
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed <= SCALE_QUOTE_BUDGET,
+        "the first quote took {elapsed:?}"
+    );
+    router
+        .solve("T0", "T1", amount)
+        .expect("the graph of mainnet size solves");
+
+    let mut group = c.benchmark_group("scale");
+    group.sample_size(10);
+    group.bench_function("build, 10000 tokens, 30000 pools", |b| {
+        b.iter_batched(|| pools.clone(), build, BatchSize::LargeInput)
+    });
+    group.bench_function("warm round trip, 10000 tokens, 30000 pools", |b| {
+        b.iter(|| round_trip(&mut router, "T0", "T1", black_box(amount)))
+    });
+    group.finish();
+}
+
+/// Quote curve over four decades of input amounts, without modifying the router.
+fn quote_curve(c: &mut Criterion) {
+    let mut router = Router::new(example_pools());
+    router
+        .solve("ETH", "USDC", 10.0)
+        .expect("the example solves");
+    let amounts = (0..=16)
+        .map(|step| 10f64.powf(step as f64 / 4.0 - 1.0))
+        .collect::<Vec<_>>();
+    c.bench_function("quote curve, example, 17 amounts", |b| {
+        b.iter(|| router.quote_curve("ETH", "USDC", black_box(&amounts)))
+    });
+}
+
+/// Returns the total reserve of `token` across `pools`.
+fn reserve_of(pools: &[UniV2Pool], token: &str) -> f64 {
+    pools
+        .iter()
+        .map(|pool| match (pool.token0 == token, pool.token1 == token) {
+            (true, _) => pool.reserve0,
+            (_, true) => pool.reserve1,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+/// Sells `amount` of `input_token` for `output_token`, then the output back.
+fn round_trip(router: &mut Router<'_>, input_token: &str, output_token: &str, amount: f64) -> f64 {
+    let output = router
+        .solve(input_token, output_token, amount)
+        .expect("the trade solves");
+    router
+        .solve(output_token, input_token, output)
+        .expect("the trade back solves")
+}
+
+criterion_group!(
+    benches,
+    cold_solve,
+    warm_solves,
+    synthetic_solves,
+    scale,
+    quote_curve
+);
+criterion_main!(benches);
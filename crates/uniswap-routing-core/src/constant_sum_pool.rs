@@ -0,0 +1,113 @@
+use crate::{
+    error::PoolError,
+    pool::Pool,
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+/// A pool of pegged tokens trading them 1:1, fee-free, until its output reserve runs out, as
+/// the stable pairs the constant-product curve models with far too much slippage.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConstantSumPool {
+    pub token0: &'static str,
+    pub token1: &'static str,
+    pub reserve0: f64,
+    pub reserve1: f64,
+    /// Identifier of the pool in the routers it enters, see [`UniV2Pool::id`]
+    pub id: Option<PoolId>,
+}
+
+impl ConstantSumPool {
+    /// Depth of the constant-product pool standing for the pool in the router, in multiples of
+    /// its average reserve, see [`ConstantSumPool::to_uni_v2`].
+    pub const DEPTH: f64 = 100.0;
+
+    /// Builds a pool without checking its reserves. Panics if `token0` and `token1` are the same
+    /// token.
+    pub fn new(token0: &'static str, token1: &'static str, reserve0: f64, reserve1: f64) -> Self {
+        assert_ne!(token0, token1, "a pool needs two distinct tokens");
+        Self {
+            token0,
+            token1,
+            reserve0,
+            reserve1,
+            id: None,
+        }
+    }
+
+    /// Sets the identifier of the pool, see [`UniV2Pool::with_id`].
+    pub fn with_id(mut self, id: impl Into<PoolId>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Returns the reserves of the input side `input_token` first, then of the other one.
+    fn reserves_from(&self, input_token: &str) -> Result<(f64, f64), PoolError> {
+        match input_token {
+            token if token == self.token0 => Ok((self.reserve0, self.reserve1)),
+            token if token == self.token1 => Ok((self.reserve1, self.reserve0)),
+            _ => Err(PoolError::UnknownToken(input_token.to_string())),
+        }
+    }
+}
+
+impl Pool for ConstantSumPool {
+    fn tokens(&self) -> (&'static str, &'static str) {
+        (self.token0, self.token1)
+    }
+
+    fn reserves(&self) -> (f64, f64) {
+        (self.reserve0, self.reserve1)
+    }
+
+    /// Returns the invariant of the constant-product equivalent of the pool, see
+    /// [`ConstantSumPool::to_uni_v2`].
+    fn sqrt_invariant(&self) -> f64 {
+        Self::DEPTH * (self.reserve0 + self.reserve1) / 2.0
+    }
+
+    /// Returns `input_amount` itself. Fails with [`PoolError::InsufficientLiquidity`] if it
+    /// exceeds the output reserve.
+    fn get_output_amount(&self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        let (_, reserve_out) = self.reserves_from(input_token)?;
+        if !(input_amount.is_finite() && input_amount >= 0.0) {
+            return Err(PoolError::InvalidAmount(input_amount));
+        }
+        if input_amount > reserve_out {
+            return Err(PoolError::InsufficientLiquidity {
+                output_amount: input_amount,
+                reserve: reserve_out,
+            });
+        }
+        Ok(input_amount)
+    }
+
+    fn apply_swap(&mut self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        let output_amount = self.get_output_amount(input_token, input_amount)?;
+        let (reserve_in, reserve_out) = match input_token == self.token0 {
+            true => (&mut self.reserve0, &mut self.reserve1),
+            false => (&mut self.reserve1, &mut self.reserve0),
+        };
+        *reserve_in += input_amount;
+        *reserve_out -= output_amount;
+        Ok(output_amount)
+    }
+
+    /// Returns a fee-free constant-product pool at the price of 1, holding [`Self::DEPTH`] times
+    /// the average reserve of each token: trading the whole output reserve through it slips by
+    /// about `2 / DEPTH` at most, where the pool itself does not slip.
+    ///
+    /// The equivalent holds the reserves of the pool, the rest of its depth being virtual, see
+    /// [`UniV2Pool::virtual_reserves`]: the router reports the reserves, and refuses the trades
+    /// that would take more than the output reserve, as [`Pool::get_output_amount`] does.
+    fn to_uni_v2(&self) -> UniV2Pool {
+        let depth = self.sqrt_invariant();
+        let pool = UniV2Pool::new(self.token0, self.token1, self.reserve0, self.reserve1)
+            .with_fee(0.0)
+            .with_virtual_reserves(depth - self.reserve0, depth - self.reserve1);
+        match &self.id {
+            Some(id) => pool.with_id(id.clone()),
+            None => pool,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Conversions between the floating-point amounts of the [`Router`](crate::router::Router) and
+//! the integer, basis-point based conventions of on-chain swaps.
+//!
+//! Every function is pure. Conversions to integers round in the direction that keeps the caller
+//! safe: bounds protecting the trader (`minOut`) round down, amounts the quote relies on being
+//! sent round up.
+
+use crate::{error::RouterError, router::TradeResult};
+
+/// Number of basis points in one.
+const BPS: f64 = 10_000.0;
+
+/// Number of basis points between two consecutive Uniswap V3 ticks, `1.0001 = 1 + 1 bp`.
+const TICK_BASE: f64 = 1.0001;
+
+/// Which way a slippage tolerance moves an amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlippageDirection {
+    /// Lowers the amount, to bound what is received (`minOut`)
+    Down,
+    /// Raises the amount, to bound what is sent (`maxIn`)
+    Up,
+}
+
+/// Rounding direction of a conversion to raw integer units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// Slippage tolerance and validity of the quotes sent on-chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlippagePolicy {
+    /// Largest tolerated shortfall of the output, in basis points of the quote
+    pub tolerance_bps: u32,
+    /// Number of seconds a quote stays acceptable, to be added to the current block timestamp
+    pub deadline_secs: u64,
+}
+
+impl Default for SlippagePolicy {
+    fn default() -> Self {
+        Self {
+            tolerance_bps: 50,
+            deadline_secs: 120,
+        }
+    }
+}
+
+/// Parameters of an exact-input swap, in raw token units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnChainQuote {
+    /// Amount of the input token to send, rounded up so that the quoted output stays reachable
+    pub amount_in_raw: u128,
+    /// Smallest acceptable amount of the output token, the quote minus the slippage tolerance,
+    /// rounded down; never more than the quoted output
+    pub min_amount_out_raw: u128,
+    /// Validity of the quote in seconds, see [`SlippagePolicy::deadline_secs`]
+    pub deadline_hint: u64,
+}
+
+impl OnChainQuote {
+    /// Converts a quote of `output_amount` for `input_amount`, in token units, to raw units.
+    ///
+    /// Fails if an amount is negative, not finite, or too large for raw units.
+    pub fn new(
+        input_amount: f64,
+        output_amount: f64,
+        input_decimals: u8,
+        output_decimals: u8,
+        policy: &SlippagePolicy,
+    ) -> Result<Self, RouterError> {
+        let min_amount_out =
+            apply_slippage_bps(output_amount, policy.tolerance_bps, SlippageDirection::Down);
+        Ok(Self {
+            amount_in_raw: to_raw(input_amount, input_decimals, Rounding::Up)?,
+            min_amount_out_raw: to_raw(min_amount_out, output_decimals, Rounding::Down)?,
+            deadline_hint: policy.deadline_secs,
+        })
+    }
+
+    /// Converts the amounts of a committed trade, see [`OnChainQuote::new`].
+    pub fn from_trade(
+        trade: &TradeResult<'_>,
+        input_decimals: u8,
+        output_decimals: u8,
+        policy: &SlippagePolicy,
+    ) -> Result<Self, RouterError> {
+        Self::new(
+            trade.input_amount,
+            trade.output_amount,
+            input_decimals,
+            output_decimals,
+            policy,
+        )
+    }
+
+    /// Returns the input amount and the minimum output amount in token units.
+    pub fn amounts(&self, input_decimals: u8, output_decimals: u8) -> (f64, f64) {
+        (
+            from_raw(self.amount_in_raw, input_decimals),
+            from_raw(self.min_amount_out_raw, output_decimals),
+        )
+    }
+}
+
+/// Moves `amount` by `bps` basis points in `direction`: `amount · (1 ∓ bps / 10⁴)`. A downward
+/// slippage of 10 000 bp or more gives zero.
+pub fn apply_slippage_bps(amount: f64, bps: u32, direction: SlippageDirection) -> f64 {
+    let fraction = bps as f64 / BPS;
+    match direction {
+        SlippageDirection::Down => amount * (1.0 - fraction).max(0.0),
+        SlippageDirection::Up => amount * (1.0 + fraction),
+    }
+}
+
+/// Returns the relative change from `reference` to `amount` in basis points, negative when
+/// `amount` is lower.
+pub fn bps_between(reference: f64, amount: f64) -> f64 {
+    (amount - reference) / reference * BPS
+}
+
+/// Returns the Uniswap V3 tick whose price `1.0001^tick` is closest below `price`.
+///
+/// This is an approximation: the logarithm is computed in floating point, so prices within a
+/// relative `1e-12` or so of a tick boundary may land on the neighboring tick, unlike the exact
+/// fixed-point `TickMath` of the contracts.
+pub fn price_to_tick_approx(price: f64) -> i32 {
+    (price.ln() / TICK_BASE.ln()).floor() as i32
+}
+
+/// Converts `amount` token units to raw units of a token with `decimals` decimals.
+///
+/// Fails if `amount` is negative, not finite, or too large for a `u128`.
+pub fn to_raw(amount: f64, decimals: u8, rounding: Rounding) -> Result<u128, RouterError> {
+    if !(amount.is_finite() && amount >= 0.0) {
+        return Err(RouterError::InvalidAmount(amount));
+    }
+    let scaled = amount * 10f64.powi(decimals as i32);
+    let raw = match rounding {
+        Rounding::Down => scaled.floor(),
+        Rounding::Up => scaled.ceil(),
+    };
+    // `u128::MAX as f64` rounds up to 2¹²⁸, which is itself out of range
+    if raw >= u128::MAX as f64 {
+        return Err(RouterError::InvalidAmount(amount));
+    }
+    Ok(raw as u128)
+}
+
+/// Converts `raw` units of a token with `decimals` decimals to token units.
+pub fn from_raw(raw: u128, decimals: u8) -> f64 {
+    raw as f64 / 10f64.powi(decimals as i32)
+}
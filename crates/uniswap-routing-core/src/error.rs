@@ -0,0 +1,215 @@
+use crate::{
+    router::{EventKey, TokenId},
+    uni_v2_pool::PoolId,
+};
+
+use std::fmt;
+
+/// Errors returned by the [`Router`](crate::router::Router).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouterError {
+    /// The token does not appear in any pool of the router
+    UnknownToken(String),
+    /// An external solution does not have the expected shape
+    MalformedSolution(String),
+    /// An external solution violates the conservation of `token` beyond the tolerance
+    InfeasibleSolution { token: String, residual: f64 },
+    /// The token id was issued before the last compaction of the router, at generation
+    /// `generation`
+    StaleTokenId { id: TokenId, generation: u32 },
+    /// The token is blacklisted, trades from or to it are refused
+    BlacklistedToken(String),
+    /// No pool of the router has this identifier
+    UnknownPool(PoolId),
+    /// An event arrived with a key lower than the last applied one without the reorg flag
+    OutOfOrderEvent { key: EventKey, last: EventKey },
+    /// No retained snapshot predates the rollback target
+    RollbackUnavailable(EventKey),
+    /// The configuration is inconsistent
+    InvalidConfig(String),
+    /// The amount is negative, not finite, or out of the representable range
+    InvalidAmount(f64),
+    /// A result contradicts a property the model guarantees
+    ModelInconsistency(String),
+    /// The trade yields, or is bound to yield, less than the required minimum
+    InsufficientOutput {
+        output_amount: f64,
+        min_output_amount: f64,
+    },
+    /// A replica at version `expected` received a delta starting at version `found`, the deltas
+    /// in between were missed
+    VersionGap { expected: u64, found: u64 },
+    /// The token has no pool holding liquidity with a routable token, it cannot be traded
+    IsolatedToken(String),
+    /// The tokens lie in different connected components, no path of pools links them
+    NotConnected {
+        input_token: String,
+        output_token: String,
+    },
+    /// The fixed-point iteration stopped after `iterations` sweeps, its last sweep still moving a
+    /// price by the relative `residual`, above the tolerance
+    DidNotConverge { iterations: usize, residual: f64 },
+    /// The fixed-point update of `token` produced the square-root price `q`, which is not
+    /// positive and finite, typically from a non-finite reserve
+    InvalidPrice { token: String, q: f64 },
+    /// The amount extracted from `token`, or with
+    /// [`RouterConfig::strict_math`](crate::router::RouterConfig) a denominator of its update,
+    /// came out as `value`, which is not finite
+    NumericalError { token: String, value: f64 },
+    /// No pool links the two tokens directly
+    NoDirectPool {
+        input_token: String,
+        output_token: String,
+    },
+    /// The equilibrium tops the output token up by `amount` instead of draining it, beyond the
+    /// dust tolerance, see [`RouterConfig::dust_tolerance`](crate::router::RouterConfig)
+    NegativeOutput { amount: f64 },
+    /// The pool is rejected when entering the router, or its new reserves when they are updated,
+    /// see [`UniV2Pool::validate`](crate::UniV2Pool::validate); `pool` is the identifier it
+    /// would have received, or has
+    InvalidPool { pool: PoolId, error: PoolError },
+    /// A pool entering the router has the identifier of another pool
+    DuplicatePool(PoolId),
+    /// The operation on the pool failed
+    PoolOperation { pool: PoolId, error: PoolError },
+}
+
+impl fmt::Display for RouterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownToken(token) => write!(f, "unknown token {token}"),
+            Self::MalformedSolution(reason) => write!(f, "malformed solution: {reason}"),
+            Self::InfeasibleSolution { token, residual } => write!(
+                f,
+                "infeasible solution: conservation of {token} violated by a relative {residual:e}"
+            ),
+            Self::StaleTokenId { id, generation } => write!(
+                f,
+                "token id {id} is from generation {}, the router is at generation {generation}",
+                id.generation()
+            ),
+            Self::BlacklistedToken(token) => write!(f, "token {token} is blacklisted"),
+            Self::UnknownPool(id) => write!(f, "unknown pool {id}"),
+            Self::OutOfOrderEvent { key, last } => {
+                write!(f, "event {key} arrived after event {last}")
+            }
+            Self::RollbackUnavailable(key) => {
+                write!(f, "no retained snapshot to roll back to event {key}")
+            }
+            Self::InvalidConfig(reason) => write!(f, "invalid configuration: {reason}"),
+            Self::InvalidAmount(amount) => write!(f, "invalid amount {amount}"),
+            Self::ModelInconsistency(reason) => write!(f, "model inconsistency: {reason}"),
+            Self::InsufficientOutput {
+                output_amount,
+                min_output_amount,
+            } => write!(
+                f,
+                "output of {output_amount} below the minimum of {min_output_amount}"
+            ),
+            Self::VersionGap { expected, found } => write!(
+                f,
+                "delta starts at version {found} but the replica is at version {expected}"
+            ),
+            Self::IsolatedToken(token) => write!(f, "token {token} has no liquidity"),
+            Self::NotConnected {
+                input_token,
+                output_token,
+            } => write!(f, "no path of pools links {input_token} to {output_token}"),
+            Self::DidNotConverge {
+                iterations,
+                residual,
+            } => write!(
+                f,
+                "no convergence after {iterations} iterations, residual {residual:e}"
+            ),
+            Self::InvalidPrice { token, q } => {
+                write!(
+                    f,
+                    "the solver produced an invalid price {q} for token {token}"
+                )
+            }
+            Self::NumericalError { token, value } => {
+                write!(
+                    f,
+                    "the solver computed a non-finite {value} for token {token}"
+                )
+            }
+            Self::NoDirectPool {
+                input_token,
+                output_token,
+            } => write!(f, "no pool links {input_token} and {output_token} directly"),
+            Self::NegativeOutput { amount } => {
+                write!(f, "the equilibrium yields a negative output of {amount}")
+            }
+            Self::InvalidPool { pool, error } => write!(f, "invalid pool {pool}: {error}"),
+            Self::DuplicatePool(pool) => write!(f, "duplicate pool {pool}"),
+            Self::PoolOperation { pool, error } => write!(f, "pool {pool}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RouterError {}
+
+/// Errors returned by [`UniV2Pool::try_new`](crate::UniV2Pool::try_new) and
+/// [`UniV2Pool::validate`](crate::UniV2Pool::validate).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolError {
+    /// A token name is empty
+    EmptyToken,
+    /// The pool pairs the token with itself
+    IdenticalTokens(String),
+    /// The reserve of the token is zero, negative or not finite
+    InvalidReserve { token: String, reserve: f64 },
+    /// The fee is not a fraction of the input in `[0, 1)`
+    InvalidFee(f64),
+    /// The token is neither of the tokens of the pool
+    UnknownToken(String),
+    /// The amount is negative or not finite
+    InvalidAmount(f64),
+    /// The requested output reaches the whole `reserve` of the pool, no input can buy it
+    InsufficientLiquidity { output_amount: f64, reserve: f64 },
+    /// The pool has no raw reserves for its exact math
+    MissingRawReserves,
+    /// The fee is not a whole number of basis points, which the exact math cannot represent
+    InexactFee(f64),
+    /// An intermediate product of the exact math exceeds `u128`
+    ExactOverflow,
+    /// Burning `shares` would leave the pool, of `total_shares`, without liquidity
+    InsufficientShares { shares: f64, total_shares: f64 },
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyToken => write!(f, "empty token name"),
+            Self::IdenticalTokens(token) => write!(f, "pairs {token} with itself"),
+            Self::InvalidReserve { token, reserve } => {
+                write!(f, "reserve of {token} is {reserve}")
+            }
+            Self::InvalidFee(fee) => write!(f, "fee of {fee} is not in [0, 1)"),
+            Self::UnknownToken(token) => write!(f, "{token} is not a token of the pool"),
+            Self::InvalidAmount(amount) => write!(f, "invalid amount {amount}"),
+            Self::InsufficientLiquidity {
+                output_amount,
+                reserve,
+            } => write!(
+                f,
+                "an output of {output_amount} drains the whole reserve of {reserve}"
+            ),
+            Self::MissingRawReserves => write!(f, "the pool has no raw reserves"),
+            Self::InexactFee(fee) => {
+                write!(f, "fee of {fee} is not a whole number of basis points")
+            }
+            Self::ExactOverflow => write!(f, "exact swap amounts overflow u128"),
+            Self::InsufficientShares {
+                shares,
+                total_shares,
+            } => write!(
+                f,
+                "burning {shares} of {total_shares} shares drains the pool"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
@@ -0,0 +1,25 @@
+//! Core Uniswap V2 routing solver: pool model, token graph and no-arbitrage equilibrium router.
+//!
+//! This crate carries no integration dependencies; CLIs and other front-ends live in
+//! `uniswap-routing-integrations`.
+
+pub mod constant_sum_pool;
+pub mod conventions;
+pub mod error;
+pub mod pool;
+pub mod router;
+#[cfg(feature = "test-utils")]
+pub mod scenario;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+pub mod uni_v2_pool;
+pub mod weighted_pool;
+
+pub use crate::{
+    constant_sum_pool::ConstantSumPool,
+    error::RouterError,
+    pool::Pool,
+    router::Router,
+    uni_v2_pool::{LpShares, PoolId, UniV2Pool},
+    weighted_pool::WeightedPool,
+};
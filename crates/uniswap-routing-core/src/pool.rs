@@ -0,0 +1,97 @@
+//! The [`Pool`] trait, through which the [`Router`](crate::router::Router) accepts pools of any
+//! AMM type.
+//!
+//! The equilibrium of the router is shaped for constant-product pools: every pool enters it as
+//! the [`UniV2Pool`] returned by [`Pool::to_uni_v2`], which stands for it in the aggregation of
+//! its pair and moves with the trades. Pools of other types are only as faithful as that
+//! equivalent.
+
+use crate::{error::PoolError, uni_v2_pool::UniV2Pool};
+
+/// A pool of two tokens.
+pub trait Pool {
+    /// Returns the tokens of the pool, `token0` first.
+    fn tokens(&self) -> (&'static str, &'static str);
+
+    /// Returns the reserves of `token0` and `token1`.
+    fn reserves(&self) -> (f64, f64);
+
+    /// Returns the square root of the constant-product invariant the pool contributes to the
+    /// liquidity of its pair at its current price.
+    fn sqrt_invariant(&self) -> f64;
+
+    /// Returns the output of swapping `input_amount` of `input_token` against the pool, leaving
+    /// it untouched. Fails if the token is not one of the pool or if the amount is negative or
+    /// not finite.
+    fn get_output_amount(&self, input_token: &str, input_amount: f64) -> Result<f64, PoolError>;
+
+    /// Swaps `input_amount` of `input_token` against the pool, moving its reserves, and returns
+    /// the output. Fails like [`Pool::get_output_amount`], leaving the pool untouched.
+    fn apply_swap(&mut self, input_token: &str, input_amount: f64) -> Result<f64, PoolError>;
+
+    /// Returns the constant-product pool standing for this one in the router. By default, the
+    /// pool of the same reserves scaled to the invariant [`Pool::sqrt_invariant`], which keeps
+    /// the ratio of the reserves as its price.
+    fn to_uni_v2(&self) -> UniV2Pool {
+        let (token0, token1) = self.tokens();
+        let (reserve0, reserve1) = self.reserves();
+        let scale = self.sqrt_invariant() / (reserve0.sqrt() * reserve1.sqrt());
+        UniV2Pool::new(token0, token1, reserve0 * scale, reserve1 * scale)
+    }
+}
+
+impl Pool for UniV2Pool {
+    fn tokens(&self) -> (&'static str, &'static str) {
+        (self.token0, self.token1)
+    }
+
+    fn reserves(&self) -> (f64, f64) {
+        (self.reserve0, self.reserve1)
+    }
+
+    fn sqrt_invariant(&self) -> f64 {
+        self.reserve0.sqrt() * self.reserve1.sqrt()
+    }
+
+    fn get_output_amount(&self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        if input_token != self.token0 && input_token != self.token1 {
+            return Err(PoolError::UnknownToken(input_token.to_string()));
+        }
+        self.output_amount_with_fee(input_token, input_amount, self.fee)
+    }
+
+    fn apply_swap(&mut self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        UniV2Pool::apply_swap(self, input_token, input_amount)
+    }
+
+    /// Returns a copy of the pool, its fee, raw reserves, decimals and identifier included.
+    fn to_uni_v2(&self) -> UniV2Pool {
+        self.clone()
+    }
+}
+
+impl<P: Pool + ?Sized> Pool for Box<P> {
+    fn tokens(&self) -> (&'static str, &'static str) {
+        (**self).tokens()
+    }
+
+    fn reserves(&self) -> (f64, f64) {
+        (**self).reserves()
+    }
+
+    fn sqrt_invariant(&self) -> f64 {
+        (**self).sqrt_invariant()
+    }
+
+    fn get_output_amount(&self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        (**self).get_output_amount(input_token, input_amount)
+    }
+
+    fn apply_swap(&mut self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        (**self).apply_swap(input_token, input_amount)
+    }
+
+    fn to_uni_v2(&self) -> UniV2Pool {
+        (**self).to_uni_v2()
+    }
+}
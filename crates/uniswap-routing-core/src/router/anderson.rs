@@ -0,0 +1,76 @@
+use crate::router::linear_system::solve_linear_system;
+
+use std::collections::VecDeque;
+
+/// Anderson mixing of the iterates of a fixed-point map `G`, see
+/// [`SolverAcceleration::Anderson`](crate::router::SolverAcceleration).
+///
+/// From the residuals `f = G(x) − x` of the last iterates, the next iterate is
+/// `G(x) − ∑ γᵢ ΔGᵢ`, where the `γ` minimize `‖f − ∑ γᵢ Δfᵢ‖` over the differences `Δf` and `ΔG`
+/// between consecutive iterates: the combination of the recent steps that best cancels the
+/// residual, assuming `G` linear over them.
+#[derive(Debug, Clone)]
+pub(super) struct Anderson {
+    /// Number of differences kept
+    memory: usize,
+    /// Residual and image of the last iterate
+    last: Option<(Vec<f64>, Vec<f64>)>,
+    /// Differences `(Δf, ΔG)` between consecutive iterates, oldest first
+    differences: VecDeque<(Vec<f64>, Vec<f64>)>,
+}
+
+impl Anderson {
+    pub(super) fn new(memory: usize) -> Self {
+        Self {
+            memory,
+            last: None,
+            differences: VecDeque::with_capacity(memory + 1),
+        }
+    }
+
+    /// Forgets the past iterates, for the next mixing to start over from a plain step.
+    pub(super) fn reset(&mut self) {
+        self.last = None;
+        self.differences.clear();
+    }
+
+    /// Records the iterate `x` and its image `image = G(x)`, and returns the mixed next iterate,
+    /// or `None` when there is no past iterate to mix with or the least-squares problem is
+    /// singular, the plain step `G(x)` being the next iterate then.
+    pub(super) fn mix(&mut self, x: &[f64], image: &[f64]) -> Option<Vec<f64>> {
+        let residual = image.iter().zip(x).map(|(g, x)| g - x).collect::<Vec<_>>();
+        if let Some((last_residual, last_image)) = self.last.take() {
+            let residual_difference = residual.iter().zip(&last_residual).map(|(f, l)| f - l);
+            let image_difference = image.iter().zip(&last_image).map(|(g, l)| g - l);
+            self.differences
+                .push_back((residual_difference.collect(), image_difference.collect()));
+            if self.differences.len() > self.memory {
+                self.differences.pop_front();
+            }
+        }
+        self.last = Some((residual.clone(), image.to_vec()));
+        if self.differences.is_empty() {
+            return None;
+        }
+
+        // Normal equations of the least-squares problem, `m × m` for a memory of `m`
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+        let size = self.differences.len();
+        let mut matrix = vec![vec![0.0; size + 1]; size];
+        for (i, (difference_i, _)) in self.differences.iter().enumerate() {
+            for (j, (difference_j, _)) in self.differences.iter().enumerate() {
+                matrix[i][j] = dot(difference_i, difference_j);
+            }
+            matrix[i][size] = dot(difference_i, &residual);
+        }
+        let weights = solve_linear_system(matrix)?;
+
+        let mut mixed = image.to_vec();
+        for (weight, (_, image_difference)) in weights.iter().zip(&self.differences) {
+            for (value, difference) in mixed.iter_mut().zip(image_difference) {
+                *value -= weight * difference;
+            }
+        }
+        Some(mixed)
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{router::Router, uni_v2_pool::PoolId};
+
+use std::collections::{HashMap, HashSet};
+
+/// Smallest decrease of the `-ln` distance accepted by a relaxation, which keeps the zero-weight
+/// back-and-forth through a single pool from passing for a cycle on rounding noise.
+const RELAXATION_EPSILON: f64 = 1e-12;
+
+/// A cycle of swaps at spot prices ending with more of its first token than it started with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitrageCycle<'l> {
+    /// Traversed tokens, the first one repeated last
+    pub tokens: Vec<&'l str>,
+    /// Pool of every hop, `pools[i]` swapping `tokens[i]` for `tokens[i + 1]`
+    pub pools: Vec<PoolId>,
+    /// Product of the spot rates along the cycle, above one
+    pub profit_factor: f64,
+}
+
+/// A directed hop through a pool: selling `from` for `to` at the spot rate `rate`.
+struct Hop {
+    from: usize,
+    to: usize,
+    pool: usize,
+    rate: f64,
+}
+
+impl<'l> Router<'l> {
+    /// Searches the individual pools for cycles whose spot rates multiply to more than
+    /// `1 + min_profit_ratio`, most profitable first.
+    ///
+    /// The search works on every pool in both directions, not on the aggregated pair liquidities,
+    /// which hide the price gaps between the pools of a pair. Those gaps are enumerated: for every
+    /// pair with several pools, the cycle buying from the pool quoting the best rate one way and
+    /// selling to the one quoting the best rate the other way is reported if profitable. Longer
+    /// cycles are found by Bellman–Ford on the `-ln` of the spot rates, every negative cycle left
+    /// in its predecessor graph being reported once. Bellman–Ford does not enumerate all of them:
+    /// an empty result rules out arbitrage beyond rounding noise, but the cycles of three tokens
+    /// or more reported are not exhaustive.
+    pub fn find_arbitrage_cycles(&self, min_profit_ratio: f64) -> Vec<ArbitrageCycle<'l>> {
+        let hops = self
+            .pools
+            .iter()
+            .enumerate()
+            .filter(|(_, pool)| pool.reserve0 > 0.0 && pool.reserve1 > 0.0)
+            .flat_map(|(position, pool)| {
+                let (token0, token1) =
+                    (self.token_index[pool.token0], self.token_index[pool.token1]);
+                let (curve0, curve1) = pool.curve_reserves();
+                [
+                    Hop {
+                        from: token0,
+                        to: token1,
+                        pool: position,
+                        rate: curve1 / curve0,
+                    },
+                    Hop {
+                        from: token1,
+                        to: token0,
+                        pool: position,
+                        rate: curve0 / curve1,
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut seen = HashSet::new();
+        let mut cycles = Vec::new();
+        for mut cycle_hops in pair_gap_cycles(&hops)
+            .into_iter()
+            .chain(negative_cycles(&hops, self.tokens.len()))
+        {
+            // Rotate to start from the smallest token, so that every cycle is reported once
+            let first = (0..cycle_hops.len())
+                .min_by_key(|&i| hops[cycle_hops[i]].from)
+                .unwrap_or(0);
+            cycle_hops.rotate_left(first);
+            if !seen.insert(cycle_hops.clone()) {
+                continue;
+            }
+
+            let profit_factor = cycle_hops
+                .iter()
+                .map(|&hop| hops[hop].rate)
+                .product::<f64>();
+            if profit_factor - 1.0 <= min_profit_ratio {
+                continue;
+            }
+            let mut tokens = cycle_hops
+                .iter()
+                .map(|&hop| self.tokens[hops[hop].from])
+                .collect::<Vec<_>>();
+            tokens.push(tokens[0]);
+            cycles.push(ArbitrageCycle {
+                tokens,
+                pools: cycle_hops
+                    .iter()
+                    .map(|&hop| self.pool_ids[hops[hop].pool].clone())
+                    .collect(),
+                profit_factor,
+            });
+        }
+        cycles.sort_by(|cycle, other| other.profit_factor.total_cmp(&cycle.profit_factor));
+        cycles
+    }
+}
+
+/// Returns, for every pair of tokens with several pools, the two hops of the cycle selling the
+/// first token to the pool quoting the best rate for it, and buying it back from the pool quoting
+/// the best rate the other way, if they are different pools: the best cycle through two pools of
+/// the pair, profitable or not. The two hops of a single pool multiply to one.
+fn pair_gap_cycles(hops: &[Hop]) -> Vec<Vec<usize>> {
+    // Best hop of every direction of every pair, by `(from, to)`
+    let mut best = HashMap::<(usize, usize), usize>::new();
+    for (position, hop) in hops.iter().enumerate() {
+        best.entry((hop.from, hop.to))
+            .and_modify(|best| {
+                if hop.rate > hops[*best].rate {
+                    *best = position;
+                }
+            })
+            .or_insert(position);
+    }
+    let mut cycles = best
+        .iter()
+        .filter(|&(&(from, to), _)| from < to)
+        .filter_map(|(&(from, to), &forward)| {
+            let backward = best[&(to, from)];
+            (hops[forward].pool != hops[backward].pool).then(|| vec![forward, backward])
+        })
+        .collect::<Vec<_>>();
+    // The map iterates in no particular order
+    cycles.sort_unstable();
+    cycles
+}
+
+/// Returns the hops of the negative cycles of the `-ln` of the rates of `hops` between
+/// `token_count` tokens left in the predecessor graph of Bellman–Ford, every token starting at
+/// distance zero as if linked to a virtual source, in no particular rotation, possibly repeated.
+fn negative_cycles(hops: &[Hop], token_count: usize) -> Vec<Vec<usize>> {
+    let mut distance = vec![0.0; token_count];
+    let mut predecessor = vec![None::<usize>; token_count];
+    let mut relaxed_last = Vec::new();
+    for _ in 0..token_count {
+        relaxed_last.clear();
+        for (position, hop) in hops.iter().enumerate() {
+            let candidate = distance[hop.from] - hop.rate.ln();
+            if candidate < distance[hop.to] - RELAXATION_EPSILON {
+                distance[hop.to] = candidate;
+                predecessor[hop.to] = Some(position);
+                relaxed_last.push(hop.to);
+            }
+        }
+        if relaxed_last.is_empty() {
+            return Vec::new();
+        }
+    }
+
+    let mut cycles = Vec::new();
+    for &token in &relaxed_last {
+        // Walking back once per token is guaranteed to end up on the cycle
+        let mut on_cycle = Some(token);
+        for _ in 0..token_count {
+            on_cycle = on_cycle.and_then(|token| predecessor[token].map(|hop| hops[hop].from));
+        }
+        let Some(start) = on_cycle else {
+            continue;
+        };
+
+        let mut cycle_hops = Vec::new();
+        let mut current = start;
+        while let Some(hop) = predecessor[current] {
+            cycle_hops.push(hop);
+            current = hops[hop].from;
+            if current == start || cycle_hops.len() > token_count {
+                break;
+            }
+        }
+        if current == start {
+            cycle_hops.reverse();
+            cycles.push(cycle_hops);
+        }
+    }
+    cycles
+}
@@ -0,0 +1,290 @@
+use crate::{router::Router, uni_v2_pool::PoolId};
+
+use std::{collections::HashMap, fmt};
+
+/// Largest relative mismatch tolerated between the aggregates of the token graph and the
+/// individual pools they are derived from.
+const AUDIT_TOLERANCE: f64 = 1e-9;
+
+/// Invariant of the router state broken, found by [`Router::verify_invariants`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvariantViolation<'l> {
+    /// A pool reserve is negative or not finite
+    PoolReserve { pool: PoolId, reserve: f64 },
+    /// A square-root price `q` is not positive or not finite
+    Price { token: &'l str, q: f64 },
+    /// The total reserve of a token is negative or not finite
+    TotalReserve { token: &'l str, total_reserve: f64 },
+    /// The total reserve of a token differs from the sum of its pool reserves
+    TotalMismatch {
+        token: &'l str,
+        total_reserve: f64,
+        pool_sum: f64,
+    },
+    /// The geometric liquidity of a pair differs from the sum of the `√k` of its pools
+    LiquidityMismatch {
+        token_a: &'l str,
+        token_b: &'l str,
+        liquidity: f64,
+        pool_sum: f64,
+    },
+    /// A geometric liquidity is negative or not finite
+    Liquidity {
+        token_a: &'l str,
+        token_b: &'l str,
+        liquidity: f64,
+    },
+    /// The liquidity matrix is not symmetric, `K(a, b) ≠ K(b, a)`
+    AsymmetricLiquidity {
+        token_a: &'l str,
+        token_b: &'l str,
+        forward: f64,
+        backward: f64,
+    },
+    /// A token is paired with itself, on the diagonal of the liquidity matrix
+    SelfPair { token: &'l str, liquidity: f64 },
+    /// The token indices do not map every token to its position, `0..n` without gaps: `index`
+    /// is `None` for a token missing from the indices, `position` for an indexed unknown token
+    TokenIndex {
+        token: &'l str,
+        index: Option<usize>,
+        position: Option<usize>,
+    },
+}
+
+impl fmt::Display for InvariantViolation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PoolReserve { pool, reserve } => {
+                write!(f, "pool {pool} has a reserve of {reserve}")
+            }
+            Self::Price { token, q } => write!(f, "token {token} has a price variable of {q}"),
+            Self::TotalReserve {
+                token,
+                total_reserve,
+            } => write!(f, "token {token} has a total reserve of {total_reserve}"),
+            Self::TotalMismatch {
+                token,
+                total_reserve,
+                pool_sum,
+            } => write!(
+                f,
+                "token {token} has a total reserve of {total_reserve} but its pools hold {pool_sum}"
+            ),
+            Self::LiquidityMismatch {
+                token_a,
+                token_b,
+                liquidity,
+                pool_sum,
+            } => write!(
+                f,
+                "pair {token_a}/{token_b} has a liquidity of {liquidity} but its pools sum to \
+                 {pool_sum}"
+            ),
+            Self::Liquidity {
+                token_a,
+                token_b,
+                liquidity,
+            } => write!(f, "pair {token_a}/{token_b} has a liquidity of {liquidity}"),
+            Self::AsymmetricLiquidity {
+                token_a,
+                token_b,
+                forward,
+                backward,
+            } => write!(
+                f,
+                "pair {token_a}/{token_b} has a liquidity of {forward} one way and {backward} the \
+                 other"
+            ),
+            Self::SelfPair { token, liquidity } => {
+                write!(
+                    f,
+                    "token {token} is paired with itself, liquidity {liquidity}"
+                )
+            }
+            Self::TokenIndex {
+                token,
+                index,
+                position,
+            } => match (index, position) {
+                (Some(index), Some(position)) => {
+                    write!(
+                        f,
+                        "token {token} at position {position} is indexed as {index}"
+                    )
+                }
+                (None, Some(position)) => {
+                    write!(f, "token {token} at position {position} is not indexed")
+                }
+                (Some(index), None) => write!(f, "unknown token {token} is indexed as {index}"),
+                (None, None) => write!(f, "token {token} is neither listed nor indexed"),
+            },
+        }
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Checks the consistency of the state and returns a description of every violation found,
+    /// none for a healthy router, see [`Router::verify_invariants`].
+    pub fn audit(&self) -> Vec<String> {
+        let mut violations = match self.verify_invariants() {
+            Ok(()) => Vec::new(),
+            Err(violations) => violations.iter().map(ToString::to_string).collect(),
+        };
+        violations.sort();
+        violations
+    }
+
+    /// Checks the invariants of the state, a debugging aid returning every violation found:
+    /// - pool reserves and token totals are finite and non-negative, prices finite and positive,
+    /// - the pool reserves of every token sum up to its total reserve,
+    /// - the liquidity matrix is symmetric, with non-negative entries and an empty diagonal,
+    /// - the geometric liquidity of every pair is the sum of the `√k` of its pools,
+    /// - the token indices map every token to its position, covering `0..n`.
+    ///
+    /// With the `paranoid` feature, debug builds assert these invariants after every solve.
+    pub fn verify_invariants(&self) -> Result<(), Vec<InvariantViolation<'l>>> {
+        let mut violations = Vec::new();
+        let graph = &self.token_graph;
+
+        for (position, &token) in self.tokens.iter().enumerate() {
+            let index = self.token_index.get(token).copied();
+            if index != Some(position) {
+                violations.push(InvariantViolation::TokenIndex {
+                    token,
+                    index,
+                    position: Some(position),
+                });
+            }
+        }
+        for (&token, &index) in &self.token_index {
+            if self.tokens.get(index) != Some(&token) && !self.tokens.contains(&token) {
+                violations.push(InvariantViolation::TokenIndex {
+                    token,
+                    index: Some(index),
+                    position: None,
+                });
+            }
+        }
+        if !violations.is_empty() {
+            // The pools cannot be attributed to their tokens
+            return Err(violations);
+        }
+
+        let mut pool_sums = vec![0.0; self.tokens.len()];
+        let mut pair_liquidities = HashMap::<(usize, usize), f64>::new();
+        for (pool, id) in self.pools.iter().zip(&self.pool_ids) {
+            for reserve in [pool.reserve0, pool.reserve1] {
+                if !(reserve.is_finite() && reserve >= 0.0) {
+                    violations.push(InvariantViolation::PoolReserve {
+                        pool: id.clone(),
+                        reserve,
+                    });
+                }
+            }
+            let index_0 = self.token_index[pool.token0];
+            let index_1 = self.token_index[pool.token1];
+            let (curve0, curve1) = pool.curve_reserves();
+            pool_sums[index_0] += curve0;
+            pool_sums[index_1] += curve1;
+            if graph.is_pool_active(pool, index_0, index_1) {
+                *pair_liquidities
+                    .entry((index_0.min(index_1), index_0.max(index_1)))
+                    .or_insert(0.0) += curve0.sqrt() * curve1.sqrt();
+            }
+        }
+
+        for (token, &name) in self.tokens.iter().enumerate() {
+            let q = graph.price(token);
+            if !(q.is_finite() && q > 0.0) {
+                violations.push(InvariantViolation::Price { token: name, q });
+            }
+            let total_reserve = graph.total_reserve(token);
+            if !(total_reserve.is_finite() && total_reserve >= 0.0) {
+                violations.push(InvariantViolation::TotalReserve {
+                    token: name,
+                    total_reserve,
+                });
+            } else if !within_tolerance(pool_sums[token], total_reserve) {
+                violations.push(InvariantViolation::TotalMismatch {
+                    token: name,
+                    total_reserve,
+                    pool_sum: pool_sums[token],
+                });
+            }
+        }
+
+        for token in 0..self.tokens.len() {
+            for (paired_token, liquidity) in graph.stored_liquidities(token) {
+                let (token_a, token_b) = (self.tokens[token], self.tokens[paired_token]);
+                if paired_token == token {
+                    violations.push(InvariantViolation::SelfPair {
+                        token: token_a,
+                        liquidity,
+                    });
+                    continue;
+                }
+                if !(liquidity.is_finite() && liquidity >= 0.0) {
+                    violations.push(InvariantViolation::Liquidity {
+                        token_a,
+                        token_b,
+                        liquidity,
+                    });
+                }
+                let backward = graph
+                    .stored_liquidities(paired_token)
+                    .find_map(|(t, liquidity)| (t == token).then_some(liquidity))
+                    .unwrap_or(0.0);
+                // Reported once per pair, from its smallest token
+                if token < paired_token && liquidity.to_bits() != backward.to_bits()
+                    || token > paired_token && backward == 0.0
+                {
+                    violations.push(InvariantViolation::AsymmetricLiquidity {
+                        token_a,
+                        token_b,
+                        forward: liquidity,
+                        backward,
+                    });
+                }
+            }
+        }
+
+        let edges = graph
+            .iter_nonzero()
+            .map(|(u, v, liquidity)| ((u, v), liquidity))
+            .collect::<HashMap<_, _>>();
+        for (&(u, v), &liquidity) in &pair_liquidities {
+            let edge_liquidity = edges.get(&(u, v)).copied().unwrap_or(0.0);
+            if !within_tolerance(liquidity, edge_liquidity) {
+                violations.push(InvariantViolation::LiquidityMismatch {
+                    token_a: self.tokens[u],
+                    token_b: self.tokens[v],
+                    liquidity: edge_liquidity,
+                    pool_sum: liquidity,
+                });
+            }
+        }
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+
+    /// Asserts the invariants in debug builds, after every operation changing the state.
+    #[cfg(feature = "paranoid")]
+    pub(super) fn assert_invariants(&self) {
+        if let Err(violations) = self.verify_invariants() {
+            debug_assert!(
+                false,
+                "{} invariant violations, first: {}",
+                violations.len(),
+                violations[0]
+            );
+        }
+    }
+}
+
+fn within_tolerance(actual: f64, expected: f64) -> bool {
+    (actual - expected).abs() <= AUDIT_TOLERANCE * expected.abs().max(actual.abs())
+}
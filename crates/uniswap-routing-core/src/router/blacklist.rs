@@ -0,0 +1,72 @@
+use crate::{
+    error::RouterError,
+    router::{Router, capture::CapturedOperation},
+};
+
+impl<'l> Router<'l> {
+    /// Excludes `token` from routing: every pair touching it counts as zero liquidity in the
+    /// equilibrium and its pools are left untouched by trades. The reserves stay accounted for,
+    /// so the exclusion can be lifted with [`Router::lift_blacklist`]. Trades from or to the
+    /// token fail with [`RouterError::BlacklistedToken`].
+    ///
+    /// Blacklisting a blacklisted token, or lifting the exclusion of a routable one, is a no-op
+    /// that publishes no version. A blacklist lifted before any trade leaves the router as it
+    /// was, see [`Router::content_hash`].
+    pub fn blacklist_token(&mut self, token: &str) -> Result<(), RouterError> {
+        self.set_blacklisted(token, true)
+    }
+
+    /// Lets `token` be routed through again. Its pools rejoin the equilibrium at the next trade,
+    /// which arbitrages away any price drift accumulated meanwhile.
+    pub fn lift_blacklist(&mut self, token: &str) -> Result<(), RouterError> {
+        self.set_blacklisted(token, false)
+    }
+
+    /// Returns the blacklisted tokens, in token index order.
+    pub fn blacklisted_tokens(&self) -> Vec<&'l str> {
+        (0..self.tokens.len())
+            .filter(|&token| self.token_graph.is_blacklisted(token))
+            .map(|token| self.tokens[token])
+            .collect()
+    }
+
+    fn set_blacklisted(&mut self, token: &str, blacklisted: bool) -> Result<(), RouterError> {
+        let index = self.index_of(token)?;
+        if self.token_graph.is_blacklisted(index) == blacklisted {
+            return Ok(());
+        }
+        let token = self.tokens[index];
+        match blacklisted {
+            true => self.config.blacklisted_tokens.push(token.to_string()),
+            false => self.config.blacklisted_tokens.retain(|name| name != token),
+        }
+        if let Some(capture) = &mut self.capture {
+            capture.push(CapturedOperation::SetBlacklisted {
+                token: token.to_string(),
+                blacklisted,
+            });
+        }
+        self.apply_blacklist();
+        self.publish_version();
+        Ok(())
+    }
+
+    /// Propagates the blacklist of the configuration to the token graph.
+    pub(super) fn apply_blacklist(&mut self) {
+        let mut flags = vec![false; self.tokens.len()];
+        for token in &self.config.blacklisted_tokens {
+            flags[self.token_index[token.as_str()]] = true;
+        }
+        self.token_graph
+            .set_blacklist(flags, &self.pools, &self.token_index);
+    }
+
+    /// Returns the index of `token`, failing if it is unknown or blacklisted.
+    pub(super) fn routable_index(&self, token: &str) -> Result<usize, RouterError> {
+        let index = self.index_of(token)?;
+        match self.token_graph.is_blacklisted(index) {
+            true => Err(RouterError::BlacklistedToken(token.to_string())),
+            false => Ok(index),
+        }
+    }
+}
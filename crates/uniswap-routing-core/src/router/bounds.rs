@@ -0,0 +1,121 @@
+use crate::{
+    error::RouterError,
+    router::{Router, check_trade_amount},
+};
+
+/// Bracket of the output of a trade, see [`Router::quote_bounds`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuoteBounds {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl QuoteBounds {
+    /// Returns whether `output_amount` lies within the bounds.
+    pub fn contains(&self, output_amount: f64) -> bool {
+        self.lower <= output_amount && output_amount <= self.upper
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Brackets the output amount [`Router::solve`] would return for the trade, in time linear in
+    /// the number of pairs of the input token and without modifying the router.
+    ///
+    /// - The upper bound is the input amount at the marginal rate `(q_out / q_in)²` of the
+    ///   equilibrium: the output of a fee-free constant-product network is concave in the input
+    ///   amount, so no trade does better than its first infinitesimal part.
+    /// - The lower bound is the output of the direct pair alone, whose pools share the price of
+    ///   the equilibrium and hence swap as a single pool with the aggregated reserves
+    ///   `K · q_in / q_out` and `K · q_out / q_in`. Routing the whole amount there is one of the
+    ///   allocations the solver chooses from, so it does at least as well. Without an active
+    ///   direct pair, the lower bound falls back to zero.
+    ///
+    /// Both bounds are widened by the dust tolerance of the output token, the precision at which
+    /// the solver reports amounts. The bracketing holds for an equilibrated router, see
+    /// [`Router::staleness`]: out of equilibrium, the trade also captures the arbitrage left in
+    /// the pools and may exceed the upper bound. It also assumes the solver converges: on
+    /// ill-conditioned components, see [`Router::conditioning_report`], the error of the solver
+    /// on tiny trades can exceed the widening.
+    ///
+    /// Fails if either token is unknown or blacklisted.
+    pub fn quote_bounds(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<QuoteBounds, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        Ok(self.bounds(input_index, output_index, input_amount))
+    }
+
+    /// Like [`Router::solve`], but refuses trades yielding less than `min_output_amount`, leaving
+    /// the router untouched.
+    ///
+    /// Trades whose [`Router::quote_bounds`] upper bound falls short are refused without running
+    /// the solver, and trades whose lower bound suffices are committed without the copy of the
+    /// equilibrium needed to undo the others. Input amounts are validated as by [`Router::solve`].
+    pub fn solve_with_min_output(
+        &mut self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+        min_output_amount: f64,
+    ) -> Result<f64, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        if !check_trade_amount(input_amount)? {
+            return match min_output_amount > 0.0 {
+                true => Err(RouterError::InsufficientOutput {
+                    output_amount: 0.0,
+                    min_output_amount,
+                }),
+                false => Ok(0.0),
+            };
+        }
+        self.settle_if_pending();
+
+        let bounds = self.bounds(input_index, output_index, input_amount);
+        if bounds.upper < min_output_amount {
+            return Err(RouterError::InsufficientOutput {
+                output_amount: bounds.upper,
+                min_output_amount,
+            });
+        }
+        let previous_graph = (bounds.lower < min_output_amount).then(|| self.token_graph.clone());
+
+        let outcome = self.solve_trade(input_index, output_index, input_amount)?;
+        if let Some(previous_graph) = previous_graph
+            && outcome.output_amount < min_output_amount
+        {
+            self.token_graph = previous_graph;
+            return Err(RouterError::InsufficientOutput {
+                output_amount: outcome.output_amount,
+                min_output_amount,
+            });
+        }
+        Ok(self
+            .commit_trade(input_index, output_index, input_amount, outcome)
+            .output_amount)
+    }
+
+    fn bounds(&self, input_index: usize, output_index: usize, input_amount: f64) -> QuoteBounds {
+        let graph = &self.token_graph;
+        let slack = graph.dust_tolerance() * graph.active_reserve(output_index);
+        let (q_in, q_out) = (graph.price(input_index), graph.price(output_index));
+
+        let rate = q_out / q_in;
+        let upper = input_amount * rate * rate;
+        let lower = match graph.liquidity(input_index, output_index) {
+            0.0 => 0.0,
+            liquidity => {
+                let (reserve_in, reserve_out) = (liquidity / rate, liquidity * rate);
+                reserve_out * input_amount / (reserve_in + input_amount)
+            }
+        };
+        QuoteBounds {
+            lower: (lower - slack).max(0.0),
+            upper: upper + slack,
+        }
+    }
+}
@@ -0,0 +1,703 @@
+use crate::{error::RouterError, router::Router, uni_v2_pool::UniV2Pool};
+
+/// Tunable behavior of a [`Router`], assembled through [`RouterBuilder`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RouterConfig {
+    /// Trade history recording, disabled by default
+    pub history: HistoryConfig,
+    /// Numéraire of the reported prices, the first token of the pool list when `None`
+    pub reference_token: Option<String>,
+    /// Whether the router keeps a replayable [`SessionCapture`](crate::router::SessionCapture) of
+    /// its trades
+    pub capture_session: bool,
+    /// Tokens excluded from routing, see [`Router::blacklist_token`]
+    pub blacklisted_tokens: Vec<String>,
+    /// Extracted amounts within this fraction of the output token total reserve are reported and
+    /// applied as zero, trades extracting a larger negative amount are refused
+    pub dust_tolerance: f64,
+    /// When prices carry over to a replaced or merged state
+    pub warm_start: WarmStartPolicy,
+    /// Number of most recent event keys remembered to skip redelivered events
+    pub event_dedup_window: usize,
+    /// Number of pre-batch snapshots kept for event rollbacks
+    pub retained_snapshots: usize,
+    /// Solver configuration of the trades no trade size band applies to, and of settlements
+    pub solver: SolverConfig,
+    /// Representation of the prices during the fixed-point iteration, for every solve
+    pub solver_domain: SolverDomain,
+    /// Relaxation factor `ω` of the fixed-point update, for every solve, see
+    /// [`RouterBuilder::relaxation`]
+    pub relaxation: f64,
+    /// Acceleration of the fixed-point iteration, for every solve
+    pub acceleration: SolverAcceleration,
+    /// Order of the price updates within a sweep, for every solve
+    pub sweep_mode: SweepMode,
+    /// Number of tokens of a connected component from which the updates of its Jacobi sweeps are
+    /// computed in parallel, with the `parallel` feature; the quotes are the same either way
+    pub parallel_sweep_threshold: usize,
+    /// Method solving the equilibrium, for every solve
+    pub solver_kind: SolverKind,
+    /// What a trade does when the solver exhausts its budget unconverged
+    pub fallback: FallbackStrategy,
+    /// How the square-root prices are rescaled after every solve
+    pub price_normalization: PriceNormalization,
+    /// Whether trades charge the swap fee of the pools
+    pub fee_model: FeeModel,
+    /// Whether the solves check the denominators of the updates finite, see
+    /// [`RouterBuilder::strict_math`]; on in debug builds by default
+    pub strict_math: bool,
+    /// Upgrades of the solver configuration according to the size of the trade
+    pub trade_size_policy: TradeSizePolicy,
+    /// Whether the router settles by itself once event batches leave it out of equilibrium
+    pub auto_settle: AutoSettle,
+    /// Staleness, see [`Router::staleness`], beyond which [`RouterConfig::auto_settle`] applies
+    pub staleness_threshold: f64,
+    /// Whether the router publishes a [`StateDelta`](crate::router::StateDelta) for every
+    /// committed operation, see [`Router::publish`]
+    pub publish_deltas: bool,
+    /// Price impact, as a fraction, beyond which the execution plans of
+    /// [`Router::solve_with_plan`] warn of the pools receiving more than their depth, see
+    /// [`UniV2Pool::max_input_for_impact`]; `None` to stay silent
+    pub plan_depth_warning: Option<f64>,
+    /// Layout of the liquidities of the token pairs in the solver
+    pub liquidity_storage: LiquidityStorage,
+    /// Number of quotes [`Router::quote_cached`] keeps for the current state, 0 to disable the
+    /// cache
+    pub quote_cache_capacity: usize,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            history: HistoryConfig::default(),
+            reference_token: None,
+            capture_session: false,
+            blacklisted_tokens: Vec::new(),
+            dust_tolerance: 1e-9,
+            warm_start: WarmStartPolicy::default(),
+            event_dedup_window: 1024,
+            retained_snapshots: 8,
+            solver: SolverConfig::default(),
+            solver_domain: SolverDomain::default(),
+            relaxation: 1.0,
+            acceleration: SolverAcceleration::default(),
+            sweep_mode: SweepMode::default(),
+            parallel_sweep_threshold: 2_000,
+            solver_kind: SolverKind::default(),
+            fallback: FallbackStrategy::default(),
+            price_normalization: PriceNormalization::default(),
+            fee_model: FeeModel::default(),
+            strict_math: cfg!(debug_assertions),
+            trade_size_policy: TradeSizePolicy::default(),
+            auto_settle: AutoSettle::default(),
+            staleness_threshold: 1e-3,
+            publish_deltas: false,
+            plan_depth_warning: None,
+            liquidity_storage: LiquidityStorage::default(),
+            quote_cache_capacity: 0,
+        }
+    }
+}
+
+/// Whether and how much trade history the router keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HistoryConfig {
+    /// No trade is recorded
+    #[default]
+    Disabled,
+    /// Every trade is recorded, memory grows with the number of trades
+    Unbounded,
+    /// Only the most recent `capacity` trades are kept
+    Capped(usize),
+}
+
+/// When the router settles after an event batch leaves it staler than
+/// [`RouterConfig::staleness_threshold`], so that the arbitrage opened by the events is not
+/// captured by the next trade.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AutoSettle {
+    /// Never, the next trade captures the arbitrage
+    #[default]
+    Off,
+    /// At the end of the batch, see [`Router::apply_events`]
+    Eager,
+    /// Before the next trade, so that consecutive batches are settled once
+    OnNextSolve,
+}
+
+/// Thresholds under which a token keeps its converged price across a
+/// [`Router::prepare_replacement`] or a [`Router::merge`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WarmStartPolicy {
+    /// Largest factor, up or down, by which the total liquidity of the token may change
+    pub max_liquidity_change: f64,
+    /// Smallest Jaccard index between the previous and new sets of paired tokens
+    pub min_neighbor_overlap: f64,
+}
+
+impl Default for WarmStartPolicy {
+    fn default() -> Self {
+        Self {
+            max_liquidity_change: 2.0,
+            min_neighbor_overlap: 0.5,
+        }
+    }
+}
+
+/// Convergence criterion and iteration budget of the equilibrium solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolverConfig {
+    /// The iteration stops once no square-root price `q` moves by `absolute_tolerance` plus this
+    /// relative amount of `q` or more in a sweep
+    pub tolerance: f64,
+    /// Change of a square-root price below which it counts as converged whatever its relative
+    /// change, for the prices so close to zero that their relative change stays large; zero,
+    /// leaving the relative criterion alone, by default
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub absolute_tolerance: f64,
+    /// Relative accuracy of the extracted amount at which the iteration of a trade stops, in place
+    /// of the tolerances, see [`RouterBuilder::output_accuracy`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub output_accuracy: Option<f64>,
+    /// Maximum number of sweeps, after which the iteration stops unconverged
+    pub max_iterations: usize,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            tolerance: 1e-12,
+            absolute_tolerance: 0.0,
+            output_accuracy: None,
+            max_iterations: 20_000,
+        }
+    }
+}
+
+/// How the fixed-point iteration represents the square-root prices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SolverDomain {
+    /// The prices themselves, updated as `q_u ← T_u / ∑ K(u, v) / q_v`
+    #[default]
+    Linear,
+    /// Their logarithms, updated as `log q_u ← log T_u − log ∑ exp(log K(u, v) − log q_v)` with
+    /// the log-sum-exp trick, for graphs whose price ratios span so many orders of magnitude that
+    /// the terms `K / q` of the linear update overflow or vanish; slower, every term costing an
+    /// exponential and a logarithm
+    Log,
+}
+
+/// Acceleration of the fixed-point iteration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SolverAcceleration {
+    /// Plain sweeps
+    #[default]
+    None,
+    /// Anderson mixing: every sweep is followed by the combination of the last `memory` sweeps,
+    /// typically 3 to 5, that best cancels the change of the prices, the plain sweep being kept
+    /// whenever the combination yields a price that is not positive and finite. The convergence
+    /// criterion applies to the plain sweeps, so that the equilibrium does not depend on the
+    /// acceleration
+    Anderson { memory: usize },
+}
+
+/// Method solving the equilibrium.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SolverKind {
+    /// Sweeps of fixed-point updates, see [`SolverDomain`], [`SweepMode`] and
+    /// [`SolverAcceleration`]
+    #[default]
+    FixedPoint,
+    /// Newton's method on the logarithms of the prices, each step halved until it reduces the
+    /// imbalance of the reserves: a handful of steps instead of thousands of sweeps, but each
+    /// of them factorizes a dense matrix in `O(n³)` for `n` tokens, which suits graphs of up to
+    /// a few hundred tokens. The tolerances apply to the steps as to the sweeps, the output
+    /// accuracy of [`SolverConfig`] does not. When a step fails, the fixed-point iteration
+    /// starts over from the initial prices, the reported iterations counting both
+    Newton,
+    /// Conjugate gradients on the linear system the equilibrium conditions form in the inverses
+    /// of the prices, preconditioned by the totals of the tokens: about the square root of the
+    /// number of sweeps of the fixed-point iteration, each iteration costing a sweep, and no
+    /// matrix beyond the liquidities, which suits the large, slowly converging graphs. The
+    /// tolerances apply to the plain updates at the solution, as to the sweeps; the output
+    /// accuracy of [`SolverConfig`] does not. When the iteration fails, the fixed-point
+    /// iteration starts over from the initial prices, the reported iterations counting both
+    ConjugateGradient,
+    /// Gauss–Seidel updates of the tokens of an active set only, see
+    /// [`SolveStats::token_updates`](crate::router::SolveStats::token_updates): the set starts
+    /// with the tokens whose update moves their price beyond the tolerances, the input token
+    /// alone after a trade on an equilibrated graph, and a token whose price moves beyond them
+    /// adds its neighbors, until no update does. A small trade then updates the tokens near the
+    /// traded pair only, instead of sweeping the whole component. The plain updates apply, in
+    /// the linear domain, without relaxation nor acceleration; the output accuracy of
+    /// [`SolverConfig`] does not apply, and the budget is that of the sweeps, in updates of as
+    /// many tokens
+    ActiveSet,
+}
+
+/// Order of the price updates within a sweep of the fixed-point iteration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SweepMode {
+    /// Every update reads the prices already updated in the sweep, by token index: about twice
+    /// as fast to converge as Jacobi sweeps, but the iterates depend on the numbering of the
+    /// tokens
+    #[default]
+    GaussSeidel,
+    /// Every update reads the prices of the previous sweep, all of them being replaced once the
+    /// sweep is over: the iterates do not depend on the order of the updates, which are
+    /// independent of each other. Their sums being correctly rounded, the results are the same
+    /// to the bit whatever the order of the pools and the numbering of the tokens, Anderson
+    /// acceleration excepted
+    Jacobi,
+}
+
+/// How the square-root prices `q`, defined up to a common factor within each connected
+/// component, are rescaled after every solve. Prices are reported in the reference token
+/// whatever the normalization.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PriceNormalization {
+    /// The geometric mean of the prices of every component is brought to 1, so that prices
+    /// spanning `r` orders of magnitude stay within `r` orders of magnitude of 1, whichever token
+    /// the pool list starts with
+    #[default]
+    GeometricMean,
+    /// The price of the reference token is brought to 1, in its component only: the prices of a
+    /// token worth `10⁹` reference tokens sit 4.5 orders of magnitude away from 1 in `q`,
+    /// wherever the rest of the graph lies
+    ReferenceToken,
+}
+
+/// Layout of the liquidities of the token pairs in the solver, which sweeps over the neighbors
+/// of every token. Both layouts hold the same values in the same order, so that the quotes do not
+/// depend on it, only the memory and the time they take do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LiquidityStorage {
+    /// Dense up to [`LiquidityStorage::DENSE_MAX_TOKENS`] tokens, or when more than
+    /// [`LiquidityStorage::DENSE_MIN_DENSITY`] of the pairs of tokens share a pool, sparse
+    /// otherwise; see [`Router::liquidity_layout`](crate::router::Router::liquidity_layout) for
+    /// the layout picked
+    #[default]
+    Auto,
+    /// A matrix of all `n²` pairs: no indirection, but memory quadratic in the number of tokens
+    /// and sweeps scanning every token for the neighbors of each, 200 MB and `2.5·10⁷` reads per
+    /// sweep at 5,000 tokens
+    Dense,
+    /// Adjacency lists of the pairs holding liquidity, sorted by token index: memory and sweeps
+    /// linear in the number of pairs
+    Sparse,
+}
+
+impl LiquidityStorage {
+    /// Largest number of tokens for which [`LiquidityStorage::Auto`] lays the liquidities out
+    /// densely, the matrix then fitting in 32 kB.
+    pub const DENSE_MAX_TOKENS: usize = 64;
+    /// Fraction of the pairs of tokens sharing a pool beyond which [`LiquidityStorage::Auto`]
+    /// lays the liquidities out densely: the sparse layout then takes half the memory of the
+    /// matrix or more, and the sweeps read a quarter of it or more either way.
+    pub const DENSE_MIN_DENSITY: f64 = 0.25;
+}
+
+/// Whether trades charge the swap fee of the pools, [`UniV2Pool::fee`] of their input. Charged
+/// by default, so that a trade through a single pool yields what
+/// [`UniV2Pool::get_output_amount`] of that pool does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FeeModel {
+    /// Pools trade along their constant-product curves free of charge, so that the pools of a
+    /// pair aggregate into a single edge of the token graph: the equilibrium of the fixed-point
+    /// iteration, linear in the number of pairs per sweep, but optimistic by the fee of every
+    /// pool crossed
+    FeeFree,
+    /// Every pool keeps its own fee on its input, as [`UniV2Pool::get_output_amount`] does, so
+    /// that cheaper pools draw more of a trade. A pool only trades once the price moves beyond
+    /// its fee band, so the pools of a pair may rest at different prices: trades are solved over
+    /// the individual pools, by Newton steps on smoothed fee bands within the iteration budget of
+    /// the solver configuration, each a dense linear solve in the number of tokens. Settlements
+    /// and the analyses of the token graph stay fee-free
+    #[default]
+    Charged,
+}
+
+/// What a trade does when the solver exhausts its budget without converging.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FallbackStrategy {
+    /// The trade fails with [`RouterError::DidNotConverge`]
+    #[default]
+    None,
+    /// The output amount is bisected: removing `Δ` from the output token leaves totals that admit
+    /// an equilibrium for exactly one `Δ`, on either side of which the spectral radius of the
+    /// fixed-point update, with every price free, lies on either side of 1. Its sign is
+    /// certified by the smallest and largest ratio of an update to its price, refined by damped
+    /// Jacobi sweeps until they both lie on the same side of 1. Every sign costs sweeps, but the
+    /// bracket of the output only shrinks, and the trade takes its middle once its width falls
+    /// within the tolerance of [`SolverConfig`], or the amount at which the sign is lost in
+    /// rounding. The bisection may take up to 100 times [`SolverConfig::max_iterations`]
+    /// sweeps, failing as without fallback beyond. Its sweeps converge no faster than those of
+    /// the solver, but they bracket the output instead of approaching it from one side, which
+    /// on slowly converging graphs brings it closer to the equilibrium than the solver at the
+    /// same number of sweeps
+    Bisection,
+}
+
+/// Solver configuration applying to the trades up to a given size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TradeSizeBand {
+    /// Largest input amount, relative to the reserve of the input token in the equilibrium, the
+    /// band applies to
+    pub max_size_ratio: f64,
+    pub solver: SolverConfig,
+}
+
+/// Which trade size band a trade is solved with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BandSelection {
+    /// Every trade is solved with [`RouterConfig::solver`]
+    #[default]
+    Disabled,
+    /// Each trade is solved with the first band its size ratio fits in, the last band if none
+    Automatic,
+    /// Every trade is solved with the band at this position
+    Pinned(usize),
+}
+
+/// Solver upgrades by trade size, to keep the relative error of the output flat across sizes.
+///
+/// The output of a trade is the difference between two totals of the output token, so a given
+/// residual translates into a relative output error roughly inversely proportional to the size
+/// of the trade. The default bands hence tighten the tolerance of small trades, and give large
+/// trades, which move prices further from the warm start, a larger iteration budget.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TradeSizePolicy {
+    pub selection: BandSelection,
+    /// Bands by increasing `max_size_ratio`
+    pub bands: Vec<TradeSizeBand>,
+}
+
+impl Default for TradeSizePolicy {
+    fn default() -> Self {
+        let band = |max_size_ratio, tolerance, max_iterations| TradeSizeBand {
+            max_size_ratio,
+            solver: SolverConfig {
+                tolerance,
+                absolute_tolerance: 0.0,
+                output_accuracy: None,
+                max_iterations,
+            },
+        };
+        Self {
+            selection: BandSelection::Disabled,
+            bands: vec![
+                band(1e-3, 1e-14, 50_000),
+                band(5e-2, 1e-12, 20_000),
+                band(1.0, 1e-11, 50_000),
+            ],
+        }
+    }
+}
+
+impl TradeSizePolicy {
+    /// Returns the position of the band applying to a trade of `size_ratio`, `None` when the
+    /// policy is disabled or has no band.
+    pub fn band(&self, size_ratio: f64) -> Option<usize> {
+        match self.selection {
+            BandSelection::Disabled => None,
+            BandSelection::Pinned(band) => Some(band),
+            BandSelection::Automatic => {
+                let last = self.bands.len().checked_sub(1)?;
+                Some(
+                    self.bands
+                        .iter()
+                        .position(|band| size_ratio <= band.max_size_ratio)
+                        .unwrap_or(last),
+                )
+            }
+        }
+    }
+
+    /// Checks that the bands are sorted and that a pinned band exists.
+    pub(super) fn validate(&self) -> Result<(), RouterError> {
+        if !self
+            .bands
+            .is_sorted_by(|band, next| band.max_size_ratio < next.max_size_ratio)
+        {
+            return Err(RouterError::InvalidConfig(
+                "trade size bands are not sorted by increasing size ratio".to_string(),
+            ));
+        }
+        if let BandSelection::Pinned(band) = self.selection
+            && band >= self.bands.len()
+        {
+            return Err(RouterError::InvalidConfig(format!(
+                "pinned trade size band {band} out of {}",
+                self.bands.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Builder for [`Router`], for callers that need more than the defaults of [`Router::new`].
+#[derive(Debug, Clone)]
+pub struct RouterBuilder {
+    pools: Vec<UniV2Pool>,
+    config: RouterConfig,
+}
+
+impl RouterBuilder {
+    pub(super) fn new(pools: Vec<UniV2Pool>) -> Self {
+        Self {
+            pools,
+            config: RouterConfig::default(),
+        }
+    }
+
+    /// Replaces the whole configuration.
+    pub fn config(mut self, config: RouterConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Records every successful trade, see [`Router::history`].
+    pub fn record_history(mut self) -> Self {
+        self.config.history = HistoryConfig::Unbounded;
+        self
+    }
+
+    /// Records successful trades, keeping only the most recent `capacity` ones.
+    pub fn history_capacity(mut self, capacity: usize) -> Self {
+        self.config.history = HistoryConfig::Capped(capacity);
+        self
+    }
+
+    /// Keeps a [`SessionCapture`](crate::router::SessionCapture) of the construction and of every trade, for later replay.
+    pub fn capture_session(mut self) -> Self {
+        self.config.capture_session = true;
+        self
+    }
+
+    /// Publishes a [`StateDelta`](crate::router::StateDelta) for every committed operation, to
+    /// keep [`ReplicaRouter`](crate::router::ReplicaRouter)s up to date.
+    pub fn publish_deltas(mut self) -> Self {
+        self.config.publish_deltas = true;
+        self
+    }
+
+    /// Excludes `token` from routing from the start, see [`Router::blacklist_token`].
+    pub fn blacklist_token(mut self, token: &str) -> Self {
+        if !self
+            .config
+            .blacklisted_tokens
+            .iter()
+            .any(|name| name == token)
+        {
+            self.config.blacklisted_tokens.push(token.to_string());
+        }
+        self
+    }
+
+    /// Sets the fraction of the output token reserve below which extracted amounts are treated as
+    /// floating-point noise, see [`RouterConfig::dust_tolerance`].
+    pub fn dust_tolerance(mut self, dust_tolerance: f64) -> Self {
+        self.config.dust_tolerance = dust_tolerance;
+        self
+    }
+
+    /// Sets when prices carry over to a replaced or merged state.
+    pub fn warm_start(mut self, policy: WarmStartPolicy) -> Self {
+        self.config.warm_start = policy;
+        self
+    }
+
+    /// Sets how many recent event keys are remembered for deduplication, see
+    /// [`Router::apply_events`].
+    pub fn event_dedup_window(mut self, keys: usize) -> Self {
+        self.config.event_dedup_window = keys;
+        self
+    }
+
+    /// Sets how many pre-batch snapshots are kept for event rollbacks.
+    pub fn retained_snapshots(mut self, snapshots: usize) -> Self {
+        self.config.retained_snapshots = snapshots;
+        self
+    }
+
+    /// Sets the solver configuration of settlements and of the trades no trade size band applies
+    /// to.
+    pub fn solver(mut self, solver: SolverConfig) -> Self {
+        self.config.solver = solver;
+        self
+    }
+
+    /// Sets the relative and absolute convergence thresholds of settlements and of the trades no
+    /// trade size band applies to, see [`SolverConfig::tolerance`].
+    pub fn tolerances(mut self, relative: f64, absolute: f64) -> Self {
+        self.config.solver.tolerance = relative;
+        self.config.solver.absolute_tolerance = absolute;
+        self
+    }
+
+    /// Stops the iteration of the trades no trade size band applies to once their output is
+    /// estimated within the relative `accuracy` of its limit, instead of once the prices meet the
+    /// tolerances.
+    ///
+    /// The error of an output is the residual of the prices divided by the size of the trade
+    /// relative to the reserves, so a fixed tolerance converges some trades further than their
+    /// output needs and stops others short of it. The remaining error is extrapolated from the
+    /// last change of the output and the rate at which the changes shrink, averaged over the last
+    /// eight sweeps; the iteration goes on while no such rate shows or the changes do not shrink,
+    /// and the tolerances decide once the output stops changing in floating point. The output
+    /// being the difference of the reserves of the output token before and after the trade, its
+    /// accuracy cannot be finer than the rounding of those reserves relative to it.
+    ///
+    /// Settlements keep the tolerances, their prices being the starting point of every later
+    /// trade, and the prices committed by a trade stopped early carry their residual over to the
+    /// next trade.
+    pub fn output_accuracy(mut self, accuracy: f64) -> Self {
+        self.config.solver.output_accuracy = Some(accuracy);
+        self
+    }
+
+    /// Sets how the fixed-point iteration represents the prices, see [`SolverDomain`].
+    pub fn solver_domain(mut self, domain: SolverDomain) -> Self {
+        self.config.solver_domain = domain;
+        self
+    }
+
+    /// Sets the relaxation factor `ω` of the fixed-point update, `q ← (1 − ω) · q + ω · q̂` with
+    /// `q̂` the Gauss–Seidel update, in `(0, 2)`; 1, the plain update, by default.
+    ///
+    /// The equilibrium does not depend on `ω`, only the number of sweeps reaching it does.
+    /// Every update moves a price towards a weighted average of its neighbors, so the plain
+    /// sweeps approach the equilibrium monotonically instead of oscillating around it: damping,
+    /// `ω < 1`, only slows them down, while moderate over-relaxation shortens the slow approach
+    /// of loosely connected graphs, `ω = 1.3` saving about 40% of the sweeps on sparse graphs.
+    /// Larger factors overshoot into divergence, stopping the solve with
+    /// [`RouterError::InvalidPrice`], all the more from prices far from the equilibrium such as
+    /// those of a router never settled. In the log domain, see [`SolverDomain`], the relaxation
+    /// applies to the logarithms of the prices.
+    pub fn relaxation(mut self, omega: f64) -> Self {
+        self.config.relaxation = omega;
+        self
+    }
+
+    /// Sets the acceleration of the fixed-point iteration, see [`SolverAcceleration`].
+    pub fn acceleration(mut self, acceleration: SolverAcceleration) -> Self {
+        self.config.acceleration = acceleration;
+        self
+    }
+
+    /// Sets the order of the price updates within a sweep, see [`SweepMode`].
+    pub fn sweep_mode(mut self, sweep_mode: SweepMode) -> Self {
+        self.config.sweep_mode = sweep_mode;
+        self
+    }
+
+    /// Sets the number of tokens of a connected component from which the updates of its Jacobi
+    /// sweeps are computed in parallel, with the `parallel` feature. Gauss–Seidel sweeps are
+    /// always sequential, each update reading the previous ones.
+    pub fn parallel_sweep_threshold(mut self, threshold: usize) -> Self {
+        self.config.parallel_sweep_threshold = threshold;
+        self
+    }
+
+    /// Sets the method solving the equilibrium, see [`SolverKind`].
+    pub fn solver_kind(mut self, kind: SolverKind) -> Self {
+        self.config.solver_kind = kind;
+        self
+    }
+
+    /// Sets what a trade does when the solver exhausts its budget, see [`FallbackStrategy`].
+    pub fn fallback(mut self, fallback: FallbackStrategy) -> Self {
+        self.config.fallback = fallback;
+        self
+    }
+
+    /// Sets whether the solves check every denominator of the fixed-point update finite, failing
+    /// with [`RouterError::NumericalError`] before anything is applied otherwise; on in debug
+    /// builds by default, at the cost of one branch per update.
+    ///
+    /// The updated prices and the output are checked whatever this flag, an update that is not
+    /// positive and finite failing with [`RouterError::InvalidPrice`], and an output that is not
+    /// finite with [`RouterError::NumericalError`]. The flag catches the intermediate values
+    /// first, naming the token whose sum overflowed or turned to NaN.
+    pub fn strict_math(mut self, strict_math: bool) -> Self {
+        self.config.strict_math = strict_math;
+        self
+    }
+
+    /// Sets the solver upgrades by trade size, see [`TradeSizePolicy`].
+    pub fn trade_size_policy(mut self, policy: TradeSizePolicy) -> Self {
+        self.config.trade_size_policy = policy;
+        self
+    }
+
+    /// Settles the router according to `mode` whenever an event batch leaves it staler than
+    /// `staleness_threshold`, see [`AutoSettle`].
+    pub fn auto_settle(mut self, mode: AutoSettle, staleness_threshold: f64) -> Self {
+        self.config.auto_settle = mode;
+        self.config.staleness_threshold = staleness_threshold;
+        self
+    }
+
+    /// Logs a warning for every swap of an execution plan giving a pool more input than its depth
+    /// at the price impact `max_impact`, a fraction in `(0, 1)`, see
+    /// [`RouterConfig::plan_depth_warning`].
+    pub fn warn_plan_depth(mut self, max_impact: f64) -> Self {
+        self.config.plan_depth_warning = Some(max_impact);
+        self
+    }
+
+    /// Sets the layout of the liquidities of the token pairs, see [`LiquidityStorage`].
+    pub fn liquidity_storage(mut self, storage: LiquidityStorage) -> Self {
+        self.config.liquidity_storage = storage;
+        self
+    }
+
+    /// Caches up to `capacity` quotes of [`Router::quote_cached`] until the next change of state,
+    /// see [`RouterConfig::quote_cache_capacity`].
+    pub fn quote_cache_capacity(mut self, capacity: usize) -> Self {
+        self.config.quote_cache_capacity = capacity;
+        self
+    }
+
+    /// Sets how the prices are rescaled after every solve, see [`PriceNormalization`].
+    pub fn price_normalization(mut self, normalization: PriceNormalization) -> Self {
+        self.config.price_normalization = normalization;
+        self
+    }
+
+    /// Sets whether trades charge the swap fee of the pools, see [`FeeModel`].
+    pub fn fee_model(mut self, fee_model: FeeModel) -> Self {
+        self.config.fee_model = fee_model;
+        self
+    }
+
+    /// Pins the numéraire of the reported prices, see [`Router::set_reference_token`].
+    pub fn reference_token(mut self, token: &str) -> Self {
+        self.config.reference_token = Some(token.to_string());
+        self
+    }
+
+    /// Builds the router, failing if the configuration names a token absent from the pools or
+    /// its trade size policy is inconsistent.
+    pub fn build<'l>(self) -> Result<Router<'l>, RouterError> {
+        Router::from_config(self.pools, self.config)
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    error::RouterError,
+    router::{Router, RouterConfig},
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+use std::collections::HashMap;
+
+/// Version of the capture layout, bumped whenever a field changes meaning.
+pub const CAPTURE_FORMAT_VERSION: u32 = 2;
+
+/// Everything needed to re-execute a router session faithfully: the configuration, the pools as
+/// they were at construction, and every subsequent operation in order, solves carrying the output
+/// the capturing build returned.
+///
+/// Amounts are plain `f64`s: serialized with a shortest round-trip representation (as
+/// `serde_json` does), they are restored bit-for-bit.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionCapture {
+    pub format_version: u32,
+    /// Version of the crate that produced the recorded outputs
+    pub crate_version: String,
+    pub config: RouterConfig,
+    pub pools: Vec<CapturedPool>,
+    pub operations: Vec<CapturedOperation>,
+}
+
+/// A state change of the session. Operations undone by an event rollback are dropped from the
+/// capture along with their effect.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapturedOperation {
+    Solve(CapturedSolve),
+    AddPool(CapturedPool),
+    RemovePool {
+        pool: PoolId,
+    },
+    UpdateReserves {
+        pool: PoolId,
+        reserve0: f64,
+        reserve1: f64,
+    },
+    SetBlacklisted {
+        token: String,
+        blacklisted: bool,
+    },
+    Compact,
+    Settle,
+    /// Pools of a [`Router::replace`], with the identifiers they were given
+    Replace {
+        pools: Vec<CapturedPool>,
+    },
+    /// Pools of a [`Router::merge`], with the identifiers they were given
+    Merge {
+        pools: Vec<CapturedPool>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapturedPool {
+    /// Identifier the capturing router assigned to the pool
+    pub id: PoolId,
+    pub token0: String,
+    pub token1: String,
+    pub reserve0: f64,
+    pub reserve1: f64,
+    /// Fee of the pool, see [`UniV2Pool::fee`]; the default fee in captures that predate it
+    #[cfg_attr(feature = "serde", serde(default = "default_fee"))]
+    pub fee: f64,
+    /// Virtual reserves of the pool, see [`UniV2Pool::virtual_reserves`]; none in captures that
+    /// predate them
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub virtual_reserves: (f64, f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapturedSolve {
+    /// Position of the solve in the session
+    pub id: u64,
+    pub input_token: String,
+    pub output_token: String,
+    pub input_amount: f64,
+    /// Hop limit of the solve, see [`Router::solve_with_max_hops`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_hops: Option<usize>,
+    pub output_amount: f64,
+}
+
+impl SessionCapture {
+    pub(super) fn new(pools: &[UniV2Pool], pool_ids: &[PoolId], config: &RouterConfig) -> Self {
+        Self {
+            format_version: CAPTURE_FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            config: config.clone(),
+            pools: pool_ids.iter().zip(pools).map(CapturedPool::from).collect(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Iterates over the captured solves, in order.
+    pub fn solves(&self) -> impl Iterator<Item = &CapturedSolve> {
+        self.operations
+            .iter()
+            .filter_map(|operation| match operation {
+                CapturedOperation::Solve(solve) => Some(solve),
+                _ => None,
+            })
+    }
+
+    pub(super) fn record(
+        &mut self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+        max_hops: Option<usize>,
+        output_amount: f64,
+    ) {
+        let id = self.solves().count() as u64;
+        self.operations
+            .push(CapturedOperation::Solve(CapturedSolve {
+                id,
+                input_token: input_token.to_string(),
+                output_token: output_token.to_string(),
+                input_amount,
+                max_hops,
+                output_amount,
+            }));
+    }
+
+    /// Records a change of the pool set or of the reserves.
+    pub(super) fn push(&mut self, operation: CapturedOperation) {
+        self.operations.push(operation);
+    }
+
+    /// Drops the operations recorded after the first `len` ones, when the state is restored from
+    /// an older snapshot.
+    pub(super) fn truncate(&mut self, len: usize) {
+        self.operations.truncate(len);
+    }
+
+    /// Re-executes the session with the current build and returns the output of every solve, in
+    /// order.
+    ///
+    /// Pools borrow their token names for `'static`, so the distinct token names of the capture
+    /// are leaked once per call; replays are meant for one-shot tools, not hot loops.
+    pub fn replay(&self) -> Result<Vec<f64>, RouterError> {
+        self.run().map(|(_, outputs)| outputs)
+    }
+
+    /// Re-executes the session with the current build and returns the router in its final
+    /// state, which makes the capture usable as a snapshot of the router. Token names are leaked
+    /// as by [`SessionCapture::replay`].
+    pub fn restore(&self) -> Result<Router<'static>, RouterError> {
+        self.run().map(|(router, _)| router)
+    }
+
+    fn run(&self) -> Result<(Router<'static>, Vec<f64>), RouterError> {
+        let mut names = HashMap::<&str, &'static str>::new();
+        let added_pools = self
+            .operations
+            .iter()
+            .flat_map(|operation| match operation {
+                CapturedOperation::AddPool(pool) => std::slice::from_ref(pool),
+                CapturedOperation::Replace { pools } | CapturedOperation::Merge { pools } => pools,
+                _ => &[],
+            });
+        for pool in self.pools.iter().chain(added_pools) {
+            for name in [&pool.token0, &pool.token1] {
+                names
+                    .entry(name)
+                    .or_insert_with(|| Box::leak(name.clone().into_boxed_str()));
+            }
+        }
+        let to_pool = |pool: &CapturedPool| {
+            UniV2Pool::new(
+                names[pool.token0.as_str()],
+                names[pool.token1.as_str()],
+                pool.reserve0,
+                pool.reserve1,
+            )
+            .with_fee(pool.fee)
+            .with_virtual_reserves(pool.virtual_reserves.0, pool.virtual_reserves.1)
+            .with_id(pool.id.clone())
+        };
+
+        let mut router = Router::builder(self.pools.iter().map(to_pool).collect::<Vec<_>>())
+            .config(self.config.clone())
+            .build()?;
+        let mut outputs = Vec::new();
+        for operation in &self.operations {
+            match operation {
+                CapturedOperation::Solve(solve) => outputs.push(match solve.max_hops {
+                    Some(max_hops) => router.solve_with_max_hops(
+                        &solve.input_token,
+                        &solve.output_token,
+                        solve.input_amount,
+                        max_hops,
+                    )?,
+                    None => {
+                        router.solve(&solve.input_token, &solve.output_token, solve.input_amount)?
+                    }
+                }),
+                CapturedOperation::AddPool(pool) => {
+                    router.add_pool(to_pool(pool))?;
+                }
+                CapturedOperation::RemovePool { pool } => {
+                    router.remove_pool(pool)?;
+                }
+                CapturedOperation::UpdateReserves {
+                    pool,
+                    reserve0,
+                    reserve1,
+                } => router.update_pool_reserves(pool, *reserve0, *reserve1)?,
+                CapturedOperation::SetBlacklisted {
+                    token,
+                    blacklisted: true,
+                } => router.blacklist_token(token)?,
+                CapturedOperation::SetBlacklisted {
+                    token,
+                    blacklisted: false,
+                } => router.lift_blacklist(token)?,
+                CapturedOperation::Compact => {
+                    router.compact();
+                }
+                CapturedOperation::Settle => {
+                    router.settle();
+                }
+                CapturedOperation::Replace { pools } => {
+                    let prepared = router
+                        .prepare_replacement(pools.iter().map(to_pool).collect::<Vec<_>>())?;
+                    router.replace(prepared);
+                }
+                CapturedOperation::Merge { pools } => {
+                    router.merge(pools.iter().map(to_pool).collect::<Vec<_>>())?;
+                }
+            }
+        }
+        Ok((router, outputs))
+    }
+}
+
+impl From<(&PoolId, &UniV2Pool)> for CapturedPool {
+    fn from((id, pool): (&PoolId, &UniV2Pool)) -> Self {
+        Self {
+            id: id.clone(),
+            token0: pool.token0.to_string(),
+            token1: pool.token1.to_string(),
+            reserve0: pool.reserve0,
+            reserve1: pool.reserve1,
+            fee: pool.fee,
+            virtual_reserves: pool.virtual_reserves,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_fee() -> f64 {
+    UniV2Pool::FEE
+}
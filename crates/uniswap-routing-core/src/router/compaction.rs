@@ -0,0 +1,175 @@
+use crate::{
+    error::RouterError,
+    router::{Router, capture::CapturedOperation},
+};
+
+use std::{collections::HashSet, fmt};
+
+/// Handle on a token of a [`Router`], valid until the next [`Router::compact`].
+///
+/// The handle carries the generation of the index layout it was issued under, so that a handle
+/// outliving a compaction is rejected instead of silently designating another token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenId {
+    index: usize,
+    generation: u32,
+}
+
+impl TokenId {
+    /// Position of the token in the router, in the layout of its generation.
+    pub fn index(self) -> usize {
+        self.index
+    }
+
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for TokenId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.index, self.generation)
+    }
+}
+
+/// Old to new token ids after a [`Router::compact`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionMap<'l> {
+    pub previous_generation: u32,
+    pub generation: u32,
+    /// New index of every token of the previous layout, `None` for the removed ones
+    new_indices: Vec<Option<usize>>,
+    /// Tokens dropped by the compaction, in their previous index order
+    pub removed: Vec<&'l str>,
+}
+
+impl CompactionMap<'_> {
+    /// Translates an id of the previous generation, `None` if its token was removed.
+    pub fn remap(&self, id: TokenId) -> Result<Option<TokenId>, RouterError> {
+        if id.generation != self.previous_generation {
+            return Err(RouterError::StaleTokenId {
+                id,
+                generation: self.previous_generation,
+            });
+        }
+        let new_index = self
+            .new_indices
+            .get(id.index)
+            .ok_or_else(|| RouterError::UnknownToken(id.to_string()))?;
+        Ok(new_index.map(|index| TokenId {
+            index,
+            generation: self.generation,
+        }))
+    }
+
+    /// Iterates over the `(old, new)` ids of the tokens kept by the compaction.
+    pub fn iter(&self) -> impl Iterator<Item = (TokenId, TokenId)> + '_ {
+        self.new_indices
+            .iter()
+            .enumerate()
+            .filter_map(|(old_index, new_index)| {
+                let old = TokenId {
+                    index: old_index,
+                    generation: self.previous_generation,
+                };
+                new_index.map(|index| {
+                    let new = TokenId {
+                        index,
+                        generation: self.generation,
+                    };
+                    (old, new)
+                })
+            })
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Returns the id of `token` in the current index layout.
+    pub fn token_id(&self, token: &str) -> Result<TokenId, RouterError> {
+        Ok(TokenId {
+            index: self.index_of(token)?,
+            generation: self.generation,
+        })
+    }
+
+    /// Resolves an id issued by [`Router::token_id`], failing if a compaction happened since.
+    pub fn token_name(&self, id: TokenId) -> Result<&'l str, RouterError> {
+        if id.generation != self.generation {
+            return Err(RouterError::StaleTokenId {
+                id,
+                generation: self.generation,
+            });
+        }
+        self.tokens
+            .get(id.index)
+            .copied()
+            .ok_or_else(|| RouterError::UnknownToken(id.to_string()))
+    }
+
+    /// Drops the tokens no pool references anymore, typically after [`Router::remove_pool`], and
+    /// renumbers the others in their previous relative order.
+    ///
+    /// Token indices are otherwise never reused nor reordered, so this is the only operation
+    /// invalidating [`TokenId`]s: it starts a new generation, and the returned map translates the
+    /// ids of the previous one. If the reference token is removed, prices fall back to being
+    /// reported in the first remaining token.
+    pub fn compact(&mut self) -> CompactionMap<'l> {
+        let referenced = self
+            .pools
+            .iter()
+            .flat_map(|pool| [self.token_index[pool.token0], self.token_index[pool.token1]])
+            .collect::<HashSet<_>>();
+
+        let mut new_indices = Vec::with_capacity(self.tokens.len());
+        let mut tokens = Vec::with_capacity(referenced.len());
+        let mut removed = Vec::new();
+        for (index, &token) in self.tokens.iter().enumerate() {
+            match referenced.contains(&index) {
+                true => {
+                    new_indices.push(Some(tokens.len()));
+                    tokens.push(token);
+                }
+                false => {
+                    new_indices.push(None);
+                    removed.push(token);
+                }
+            }
+        }
+
+        let reference_token = self.token_graph.reference_token();
+        if new_indices[reference_token].is_none() {
+            log::warn!(
+                "compaction removed the reference token {}, falling back to the first token",
+                self.tokens[reference_token]
+            );
+            self.config.reference_token = None;
+        }
+        self.config
+            .blacklisted_tokens
+            .retain(|token| !removed.contains(&token.as_str()));
+        self.token_graph.compact(&new_indices);
+        self.token_index = tokens
+            .iter()
+            .enumerate()
+            .map(|(index, &token)| (token, index))
+            .collect();
+        self.tokens = tokens;
+        self.apply_blacklist();
+
+        if let Some(capture) = &mut self.capture {
+            capture.push(CapturedOperation::Compact);
+        }
+        let previous_generation = self.generation;
+        self.latest_generation += 1;
+        self.generation = self.latest_generation;
+        self.publish_version();
+
+        CompactionMap {
+            previous_generation,
+            generation: self.generation,
+            new_indices,
+            removed,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::router::{Router, token_graph::TokenGraph};
+
+/// Components whose estimated spectral radius exceeds this value are flagged as slow-converging:
+/// the error of the fixed-point iteration then shrinks by less than 0.1% per sweep.
+const SLOW_CONVERGENCE_THRESHOLD: f64 = 0.999;
+
+/// Number of edges reported per component as most responsible for slow convergence.
+const CRITICAL_EDGES: usize = 3;
+
+const POWER_ITERATION_TOLERANCE: f64 = 1e-12;
+const POWER_ITERATION_MAX_ITERS: usize = 100_000;
+
+/// Convergence outlook of the equilibrium solver, per connected component of the token graph.
+#[derive(Debug, Clone)]
+pub struct ConditioningReport<'l> {
+    pub components: Vec<ComponentConditioning<'l>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ComponentConditioning<'l> {
+    /// Tokens of the component, in index order
+    pub tokens: Vec<&'l str>,
+    /// Token held fixed for the estimate, as the output token of a trade would be
+    pub pinned_token: &'l str,
+    /// Estimated spectral radius of the linearized fixed-point map: the factor by which the
+    /// solver error shrinks per sweep, asymptotically
+    pub spectral_radius: f64,
+    /// Whether `spectral_radius` is close enough to 1 for the solver to need many sweeps
+    pub slow_converging: bool,
+    /// Edges whose extra liquidity would reduce the spectral radius the most, most helpful first
+    pub critical_edges: Vec<EdgeSensitivity<'l>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EdgeSensitivity<'l> {
+    pub token_a: &'l str,
+    pub token_b: &'l str,
+    /// Geometric liquidity `K(a, b)` of the edge
+    pub liquidity: f64,
+    /// Elasticity `K · ∂ρ/∂K` of the spectral radius to the edge liquidity, negative when adding
+    /// liquidity speeds convergence up
+    pub sensitivity: f64,
+}
+
+impl<'l> Router<'l> {
+    /// Predicts how hard each connected component is for the equilibrium solver, around the
+    /// current prices.
+    ///
+    /// In log-prices `x = ln q`, the update `q_u ← T_u / Σ_v K(u, v) / q_v` linearizes to
+    /// `δx_u ← Σ_v W(u, v) δx_v` with `W(u, v) = K(u, v) / (q_v · D_u)` and
+    /// `D_u = Σ_v K(u, v) / q_v`. Each row of `W` sums to one, the scale invariance of the
+    /// prices; pinning one token (as the output token is during a trade) removes it, and the
+    /// spectral radius `ρ` of the remaining non-negative matrix is the asymptotic error reduction
+    /// per sweep. It is estimated by power iteration on the sparse structure, pinning the
+    /// best-connected token of the component.
+    ///
+    /// The sensitivity of `ρ` to each edge follows from the right and left Perron vectors `x`
+    /// and `y`: `∂ρ/∂K(u, v) = (yᵀ ∂W x) / (yᵀ x)`, which singles out low-liquidity bridges
+    /// between otherwise well-connected clusters.
+    pub fn conditioning_report(&self) -> ConditioningReport<'l> {
+        let components = self
+            .token_graph
+            .connected_components()
+            .into_iter()
+            .map(|component| self.component_conditioning(component))
+            .collect();
+
+        ConditioningReport { components }
+    }
+
+    fn component_conditioning(&self, component: Vec<usize>) -> ComponentConditioning<'l> {
+        let graph = &self.token_graph;
+        let pinned = component
+            .iter()
+            .copied()
+            .max_by(|&a, &b| {
+                let degree = |token| graph.neighbors(token).count();
+                degree(a).cmp(&degree(b)).then(b.cmp(&a))
+            })
+            .expect("components are never empty");
+
+        let linearization = Linearization::new(graph, &component, pinned);
+        let (spectral_radius, right) = linearization.perron(false);
+        let (_, left) = linearization.perron(true);
+        let normalization = (0..graph.len()).map(|t| left[t] * right[t]).sum::<f64>();
+
+        let mut critical_edges = graph
+            .sorted_edges()
+            .into_iter()
+            .filter(|&(u, _, _)| linearization.contains(u))
+            .map(|(u, v, liquidity)| {
+                let derivative = linearization.row_derivative(u, v, spectral_radius, &left, &right)
+                    + linearization.row_derivative(v, u, spectral_radius, &left, &right);
+                let sensitivity = match normalization > 0.0 {
+                    true => liquidity * derivative / normalization,
+                    false => 0.0,
+                };
+                EdgeSensitivity {
+                    token_a: self.tokens[u],
+                    token_b: self.tokens[v],
+                    liquidity,
+                    sensitivity,
+                }
+            })
+            .collect::<Vec<_>>();
+        critical_edges.sort_by(|a, b| a.sensitivity.total_cmp(&b.sensitivity));
+        critical_edges.truncate(CRITICAL_EDGES);
+
+        ComponentConditioning {
+            tokens: component.iter().map(|&token| self.tokens[token]).collect(),
+            pinned_token: self.tokens[pinned],
+            spectral_radius,
+            slow_converging: spectral_radius > SLOW_CONVERGENCE_THRESHOLD,
+            critical_edges,
+        }
+    }
+}
+
+/// Sparse linearization `W` of the fixed-point map over one component, the pinned token removed.
+struct Linearization<'g> {
+    graph: &'g TokenGraph,
+    /// Tokens of the component, pinned token excluded
+    free_tokens: Vec<usize>,
+    in_component: Vec<bool>,
+    pinned: usize,
+    /// `D_u = Σ_v K(u, v) / q_v`, indexed by token
+    denominators: Vec<f64>,
+}
+
+impl<'g> Linearization<'g> {
+    fn new(graph: &'g TokenGraph, component: &[usize], pinned: usize) -> Self {
+        let mut in_component = vec![false; graph.len()];
+        let mut denominators = vec![0.0; graph.len()];
+        for &token in component {
+            in_component[token] = true;
+            denominators[token] = graph
+                .sorted_neighbors(token)
+                .into_iter()
+                .map(|(paired_token, liquidity)| liquidity / graph.price(paired_token))
+                .sum();
+        }
+
+        Self {
+            graph,
+            free_tokens: component.iter().copied().filter(|&t| t != pinned).collect(),
+            in_component,
+            pinned,
+            denominators,
+        }
+    }
+
+    fn contains(&self, token: usize) -> bool {
+        self.in_component[token]
+    }
+
+    fn weight(&self, u: usize, v: usize, liquidity: f64) -> f64 {
+        liquidity / (self.graph.price(v) * self.denominators[u])
+    }
+
+    /// Computes `W x` (or `Wᵀ x` when `transposed`), the pinned entry being forced to zero.
+    fn apply(&self, x: &[f64], transposed: bool) -> Vec<f64> {
+        let mut result = vec![0.0; x.len()];
+        for &u in &self.free_tokens {
+            for (v, liquidity) in self.graph.sorted_neighbors(u) {
+                if v == self.pinned {
+                    continue;
+                }
+                match transposed {
+                    false => result[u] += self.weight(u, v, liquidity) * x[v],
+                    true => result[v] += self.weight(u, v, liquidity) * x[u],
+                }
+            }
+        }
+        result
+    }
+
+    /// Power iteration on `(W + I) / 2`, which shares the Perron vector of `W` but has a strictly
+    /// dominant eigenvalue even on bipartite graphs. Returns `(ρ, vector)`.
+    fn perron(&self, transposed: bool) -> (f64, Vec<f64>) {
+        let mut vector = vec![0.0; self.graph.len()];
+        if self.free_tokens.is_empty() {
+            return (0.0, vector);
+        }
+        for &token in &self.free_tokens {
+            vector[token] = 1.0;
+        }
+
+        let mut radius = 0.0;
+        for _ in 0..POWER_ITERATION_MAX_ITERS {
+            let applied = self.apply(&vector, transposed);
+            let shifted = vector
+                .iter()
+                .zip(&applied)
+                .map(|(x, wx)| 0.5 * (x + wx))
+                .collect::<Vec<_>>();
+            let norm = shifted.iter().fold(0.0, |max: f64, x| max.max(x.abs()));
+            if norm == 0.0 {
+                return (0.0, vector);
+            }
+            radius = 2.0 * norm - 1.0;
+            let updated = shifted.into_iter().map(|x| x / norm).collect::<Vec<_>>();
+            // The radius settles long before the vector does, which the sensitivities rely on
+            let change = updated
+                .iter()
+                .zip(&vector)
+                .fold(0.0, |max: f64, (new, old)| max.max((new - old).abs()));
+            vector = updated;
+            if change < POWER_ITERATION_TOLERANCE {
+                break;
+            }
+        }
+        (radius.max(0.0), vector)
+    }
+
+    /// Contribution of row `u` of `W` to `yᵀ (∂W / ∂K(u, v)) x`.
+    ///
+    /// Differentiating `W(u, w) = K(u, w) / (q_w · D_u)` gives
+    /// `∂W(u, ·) x / ∂K(u, v) = (x_v − (W x)_u) / (q_v · D_u)`, with `(W x)_u = ρ x_u`.
+    fn row_derivative(&self, u: usize, v: usize, radius: f64, left: &[f64], right: &[f64]) -> f64 {
+        if u == self.pinned {
+            return 0.0;
+        }
+        let x_v = match v == self.pinned {
+            true => 0.0,
+            false => right[v],
+        };
+        left[u] * (x_v - radius * right[u]) / (self.graph.price(v) * self.denominators[u])
+    }
+}
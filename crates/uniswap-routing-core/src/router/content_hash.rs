@@ -0,0 +1,34 @@
+use crate::router::Router;
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+impl Router<'_> {
+    /// Returns a fingerprint of the state that determines the answers of the router: tokens,
+    /// pools and their identifiers, prices, totals and aggregated liquidities, blacklist,
+    /// reference token, configuration and pending settlement. Floats are hashed bit for bit.
+    ///
+    /// The trade history, the session capture, the event journal and the solve diagnostics are
+    /// left out, so that operations detected as no-ops leave the fingerprint unchanged. It is
+    /// stable within a build only, the hashing algorithm being the one of the standard library.
+    pub fn content_hash(&self) -> u64 {
+        let mut state = DefaultHasher::new();
+        self.tokens.hash(&mut state);
+        for pool in &self.pools {
+            (pool.token0, pool.token1).hash(&mut state);
+            (pool.reserve0.to_bits(), pool.reserve1.to_bits()).hash(&mut state);
+            let (virtual0, virtual1) = pool.virtual_reserves;
+            (virtual0.to_bits(), virtual1.to_bits()).hash(&mut state);
+        }
+        self.pool_ids.hash(&mut state);
+        self.next_pool_id.hash(&mut state);
+        self.token_graph.hash_content(&mut state);
+        // The configuration holds floats, whose debug output round-trips
+        format!("{:?}", self.config).hash(&mut state);
+        self.generation.hash(&mut state);
+        self.settle_pending.hash(&mut state);
+        self.initial_arbitrage_value
+            .map(f64::to_bits)
+            .hash(&mut state);
+        state.finish()
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{
+    error::RouterError,
+    router::{Router, check_trade_amount},
+};
+
+impl Router<'_> {
+    /// Returns the output amount [`Router::solve`] would return for each of `amounts`, in the
+    /// same order, without modifying the router.
+    ///
+    /// Every amount is solved from the current state, but the amounts are processed by
+    /// increasing size, each solve starting from the prices of the previous one instead of the
+    /// current ones: along a sweep of closely spaced amounts, the previous equilibrium is closer
+    /// than the untraded one and the solves need fewer sweeps, about a fifth less for 100
+    /// amounts spread over five decades. Extrapolating the prices of the previous solves would
+    /// save more, but carries the convergence error of each solve over to the next. The results
+    /// agree with individual quotes up to the solver tolerance.
+    ///
+    /// Amounts of zero yield zero, as by [`Router::solve`]. Fails with
+    /// [`RouterError::InvalidAmount`] if an amount is negative or not finite, if either token is
+    /// unknown or blacklisted, or if the tokens are not connected, see [`Router::solve`].
+    pub fn quote_curve(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        amounts: &[f64],
+    ) -> Result<Vec<f64>, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        for &amount in amounts {
+            check_trade_amount(amount)?;
+        }
+        self.check_connected(input_index, output_index)?;
+        let mut scratch = self.scratch_copy();
+        scratch.settle_if_pending();
+        let base = &scratch.token_graph;
+
+        let mut order = (0..amounts.len()).collect::<Vec<_>>();
+        order.sort_by(|&i, &j| amounts[i].total_cmp(&amounts[j]));
+
+        let mut outputs = vec![0.0; amounts.len()];
+        let mut previous_prices = None::<Vec<f64>>;
+        for position in order {
+            let input_amount = amounts[position];
+            if input_amount == 0.0 {
+                continue;
+            }
+            let mut graph = base.clone();
+            for (token, &q) in previous_prices.iter().flatten().enumerate() {
+                graph.set_price(token, q);
+            }
+            let (_, _, solver) = scratch.trade_solver(input_index, input_amount);
+            graph.set_solver(solver);
+            outputs[position] = graph
+                .apply_trade_and_solve(input_index, output_index, input_amount)
+                .output_amount;
+            previous_prices = Some((0..graph.len()).map(|token| graph.price(token)).collect());
+        }
+        Ok(outputs)
+    }
+}
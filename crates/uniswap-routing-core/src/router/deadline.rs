@@ -0,0 +1,50 @@
+use crate::{
+    error::RouterError,
+    router::{Router, TradeResult, check_trade_amount},
+};
+
+use std::time::{Duration, Instant};
+
+impl<'l> Router<'l> {
+    /// Like [`Router::solve`], but stops the iteration once `budget` has elapsed and commits the
+    /// trade at the prices reached, returning it with the residual of its last sweep for the
+    /// caller to decide whether to trust the output.
+    ///
+    /// The clock is read every 64 sweeps, or after every Newton step, so the call may overrun
+    /// the budget by that many sweeps. A trade stopped at its deadline reports a residual above
+    /// the tolerance, and its prices carry that residual over to the next trade; it does not
+    /// fail with [`RouterError::DidNotConverge`], which still applies to a trade exhausting its
+    /// sweeps within the budget. A pending settlement, see [`Router::settle`], runs first and
+    /// outside the budget.
+    ///
+    /// Fails as [`Router::solve`] otherwise. A zero amount gives an empty trade.
+    pub fn solve_with_deadline(
+        &mut self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+        budget: Duration,
+    ) -> Result<TradeResult<'l>, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        if !check_trade_amount(input_amount)? {
+            return Ok(TradeResult {
+                input_token: self.tokens[input_index],
+                output_token: self.tokens[output_index],
+                input_amount,
+                output_amount: 0.0,
+                iterations: 0,
+                residual: 0.0,
+            });
+        }
+        self.settle_if_pending();
+
+        // A budget beyond the range of the clock is no deadline
+        self.token_graph
+            .set_deadline(Instant::now().checked_add(budget));
+        let outcome = self.solve_trade(input_index, output_index, input_amount);
+        self.token_graph.set_deadline(None);
+
+        Ok(self.commit_trade(input_index, output_index, input_amount, outcome?))
+    }
+}
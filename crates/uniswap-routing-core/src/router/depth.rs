@@ -0,0 +1,133 @@
+use crate::{error::RouterError, router::Router};
+
+/// Relative width of the bracket at which the depth search stops.
+const DEPTH_PRECISION: f64 = 1e-6;
+
+/// Bound on the number of times the initial guess is doubled or halved while bracketing.
+const MAX_BRACKETING_STEPS: usize = 200;
+
+/// Depth of one ordered token pair, see [`Router::depth_table`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PairDepth<'l> {
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    /// Input amount reaching the price impact, in the input token
+    pub depth: f64,
+}
+
+impl<'l> Router<'l> {
+    /// Returns the input amount whose price impact reaches `impact_bps` basis points, without
+    /// modifying the router.
+    ///
+    /// The price impact of a trade is the shortfall of its average rate from the spot rate of
+    /// the equilibrium, `1 − output / (input · (q_out / q_in)²)`. It grows with the input
+    /// amount, the output being concave, and is bisected on a logarithmic scale from a guess
+    /// bracketed by doubling and halving, each probe being a quote as [`Router::solve`] would
+    /// return it. The depth is found to a relative precision of `1e-6`.
+    ///
+    /// For a single pair of pools sharing their price, the depth is
+    /// `K / rate · impact / (1 − impact)` with `rate = q_out / q_in`: linear in the aggregated
+    /// liquidity `K` of the pair. The depth is measured on a settled copy of the pools, see
+    /// [`Router::settle`], since out of equilibrium small trades would also capture the
+    /// arbitrage left in the pools.
+    ///
+    /// Fails if either token is unknown or blacklisted, if the tokens are not connected, see
+    /// [`Router::solve`], and with
+    /// [`RouterError::InvalidAmount`] if `impact_bps` is not strictly between 0 and 10 000.
+    pub fn depth(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        impact_bps: f64,
+    ) -> Result<f64, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        self.check_connected(input_index, output_index)?;
+        if !(impact_bps > 0.0 && impact_bps < 10_000.0) {
+            return Err(RouterError::InvalidAmount(impact_bps));
+        }
+        let mut scratch = self.scratch_copy();
+        scratch.settle_components();
+        Ok(scratch.depth_between(input_index, output_index, impact_bps / 10_000.0))
+    }
+
+    /// Returns the depth at `impact_bps` basis points, see [`Router::depth`], of every ordered
+    /// pair of routable tokens within the same connected component, by input token then output
+    /// token.
+    pub fn depth_table(&self, impact_bps: f64) -> Result<Vec<PairDepth<'l>>, RouterError> {
+        if !(impact_bps > 0.0 && impact_bps < 10_000.0) {
+            return Err(RouterError::InvalidAmount(impact_bps));
+        }
+        let mut scratch = self.scratch_copy();
+        scratch.settle_components();
+
+        let mut depths = Vec::new();
+        for component in scratch.token_graph.connected_components() {
+            for &input_index in &component {
+                for &output_index in component.iter().filter(|&&token| token != input_index) {
+                    depths.push(PairDepth {
+                        input_token: self.tokens[input_index],
+                        output_token: self.tokens[output_index],
+                        depth: scratch.depth_between(
+                            input_index,
+                            output_index,
+                            impact_bps / 10_000.0,
+                        ),
+                    });
+                }
+            }
+        }
+        depths.sort_by_key(|depth| {
+            (
+                self.token_index[depth.input_token],
+                self.token_index[depth.output_token],
+            )
+        });
+        Ok(depths)
+    }
+
+    fn depth_between(&self, input_index: usize, output_index: usize, impact: f64) -> f64 {
+        let graph = &self.token_graph;
+        let rate = graph.price(output_index) / graph.price(input_index);
+        let spot_rate = rate * rate;
+        let mut probe = graph.clone();
+        let mut impact_of = |input_amount: f64| {
+            probe.restore_state(graph);
+            let (_, _, solver) = self.trade_solver(input_index, input_amount);
+            probe.set_solver(solver);
+            let output_amount = probe
+                .apply_trade_and_solve(input_index, output_index, input_amount)
+                .output_amount;
+            1.0 - output_amount / (input_amount * spot_rate)
+        };
+
+        // Exact for a lone constant-product pool holding the whole reserve of the input token
+        let guess = graph.active_reserve(input_index) * impact / (1.0 - impact);
+        let (mut low, mut high) = (guess, guess);
+        for _ in 0..MAX_BRACKETING_STEPS {
+            if impact_of(high) >= impact {
+                break;
+            }
+            low = high;
+            high *= 2.0;
+        }
+        if low == high {
+            for _ in 0..MAX_BRACKETING_STEPS {
+                low /= 2.0;
+                if impact_of(low) < impact {
+                    break;
+                }
+                high = low;
+            }
+        }
+        while high - low > DEPTH_PRECISION * high {
+            let middle = (low * high).sqrt();
+            match impact_of(middle) < impact {
+                true => low = middle,
+                false => high = middle,
+            }
+        }
+        high
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    error::RouterError,
+    router::{Router, exact::exact_swap, swap_plan::PoolSwap},
+};
+
+impl<'l> Router<'l> {
+    /// Splits `input_amount` optimally across the pools linking `input_token` and
+    /// `output_token` directly, in closed form and without modifying the router, returning the
+    /// swap of every pool receiving a share of the input, in pool order.
+    ///
+    /// After the split, every pool receiving input ends at the same marginal rate
+    /// `m = r_out · r_in / (r_in + a)²`, so pool `i` receives `a_i = √k_i / √m − r_in,i`. Pools
+    /// are taken by decreasing spot rate while their spot rate exceeds the common marginal rate
+    /// `√m = Σ √k_i / (input_amount + Σ r_in,i)` of the pools taken so far: the input goes to the
+    /// pools with the best prices first, until it aligns their marginal prices with the next
+    /// one, and pools sharing a price receive shares proportional to `√k`.
+    ///
+    /// Only the traded direction is used: when the pools are out of line with each other,
+    /// [`Router::solve`] also arbitrages between them and returns more. On a router made of
+    /// these pools only, settled beforehand, it returns the sum of the outputs up to its
+    /// tolerance. Shares of pools with raw reserves are also requoted through their exact math.
+    ///
+    /// Fails if either token is unknown or blacklisted, with [`RouterError::InvalidAmount`] if
+    /// `input_amount` is negative or not finite, and with [`RouterError::NoDirectPool`] if no
+    /// pool links the tokens.
+    pub fn optimal_direct_split(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<Vec<PoolSwap<'l>>, RouterError> {
+        let input_token = self.tokens[self.routable_index(input_token)?];
+        let output_token = self.tokens[self.routable_index(output_token)?];
+        if !(input_amount.is_finite() && input_amount >= 0.0) {
+            return Err(RouterError::InvalidAmount(input_amount));
+        }
+
+        // Reserves of the direct pools in the traded direction, by decreasing spot rate
+        let mut pools = self
+            .pools
+            .iter()
+            .zip(&self.pool_ids)
+            .enumerate()
+            .filter_map(|(position, (pool, id))| {
+                let (curve0, curve1) = pool.curve_reserves();
+                let reserves = match (pool.token0, pool.token1) {
+                    (token0, token1) if (token0, token1) == (input_token, output_token) => {
+                        (curve0, curve1)
+                    }
+                    (token0, token1) if (token1, token0) == (input_token, output_token) => {
+                        (curve1, curve0)
+                    }
+                    _ => return None,
+                };
+                Some((position, id, reserves))
+            })
+            .collect::<Vec<_>>();
+        if pools.is_empty() {
+            return Err(RouterError::NoDirectPool {
+                input_token: input_token.to_string(),
+                output_token: output_token.to_string(),
+            });
+        }
+        pools.sort_by(|(_, _, (in_a, out_a)), (_, _, (in_b, out_b))| {
+            (out_b / in_b).total_cmp(&(out_a / in_a))
+        });
+
+        let (mut liquidity, mut reserve_in) = (0.0, 0.0);
+        let mut active = 0;
+        for &(_, _, (pool_in, pool_out)) in &pools {
+            if active > 0 {
+                let marginal_root = liquidity / (input_amount + reserve_in);
+                if pool_out / pool_in <= marginal_root * marginal_root {
+                    break;
+                }
+            }
+            liquidity += (pool_in * pool_out).sqrt();
+            reserve_in += pool_in;
+            active += 1;
+        }
+        let marginal_root = liquidity / (input_amount + reserve_in);
+
+        let mut swaps = pools[..active]
+            .iter()
+            .filter_map(|&(position, id, (pool_in, pool_out))| {
+                let share = ((pool_in * pool_out).sqrt() / marginal_root - pool_in).max(0.0);
+                (share > 0.0).then(|| {
+                    (
+                        position,
+                        PoolSwap {
+                            pool: id.clone(),
+                            input_token,
+                            output_token,
+                            input_amount: share,
+                            output_amount: pool_out * share / (pool_in + share),
+                            exact: exact_swap(&self.pools[position], input_token, share),
+                        },
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        swaps.sort_by_key(|&(position, _)| position);
+        Ok(swaps.into_iter().map(|(_, swap)| swap).collect())
+    }
+}
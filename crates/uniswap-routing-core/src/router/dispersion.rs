@@ -0,0 +1,78 @@
+use crate::router::Router;
+
+use std::collections::BTreeMap;
+
+/// Disagreement between the spot prices of the pools of one pair, see
+/// [`Router::pair_price_dispersion`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PairDispersion<'l> {
+    pub token_a: &'l str,
+    pub token_b: &'l str,
+    /// Number of pools of the pair with a spot price
+    pub pools: usize,
+    /// Lowest spot price, in `token_b` per `token_a`
+    pub min_price: f64,
+    /// Highest spot price, in `token_b` per `token_a`
+    pub max_price: f64,
+    /// Mean of the spot prices weighted by the geometric liquidity `√k` of their pools
+    pub mean_price: f64,
+    /// Relative spread `(max_price − min_price) / mean_price`
+    pub spread: f64,
+}
+
+impl<'l> Router<'l> {
+    /// Returns the dispersion of the spot prices across the pools of every pair with at least
+    /// two of them, ordered by the token indices of the pair, `token_a` first.
+    ///
+    /// The token graph aggregates the pools of a pair into a single liquidity, which the
+    /// equilibrium trades at a single price: the dispersion is read from the pools themselves.
+    /// Pools straight from on-chain snapshots may disagree, which can reveal stale data; the
+    /// pools moved by a trade or a settlement share the price of their pair. Empty pools and
+    /// pools with non-finite reserves have no spot price and are left out.
+    pub fn pair_price_dispersion(&self) -> Vec<PairDispersion<'l>> {
+        // Spot price of `b` per `a` and geometric liquidity of every pool, by pair
+        let mut pairs = BTreeMap::<(usize, usize), Vec<(f64, f64)>>::new();
+        for pool in &self.pools {
+            let (index_0, index_1) = (self.token_index[pool.token0], self.token_index[pool.token1]);
+            let (curve0, curve1) = pool.curve_reserves();
+            let price = curve1 / curve0;
+            if !(price.is_finite() && price > 0.0) {
+                continue;
+            }
+            let (key, price) = match index_0 < index_1 {
+                true => ((index_0, index_1), price),
+                false => ((index_1, index_0), price.recip()),
+            };
+            let liquidity = curve0.sqrt() * curve1.sqrt();
+            pairs.entry(key).or_default().push((price, liquidity));
+        }
+
+        pairs
+            .into_iter()
+            .filter(|(_, prices)| prices.len() >= 2)
+            .map(|((a, b), prices)| {
+                let min_price = prices
+                    .iter()
+                    .map(|&(price, _)| price)
+                    .fold(f64::MAX, f64::min);
+                let max_price = prices.iter().map(|&(price, _)| price).fold(0.0, f64::max);
+                let liquidity = prices.iter().map(|&(_, liquidity)| liquidity).sum::<f64>();
+                let mean_price = prices
+                    .iter()
+                    .map(|&(price, liquidity)| price * liquidity)
+                    .sum::<f64>()
+                    / liquidity;
+                PairDispersion {
+                    token_a: self.tokens[a],
+                    token_b: self.tokens[b],
+                    pools: prices.len(),
+                    min_price,
+                    max_price,
+                    mean_price,
+                    spread: (max_price - min_price) / mean_price,
+                }
+            })
+            .collect()
+    }
+}
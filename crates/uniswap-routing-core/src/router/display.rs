@@ -0,0 +1,101 @@
+use crate::router::Router;
+
+use std::fmt;
+
+/// Number of significant digits used for reserves, prices and liquidities.
+const SIGNIFICANT_DIGITS: usize = 6;
+
+/// Prints the aggregated state of the router, in token index order:
+/// - one row per token with its total reserve and its price in units of the reference token,
+///   `(q_ref / q_u)²`,
+/// - one row per non-zero pair with its geometric liquidity `K(u, v)`.
+impl fmt::Display for Router<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.tokens.is_empty() {
+            return writeln!(f, "Empty router");
+        }
+        let reference_token = self.token_graph.reference_token();
+        let reference = self.tokens[reference_token];
+        let reference_q = self.token_graph.price(reference_token);
+
+        let token_rows = self
+            .tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| {
+                let price = (reference_q / self.token_graph.price(index)).powi(2);
+                [
+                    index.to_string(),
+                    token.to_string(),
+                    significant(self.token_graph.held_reserve(index)),
+                    significant(price),
+                ]
+            })
+            .collect::<Vec<_>>();
+        writeln!(f, "Tokens (prices in {reference}):")?;
+        write_table(f, ["#", "token", "reserve", "price"], &token_rows)?;
+
+        let pair_rows = self
+            .token_graph
+            .sorted_edges()
+            .into_iter()
+            .map(|(u, v, liquidity)| {
+                [
+                    self.tokens[u].to_string(),
+                    self.tokens[v].to_string(),
+                    significant(liquidity),
+                ]
+            })
+            .collect::<Vec<_>>();
+        writeln!(f, "Pairwise liquidities:")?;
+        write_table(f, ["token", "token", "K"], &pair_rows)
+    }
+}
+
+/// Writes rows with text columns left-aligned and numeric columns right-aligned.
+fn write_table<const N: usize>(
+    f: &mut fmt::Formatter<'_>,
+    header: [&str; N],
+    rows: &[[String; N]],
+) -> fmt::Result {
+    let widths: [usize; N] = std::array::from_fn(|column| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .chain([header[column].len()])
+            .max()
+            .unwrap_or_default()
+    });
+    let is_numeric: [bool; N] =
+        std::array::from_fn(|column| rows.iter().all(|row| row[column].parse::<f64>().is_ok()));
+
+    let header = header.map(str::to_string);
+    for row in std::iter::once(&header).chain(rows) {
+        write!(f, " ")?;
+        for (column, cell) in row.iter().enumerate() {
+            match is_numeric[column] {
+                true => write!(f, " {cell:>width$}", width = widths[column])?,
+                false => write!(f, " {cell:<width$}", width = widths[column])?,
+            }
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// Formats `value` with [`SIGNIFICANT_DIGITS`] significant digits, switching to scientific
+/// notation for very small or very large magnitudes so that the output stays stable across runs.
+fn significant(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    match magnitude {
+        -4..15 => {
+            let decimals = (SIGNIFICANT_DIGITS as i32 - 1 - magnitude).max(0) as usize;
+            let scale = 10f64.powi(magnitude + 1 - SIGNIFICANT_DIGITS as i32);
+            let rounded = (value / scale).round() * scale;
+            format!("{rounded:.decimals$}")
+        }
+        _ => format!("{value:.prec$e}", prec = SIGNIFICANT_DIGITS - 1),
+    }
+}
@@ -0,0 +1,31 @@
+use crate::router::Router;
+
+impl Router<'_> {
+    /// Returns the largest relative imbalance `|∑ K(u, v) q_u / q_v − T_u| / T_u`, over the
+    /// routable tokens with liquidity, between the total of a token and the total its current
+    /// price implies, evaluated without iterating: zero at the equilibrium, NaN if a price or a
+    /// total is not finite.
+    ///
+    /// A converged solve leaves it about the relative tolerance of the solver, every sweep of
+    /// the fixed-point iteration moving the prices by these very imbalances; pool events raise
+    /// it like [`Router::staleness`], which measures the pools instead of the totals.
+    pub fn equilibrium_residual(&self) -> f64 {
+        (0..self.token_graph.len())
+            .filter_map(|token| self.token_graph.relative_imbalance(token))
+            .map(f64::abs)
+            .fold(0.0, |residual: f64, imbalance| {
+                match residual.is_nan() || imbalance.is_nan() {
+                    true => f64::NAN,
+                    false => residual.max(imbalance),
+                }
+            })
+    }
+
+    /// Returns whether every routable token with liquidity holds the total its current price
+    /// implies within the relative `tolerance`, see [`Router::equilibrium_residual`]: a
+    /// settlement or a zero-input solve would then leave the prices where they are, up to that
+    /// tolerance.
+    pub fn is_equilibrated(&self, tolerance: f64) -> bool {
+        self.equilibrium_residual() <= tolerance
+    }
+}
@@ -0,0 +1,390 @@
+use crate::{
+    error::RouterError,
+    router::{Router, token_graph::TokenGraph},
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    sync::Arc,
+};
+
+/// Idempotency key of an event. Keys are expected to be non-decreasing in arrival order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventKey(pub u128);
+
+impl EventKey {
+    /// Key of the `log_index`-th log of `block`, which orders on-chain events chronologically.
+    pub fn from_log(block: u64, log_index: u64) -> Self {
+        Self(((block as u128) << 64) | log_index as u128)
+    }
+}
+
+impl fmt::Display for EventKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A change of the pool set, as emitted by an event source.
+#[derive(Debug, Clone)]
+pub struct PoolEvent {
+    /// Events with a key are applied at most once, unkeyed events every time they are received
+    pub key: Option<EventKey>,
+    /// Allows the key to be lower than the last applied one, when the source replays a fork
+    pub reorg: bool,
+    pub kind: PoolEventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum PoolEventKind {
+    /// Absolute reserves of a pool, as emitted by the pair contract, failing the batch if the
+    /// pool cannot hold them, see [`Router::update_pool_reserves`]
+    Sync {
+        pool: PoolId,
+        reserve0: f64,
+        reserve1: f64,
+    },
+    NewPool(UniV2Pool),
+    RemovePool(PoolId),
+    /// Restores the state as it was right after the event `to_key` was applied
+    Rollback {
+        to_key: EventKey,
+    },
+}
+
+impl PoolEvent {
+    pub fn new(kind: PoolEventKind) -> Self {
+        Self {
+            key: None,
+            reorg: false,
+            kind,
+        }
+    }
+
+    pub fn with_key(mut self, key: EventKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Flags the event as part of a reorganization, exempting it from the ordering validation.
+    pub fn reorg(mut self) -> Self {
+        self.reorg = true;
+        self
+    }
+}
+
+/// What a call to [`Router::apply_events`] did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventBatchSummary {
+    /// Number of events applied, rollbacks included
+    pub applied: usize,
+    /// Keys of the events skipped because they had already been applied
+    pub duplicates: Vec<EventKey>,
+    /// Identifiers assigned to the pools added by the batch, rolled back ones excluded
+    pub new_pools: Vec<PoolId>,
+    /// Target of the last rollback of the batch
+    pub rolled_back_to: Option<EventKey>,
+    /// Staleness of the router after the batch, before any automatic settlement, see
+    /// [`Router::staleness`]
+    pub staleness: f64,
+    /// Whether the router settled at the end of the batch, see
+    /// [`AutoSettle::Eager`](crate::router::AutoSettle::Eager)
+    pub settled: bool,
+}
+
+/// Deduplication window, event log and snapshots backing [`Router::apply_events`].
+#[derive(Debug, Clone, Default)]
+pub(super) struct EventJournal<'l> {
+    /// Most recent keys, oldest first, mirrored in `seen_keys` for lookups
+    recent_keys: VecDeque<EventKey>,
+    seen_keys: HashSet<EventKey>,
+    last_key: Option<EventKey>,
+    /// Pool events applied since the oldest retained snapshot
+    log: VecDeque<LoggedEvent>,
+    /// Number of events ever dropped from the front of `log`
+    log_start: usize,
+    /// Pre-batch snapshots, oldest first
+    snapshots: VecDeque<Arc<Snapshot<'l>>>,
+}
+
+#[derive(Debug, Clone)]
+struct LoggedEvent {
+    /// Last key applied when the event was, its own key for keyed events
+    position: Option<EventKey>,
+    kind: PoolEventKind,
+}
+
+/// State of the router before a batch of events.
+#[derive(Debug)]
+struct Snapshot<'l> {
+    /// Last key applied when the snapshot was taken
+    position: Option<EventKey>,
+    /// Length of the event log, counted from its very first event, when the snapshot was taken
+    log_end: usize,
+    /// Number of operations in the session capture when the snapshot was taken
+    capture_len: usize,
+    token_index: HashMap<&'l str, usize>,
+    tokens: Vec<&'l str>,
+    pools: Vec<UniV2Pool>,
+    pool_ids: Vec<PoolId>,
+    next_pool_id: u64,
+    token_graph: TokenGraph,
+    generation: u32,
+    reference_token: Option<String>,
+    blacklisted_tokens: Vec<String>,
+    settle_pending: bool,
+    initial_arbitrage_value: Option<f64>,
+}
+
+impl<'l> Router<'l> {
+    /// Applies a batch of pool events, atomically: if any event fails, the router is left as it
+    /// was before the batch.
+    ///
+    /// Keyed events already seen among the last
+    /// [`event_dedup_window`](crate::router::RouterConfig::event_dedup_window) keys are skipped
+    /// and reported as duplicates. A key lower than the last applied one is rejected unless the
+    /// event is flagged as a reorg. A [`PoolEventKind::Rollback`] restores the most recent
+    /// retained pre-batch snapshot taken at or before its target, then replays the logged events
+    /// up to the target; trades committed after that snapshot are discarded with it, although
+    /// they stay in the trade history.
+    ///
+    /// An empty batch, or one made only of duplicates and of `Sync` events repeating the
+    /// current reserves, leaves the router untouched, see [`Router::content_hash`]: the token
+    /// graph is not re-aggregated and no automatic settlement runs. Only the keys of its events
+    /// are remembered, and its non-empty batches retain a snapshot like any other.
+    ///
+    /// A batch leaving the router staler than
+    /// [`staleness_threshold`](crate::router::RouterConfig::staleness_threshold) is settled as
+    /// configured by [`auto_settle`](crate::router::RouterConfig::auto_settle).
+    pub fn apply_events(
+        &mut self,
+        events: impl IntoIterator<Item = PoolEvent>,
+    ) -> Result<EventBatchSummary, RouterError> {
+        let mut events = events.into_iter().peekable();
+        if events.peek().is_none() {
+            return Ok(EventBatchSummary {
+                staleness: self.staleness(),
+                ..EventBatchSummary::default()
+            });
+        }
+        let journal = self.events.clone();
+        let snapshot = Arc::new(self.snapshot());
+        self.events
+            .retain_snapshot(snapshot.clone(), self.config.retained_snapshots);
+
+        let mut summary = EventBatchSummary::default();
+        let mut stale_graph = false;
+        for event in events {
+            if let Err(error) = self.apply_event(event, &mut summary, &mut stale_graph) {
+                self.restore(&snapshot);
+                self.events = journal;
+                return Err(error);
+            }
+        }
+
+        if !stale_graph && summary.rolled_back_to.is_none() {
+            summary.staleness = self.staleness();
+            return Ok(summary);
+        }
+        if stale_graph {
+            self.rebuild_token_graph();
+        }
+        summary.new_pools.retain(|id| self.pool_ids.contains(id));
+        (summary.staleness, summary.settled) = self.apply_auto_settle();
+        self.publish_version();
+        Ok(summary)
+    }
+
+    fn apply_event(
+        &mut self,
+        event: PoolEvent,
+        summary: &mut EventBatchSummary,
+        stale_graph: &mut bool,
+    ) -> Result<(), RouterError> {
+        if let Some(key) = event.key {
+            if self.events.seen_keys.contains(&key) {
+                summary.duplicates.push(key);
+                return Ok(());
+            }
+            if let Some(last) = self.events.last_key
+                && key < last
+                && !event.reorg
+            {
+                return Err(RouterError::OutOfOrderEvent { key, last });
+            }
+        }
+
+        match event.kind {
+            PoolEventKind::Rollback { to_key } => {
+                let replayed = self.rollback(to_key)?;
+                *stale_graph = replayed > 0;
+                summary.rolled_back_to = Some(to_key);
+            }
+            kind => {
+                if let Some(key) = event.key {
+                    self.events.last_key = Some(key);
+                }
+                let unchanged = matches!(
+                    &kind,
+                    PoolEventKind::Sync { pool, reserve0, reserve1 }
+                        if self.has_reserves(pool, *reserve0, *reserve1)
+                );
+                if !unchanged {
+                    if let Some(id) = self.apply_pool_event(&kind)? {
+                        summary.new_pools.push(id);
+                    }
+                    *stale_graph = true;
+                }
+                self.events.log.push_back(LoggedEvent {
+                    position: self.events.last_key,
+                    kind,
+                });
+            }
+        }
+        if let Some(key) = event.key {
+            self.events.remember(key, self.config.event_dedup_window);
+        }
+        summary.applied += 1;
+        Ok(())
+    }
+
+    /// Applies a pool event without rebuilding the token graph, returning the identifier of the
+    /// added pool if any.
+    fn apply_pool_event(&mut self, kind: &PoolEventKind) -> Result<Option<PoolId>, RouterError> {
+        match kind {
+            PoolEventKind::Sync {
+                pool,
+                reserve0,
+                reserve1,
+            } => {
+                self.set_pool_reserves(pool, *reserve0, *reserve1)?;
+            }
+            PoolEventKind::NewPool(pool) => return self.insert_pool(pool.clone()).map(Some),
+            PoolEventKind::RemovePool(pool) => {
+                self.take_pool(pool)?;
+            }
+            PoolEventKind::Rollback { .. } => unreachable!("rollbacks are never logged"),
+        }
+        Ok(None)
+    }
+
+    /// Restores the state right after `to_key` and returns the number of replayed events.
+    fn rollback(&mut self, to_key: EventKey) -> Result<usize, RouterError> {
+        let snapshot = self
+            .events
+            .snapshot_before(to_key)
+            .ok_or(RouterError::RollbackUnavailable(to_key))?;
+        let replayed = self.events.rewind(&snapshot, to_key);
+        self.restore(&snapshot);
+        for event in &replayed {
+            self.apply_pool_event(&event.kind)?;
+        }
+        let count = replayed.len();
+        self.events.log.extend(replayed);
+        Ok(count)
+    }
+
+    fn snapshot(&self) -> Snapshot<'l> {
+        Snapshot {
+            position: self.events.last_key,
+            log_end: self.events.log_start + self.events.log.len(),
+            capture_len: self
+                .capture
+                .as_ref()
+                .map_or(0, |capture| capture.operations.len()),
+            token_index: self.token_index.clone(),
+            tokens: self.tokens.clone(),
+            pools: self.pools.clone(),
+            pool_ids: self.pool_ids.clone(),
+            next_pool_id: self.next_pool_id,
+            token_graph: self.token_graph.clone(),
+            generation: self.generation,
+            reference_token: self.config.reference_token.clone(),
+            blacklisted_tokens: self.config.blacklisted_tokens.clone(),
+            settle_pending: self.settle_pending,
+            initial_arbitrage_value: self.initial_arbitrage_value,
+        }
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot<'l>) {
+        self.token_index = snapshot.token_index.clone();
+        self.tokens = snapshot.tokens.clone();
+        self.pools = snapshot.pools.clone();
+        self.pool_ids = snapshot.pool_ids.clone();
+        self.next_pool_id = snapshot.next_pool_id;
+        self.token_graph = snapshot.token_graph.clone();
+        self.generation = snapshot.generation;
+        self.config.reference_token = snapshot.reference_token.clone();
+        self.config.blacklisted_tokens = snapshot.blacklisted_tokens.clone();
+        self.settle_pending = snapshot.settle_pending;
+        self.initial_arbitrage_value = snapshot.initial_arbitrage_value;
+        if let Some(capture) = &mut self.capture {
+            capture.truncate(snapshot.capture_len);
+        }
+    }
+}
+
+impl<'l> EventJournal<'l> {
+    fn remember(&mut self, key: EventKey, window: usize) {
+        if window == 0 {
+            return;
+        }
+        if self.recent_keys.len() == window
+            && let Some(oldest) = self.recent_keys.pop_front()
+        {
+            self.seen_keys.remove(&oldest);
+        }
+        self.recent_keys.push_back(key);
+        self.seen_keys.insert(key);
+    }
+
+    /// Keeps `snapshot` and drops the oldest snapshots, with the log entries only they needed,
+    /// beyond `capacity`.
+    fn retain_snapshot(&mut self, snapshot: Arc<Snapshot<'l>>, capacity: usize) {
+        self.snapshots.push_back(snapshot);
+        while self.snapshots.len() > capacity {
+            self.snapshots.pop_front();
+        }
+        let needed_from = self
+            .snapshots
+            .front()
+            .map_or(self.log_start + self.log.len(), |oldest| oldest.log_end);
+        while self.log_start < needed_from {
+            self.log.pop_front();
+            self.log_start += 1;
+        }
+    }
+
+    fn snapshot_before(&self, to_key: EventKey) -> Option<Arc<Snapshot<'l>>> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.position <= Some(to_key))
+            .cloned()
+    }
+
+    /// Forgets everything that happened after `snapshot`, returning the logged events up to
+    /// `to_key` that must be applied again on top of it.
+    fn rewind(&mut self, snapshot: &Arc<Snapshot<'l>>, to_key: EventKey) -> Vec<LoggedEvent> {
+        while self
+            .snapshots
+            .back()
+            .is_some_and(|newest| !Arc::ptr_eq(newest, snapshot))
+        {
+            self.snapshots.pop_back();
+        }
+        let replayed = self
+            .log
+            .drain(snapshot.log_end - self.log_start..)
+            .filter(|event| event.position <= Some(to_key))
+            .collect();
+
+        self.recent_keys.retain(|&key| key <= to_key);
+        self.seen_keys.retain(|&key| key <= to_key);
+        self.last_key = Some(to_key);
+        replayed
+    }
+}
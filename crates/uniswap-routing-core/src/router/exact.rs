@@ -0,0 +1,75 @@
+use crate::uni_v2_pool::UniV2Pool;
+
+/// A swap requoted through the exact integer math of its pool, see
+/// [`UniV2Pool::get_output_amount_exact`], in the smallest units of the tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExactSwap {
+    /// Input converted to the smallest units at the decimals of the token, or without them at the
+    /// ratio of the raw and float reserves of the pool, rounded to the nearest unit
+    pub input_amount: u128,
+    /// Output the pair contract returns for `input_amount`
+    pub output_amount: u128,
+}
+
+/// Requotes the swap of `input_amount` of `input_token` against `pool` through its exact math,
+/// or returns `None` if the pool has no raw reserves or its exact math fails.
+pub(super) fn exact_swap(
+    pool: &UniV2Pool,
+    input_token: &str,
+    input_amount: f64,
+) -> Option<ExactSwap> {
+    let (raw0, raw1) = pool.raw_reserves?;
+    let input_amount = match pool.to_raw_amount(input_token, input_amount) {
+        Some(raw_amount) => raw_amount,
+        None => {
+            let (raw_in, reserve_in) = match input_token == pool.token0 {
+                true => (raw0, pool.reserve0),
+                false => (raw1, pool.reserve1),
+            };
+            // Saturating, negative amounts becoming zero
+            (input_amount * (raw_in as f64 / reserve_in)).round() as u128
+        }
+    };
+    let output_amount = pool
+        .get_output_amount_exact(input_token, input_amount)
+        .ok()?;
+    Some(ExactSwap {
+        input_amount,
+        output_amount,
+    })
+}
+
+/// Requotes the move of `pool` from the float reserves `before` to its current ones through its
+/// exact math, and moves its raw reserves by the exact amounts, as the pair contract would. The
+/// raw reserves are dropped if the requote fails.
+///
+/// Under [`FeeModel::FeeFree`](crate::router::FeeModel::FeeFree), the exact math still charges the
+/// fee of the pool, returning less than the float move.
+pub(super) fn advance_raw_reserves(pool: &mut UniV2Pool, before: (f64, f64)) -> Option<ExactSwap> {
+    pool.raw_reserves?;
+    let (input_token, input_amount) = match pool.reserve0 > before.0 {
+        true => (pool.token0, pool.reserve0 - before.0),
+        false => (pool.token1, pool.reserve1 - before.1),
+    };
+    let pool_before = UniV2Pool {
+        reserve0: before.0,
+        reserve1: before.1,
+        ..pool.clone()
+    };
+    let exact = exact_swap(&pool_before, input_token, input_amount);
+    pool.raw_reserves = exact.and_then(|exact| {
+        let (raw0, raw1) = pool.raw_reserves?;
+        match input_token == pool.token0 {
+            true => Some((
+                raw0.checked_add(exact.input_amount)?,
+                raw1 - exact.output_amount,
+            )),
+            false => Some((
+                raw0 - exact.output_amount,
+                raw1.checked_add(exact.input_amount)?,
+            )),
+        }
+    });
+    exact.filter(|_| pool.raw_reserves.is_some())
+}
@@ -0,0 +1,189 @@
+use crate::{
+    error::RouterError,
+    router::{Router, TradeResult, token_graph::SolveOutcome},
+};
+
+/// Maximum relative conservation residual `|∑ K(u, v) q_u / q_v − T_u| / T_u` accepted from an
+/// external solution.
+pub const FEASIBILITY_TOLERANCE: f64 = 1e-9;
+
+/// A trade expressed as a standalone optimization problem, for external (e.g. convex) solvers.
+///
+/// Tokens are referred to by their position in `tokens`. Each pair of tokens `(a, b)` sharing
+/// pools is a constant-product market of invariant `liquidity²`, holding `liquidity · q_a / q_b`
+/// of `a` and `liquidity · q_b / q_a` of `b` once every pair is at the price ratio `(q_b / q_a)²`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoutingProblem {
+    pub tokens: Vec<String>,
+    pub input_token: usize,
+    pub output_token: usize,
+    pub input_amount: f64,
+    /// Total reserve `T_u` of each token across all pools, before the trade, excluding the pools
+    /// touching a blacklisted token
+    pub reserves: Vec<f64>,
+    /// Every pair with non-zero geometric liquidity `K(a, b) = Σ √kᵢ`, once, blacklisted tokens
+    /// excluded
+    pub liquidities: Vec<PairLiquidity>,
+    /// Current square-root prices `q`, a good starting point for iterative solvers
+    pub initial_prices: Vec<f64>,
+    /// Human-readable statement of the objective
+    pub objective: String,
+    /// Human-readable statement of the constraints
+    pub constraints: Vec<String>,
+    /// Relative residual the solution must achieve on every conservation constraint
+    pub feasibility_tolerance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PairLiquidity {
+    pub token_a: usize,
+    pub token_b: usize,
+    pub liquidity: f64,
+}
+
+/// Square-root prices computed outside the router for a given trade.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExternalSolution {
+    pub input_token: String,
+    pub output_token: String,
+    pub input_amount: f64,
+    /// Square-root price `q` of every token, in the token order of the [`RoutingProblem`]
+    pub prices: Vec<f64>,
+}
+
+impl<'l> Router<'l> {
+    /// Exports the trade of `input_amount` of `input_token` for `output_token` as a
+    /// [`RoutingProblem`], without modifying the router.
+    pub fn export_problem(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<RoutingProblem, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        let graph = &self.token_graph;
+
+        Ok(RoutingProblem {
+            tokens: self.tokens.iter().map(|token| token.to_string()).collect(),
+            input_token: input_index,
+            output_token: output_index,
+            input_amount,
+            reserves: (0..graph.len()).map(|t| graph.active_reserve(t)).collect(),
+            liquidities: graph
+                .sorted_edges()
+                .into_iter()
+                .map(|(token_a, token_b, liquidity)| PairLiquidity {
+                    token_a,
+                    token_b,
+                    liquidity,
+                })
+                .collect(),
+            initial_prices: (0..graph.len()).map(|t| graph.price(t)).collect(),
+            objective: format!(
+                "maximize the amount of {output_token} extracted, T_out − ∑_v K(out, v) q_out / q_v"
+            ),
+            constraints: vec![
+                "every pair (a, b) stays on its constant-product curve: it holds K(a, b) q_a / q_b \
+                 of a and K(a, b) q_b / q_a of b"
+                    .to_string(),
+                format!(
+                    "conservation: ∑_v K(u, v) q_u / q_v = T_u for every token u other than \
+                     {output_token}, where T_{input_token} includes the input amount"
+                ),
+                "q_u > 0 for every token".to_string(),
+            ],
+            feasibility_tolerance: FEASIBILITY_TOLERANCE,
+        })
+    }
+
+    /// Validates externally computed prices against the conservation constraints of the current
+    /// state and, if feasible, commits the trade they describe exactly like [`Router::solve`]
+    /// would have. Fails with [`RouterError::InvalidAmount`] if the input amount of the solution
+    /// is negative or not finite, with [`RouterError::NumericalError`] if the output the prices
+    /// extract is not finite, and with [`RouterError::NegativeOutput`] if they top the output
+    /// token up beyond the dust tolerance, leaving the router untouched.
+    pub fn import_solution(
+        &mut self,
+        solution: &ExternalSolution,
+    ) -> Result<TradeResult<'l>, RouterError> {
+        let input_index = self.routable_index(&solution.input_token)?;
+        let output_index = self.routable_index(&solution.output_token)?;
+        if !(solution.input_amount.is_finite() && solution.input_amount >= 0.0) {
+            return Err(RouterError::InvalidAmount(solution.input_amount));
+        }
+        let graph = &self.token_graph;
+        let prices = &solution.prices;
+
+        if prices.len() != graph.len() {
+            return Err(RouterError::MalformedSolution(format!(
+                "expected {} prices, got {}",
+                graph.len(),
+                prices.len()
+            )));
+        }
+        if let Some(token) = prices.iter().position(|q| !(q.is_finite() && *q > 0.0)) {
+            return Err(RouterError::MalformedSolution(format!(
+                "price of {} is {}",
+                self.tokens[token], prices[token]
+            )));
+        }
+
+        for token in (0..graph.len()).filter(|&t| t != output_index) {
+            if graph.neighbors(token).next().is_none() {
+                continue;
+            }
+            let mut expected = graph.active_reserve(token);
+            if token == input_index {
+                expected += solution.input_amount;
+            }
+            let residual =
+                (graph.implied_reserve(token, |t| prices[t]) - expected).abs() / expected;
+            if residual.is_nan() || residual > FEASIBILITY_TOLERANCE {
+                return Err(RouterError::InfeasibleSolution {
+                    token: self.tokens[token].to_string(),
+                    residual,
+                });
+            }
+        }
+
+        let checkpoint = self.token_graph.checkpoint(input_index, output_index);
+        let output_amount = self.token_graph.apply_trade_with_prices(
+            input_index,
+            output_index,
+            solution.input_amount,
+            prices,
+        );
+        if !output_amount.is_finite() {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(RouterError::NumericalError {
+                token: self.tokens[output_index].to_string(),
+                value: output_amount,
+            });
+        }
+        if output_amount < 0.0 {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(RouterError::NegativeOutput {
+                amount: output_amount,
+            });
+        }
+        let outcome = SolveOutcome {
+            output_amount,
+            iterations: 0,
+            residual: 0.0,
+            converged: true,
+            invalid_price: None,
+            numerical_error: None,
+            damping_events: 0,
+            final_damping: 1.0,
+            deadline_reached: false,
+            bisected: false,
+            normalization_skipped: false,
+            token_updates: 0,
+        };
+        Ok(self.commit_trade(input_index, output_index, solution.input_amount, outcome))
+    }
+}
@@ -0,0 +1,582 @@
+use crate::{
+    router::{
+        FeeModel, Router, SolverConfig, exact::advance_raw_reserves,
+        linear_system::solve_linear_system, token_graph::SolveOutcome, verification::TradedPool,
+    },
+    uni_v2_pool::UniV2Pool,
+};
+
+use std::{cmp::Ordering, collections::VecDeque, time::Instant};
+
+/// Relative imbalance of every token below which a fee-charging trade counts as converged,
+/// whatever the tolerance of the solver: the rounding of the sums of large reserves keeps the
+/// iteration from going much further, and [`FeeTrade::reserves`] settles the rest exactly.
+const IMBALANCE_TOLERANCE: f64 = 1e-12;
+
+/// Width of the fee bands smoothing, relative to the reserves, that the continuation starts
+/// from, see [`smoothed_reserve`].
+const INITIAL_SMOOTHING: f64 = 1e-3;
+
+/// Narrowest smoothing of the fee bands, below the rounding of the reserves.
+const FINAL_SMOOTHING: f64 = 1e-15;
+
+/// Factor by which the smoothing narrows once the smoothed balances hold within
+/// [`STAGE_TOLERANCE`] of it.
+const SMOOTHING_DECREASE: f64 = 0.1;
+
+/// Largest relative imbalance of the smoothed balances, as a fraction of the smoothing, at
+/// which the smoothing narrows.
+const STAGE_TOLERANCE: f64 = 1e-2;
+
+/// Largest change of the logarithm of a price in a Newton step, beyond which the step is
+/// scaled down.
+const MAX_LOG_STEP: f64 = 1.0;
+
+/// Number of bisections of a Newton step by its line search, enough to reach the rounding of
+/// the prices.
+const LINE_SEARCH_BISECTIONS: usize = 64;
+
+/// A pool taking part in a fee-charging trade.
+struct FeePool {
+    /// Position of the pool in the router
+    position: usize,
+    tokens: (usize, usize),
+    /// Curve reserves of the pool, see [`UniV2Pool::curve_reserves`]
+    reserves: (f64, f64),
+    sqrt_k: f64,
+    /// Fraction `γ = 1 − fee` of its input the pool trades on
+    gamma: f64,
+}
+
+impl FeePool {
+    /// Returns the fee-free holdings `√(k/γ) · q_0 / q_1` of `token0` of the pool at the prices
+    /// `q`.
+    fn target(&self, q: &[f64]) -> f64 {
+        self.sqrt_k / self.gamma.sqrt() * q[self.tokens.0] / q[self.tokens.1]
+    }
+
+    /// Returns the reserve of its side `is_token0` the pool ends with at the prices `q`.
+    fn reserve_at(&self, q: &[f64], is_token0: bool) -> f64 {
+        let target = self.target(q);
+        match is_token0 {
+            true => fee_reserve(self.reserves.0, self.gamma, target),
+            false => fee_reserve(
+                self.reserves.1,
+                self.gamma,
+                self.sqrt_k * self.sqrt_k / self.gamma / target,
+            ),
+        }
+    }
+
+    /// Returns the reserve of its side `is_token0` the pool ends with at the prices `q` along
+    /// the fee bands smoothed by `smoothing`, see [`smoothed_reserve`], and its derivative in
+    /// the logarithm of the price ratio of that side.
+    fn smoothed_reserve_at(&self, q: &[f64], is_token0: bool, smoothing: f64) -> (f64, f64) {
+        let target = self.target(q);
+        match is_token0 {
+            true => smoothed_reserve(self.reserves.0, self.gamma, target, smoothing),
+            false => smoothed_reserve(
+                self.reserves.1,
+                self.gamma,
+                self.sqrt_k * self.sqrt_k / self.gamma / target,
+                smoothing,
+            ),
+        }
+    }
+
+    /// Returns the reserve of the other side the pool ends with once a swap leaves it with
+    /// `reserve` of its side `is_token0`, keeping its fee on whichever side it receives.
+    fn swap_reserve(&self, reserve: f64, is_token0: bool) -> f64 {
+        let (own, other) = match is_token0 {
+            true => self.reserves,
+            false => (self.reserves.1, self.reserves.0),
+        };
+        if reserve >= own {
+            own * other / (own + self.gamma * (reserve - own))
+        } else {
+            other + (own * other / reserve - other) / self.gamma
+        }
+    }
+}
+
+/// The pools of the component of the output token in a fee-charging trade, and the totals of
+/// every token they must end up holding.
+struct FeeTrade {
+    pools: Vec<FeePool>,
+    /// Pools of every token, and whether it is their `token0`
+    adjacency: Vec<Vec<(usize, bool)>>,
+    totals: Vec<f64>,
+    /// Tokens of the component but the output one, whose prices are solved for
+    free: Vec<usize>,
+    /// Row of every free token in the Newton steps, `usize::MAX` for the others
+    row_of: Vec<usize>,
+    output_token: usize,
+}
+
+impl FeeTrade {
+    /// Returns the reserve of `token` its pools hold at the prices `q`.
+    fn holdings(&self, q: &[f64], token: usize) -> f64 {
+        self.adjacency[token]
+            .iter()
+            .map(|&(pool, is_token0)| self.pools[pool].reserve_at(q, is_token0))
+            .sum()
+    }
+
+    /// Returns the largest imbalance of a free token at the prices `q`, relative to its total.
+    fn imbalance(&self, q: &[f64]) -> f64 {
+        self.free
+            .iter()
+            .map(|&token| (self.holdings(q, token) / self.totals[token] - 1.0).abs())
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns the imbalance of every free token relative to its total at the prices `q` along
+    /// the fee bands smoothed by `smoothing`, in row order.
+    fn smoothed_imbalances(&self, q: &[f64], smoothing: f64) -> Vec<f64> {
+        self.free
+            .iter()
+            .map(|&token| {
+                let holdings = self.adjacency[token]
+                    .iter()
+                    .map(|&(pool, is_token0)| {
+                        self.pools[pool]
+                            .smoothed_reserve_at(q, is_token0, smoothing)
+                            .0
+                    })
+                    .sum::<f64>();
+                holdings / self.totals[token] - 1.0
+            })
+            .collect()
+    }
+
+    /// Returns the Newton step of the logarithms of the prices of the free tokens from the
+    /// prices `q` on the balances smoothed by `smoothing`, given their `imbalances`, or `None`
+    /// if its linear system is singular.
+    ///
+    /// A pool holding `r` of a token adds the derivative `r'` of `r` in the logarithm of its
+    /// price ratio to the derivative of the holdings of the token in its own logarithm, and
+    /// `−r'` in that of the other token. Deep in its band, `r'` vanishes: it is kept above
+    /// `smoothing · r`, so that tokens whose pools all idle still move with their neighbours.
+    fn newton_step(&self, q: &[f64], smoothing: f64, imbalances: &[f64]) -> Option<Vec<f64>> {
+        let size = self.free.len();
+        let mut rows = vec![vec![0.0; size + 1]; size];
+        for pool in &self.pools {
+            for (is_token0, token, other) in [
+                (true, pool.tokens.0, pool.tokens.1),
+                (false, pool.tokens.1, pool.tokens.0),
+            ] {
+                let row = self.row_of[token];
+                if row == usize::MAX {
+                    continue;
+                }
+                let (reserve, derivative) = pool.smoothed_reserve_at(q, is_token0, smoothing);
+                let derivative = derivative.max(smoothing * reserve) / self.totals[token];
+                rows[row][row] += derivative;
+                if self.row_of[other] != usize::MAX {
+                    rows[row][self.row_of[other]] -= derivative;
+                }
+            }
+        }
+        for (row, imbalance) in rows.iter_mut().zip(imbalances) {
+            row[size] = -imbalance;
+        }
+        solve_linear_system(rows)
+    }
+
+    /// Moves the prices `q` along `step` to where the value given up by the pools stops
+    /// decreasing, given the current smoothed `imbalances` of the tokens, which it updates.
+    /// Returns whether the prices moved.
+    ///
+    /// Every token being worth `1 / q²`, the value given up by the pools is a convex function
+    /// of the token values, the dual of the trade, whose derivative along the step is the sum of
+    /// the excess holdings of the tokens weighted by their values and changes: its zero is
+    /// bisected unless it stays negative over the whole step. Unlike the imbalances, it
+    /// locates the point where a step moving a token across its fee bands balances it.
+    fn line_search(
+        &self,
+        q: &mut [f64],
+        step: &[f64],
+        smoothing: f64,
+        imbalances: &mut Vec<f64>,
+    ) -> bool {
+        let largest = step
+            .iter()
+            .fold(0.0, |max: f64, change| max.max(change.abs()));
+        let length = match largest > MAX_LOG_STEP {
+            true => MAX_LOG_STEP / largest,
+            false => 1.0,
+        };
+        let mut stepped = q.to_vec();
+        let mut stepped_imbalances = Vec::new();
+        let mut slope = |fraction: f64, stepped_imbalances: &mut Vec<f64>| {
+            for (&token, &change) in self.free.iter().zip(step) {
+                stepped[token] = q[token] * (fraction * length * change).exp();
+            }
+            *stepped_imbalances = self.smoothed_imbalances(&stepped, smoothing);
+            self.free
+                .iter()
+                .zip(step)
+                .zip(stepped_imbalances.iter())
+                .map(|((&token, &change), imbalance)| {
+                    imbalance * self.totals[token] / (stepped[token] * stepped[token]) * change
+                })
+                .sum::<f64>()
+        };
+        let initial = slope(0.0, &mut stepped_imbalances);
+        if initial.is_nan() || initial >= 0.0 {
+            return false;
+        }
+        let (mut low, mut high) = (0.0, 1.0);
+        match slope(1.0, &mut stepped_imbalances) <= 0.0 {
+            true => low = 1.0,
+            false => {
+                for _ in 0..LINE_SEARCH_BISECTIONS {
+                    let middle = (low + high) / 2.0;
+                    match slope(middle, &mut stepped_imbalances) <= 0.0 {
+                        true => low = middle,
+                        false => high = middle,
+                    }
+                }
+            }
+        }
+        if low == 0.0 {
+            return false;
+        }
+        for (&token, &change) in self.free.iter().zip(step) {
+            q[token] *= (low * length * change).exp();
+        }
+        *imbalances = self.smoothed_imbalances(q, smoothing);
+        true
+    }
+
+    /// Returns the reserves every pool ends with at the prices `q`, in pool order, and the
+    /// amount of the output token extracted, or `None` if the imbalances left cannot be
+    /// settled.
+    ///
+    /// At the equilibrium the pools hold the totals of every token: what they hold more or less
+    /// is settled through the pools towards the output token, along a breadth-first tree from
+    /// it, the pool of every token holding the most of it towards the output among those that
+    /// trade, if any, so that idle pools are not moved by rounding. Each such pool swaps the
+    /// excess of its token, keeping its fee, and passes the change of its other reserve on to
+    /// the next token, the output token absorbing the last change.
+    fn reserves(&self, q: &[f64]) -> Option<(Vec<(f64, f64)>, f64)> {
+        let mut reserves = self
+            .pools
+            .iter()
+            .map(|pool| (pool.reserve_at(q, true), pool.reserve_at(q, false)))
+            .collect::<Vec<_>>();
+        let trading = self
+            .pools
+            .iter()
+            .zip(&reserves)
+            .map(|(pool, &reserves)| reserves != pool.reserves)
+            .collect::<Vec<_>>();
+        let mut excess = self.totals.iter().map(|total| -total).collect::<Vec<_>>();
+        for (pool, &(reserve_0, reserve_1)) in self.pools.iter().zip(&reserves) {
+            excess[pool.tokens.0] += reserve_0;
+            excess[pool.tokens.1] += reserve_1;
+        }
+
+        let mut distances = vec![usize::MAX; self.totals.len()];
+        distances[self.output_token] = 0;
+        let mut order = Vec::new();
+        let mut queue = VecDeque::from([self.output_token]);
+        while let Some(token) = queue.pop_front() {
+            order.push(token);
+            for &(pool, is_token0) in &self.adjacency[token] {
+                let pool = &self.pools[pool];
+                let other = match is_token0 {
+                    true => pool.tokens.1,
+                    false => pool.tokens.0,
+                };
+                if distances[other] == usize::MAX {
+                    distances[other] = distances[token] + 1;
+                    queue.push_back(other);
+                }
+            }
+        }
+
+        for &token in order.iter().skip(1).rev() {
+            let side = |&(pool, is_token0): &(usize, bool)| {
+                let pool = &self.pools[pool];
+                match is_token0 {
+                    true => (pool.tokens.1, pool.reserves.0),
+                    false => (pool.tokens.0, pool.reserves.1),
+                }
+            };
+            let &(pool, is_token0) = self.adjacency[token]
+                .iter()
+                .filter(|link| distances[side(link).0] + 1 == distances[token])
+                .max_by(|a, b| {
+                    (trading[a.0], side(a).1)
+                        .partial_cmp(&(trading[b.0], side(b).1))
+                        .unwrap_or(Ordering::Equal)
+                })?;
+            let fee_pool = &self.pools[pool];
+            let (own, other) = match is_token0 {
+                true => (&mut reserves[pool].0, fee_pool.tokens.1),
+                false => (&mut reserves[pool].1, fee_pool.tokens.0),
+            };
+            *own -= excess[token];
+            if !(own.is_finite() && *own > 0.0) {
+                return None;
+            }
+            let other_reserve = fee_pool.swap_reserve(*own, is_token0);
+            let previous = match is_token0 {
+                true => std::mem::replace(&mut reserves[pool].1, other_reserve),
+                false => std::mem::replace(&mut reserves[pool].0, other_reserve),
+            };
+            excess[other] += other_reserve - previous;
+            excess[token] = 0.0;
+        }
+        Some((reserves, -excess[self.output_token]))
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Returns the fee `pool` charges on its input under the fee model of the router.
+    pub(super) fn pool_fee(&self, pool: &UniV2Pool) -> f64 {
+        match self.config.fee_model {
+            FeeModel::FeeFree => 0.0,
+            FeeModel::Charged => pool.fee,
+        }
+    }
+
+    /// Gathers the active pools of the component of `output_token` for a fee-charging trade.
+    fn fee_trade(&self, input_token: usize, output_token: usize, input_amount: f64) -> FeeTrade {
+        let graph = &self.token_graph;
+        let mut pools = Vec::new();
+        let mut adjacency = vec![Vec::new(); self.tokens.len()];
+        let mut totals = vec![0.0; self.tokens.len()];
+        for (position, pool) in self.pools.iter().enumerate() {
+            let (index_0, index_1) = (self.token_index[pool.token0], self.token_index[pool.token1]);
+            if !graph.is_pool_active(pool, index_0, index_1)
+                || !graph.are_connected(output_token, index_0)
+            {
+                continue;
+            }
+            adjacency[index_0].push((pools.len(), true));
+            adjacency[index_1].push((pools.len(), false));
+            let (curve0, curve1) = pool.curve_reserves();
+            totals[index_0] += curve0;
+            totals[index_1] += curve1;
+            pools.push(FeePool {
+                position,
+                tokens: (index_0, index_1),
+                reserves: (curve0, curve1),
+                sqrt_k: curve0.sqrt() * curve1.sqrt(),
+                gamma: 1.0 - self.pool_fee(pool),
+            });
+        }
+        totals[input_token] += input_amount;
+
+        let free = graph
+            .component_tokens(output_token)
+            .into_iter()
+            .filter(|&token| token != output_token && !adjacency[token].is_empty())
+            .collect::<Vec<_>>();
+        let mut row_of = vec![usize::MAX; self.tokens.len()];
+        for (row, &token) in free.iter().enumerate() {
+            row_of[token] = row;
+        }
+        FeeTrade {
+            pools,
+            adjacency,
+            totals,
+            free,
+            row_of,
+            output_token,
+        }
+    }
+
+    /// Solves a trade over the individual pools of the component of `output_token`, every pool
+    /// keeping its fee, and leaves the resulting square-root prices in the token graph; the
+    /// pools are only moved by [`Router::write_back_fee_reserves`].
+    ///
+    /// A pool holding `x` of `u` and `y` of `v`, of invariant `k`, sells `u` to the trade once
+    /// `√(k/γ) · q_u / q_v` falls below `x`, and buys it once that amount exceeds `x / γ`: the
+    /// reserve of `u` it ends with is `r(q_u / q_v)`, see [`fee_reserve`], a non-decreasing
+    /// piecewise-linear function of `q_u`. The equilibrium balances `Σ r = T_u` for every token
+    /// but the output one, which keeps its price.
+    ///
+    /// Across its fee band, a pool holds its reserves whatever the prices, which leaves Newton
+    /// steps on these balances singular. They are taken on balances whose fee bands are smoothed
+    /// instead, see [`smoothed_reserve`], the smoothing narrowing tenfold from `1e-3` every time
+    /// the smoothed balances hold within a hundredth of it, down to `1e-15`. The iteration stops once no token
+    /// is out of balance by the tolerance of the solver relative to its total, or by `1e-12`,
+    /// the last imbalances being settled by the write-back, see [`FeeTrade::reserves`]. The
+    /// deadline of the token graph applies.
+    pub(super) fn apply_trade_with_fees(
+        &mut self,
+        input_token: usize,
+        output_token: usize,
+        input_amount: f64,
+        solver: SolverConfig,
+    ) -> SolveOutcome {
+        let trade = self.fee_trade(input_token, output_token, input_amount);
+        let mut q = (0..self.tokens.len())
+            .map(|token| self.token_graph.price(token))
+            .collect::<Vec<_>>();
+        let mut outcome = SolveOutcome {
+            output_amount: 0.0,
+            iterations: 0,
+            residual: trade.imbalance(&q),
+            converged: false,
+            invalid_price: None,
+            numerical_error: None,
+            damping_events: 0,
+            final_damping: 1.0,
+            deadline_reached: false,
+            bisected: false,
+            normalization_skipped: false,
+            token_updates: 0,
+        };
+        let tolerance = solver.tolerance.max(IMBALANCE_TOLERANCE);
+        let deadline = self.token_graph.deadline();
+        let mut smoothing = INITIAL_SMOOTHING;
+        let mut imbalances = trade.smoothed_imbalances(&q, smoothing);
+        while outcome.residual > tolerance && outcome.iterations < solver.max_iterations {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                outcome.deadline_reached = true;
+                break;
+            }
+            let smoothed = imbalances.iter().fold(0.0, |max: f64, x| max.max(x.abs()));
+            if smoothed <= STAGE_TOLERANCE * smoothing && smoothing > FINAL_SMOOTHING {
+                smoothing = (smoothing * SMOOTHING_DECREASE).max(FINAL_SMOOTHING);
+                imbalances = trade.smoothed_imbalances(&q, smoothing);
+                continue;
+            }
+
+            outcome.iterations += 1;
+            let stepped = trade
+                .newton_step(&q, smoothing, &imbalances)
+                .is_some_and(|step| trade.line_search(&mut q, &step, smoothing, &mut imbalances));
+            if !stepped {
+                // The smoothed balances hold as well as the rounding allows
+                match smoothing > FINAL_SMOOTHING {
+                    true => {
+                        smoothing = (smoothing * SMOOTHING_DECREASE).max(FINAL_SMOOTHING);
+                        imbalances = trade.smoothed_imbalances(&q, smoothing);
+                    }
+                    false => break,
+                }
+            }
+            if let Some((token, price)) = trade
+                .free
+                .iter()
+                .map(|&token| (token, q[token]))
+                .find(|(_, price)| !(price.is_finite() && *price > 0.0))
+            {
+                outcome.invalid_price = Some((token, price));
+                return outcome;
+            }
+            outcome.residual = trade.imbalance(&q);
+        }
+        outcome.converged = outcome.residual <= tolerance;
+
+        match trade.reserves(&q) {
+            Some((_, output_amount)) => outcome.output_amount = output_amount,
+            None => outcome.converged = false,
+        }
+        for &token in &trade.free {
+            self.token_graph.set_price(token, q[token]);
+        }
+        outcome
+    }
+
+    /// Returns the curve reserves every pool of the component of `output_token` trades to at the
+    /// prices of the token graph, with its position, see [`Router::apply_trade_with_fees`];
+    /// none if they cannot be settled.
+    pub(super) fn fee_trade_reserves(
+        &self,
+        input_token: usize,
+        output_token: usize,
+        input_amount: f64,
+    ) -> Vec<(usize, (f64, f64))> {
+        let trade = self.fee_trade(input_token, output_token, input_amount);
+        let q = (0..self.tokens.len())
+            .map(|token| self.token_graph.price(token))
+            .collect::<Vec<_>>();
+        match trade.reserves(&q) {
+            Some((reserves, _)) => trade
+                .pools
+                .iter()
+                .map(|fee_pool| fee_pool.position)
+                .zip(reserves)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Moves every pool of the component of `output_token` to the reserves it trades to at the
+    /// prices of the token graph, see [`Router::apply_trade_with_fees`], and rebuilds the token
+    /// graph from the pools.
+    pub(super) fn write_back_fee_reserves(
+        &mut self,
+        input_token: usize,
+        output_token: usize,
+        input_amount: f64,
+    ) -> Vec<TradedPool<'l>> {
+        let trade = self.fee_trade(input_token, output_token, input_amount);
+        let q = (0..self.tokens.len())
+            .map(|token| self.token_graph.price(token))
+            .collect::<Vec<_>>();
+        let mut traded_pools = Vec::new();
+        if let Some((reserves, _)) = trade.reserves(&q) {
+            for (fee_pool, after) in trade.pools.iter().zip(reserves) {
+                let pool = &mut self.pools[fee_pool.position];
+                if after != fee_pool.reserves {
+                    let before = (pool.reserve0, pool.reserve1);
+                    pool.reserve0 = after.0 - pool.virtual_reserves.0;
+                    pool.reserve1 = after.1 - pool.virtual_reserves.1;
+                    traded_pools.push(TradedPool {
+                        id: self.pool_ids[fee_pool.position].clone(),
+                        token0: pool.token0,
+                        token1: pool.token1,
+                        before,
+                        after: (pool.reserve0, pool.reserve1),
+                        exact: advance_raw_reserves(pool, before),
+                    });
+                }
+            }
+        }
+        self.rebuild_token_graph();
+        traded_pools
+    }
+}
+
+/// Returns the reserve of a token that a pool holding `reserve` of it, of fee factor `γ`,
+/// ends with once traded to the square-root price ratio `q_token / q_other`, given `target`,
+/// its fee-free holdings `√(k/γ) · q_token / q_other` at that ratio.
+///
+/// Along the fee-charging curve, the pool holds `target` when it sells the token,
+/// `target − reserve · (1/γ − 1)` when it buys it, the fee staying in the pool, and keeps
+/// `reserve` in between, where no trade covers the fee.
+fn fee_reserve(reserve: f64, gamma: f64, target: f64) -> f64 {
+    if target < reserve {
+        target
+    } else if target * gamma > reserve {
+        target - reserve * (1.0 / gamma - 1.0)
+    } else {
+        reserve
+    }
+}
+
+/// Returns the reserve of a token that a pool holding `reserve` of it, of fee factor `γ`, ends
+/// with at its fee-free holdings `target`, see [`fee_reserve`], with both edges of its fee band
+/// smoothed over a relative width `smoothing`, and the derivative of that reserve in the
+/// logarithm of `target`.
+///
+/// With `s = target / reserve`, the reserve is `reserve · (1 − μ · ln(1 + e^((1 − s)/μ)) +
+/// μ/γ · ln(1 + e^((γs − 1)/μ)))` for the smoothing `μ`, which tends to [`fee_reserve`] as `μ`
+/// vanishes, within `μ · ln 2 · reserve / γ` of it.
+fn smoothed_reserve(reserve: f64, gamma: f64, target: f64, smoothing: f64) -> (f64, f64) {
+    let ratio = target / reserve;
+    let (sell, buy) = ((1.0 - ratio) / smoothing, (gamma * ratio - 1.0) / smoothing);
+    let softplus = |z: f64| z.max(0.0) + (-z.abs()).exp().ln_1p();
+    let logistic = |z: f64| 1.0 / (1.0 + (-z).exp());
+    (
+        reserve * (1.0 - smoothing * softplus(sell) + smoothing / gamma * softplus(buy)),
+        target * (logistic(sell) + logistic(buy)),
+    )
+}
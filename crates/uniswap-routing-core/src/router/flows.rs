@@ -0,0 +1,72 @@
+use crate::router::Router;
+
+use std::collections::HashMap;
+
+/// Net movement of tokens across the pools of one pair during a trade, see
+/// [`Router::flow_decomposition`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EdgeFlow<'l> {
+    /// Token the pools of the pair received on balance
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    /// Net amount of `input_token` the pools of the pair received
+    pub input_amount: f64,
+    /// Net amount of `output_token` the pools of the pair paid out
+    pub output_amount: f64,
+}
+
+impl<'l> Router<'l> {
+    /// Returns the flows of the last trade along the pairs of the token graph, one per pair
+    /// whose pools moved, ordered by the token indices of the pair.
+    ///
+    /// The flow of a pair nets the reserve changes of its pools, which the trade moved to their
+    /// shares `√k · q_u / q_v` of the new equilibrium: it is the change of the implied reserves
+    /// `K · q_u / q_v` of the pair. The flows are conserved: at every token other than the traded
+    /// ones, the amounts received from the pairs equal the amounts sent to them, while the input
+    /// token sends the input amount on balance and the output token receives the output amount.
+    ///
+    /// Pools of the same pair may be traded in opposite directions when the trade also absorbs
+    /// arbitrage left between them, the flow of the pair being their net. Returns an empty list
+    /// before the first trade.
+    pub fn flow_decomposition(&self) -> Vec<EdgeFlow<'l>> {
+        let Some(trade) = &self.last_trade else {
+            return Vec::new();
+        };
+
+        // Net reserve changes of every pair, keyed by the token indices of the pair in order
+        let mut deltas = HashMap::<(usize, usize), (f64, f64)>::new();
+        for pool in &trade.pools {
+            let (index_0, index_1) = (self.token_index[pool.token0], self.token_index[pool.token1]);
+            let delta0 = pool.after.0 - pool.before.0;
+            let delta1 = pool.after.1 - pool.before.1;
+            let (key, delta) = match index_0 < index_1 {
+                true => ((index_0, index_1), (delta0, delta1)),
+                false => ((index_1, index_0), (delta1, delta0)),
+            };
+            let entry = deltas.entry(key).or_insert((0.0, 0.0));
+            entry.0 += delta.0;
+            entry.1 += delta.1;
+        }
+
+        let mut deltas = deltas.into_iter().collect::<Vec<_>>();
+        deltas.sort_by_key(|&(key, _)| key);
+        deltas
+            .into_iter()
+            .map(|((u, v), (delta_u, delta_v))| match delta_u > 0.0 {
+                true => EdgeFlow {
+                    input_token: self.tokens[u],
+                    output_token: self.tokens[v],
+                    input_amount: delta_u,
+                    output_amount: -delta_v,
+                },
+                false => EdgeFlow {
+                    input_token: self.tokens[v],
+                    output_token: self.tokens[u],
+                    input_amount: delta_v,
+                    output_amount: -delta_u,
+                },
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,64 @@
+use crate::router::builder::HistoryConfig;
+
+use std::collections::VecDeque;
+
+/// A successful trade, as recorded by the router when history is enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeRecord<'l> {
+    /// Position of the trade among all the trades ever recorded by the router
+    pub sequence: u64,
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    pub input_amount: f64,
+    pub output_amount: f64,
+    /// Number of fixed-point sweeps the solver used
+    pub iterations: usize,
+}
+
+/// Ring buffer of trade records, kept contiguous so it can be borrowed as a slice.
+#[derive(Debug, Clone)]
+pub(super) struct TradeHistory<'l> {
+    config: HistoryConfig,
+    records: VecDeque<TradeRecord<'l>>,
+    next_sequence: u64,
+}
+
+impl<'l> TradeHistory<'l> {
+    pub(super) fn new(config: HistoryConfig) -> Self {
+        Self {
+            config,
+            records: VecDeque::new(),
+            next_sequence: 0,
+        }
+    }
+
+    /// Appends a record built from the next sequence number, evicting the oldest record if the
+    /// capacity is reached. Does nothing when history is disabled.
+    pub(super) fn record(&mut self, record: impl FnOnce(u64) -> TradeRecord<'l>) {
+        let capacity = match self.config {
+            HistoryConfig::Disabled => return,
+            HistoryConfig::Unbounded => usize::MAX,
+            HistoryConfig::Capped(capacity) => capacity,
+        };
+        let record = record(self.next_sequence);
+        self.next_sequence += 1;
+
+        if capacity == 0 {
+            return;
+        }
+        if self.records.len() == capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        self.records.make_contiguous();
+    }
+
+    pub(super) fn records(&self) -> &[TradeRecord<'l>] {
+        // Contiguity is restored after every push
+        self.records.as_slices().0
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.records.clear();
+    }
+}
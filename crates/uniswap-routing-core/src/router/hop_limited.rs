@@ -0,0 +1,39 @@
+use crate::{
+    error::RouterError,
+    router::{Router, check_trade_amount, token_graph::HopLimit},
+};
+
+impl<'l> Router<'l> {
+    /// Like [`Router::solve`], but only routes along paths of at most `max_hops` pools between
+    /// `input_token` and `output_token`: the equilibrium is restricted to the edges lying on such
+    /// a path, every other pool keeping its reserves.
+    ///
+    /// `max_hops = 1` trades against the direct pools of the pair only, while a `max_hops` at
+    /// least the diameter of the graph gives the same answer as [`Router::solve`], and fails in the
+    /// same cases, with [`RouterError::NotConnected`] if no such path links the tokens.
+    pub fn solve_with_max_hops(
+        &mut self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+        max_hops: usize,
+    ) -> Result<f64, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        if !check_trade_amount(input_amount)? {
+            return Ok(0.0);
+        }
+        self.settle_if_pending();
+
+        let hop_limit = HopLimit::new(&self.token_graph, input_index, output_index, max_hops);
+        self.token_graph
+            .set_hop_limit(Some(hop_limit), &self.pools, &self.token_index);
+        let trade = self
+            .solve_trade(input_index, output_index, input_amount)
+            .map(|outcome| self.commit_trade(input_index, output_index, input_amount, outcome));
+        self.token_graph
+            .set_hop_limit(None, &self.pools, &self.token_index);
+
+        Ok(trade?.output_amount)
+    }
+}
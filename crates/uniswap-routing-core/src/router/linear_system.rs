@@ -0,0 +1,33 @@
+/// Solves the square system whose rows are `[a | b]` by Gaussian elimination with partial
+/// pivoting, `None` if it is singular to working precision.
+pub(super) fn solve_linear_system(mut rows: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let size = rows.len();
+    let scale = rows
+        .iter()
+        .flat_map(|row| &row[..size])
+        .fold(0.0_f64, |max, value| max.max(value.abs()));
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))?;
+        if rows[pivot][column].abs() <= f64::EPSILON * scale || !rows[pivot][column].is_finite() {
+            return None;
+        }
+        rows.swap(column, pivot);
+        let (upper, lower) = rows.split_at_mut(column + 1);
+        let pivot_row = &upper[column];
+        for row in lower {
+            let factor = row[column] / pivot_row[column];
+            for (value, pivot_value) in row[column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+    let mut solution = vec![0.0; size];
+    for row in (0..size).rev() {
+        let known = (row + 1..size)
+            .map(|k| rows[row][k] * solution[k])
+            .sum::<f64>();
+        solution[row] = (rows[row][size] - known) / rows[row][row];
+    }
+    Some(solution)
+}
@@ -0,0 +1,381 @@
+use crate::router::LiquidityStorage;
+
+use itertools::Either;
+use std::{ops::Deref, sync::OnceLock};
+
+/// Fraction of the tokens a token of the dense layout must share pools with for the solver to
+/// sum its row in full, see [`PairwiseLiquidities::full_row`].
+const FULL_ROW_MIN_DENSITY: f64 = 0.125;
+
+/// Geometric liquidities of the token pairs, symmetric, in one of two layouts answering the
+/// same queries with the same values, so that the solver sums over the neighbors of a token in
+/// the same order, by token index, and the quotes are the same bit for bit whichever is used.
+///
+/// Both layouts are built at once from the list of pairs, see
+/// [`PairwiseLiquidities::from_pairs`], on the first query after the pool set changes, see
+/// [`LazyLiquidities`].
+#[derive(Debug, Clone)]
+pub(super) enum PairwiseLiquidities {
+    /// Every liquidity, zero for the pairs without pools, in a single buffer holding the matrix
+    /// row by row, both sides of every pair, see [`PairwiseLiquidities::index`]: `n²` floats,
+    /// scanned in full to list the neighbors of a token, but a single indexing per lookup. The
+    /// number of neighbors of every token is kept alongside, see
+    /// [`PairwiseLiquidities::full_row`]
+    Dense {
+        len: usize,
+        rows: Vec<f64>,
+        degrees: Vec<usize>,
+    },
+    /// The neighbors of every token with their liquidities, sorted by token index, packed one
+    /// token after the other in compressed sparse rows: those of token `t` are
+    /// `pairs[offsets[t]..offsets[t + 1]]`. Memory and scans in the number of pairs, lookups by
+    /// binary search
+    Sparse {
+        offsets: Vec<usize>,
+        pairs: Vec<(usize, f64)>,
+    },
+}
+
+/// Liquidities of the pools of a graph, laid out as [`PairwiseLiquidities`] on first query, so
+/// that a change of the pool set, [`Router::add_pool`](crate::router::Router::add_pool) or
+/// [`Router::remove_pool`](crate::router::Router::remove_pool), only lists the pairs, and
+/// successive changes lay the liquidities out once, for the next solve.
+#[derive(Debug, Clone)]
+pub(super) struct LazyLiquidities {
+    len: usize,
+    storage: LiquidityStorage,
+    /// The `(u, v, liquidity)` of every pool, until laid out by [`LazyLiquidities::lay_out`]
+    pending: Vec<(usize, usize, f64)>,
+    laid_out: OnceLock<PairwiseLiquidities>,
+}
+
+impl LazyLiquidities {
+    /// Returns the liquidities of `n` tokens, to be laid out as [`PairwiseLiquidities::from_pairs`]
+    /// does from `pairs`.
+    pub(super) fn new(
+        n: usize,
+        storage: LiquidityStorage,
+        pairs: impl IntoIterator<Item = (usize, usize, f64)>,
+    ) -> Self {
+        Self {
+            len: n,
+            storage,
+            pending: pairs.into_iter().collect(),
+            laid_out: OnceLock::new(),
+        }
+    }
+
+    /// Lays the liquidities out if they are not yet, and frees the list of pairs.
+    pub(super) fn lay_out(&mut self) {
+        let pairs = std::mem::take(&mut self.pending);
+        self.laid_out
+            .get_or_init(|| PairwiseLiquidities::from_pairs(self.len, self.storage, pairs));
+    }
+
+    /// Lays the liquidities out from the list of pairs, which it keeps, on the first query.
+    #[cold]
+    fn lay_out_pending(&self) -> &PairwiseLiquidities {
+        self.laid_out.get_or_init(|| {
+            PairwiseLiquidities::from_pairs(self.len, self.storage, self.pending.iter().copied())
+        })
+    }
+
+    /// Returns whether the liquidities are laid out.
+    #[cfg(test)]
+    pub(super) fn is_laid_out(&self) -> bool {
+        self.laid_out.get().is_some()
+    }
+
+    /// Iterates over the pairs of tokens linked by a non-zero liquidity, in no particular order
+    /// and possibly more than once, without laying the liquidities out.
+    pub(super) fn linked_pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        match self.laid_out.get() {
+            Some(liquidities) => Either::Left(liquidities.pairs().map(|(u, v, _)| (u, v))),
+            None => Either::Right(
+                self.pending
+                    .iter()
+                    .filter(|&&(_, _, liquidity)| liquidity != 0.0)
+                    .map(|&(u, v, _)| (u, v)),
+            ),
+        }
+    }
+}
+
+impl Deref for LazyLiquidities {
+    type Target = PairwiseLiquidities;
+
+    /// Inlined down to the check of the laid-out liquidities, which every update of the sweeps
+    /// goes through, the layout itself kept out of line.
+    #[inline]
+    fn deref(&self) -> &PairwiseLiquidities {
+        match self.laid_out.get() {
+            Some(liquidities) => liquidities,
+            None => self.lay_out_pending(),
+        }
+    }
+}
+
+impl PairwiseLiquidities {
+    /// Returns the liquidities of `n` tokens, in the layout `storage` picks for them, summing
+    /// those of every `(u, v, liquidity)` of `pairs` into the pair `(u, v)`, on both sides, in
+    /// the order of `pairs`.
+    ///
+    /// [`LiquidityStorage::Auto`] beyond [`LiquidityStorage::DENSE_MAX_TOKENS`] tokens first
+    /// packs the pairs sparsely to count them, then lays them out densely if they are more than
+    /// [`LiquidityStorage::DENSE_MIN_DENSITY`] of all the pairs; each liquidity is then a single
+    /// sum, the same as if it had been summed densely.
+    pub(super) fn from_pairs(
+        n: usize,
+        storage: LiquidityStorage,
+        pairs: impl IntoIterator<Item = (usize, usize, f64)>,
+    ) -> Self {
+        match storage {
+            LiquidityStorage::Auto if n <= LiquidityStorage::DENSE_MAX_TOKENS => {
+                Self::dense(n, pairs)
+            }
+            LiquidityStorage::Auto => {
+                let sparse = Self::sparse(n, pairs);
+                match sparse.density() > LiquidityStorage::DENSE_MIN_DENSITY {
+                    true => Self::dense(n, sparse.pairs()),
+                    false => sparse,
+                }
+            }
+            LiquidityStorage::Dense => Self::dense(n, pairs),
+            LiquidityStorage::Sparse => Self::sparse(n, pairs),
+        }
+    }
+
+    /// Lays out the liquidities of `n` tokens in a matrix.
+    fn dense(n: usize, pairs: impl IntoIterator<Item = (usize, usize, f64)>) -> Self {
+        let mut liquidities = Self::Dense {
+            len: n,
+            rows: vec![0.0; n * n],
+            degrees: Vec::new(),
+        };
+        for (u, v, liquidity) in pairs {
+            liquidities.add(u, v, liquidity);
+        }
+        if let Self::Dense { rows, degrees, .. } = &mut liquidities {
+            *degrees = rows
+                .chunks(n.max(1))
+                .map(|row| row.iter().filter(|&&liquidity| liquidity != 0.0).count())
+                .collect();
+        }
+        liquidities
+    }
+
+    /// Packs the liquidities of `n` tokens in compressed sparse rows.
+    fn sparse(n: usize, pairs: impl IntoIterator<Item = (usize, usize, f64)>) -> Self {
+        // Both sides of every pair, grouped by token then by neighbor, the stable sort keeping
+        // the order of `pairs` within a pair so that the sums are those of the dense layout
+        let mut sides = pairs
+            .into_iter()
+            .flat_map(|(u, v, liquidity)| [(u, v, liquidity), (v, u, liquidity)])
+            .collect::<Vec<_>>();
+        sides.sort_by_key(|&(u, v, _)| (u, v));
+        let mut offsets = vec![0; n + 1];
+        let mut packed = Vec::<(usize, f64)>::with_capacity(sides.len());
+        let mut last = None;
+        for (u, v, liquidity) in sides {
+            match last == Some((u, v)) {
+                true => packed.last_mut().expect("the pair was pushed").1 += liquidity,
+                false => {
+                    packed.push((v, liquidity));
+                    offsets[u + 1] += 1;
+                    last = Some((u, v));
+                }
+            }
+        }
+        for token in 0..n {
+            offsets[token + 1] += offsets[token];
+        }
+        Self::Sparse {
+            offsets,
+            pairs: packed,
+        }
+    }
+
+    /// Returns the number of pairs with non-zero liquidity over the number of pairs of distinct
+    /// tokens, zero below two tokens.
+    fn density(&self) -> f64 {
+        let n = self.len();
+        let pairs = (0..n)
+            .map(|token| self.neighbors(token).count())
+            .sum::<usize>()
+            / 2;
+        match n < 2 {
+            true => 0.0,
+            false => pairs as f64 / (n * (n - 1) / 2) as f64,
+        }
+    }
+
+    /// Iterates over every pair with non-zero liquidity `(u, v, liquidity)` once, with `u < v`,
+    /// sorted by `(u, v)`.
+    pub(super) fn pairs(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        (0..self.len()).flat_map(move |u| {
+            self.neighbors(u)
+                .filter(move |&(v, _)| u < v)
+                .map(move |(v, liquidity)| (u, v, liquidity))
+        })
+    }
+
+    /// Returns the number of tokens.
+    fn len(&self) -> usize {
+        match self {
+            Self::Dense { len, .. } => *len,
+            Self::Sparse { offsets, .. } => offsets.len() - 1,
+        }
+    }
+
+    /// Returns the layout in use, [`LiquidityStorage::Dense`] or [`LiquidityStorage::Sparse`].
+    pub(super) fn layout(&self) -> LiquidityStorage {
+        match self {
+            Self::Dense { .. } => LiquidityStorage::Dense,
+            Self::Sparse { .. } => LiquidityStorage::Sparse,
+        }
+    }
+
+    /// Returns the liquidity stored for `u` towards `v`, zero if they share no pool.
+    pub(super) fn get(&self, u: usize, v: usize) -> f64 {
+        match self {
+            Self::Dense { len, rows, .. } => rows[Self::index(*len, u, v)],
+            Self::Sparse { offsets, pairs } => {
+                let neighbors = &pairs[offsets[u]..offsets[u + 1]];
+                neighbors
+                    .binary_search_by_key(&v, |&(paired_token, _)| paired_token)
+                    .map_or(0.0, |position| neighbors[position].1)
+            }
+        }
+    }
+
+    /// Returns the liquidity stored for `u` towards `v`, inserting a zero one if they share no
+    /// pool, which shifts all the pairs of the tokens after `u` in the sparse layout. Both
+    /// layouts store a liquidity for each side of the pair, which the caller keeps equal, see
+    /// [`PairwiseLiquidities::add`].
+    fn get_mut(&mut self, u: usize, v: usize) -> &mut f64 {
+        match self {
+            Self::Dense { len, rows, .. } => &mut rows[Self::index(*len, u, v)],
+            Self::Sparse { offsets, pairs } => {
+                let start = offsets[u];
+                let position = pairs[start..offsets[u + 1]]
+                    .binary_search_by_key(&v, |&(paired_token, _)| paired_token)
+                    .unwrap_or_else(|position| {
+                        pairs.insert(start + position, (v, 0.0));
+                        for offset in &mut offsets[u + 1..] {
+                            *offset += 1;
+                        }
+                        position
+                    });
+                &mut pairs[start + position].1
+            }
+        }
+    }
+
+    /// Adds `liquidity` to the pair `(u, v)`, on both sides.
+    fn add(&mut self, u: usize, v: usize, liquidity: f64) {
+        *self.get_mut(u, v) += liquidity;
+        *self.get_mut(v, u) += liquidity;
+    }
+
+    /// Returns the liquidities of `token` towards every token, by token index, zero for the
+    /// tokens it shares no pool with, if the layout is dense and `token` shares pools with at
+    /// least [`FULL_ROW_MIN_DENSITY`] of the tokens. Past that density, a sum over the whole
+    /// row, zeros included, beats one skipping the zeros, whose pattern defeats the branch
+    /// predictor: 2.3 µs instead of 6.6 µs per update at 1,000 tokens and 30% density.
+    pub(super) fn full_row(&self, token: usize) -> Option<&[f64]> {
+        match self {
+            Self::Dense { len, rows, degrees }
+                if degrees[token] as f64 >= FULL_ROW_MIN_DENSITY * *len as f64 =>
+            {
+                Some(&rows[token * len..(token + 1) * len])
+            }
+            _ => None,
+        }
+    }
+
+    /// Iterates over the tokens paired with `token` by a non-zero liquidity, by token index.
+    pub(super) fn neighbors(
+        &self,
+        token: usize,
+    ) -> impl Iterator<Item = (usize, f64)> + Clone + '_ {
+        match self {
+            Self::Dense { len, rows, .. } => Either::Left(
+                rows[token * len..(token + 1) * len]
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .filter(|&(_, liquidity)| liquidity != 0.0),
+            ),
+            Self::Sparse { offsets, pairs } => Either::Right(
+                pairs[offsets[token]..offsets[token + 1]]
+                    .iter()
+                    .filter(|&&(_, liquidity)| liquidity != 0.0)
+                    .copied(),
+            ),
+        }
+    }
+
+    /// Returns the position of the liquidity of `u` towards `v` among those of `n` tokens in the
+    /// dense layout, the row of `u` holding its liquidities towards every token by token index.
+    fn index(n: usize, u: usize, v: usize) -> usize {
+        u * n + v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LazyLiquidities, PairwiseLiquidities};
+    use crate::router::LiquidityStorage;
+
+    #[test]
+    fn lazy_liquidities_are_laid_out_on_first_query() {
+        let pairs = [(0, 2, 1.5), (1, 2, 2.0), (0, 2, 0.5), (2, 3, 4.0)];
+        for storage in [LiquidityStorage::Dense, LiquidityStorage::Sparse] {
+            let mut lazy = LazyLiquidities::new(4, storage, pairs);
+            let mut linked = lazy.linked_pairs().collect::<Vec<_>>();
+            linked.sort_unstable();
+            linked.dedup();
+            assert_eq!(linked, [(0, 2), (1, 2), (2, 3)]);
+            assert!(!lazy.is_laid_out());
+
+            let eager = PairwiseLiquidities::from_pairs(4, storage, pairs);
+            assert_eq!(
+                lazy.pairs().collect::<Vec<_>>(),
+                eager.pairs().collect::<Vec<_>>()
+            );
+            assert!(lazy.is_laid_out());
+            assert_eq!(lazy.layout(), storage);
+            lazy.lay_out();
+            assert!(lazy.pending.is_empty());
+            assert_eq!(lazy.get(2, 0), 2.0);
+        }
+    }
+
+    #[test]
+    fn dense_index_at_the_boundaries() {
+        for n in 1..=64 {
+            let last = n - 1;
+            assert_eq!(PairwiseLiquidities::index(n, 0, 0), 0);
+            assert_eq!(PairwiseLiquidities::index(n, 0, last), last);
+            assert_eq!(PairwiseLiquidities::index(n, last, 0), last * n);
+            assert_eq!(PairwiseLiquidities::index(n, last, last), n * n - 1);
+            for token in 0..n {
+                assert_eq!(PairwiseLiquidities::index(n, token, token), token * (n + 1));
+            }
+        }
+    }
+
+    #[test]
+    fn dense_index_is_a_bijection_onto_the_buffer() {
+        for n in 1..=64 {
+            let mut seen = vec![false; n * n];
+            for u in 0..n {
+                for v in 0..n {
+                    let index = PairwiseLiquidities::index(n, u, v);
+                    assert!(!seen[index], "({u}, {v}) of {n} tokens shares {index}");
+                    seen[index] = true;
+                }
+            }
+        }
+    }
+}
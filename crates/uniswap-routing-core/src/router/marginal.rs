@@ -0,0 +1,33 @@
+use crate::{error::RouterError, router::Router};
+
+impl Router<'_> {
+    /// Returns the derivative of the output amount of the trade with respect to its input amount,
+    /// at `input_amount`, without modifying the router.
+    ///
+    /// The router splits a trade optimally across the pools, so by the envelope theorem the last
+    /// unit of input trades at the common marginal rate of the equilibrium after the trade,
+    /// `(q_out / q_in)²`: the derivative is read from a single solve instead of finite
+    /// differences, whose step would have to beat the solver tolerance. It decreases with the
+    /// trade size, the output being concave, and tends to the spot rate of the current
+    /// equilibrium as `input_amount → 0`. Out of equilibrium, the limit is the spot rate after
+    /// the arbitrage left in the pools is absorbed.
+    ///
+    /// Fails if either token is unknown or blacklisted, or if the solve does not converge, see
+    /// [`Router::solve`].
+    pub fn marginal_rate(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<f64, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        let mut scratch = self.scratch_copy();
+        scratch.settle_if_pending();
+
+        scratch.solve_trade(input_index, output_index, input_amount)?;
+        let graph = &scratch.token_graph;
+        let rate = graph.price(output_index) / graph.price(input_index);
+        Ok(rate * rate)
+    }
+}
@@ -0,0 +1,856 @@
+mod anderson;
+mod arbitrage;
+mod audit;
+mod blacklist;
+mod bounds;
+mod builder;
+mod capture;
+mod compaction;
+mod conditioning;
+mod content_hash;
+mod curve;
+mod deadline;
+mod depth;
+mod direct_split;
+mod dispersion;
+mod display;
+mod equilibrium;
+mod events;
+mod exact;
+mod external;
+mod fees;
+mod flows;
+mod history;
+mod hop_limited;
+mod linear_system;
+mod liquidities;
+mod marginal;
+mod pair_grid;
+mod parallel;
+mod path_search;
+mod pool_updates;
+mod profiles;
+mod quote_cache;
+mod quote_shape;
+mod replica;
+mod round_trip;
+mod routing_comparison;
+mod scratch;
+mod self_test;
+mod settle;
+mod staleness;
+mod swap_plan;
+mod token_graph;
+mod tvl;
+mod verification;
+mod warm_start;
+
+pub use crate::router::{
+    arbitrage::ArbitrageCycle,
+    audit::InvariantViolation,
+    bounds::QuoteBounds,
+    builder::{
+        AutoSettle, BandSelection, FallbackStrategy, FeeModel, HistoryConfig, LiquidityStorage,
+        PriceNormalization, RouterBuilder, RouterConfig, SolverAcceleration, SolverConfig,
+        SolverDomain, SolverKind, SweepMode, TradeSizeBand, TradeSizePolicy, WarmStartPolicy,
+    },
+    capture::{
+        CAPTURE_FORMAT_VERSION, CapturedOperation, CapturedPool, CapturedSolve, SessionCapture,
+    },
+    compaction::{CompactionMap, TokenId},
+    conditioning::{ComponentConditioning, ConditioningReport, EdgeSensitivity},
+    depth::PairDepth,
+    dispersion::PairDispersion,
+    events::{EventBatchSummary, EventKey, PoolEvent, PoolEventKind},
+    exact::ExactSwap,
+    external::{ExternalSolution, PairLiquidity, RoutingProblem},
+    flows::EdgeFlow,
+    history::TradeRecord,
+    pair_grid::PairQuote,
+    path_search::{PathQuote, PathSearchRequest, PathSearchResult},
+    profiles::{ConfigDiff, FieldDifference, Profile},
+    quote_shape::{ShapeReport, ShapeSample, ShapeViolation, ShapeViolationKind},
+    replica::{ReplicaRouter, ReplicaSnapshot, StateDelta},
+    routing_comparison::RoutingComparison,
+    self_test::{CheckOutcome, SelfTestCheck, SelfTestConfig, SelfTestReport},
+    settle::{ComponentSettlement, Settlement},
+    swap_plan::PoolSwap,
+    verification::{
+        PoolViolation, PoolViolationKind, Reconciliation, TokenImbalance, TokenReconciliation,
+        VerificationReport,
+    },
+    warm_start::{PreparedReplacement, TokenWarmStart, WarmStartDecision, WarmStartReport},
+};
+
+use crate::{
+    error::{PoolError, RouterError},
+    pool::Pool,
+    router::{
+        events::EventJournal,
+        exact::advance_raw_reserves,
+        history::TradeHistory,
+        pool_updates::PoolAddition,
+        quote_cache::QuoteCache,
+        replica::Replication,
+        token_graph::{SolveOutcome, TokenGraph},
+        verification::{LastTrade, TradedPool},
+    },
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+use {
+    itertools::Itertools as _,
+    std::{
+        collections::{HashMap, HashSet},
+        time::{Duration, Instant},
+    },
+};
+
+/// A committed trade.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeResult<'l> {
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    pub input_amount: f64,
+    pub output_amount: f64,
+    /// Number of fixed-point sweeps the solver used, zero when the prices came from elsewhere
+    pub iterations: usize,
+    /// Largest relative price change of the last sweep, zero when the prices came from elsewhere
+    pub residual: f64,
+}
+
+/// Diagnostics of the last equilibrium computed for a trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolveStats {
+    /// Number of fixed-point sweeps performed
+    pub iterations: usize,
+    /// Largest relative price change of the last sweep
+    pub final_residual: f64,
+    /// Whether the final residual fell below the tolerance, a trade failing with
+    /// [`RouterError::DidNotConverge`] otherwise
+    pub converged: bool,
+    /// Wall-clock time of the equilibrium computation
+    pub elapsed: Duration,
+    /// Input amount relative to the reserve of the input token in the equilibrium
+    pub size_ratio: f64,
+    /// Trade size band whose solver configuration was used, `None` for
+    /// [`RouterConfig::solver`]
+    pub band: Option<usize>,
+    /// Number of times the sweeps stalled and the solver halved its damping factor
+    pub damping_events: usize,
+    /// Damping factor of the last sweep, multiplying [`RouterConfig::relaxation`]; 1.0 unless
+    /// the iteration ended stalled
+    pub final_damping: f64,
+    /// Method that produced the output amount
+    pub path: SolvePath,
+    /// Whether the prices were left unnormalized after the solve, the reference price or the
+    /// geometric mean of the prices being zero or not finite, see [`PriceNormalization`]; the
+    /// output is unaffected, the normalization only rescaling the prices
+    pub normalization_skipped: bool,
+    /// Number of single-token price updates of the fixed-point iteration, counting those of
+    /// [`SolverKind::ActiveSet`] and those of the fixed-point iteration other solver kinds fall
+    /// back to; the steps of [`SolverKind::Newton`] and [`SolverKind::ConjugateGradient`] and
+    /// the sweeps of the bisection fallback, which move every price at once, are not counted
+    pub token_updates: usize,
+}
+
+/// Method that produced the output amount of a trade, see [`SolveStats::path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SolvePath {
+    /// The solver kind of the configuration, see [`RouterConfig::solver_kind`]
+    Solver,
+    /// The bisection fallback, after the solver exhausted its budget, see
+    /// [`FallbackStrategy::Bisection`]; the residual and the iterations are then those of the
+    /// bisection, the iterations including the sweeps of the solver
+    Bisection,
+}
+
+#[derive(Debug, Clone)]
+pub struct Router<'l> {
+    /// Mapping token -> integer index
+    token_index: HashMap<&'l str, usize>,
+    /// Mapping integer index -> token
+    tokens: Vec<&'l str>,
+    /// Individual pools, whose reserves are kept consistent with the equilibrium after each trade
+    pools: Vec<UniV2Pool>,
+    /// Identifiers of the pools, index-aligned with `pools`
+    pool_ids: Vec<PoolId>,
+    /// Number of pools ever registered, from which the next identifier is derived
+    next_pool_id: u64,
+    /// Internal representation of the tokens and their pools relationships
+    token_graph: TokenGraph,
+    /// Configuration the router was built with
+    config: RouterConfig,
+    /// Successful trades, when enabled in the configuration
+    history: TradeHistory<'l>,
+    /// Replayable record of the session, when enabled in the configuration
+    capture: Option<SessionCapture>,
+    /// Deduplication and rollback state of the pool events
+    events: EventJournal<'l>,
+    /// Generation of the token index layout, identifying the compaction it results from
+    generation: u32,
+    /// Highest generation ever issued, so that generations are never reused across rollbacks
+    latest_generation: u32,
+    /// Diagnostics of the last trade solved by the router
+    last_solve_stats: Option<SolveStats>,
+    /// Pools moved by the last committed trade, see [`Router::verify_last_solution`]
+    last_trade: Option<LastTrade<'l>>,
+    /// Identifiers of the pools of past trades, whose buffers the next trades reuse, see
+    /// [`Router::write_back_pool_reserves`]
+    spare_pool_ids: Vec<PoolId>,
+    /// Set when an event batch left the router stale under [`AutoSettle::OnNextSolve`]
+    settle_pending: bool,
+    /// Arbitrage value absorbed by the first equilibration, see
+    /// [`Router::initial_arbitrage_value`]
+    initial_arbitrage_value: Option<f64>,
+    /// Last published state and delta, when enabled in the configuration
+    replication: Option<Replication>,
+    /// State before the last pool addition, see [`Router::remove_pool`]
+    last_addition: Option<PoolAddition>,
+    /// Number of operations that may have changed the state, see [`Router::publish_version`]
+    state_version: u64,
+    /// Quotes of the current state, see [`Router::quote_cached`]
+    quote_cache: QuoteCache,
+}
+
+impl<'l> Router<'l> {
+    /// Builds a router over `pools` with the default configuration. Pools of any type enter it as
+    /// their constant-product equivalent, see [`Pool::to_uni_v2`], such as `Vec<UniV2Pool>` or
+    /// `Vec<Box<dyn Pool>>`.
+    ///
+    /// Panics if a pool is invalid, see [`UniV2Pool::validate`]; [`RouterBuilder::build`]
+    /// returns the error instead.
+    pub fn new(pools: Vec<impl Pool>) -> Self {
+        match Self::builder(pools).build() {
+            Ok(router) => router,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Starts building a router with a non-default configuration.
+    pub fn builder(pools: Vec<impl Pool>) -> RouterBuilder {
+        RouterBuilder::new(pools.iter().map(Pool::to_uni_v2).collect())
+    }
+
+    fn from_config(pools: Vec<UniV2Pool>, config: RouterConfig) -> Result<Self, RouterError> {
+        check_pools(&pools, &[], 0)?;
+        let tokens = pools
+            .iter()
+            .flat_map(|p| [p.token0, p.token1])
+            .unique()
+            .collect::<Vec<_>>();
+        Self::from_tokens(tokens, pools, config)
+    }
+
+    /// Builds a router indexing `tokens` in this order, which must include every token of
+    /// `pools`.
+    fn from_tokens(
+        tokens: Vec<&'l str>,
+        pools: Vec<UniV2Pool>,
+        config: RouterConfig,
+    ) -> Result<Self, RouterError> {
+        let token_index = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, &token)| (token, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut token_graph =
+            TokenGraph::from_pools(&pools, &token_index, config.liquidity_storage);
+        if let Some(reference_token) = &config.reference_token {
+            let reference_index = *token_index
+                .get(reference_token.as_str())
+                .ok_or_else(|| RouterError::UnknownToken(reference_token.clone()))?;
+            token_graph.set_reference_token(reference_index);
+        }
+        token_graph.set_dust_tolerance(config.dust_tolerance);
+        token_graph.set_solver(config.solver);
+        token_graph.set_domain(config.solver_domain);
+        if !(config.relaxation > 0.0 && config.relaxation < 2.0) {
+            return Err(RouterError::InvalidConfig(format!(
+                "relaxation factor {} outside (0, 2)",
+                config.relaxation
+            )));
+        }
+        token_graph.set_relaxation(config.relaxation);
+        if config.acceleration == (SolverAcceleration::Anderson { memory: 0 }) {
+            return Err(RouterError::InvalidConfig(
+                "Anderson acceleration without memory".to_string(),
+            ));
+        }
+        token_graph.set_acceleration(config.acceleration);
+        token_graph.set_sweep_mode(config.sweep_mode);
+        token_graph.set_parallel_sweep_threshold(config.parallel_sweep_threshold);
+        token_graph.set_kind(config.solver_kind);
+        token_graph.set_fallback(config.fallback);
+        token_graph.set_strict_math(config.strict_math);
+        token_graph.set_normalization(config.price_normalization);
+        config.trade_size_policy.validate()?;
+        if let Some(max_impact) = config.plan_depth_warning
+            && !(max_impact > 0.0 && max_impact < 1.0)
+        {
+            return Err(RouterError::InvalidConfig(format!(
+                "plan depth warning at a price impact of {max_impact} outside (0, 1)"
+            )));
+        }
+        if let Some(token) = config
+            .blacklisted_tokens
+            .iter()
+            .find(|token| !token_index.contains_key(token.as_str()))
+        {
+            return Err(RouterError::UnknownToken(token.clone()));
+        }
+        let pool_ids = pools
+            .iter()
+            .zip(0..)
+            .map(|(pool, n)| pool_id(pool, n))
+            .collect::<Vec<_>>();
+        let capture = config
+            .capture_session
+            .then(|| SessionCapture::new(&pools, &pool_ids, &config));
+
+        let mut router = Router {
+            token_index,
+            tokens,
+            next_pool_id: pool_ids.len() as u64,
+            pools,
+            pool_ids,
+            token_graph,
+            history: TradeHistory::new(config.history),
+            capture,
+            events: EventJournal::default(),
+            generation: 0,
+            latest_generation: 0,
+            last_solve_stats: None,
+            last_trade: None,
+            spare_pool_ids: Vec::new(),
+            settle_pending: false,
+            initial_arbitrage_value: None,
+            replication: None,
+            last_addition: None,
+            state_version: 0,
+            quote_cache: QuoteCache::default(),
+            config,
+        };
+        router.apply_blacklist();
+        router.start_replication();
+        Ok(router)
+    }
+
+    pub fn config(&self) -> &RouterConfig {
+        &self.config
+    }
+
+    /// Returns the numéraire in which prices are reported.
+    pub fn reference_token(&self) -> &'l str {
+        self.tokens[self.token_graph.reference_token()]
+    }
+
+    /// Pins the numéraire in which prices are reported, from the next solve onward, so that
+    /// prices are interpretable regardless of the order of the pool list. Pinning the pinned
+    /// token again leaves the router untouched.
+    pub fn set_reference_token(&mut self, token: &str) -> Result<(), RouterError> {
+        let index = self.index_of(token)?;
+        if self.config.reference_token.as_deref() == Some(token) {
+            return Ok(());
+        }
+        self.token_graph.set_reference_token(index);
+        self.config.reference_token = Some(token.to_string());
+        self.publish_version();
+        Ok(())
+    }
+
+    /// Returns the recorded trades, oldest first. Always empty unless history was enabled
+    /// through [`RouterBuilder::record_history`] or [`RouterBuilder::history_capacity`].
+    pub fn history(&self) -> &[TradeRecord<'l>] {
+        self.history.records()
+    }
+
+    /// Returns the replayable record of the session, `None` unless enabled through
+    /// [`RouterBuilder::capture_session`].
+    pub fn session_capture(&self) -> Option<&SessionCapture> {
+        self.capture.as_ref()
+    }
+
+    /// Returns every pair of tokens sharing at least one pool, with their aggregated geometric
+    /// liquidity `K(u, v) = Σ √kᵢ`, in token index order.
+    pub fn edges(&self) -> impl Iterator<Item = (&'l str, &'l str, f64)> + '_ {
+        self.token_graph
+            .sorted_edges()
+            .into_iter()
+            .map(|(u, v, liquidity)| (self.tokens[u], self.tokens[v], liquidity))
+    }
+
+    /// Returns the layout of the liquidities in the solver, [`LiquidityStorage::Dense`] or
+    /// [`LiquidityStorage::Sparse`], as picked by
+    /// [`RouterConfig::liquidity_storage`](RouterConfig::liquidity_storage) for the current
+    /// pools.
+    pub fn liquidity_layout(&self) -> LiquidityStorage {
+        self.token_graph.liquidity_layout()
+    }
+
+    /// Returns the denominators `Σ K(u, v) / q_v` of the fixed-point updates of every token at the
+    /// current prices, by token index and in the scaled units of the solver: as the sweeps sum
+    /// them if `full_rows`, the rows of the dense layout holding enough pairs in full, zeros
+    /// included, or over the neighbors of every token alone otherwise. Both are the same bit for
+    /// bit; the linear solver domain is assumed. For the benchmarks and tests of the solver.
+    #[cfg(feature = "test-utils")]
+    pub fn sweep_denominators(&self, full_rows: bool) -> Vec<f64> {
+        self.token_graph.sweep_denominators(full_rows)
+    }
+
+    /// Returns the diagnostics of the last trade solved by the router, `None` before the first
+    /// one. Trades whose prices come from [`Router::import_solution`] leave them untouched.
+    pub fn last_solve_stats(&self) -> Option<SolveStats> {
+        self.last_solve_stats
+    }
+
+    /// Forgets the recorded trades. Sequence numbers keep increasing afterwards.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Solves for the maximum output amount of `output_token` that can be obtained by selling
+    /// `input_amount` of `input_token`, updating the internal state of the router accordingly.
+    ///
+    /// A zero `input_amount` is a no-op returning zero, leaving the router untouched. Under
+    /// [`FeeModel::Charged`], every pool keeps its fee on the amounts it receives.
+    ///
+    /// The router reuses its buffers from one solve to the next: once the first trades have grown
+    /// them, fee-free solves by the fixed-point iteration perform no heap allocation, Anderson
+    /// acceleration excepted. Every copy of the router has buffers of its own, so that copies
+    /// solving on distinct threads share nothing.
+    ///
+    /// Fails, leaving the router untouched:
+    /// - with [`RouterError::InvalidAmount`] if `input_amount` is negative or not finite,
+    /// - if either token is unknown or blacklisted,
+    /// - with [`RouterError::IsolatedToken`] if either token has no pool holding liquidity with a
+    ///   routable token,
+    /// - with [`RouterError::NotConnected`] if the tokens lie in different connected components,
+    /// - with [`RouterError::InvalidPrice`] if the reserves drive a price to zero or to a
+    ///   non-finite value,
+    /// - with [`RouterError::NumericalError`] if the extracted amount or, with
+    ///   [`RouterConfig::strict_math`], a sum of the update is not finite,
+    /// - with [`RouterError::DidNotConverge`] if the fixed-point iteration exhausts
+    ///   [`SolverConfig::max_iterations`] before its residual falls below
+    ///   [`SolverConfig::tolerance`],
+    /// - with [`RouterError::NegativeOutput`] if the equilibrium tops the output token up by
+    ///   more than [`RouterConfig::dust_tolerance`] of its reserve, a negative output within the
+    ///   dust tolerance being returned as zero.
+    ///
+    /// Solving commits the trade and takes `&mut self`: a router shared between threads quoting
+    /// with [`Router::quote`] is solved by one of them at a time, which the caller coordinates,
+    /// for instance behind a [`std::sync::RwLock`] whose readers quote and whose writer solves.
+    pub fn solve(
+        &mut self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<f64, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        if !check_trade_amount(input_amount)? {
+            return Ok(0.0);
+        }
+        self.settle_if_pending();
+
+        let outcome = self.solve_trade(input_index, output_index, input_amount)?;
+        Ok(self
+            .commit_trade(input_index, output_index, input_amount, outcome)
+            .output_amount)
+    }
+
+    /// Returns the output amount [`Router::solve`] would return for the trade, without modifying
+    /// the router: the trade is solved on a copy, with buffers of its own.
+    ///
+    /// The router is [`Send`] and [`Sync`], and quotes only read it, so that any number of
+    /// threads may quote concurrently through a shared reference, each getting the quote it
+    /// would have got alone, see [`SelfTestConfig::concurrency`]. The copy costs a clone of the
+    /// pools and of the token graph per quote; see [`Router::quote_cached`] for repeated quotes.
+    ///
+    /// Fails as [`Router::solve`] does.
+    pub fn quote(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<f64, RouterError> {
+        self.scratch_copy()
+            .solve(input_token, output_token, input_amount)
+    }
+
+    /// Applies a trade to the token graph and solves the equilibrium, with the solver
+    /// configuration of the trade size band of the trade.
+    ///
+    /// Fails as [`Router::check_connected`] if the tokens cannot trade with each other, with
+    /// [`RouterError::InvalidPrice`] if the fixed-point iteration produces a price that is
+    /// not positive and finite, with [`RouterError::NumericalError`] if the output, or with
+    /// strict math a denominator of the update, is not finite, with [`RouterError::DidNotConverge`] if it exhausts its sweeps
+    /// above the tolerance, and with [`RouterError::NegativeOutput`] if the extracted amount is
+    /// negative beyond the dust tolerance, the token graph being restored as it was before the
+    /// trade.
+    fn solve_trade(
+        &mut self,
+        input_index: usize,
+        output_index: usize,
+        input_amount: f64,
+    ) -> Result<SolveOutcome, RouterError> {
+        self.check_connected(input_index, output_index)?;
+        let unmeasured = self.initial_arbitrage_value.is_none();
+        self.measure_initial_arbitrage();
+        let outcome = self.equilibrate_trade(input_index, output_index, input_amount);
+        if outcome.is_err() && unmeasured {
+            // The pools are still unequilibrated, the next trade measures them again
+            self.initial_arbitrage_value = None;
+        }
+        outcome
+    }
+
+    /// Solves a trade as [`Router::solve_trade`], once the initial arbitrage is measured.
+    fn equilibrate_trade(
+        &mut self,
+        input_index: usize,
+        output_index: usize,
+        input_amount: f64,
+    ) -> Result<SolveOutcome, RouterError> {
+        let (size_ratio, band, solver) = self.trade_solver(input_index, input_amount);
+        let checkpoint = self.token_graph.checkpoint(input_index, output_index);
+
+        let start = Instant::now();
+        let outcome = match self.config.fee_model {
+            FeeModel::FeeFree => {
+                self.token_graph.set_solver(solver);
+                let outcome =
+                    self.token_graph
+                        .apply_trade_and_solve(input_index, output_index, input_amount);
+                self.token_graph.set_solver(self.config.solver);
+                outcome
+            }
+            FeeModel::Charged => {
+                self.apply_trade_with_fees(input_index, output_index, input_amount, solver)
+            }
+        };
+        let elapsed = start.elapsed();
+
+        self.last_solve_stats = Some(SolveStats {
+            iterations: outcome.iterations,
+            final_residual: outcome.residual,
+            converged: outcome.converged,
+            elapsed,
+            size_ratio,
+            band,
+            damping_events: outcome.damping_events,
+            final_damping: outcome.final_damping,
+            path: match outcome.bisected {
+                true => SolvePath::Bisection,
+                false => SolvePath::Solver,
+            },
+            normalization_skipped: outcome.normalization_skipped,
+            token_updates: outcome.token_updates,
+        });
+        if let Some((token, q)) = outcome.invalid_price {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(RouterError::InvalidPrice {
+                token: self.tokens[token].to_string(),
+                q,
+            });
+        }
+        if let Some((token, value)) = outcome.numerical_error {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(RouterError::NumericalError {
+                token: self.tokens[token].to_string(),
+                value,
+            });
+        }
+        // Whatever the fee model and the strictness of the math, no output but a finite one is
+        // committed
+        if !outcome.output_amount.is_finite() {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(RouterError::NumericalError {
+                token: self.tokens[output_index].to_string(),
+                value: outcome.output_amount,
+            });
+        }
+        if !outcome.converged && !outcome.deadline_reached {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(RouterError::DidNotConverge {
+                iterations: outcome.iterations,
+                residual: outcome.residual,
+            });
+        }
+        if outcome.output_amount < 0.0 {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(RouterError::NegativeOutput {
+                amount: outcome.output_amount,
+            });
+        }
+        if let Err(error) = self.check_withdrawals(input_index, output_index, input_amount) {
+            self.token_graph.restore_checkpoint(checkpoint);
+            return Err(error);
+        }
+        Ok(outcome)
+    }
+
+    /// Fails with [`RouterError::PoolOperation`] and [`PoolError::InsufficientLiquidity`] if the
+    /// trade just solved would take the whole reserve of a pool of virtual reserves, or more, see
+    /// [`UniV2Pool::virtual_reserves`]: their curves reach beyond what they hold.
+    fn check_withdrawals(
+        &self,
+        input_index: usize,
+        output_index: usize,
+        input_amount: f64,
+    ) -> Result<(), RouterError> {
+        if !self.pools.iter().any(UniV2Pool::has_virtual_reserves) {
+            return Ok(());
+        }
+        let curve_reserves = match self.config.fee_model {
+            FeeModel::FeeFree => self
+                .pools
+                .iter()
+                .enumerate()
+                .filter_map(|(position, pool)| {
+                    let index_0 = self.token_index[pool.token0];
+                    let index_1 = self.token_index[pool.token1];
+                    (self.token_graph.is_pool_active(pool, index_0, index_1)
+                        && self.token_graph.are_connected(output_index, index_0))
+                    .then(|| {
+                        let reserves = self
+                            .token_graph
+                            .equilibrium_reserves(pool, index_0, index_1);
+                        (position, reserves)
+                    })
+                })
+                .collect::<Vec<_>>(),
+            FeeModel::Charged => self.fee_trade_reserves(input_index, output_index, input_amount),
+        };
+        for (position, (curve0, curve1)) in curve_reserves {
+            let pool = &self.pools[position];
+            let sides = [
+                (pool.reserve0, curve0 - pool.virtual_reserves.0),
+                (pool.reserve1, curve1 - pool.virtual_reserves.1),
+            ];
+            for (reserve, after) in sides {
+                if pool.has_virtual_reserves() && after <= 0.0 {
+                    return Err(RouterError::PoolOperation {
+                        pool: self.pool_ids[position].clone(),
+                        error: PoolError::InsufficientLiquidity {
+                            output_amount: reserve - after,
+                            reserve,
+                        },
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails with [`RouterError::IsolatedToken`] if either token has no pool holding liquidity,
+    /// and with [`RouterError::NotConnected`] if no path of pools links them.
+    fn check_connected(&self, input_index: usize, output_index: usize) -> Result<(), RouterError> {
+        for token in [input_index, output_index] {
+            if self.token_graph.is_isolated(token) {
+                return Err(RouterError::IsolatedToken(self.tokens[token].to_string()));
+            }
+        }
+        if !self.token_graph.are_connected(input_index, output_index) {
+            return Err(RouterError::NotConnected {
+                input_token: self.tokens[input_index].to_string(),
+                output_token: self.tokens[output_index].to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the size ratio of a trade, its trade size band and the solver configuration it is
+    /// solved with.
+    fn trade_solver(
+        &self,
+        input_index: usize,
+        input_amount: f64,
+    ) -> (f64, Option<usize>, SolverConfig) {
+        let size_ratio = input_amount / self.token_graph.active_reserve(input_index);
+        let policy = &self.config.trade_size_policy;
+        let band = policy.band(size_ratio);
+        let solver = band.map_or(self.config.solver, |band| policy.bands[band].solver);
+        (size_ratio, band, solver)
+    }
+
+    /// Propagates a trade already applied to the token graph to the pools, the history and the
+    /// session capture.
+    fn commit_trade(
+        &mut self,
+        input_index: usize,
+        output_index: usize,
+        input_amount: f64,
+        outcome: SolveOutcome,
+    ) -> TradeResult<'l> {
+        // The pools of the previous trade are replaced, their buffers reused
+        let mut pools = self
+            .last_trade
+            .take()
+            .map_or_else(Vec::new, |trade| trade.pools);
+        match self.config.fee_model {
+            FeeModel::FeeFree => self.write_back_pool_reserves(Some(output_index), &mut pools),
+            FeeModel::Charged => {
+                pools = self.write_back_fee_reserves(input_index, output_index, input_amount)
+            }
+        }
+
+        let (input_token, output_token) = (self.tokens[input_index], self.tokens[output_index]);
+        self.last_trade = Some(LastTrade {
+            input_token,
+            output_token,
+            input_amount,
+            output_amount: outcome.output_amount,
+            pools,
+        });
+        self.history.record(|sequence| TradeRecord {
+            sequence,
+            input_token,
+            output_token,
+            input_amount,
+            output_amount: outcome.output_amount,
+            iterations: outcome.iterations,
+        });
+        if let Some(capture) = &mut self.capture {
+            let max_hops = self.token_graph.hop_limit().map(|limit| limit.max_hops);
+            capture.record(
+                input_token,
+                output_token,
+                input_amount,
+                max_hops,
+                outcome.output_amount,
+            );
+        }
+
+        self.publish_version();
+        #[cfg(feature = "paranoid")]
+        {
+            self.assert_invariants();
+            self.assert_reconciled();
+        }
+
+        TradeResult {
+            input_token,
+            output_token,
+            input_amount,
+            output_amount: outcome.output_amount,
+            iterations: outcome.iterations,
+            residual: outcome.residual,
+        }
+    }
+
+    /// Copy of the router for quotes that must leave it untouched, without history nor capture.
+    fn scratch_copy(&self) -> Router<'l> {
+        let mut copy = self.clone();
+        copy.history = TradeHistory::new(HistoryConfig::Disabled);
+        copy.capture = None;
+        copy.replication = None;
+        copy.last_addition = None;
+        copy
+    }
+
+    fn index_of(&self, token: &str) -> Result<usize, RouterError> {
+        self.token_index
+            .get(token)
+            .copied()
+            .ok_or_else(|| RouterError::UnknownToken(token.to_string()))
+    }
+
+    /// Redistributes the equilibrium onto the individual pools.
+    ///
+    /// At equilibrium, a pool with invariant `k` linking `u` and `v` holds
+    /// `r_u = √k · q_u / q_v` and `r_v = √k · q_v / q_u`, so that its invariant is preserved and
+    /// the pools of a pair sum up to the aggregated edge reserves `K(u, v) · q_u / q_v`. Pools
+    /// out of the equilibrium, touching a blacklisted token, beyond the hop limit or empty, keep
+    /// their reserves. With `component_of`, only the pools of the connected component of that
+    /// token are redistributed, the others keeping their reserves bit for bit.
+    ///
+    /// Replaces the pools of `traded_pools` with those whose reserves changed, in pool order,
+    /// their identifiers reusing the buffers of the spare ones. A spare too short for the
+    /// identifier it receives is grown to hold the longest of all, so that the spares stop
+    /// growing once enough of them exist, whichever pools the trades move.
+    fn write_back_pool_reserves(
+        &mut self,
+        component_of: Option<usize>,
+        traded_pools: &mut Vec<TradedPool<'l>>,
+    ) {
+        self.spare_pool_ids
+            .extend(traded_pools.drain(..).map(|traded| traded.id));
+        let mut longest_id = None;
+        for (pool, id) in self.pools.iter_mut().zip(&self.pool_ids) {
+            let index_0 = self.token_index[pool.token0];
+            let index_1 = self.token_index[pool.token1];
+            if !self.token_graph.is_pool_active(pool, index_0, index_1)
+                || component_of.is_some_and(|token| !self.token_graph.are_connected(token, index_0))
+            {
+                continue;
+            }
+            let before = (pool.reserve0, pool.reserve1);
+            let (curve0, curve1) = self
+                .token_graph
+                .equilibrium_reserves(pool, index_0, index_1);
+            pool.reserve0 = curve0 - pool.virtual_reserves.0;
+            pool.reserve1 = curve1 - pool.virtual_reserves.1;
+            if (pool.reserve0, pool.reserve1) != before {
+                let exact = advance_raw_reserves(pool, before);
+                let mut traded_id = self.spare_pool_ids.pop().unwrap_or(PoolId(String::new()));
+                if traded_id.0.capacity() < id.0.len() {
+                    let longest = *longest_id.get_or_insert_with(|| {
+                        self.pool_ids.iter().map(|id| id.0.len()).max().unwrap_or(0)
+                    });
+                    traded_id.0.reserve_exact(longest - traded_id.0.len());
+                }
+                traded_id.0.clone_from(&id.0);
+                traded_pools.push(TradedPool {
+                    id: traded_id,
+                    token0: pool.token0,
+                    token1: pool.token1,
+                    before,
+                    after: (pool.reserve0, pool.reserve1),
+                    exact,
+                });
+            }
+        }
+    }
+}
+
+// Quotes take `&self` to be served from several threads at once, see [`Router::quote`]
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Router<'static>>();
+};
+
+/// Returns the identifier of a pool entering the router as its `n`-th: its own, or else the
+/// synthetic one.
+fn pool_id(pool: &UniV2Pool, n: u64) -> PoolId {
+    pool.id.clone().unwrap_or_else(|| PoolId::synthetic(n))
+}
+
+/// Checks pools entering the router from its `first`-th on, alongside the pools of identifiers
+/// `existing`: fails with [`RouterError::InvalidPool`] if [`UniV2Pool::validate`] rejects one of
+/// them, and with [`RouterError::DuplicatePool`] if one of them has the identifier of another.
+fn check_pools(pools: &[UniV2Pool], existing: &[PoolId], first: u64) -> Result<(), RouterError> {
+    let mut ids = existing.iter().cloned().collect::<HashSet<_>>();
+    for (pool, n) in pools.iter().zip(first..) {
+        let id = pool_id(pool, n);
+        pool.validate().map_err(|error| RouterError::InvalidPool {
+            pool: id.clone(),
+            error,
+        })?;
+        if !ids.insert(id.clone()) {
+            return Err(RouterError::DuplicatePool(id));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the input amount of a trade, returning whether it is positive: trades of zero are
+/// no-ops. Fails with [`RouterError::InvalidAmount`] if the amount is negative or not finite.
+fn check_trade_amount(input_amount: f64) -> Result<bool, RouterError> {
+    if !(input_amount.is_finite() && input_amount >= 0.0) {
+        return Err(RouterError::InvalidAmount(input_amount));
+    }
+    Ok(input_amount > 0.0)
+}
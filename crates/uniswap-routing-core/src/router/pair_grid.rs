@@ -0,0 +1,123 @@
+use crate::router::{Router, parallel::map_ordered};
+
+use std::collections::HashMap;
+
+/// Quote of one ordered token pair, see [`Router::quote_all_pairs`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PairQuote<'l> {
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    /// Notional converted to the input token, `None` when the input token is not connected to
+    /// the reference token and has no price in it
+    pub input_amount: Option<f64>,
+    /// `None` when the tokens lie in different connected components, or when the input amount
+    /// is unknown
+    pub output_amount: Option<f64>,
+}
+
+impl<'l> Router<'l> {
+    /// Quotes every ordered pair of routable tokens for the input amount worth
+    /// `notional_in_reference` units of the reference token at the current prices, without
+    /// modifying the router.
+    ///
+    /// Each pair is quoted as [`Router::solve`] would, from the current equilibrium. The
+    /// connected components are extracted once, and the trades from each input token are solved
+    /// on a single working copy of its component graph, reset to the baseline in place between
+    /// output tokens so that the loop over the pairs does not allocate. Input tokens are
+    /// processed in parallel with the `parallel` feature.
+    ///
+    /// Every quote is a full solve: the grid of `n` tokens costs about `n²` times the average
+    /// [`Router::solve`].
+    ///
+    /// Pairs are listed by input token, then by output token, in the order tokens first appear
+    /// in the pools.
+    pub fn quote_all_pairs(&self, notional_in_reference: f64) -> Vec<PairQuote<'l>> {
+        let mut scratch = self.scratch_copy();
+        scratch.settle_if_pending();
+        let graph = &scratch.token_graph;
+
+        let reference_token = graph.reference_token();
+        let reference_q = graph.price(reference_token);
+        let mut component_of = vec![None; graph.len()];
+        let components = graph.component_graphs();
+        for (position, (component, _)) in components.iter().enumerate() {
+            for &token in component {
+                component_of[token] = Some(position);
+            }
+        }
+        let priced_component = component_of[reference_token];
+
+        // Input amount and solver configuration of every token of the reference component
+        let trades = (0..graph.len())
+            .map(|token| {
+                (component_of[token].is_some() && component_of[token] == priced_component).then(
+                    || {
+                        let rate = graph.price(token) / reference_q;
+                        let input_amount = notional_in_reference * rate * rate;
+                        let (_, _, solver) = scratch.trade_solver(token, input_amount);
+                        (input_amount, solver)
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // One work item per priced input token, solving its trades to every other token of its
+        // component on a working copy of the component graph
+        let trades = &trades;
+        let items = components
+            .iter()
+            .flat_map(|(component, baseline)| {
+                component
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(input, &token)| {
+                        trades[token].map(|trade| (component.len(), baseline, input, trade))
+                    })
+            })
+            .collect::<Vec<_>>();
+        let rows = map_ordered(items, |(len, baseline, input, (input_amount, solver))| {
+            let mut working = baseline.clone();
+            working.set_solver(solver);
+            (0..len)
+                .map(|output| {
+                    (output != input).then(|| {
+                        working.restore_state(baseline);
+                        working
+                            .apply_trade_and_solve(input, output, input_amount)
+                            .output_amount
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut rows = rows.into_iter();
+        let mut outputs = HashMap::new();
+        for (component, _) in &components {
+            for &token in component.iter().filter(|&&token| trades[token].is_some()) {
+                let row = rows.next().expect("one row per priced input token");
+                for (&output_token, output_amount) in component.iter().zip(row) {
+                    if let Some(output_amount) = output_amount {
+                        outputs.insert((token, output_token), output_amount);
+                    }
+                }
+            }
+        }
+
+        let mut quotes = Vec::new();
+        for input_index in (0..graph.len()).filter(|&token| !graph.is_blacklisted(token)) {
+            for output_index in (0..graph.len()).filter(|&token| !graph.is_blacklisted(token)) {
+                if input_index == output_index {
+                    continue;
+                }
+                let output_amount = outputs.get(&(input_index, output_index)).copied();
+                quotes.push(PairQuote {
+                    input_token: self.tokens[input_index],
+                    output_token: self.tokens[output_index],
+                    input_amount: trades[input_index].map(|(input_amount, _)| input_amount),
+                    output_amount,
+                });
+            }
+        }
+        quotes
+    }
+}
@@ -0,0 +1,42 @@
+/// Maps `f` over independent work items, typically one per connected component, on the rayon
+/// thread pool with the `parallel` feature and sequentially otherwise. The results keep the order
+/// of `items` either way, so that merging them is deterministic.
+pub(super) fn map_ordered<I, T>(items: Vec<I>, f: impl Fn(I) -> T + Send + Sync) -> Vec<T>
+where
+    I: Send,
+    T: Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::iter::{IntoParallelIterator as _, ParallelIterator as _};
+        items.into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        items.into_iter().map(f).collect()
+    }
+}
+
+/// Appends `f` of every item to `values`, in the order of `items`, on the rayon thread pool with
+/// the `parallel` feature and sequentially otherwise. Each value only depends on its item, so
+/// that they are the same either way.
+pub(super) fn extend_ordered<I, T>(
+    values: &mut Vec<T>,
+    items: &[I],
+    f: impl Fn(I) -> T + Send + Sync,
+) where
+    I: Copy + Send + Sync,
+    T: Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::iter::{
+            IntoParallelRefIterator as _, ParallelExtend as _, ParallelIterator as _,
+        };
+        values.par_extend(items.par_iter().map(|&item| f(item)));
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        values.extend(items.iter().map(|&item| f(item)));
+    }
+}
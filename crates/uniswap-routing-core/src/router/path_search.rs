@@ -0,0 +1,362 @@
+use crate::{error::RouterError, router::Router};
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+/// Parameters of a bounded single-path search between two tokens.
+#[derive(Debug, Clone)]
+pub struct PathSearchRequest<'a> {
+    pub input_token: &'a str,
+    pub output_token: &'a str,
+    pub input_amount: f64,
+    /// Maximum number of pools (hops) a path may traverse
+    pub max_hops: usize,
+    /// Maximum number of candidate paths examined before the search gives up
+    pub budget: usize,
+    /// Maximum number of partial paths kept in the frontier, `None` for unbounded
+    pub beam_width: Option<usize>,
+}
+
+/// A single path quote: the traversed tokens (input first, output last) and the amount received.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathQuote<'l> {
+    pub tokens: Vec<&'l str>,
+    /// Amount held in each token of the path, the input amount first: each hop swaps the amount
+    /// of its token against the aggregated pair as a single virtual pool
+    pub amounts: Vec<f64>,
+    pub output_amount: f64,
+}
+
+/// Outcome of a bounded path search.
+#[derive(Debug, Clone)]
+pub struct PathSearchResult<'l> {
+    /// Best complete path found, `None` if the output token was not reached
+    pub best: Option<PathQuote<'l>>,
+    /// Number of candidate paths popped from the frontier
+    pub paths_examined: usize,
+    /// Set when the budget or the beam width cut the search before optimality was proven
+    pub possibly_suboptimal: bool,
+}
+
+/// A path prefix stored in the search arena: the prefixes are shared between all their
+/// extensions, which memoizes the quote of every common sub-path.
+struct Prefix {
+    token: usize,
+    parent: Option<usize>,
+    hops: usize,
+    amount: f64,
+}
+
+/// Frontier entry ordered by its admissible upper bound on the final output.
+struct Candidate {
+    upper_bound: f64,
+    prefix: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties are broken towards older prefixes to keep the search deterministic
+        self.upper_bound
+            .total_cmp(&other.upper_bound)
+            .then_with(|| other.prefix.cmp(&self.prefix))
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Searches the best single path from `input_token` to `output_token`, each hop being quoted
+    /// against the aggregated reserves of all the pools of the traversed pair.
+    ///
+    /// Paths are expanded best-first, ordered by an admissible upper bound: the amount reached so
+    /// far times the best product of per-hop spot rates to the output within the remaining hops
+    /// (a constant-product swap never returns more than its spot rate). The first complete path
+    /// popped from the frontier is therefore optimal. When the `budget` or the `beam_width` cut
+    /// the search short, the best path found so far is returned flagged as possibly suboptimal.
+    pub fn search_paths(&self, request: &PathSearchRequest) -> PathSearchResult<'l> {
+        let (mut paths, paths_examined, possibly_suboptimal) = self.search_best_paths(request, 1);
+        PathSearchResult {
+            best: paths.pop(),
+            paths_examined,
+            possibly_suboptimal,
+        }
+    }
+
+    /// Returns the single path of at most `max_hops` pools from `input_token` to `output_token`
+    /// yielding the most output for `input_amount`, as executed by a router contract swapping
+    /// along one path, each hop being quoted against the aggregated reserves of the pair as in
+    /// [`Router::search_paths`]. The search runs without budget nor beam, so the path is optimal.
+    ///
+    /// Among paths with the same output, the one whose prefixes were expanded first is kept, so
+    /// that ties resolve deterministically. Returns `None` when no path within `max_hops` reaches
+    /// the output token. Quoting every hop against the reserves of its pair, a single path never
+    /// outputs more than [`Router::solve`], up to its tolerance, which also splits the trade across
+    /// paths: the difference is the output left on the table by single-path execution.
+    ///
+    /// Fails if either token is unknown or blacklisted.
+    pub fn best_path(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+        max_hops: usize,
+    ) -> Result<Option<PathQuote<'l>>, RouterError> {
+        self.routable_index(input_token)?;
+        self.routable_index(output_token)?;
+        let result = self.search_paths(&PathSearchRequest {
+            input_token,
+            output_token,
+            input_amount,
+            max_hops,
+            budget: usize::MAX,
+            beam_width: None,
+        });
+        Ok(result.best)
+    }
+
+    /// Returns the `n` simple paths of at most `max_hops` pools from `input_token` to
+    /// `output_token` yielding the most output for `input_amount`, by decreasing output, each
+    /// hop being quoted against the aggregated reserves of the pair as in
+    /// [`Router::search_paths`]. Fewer paths are returned when fewer reach the output token.
+    ///
+    /// The best-first search goes on after the best path until `n` complete paths are known and
+    /// the upper bound of every remaining prefix falls below the `n`-th best output; prefixes
+    /// that cannot beat it are not expanded, which keeps dense graphs tractable for small `n`.
+    /// Ties keep the path whose prefixes were expanded first. The amounts along each path tell
+    /// how a manual split across the paths would trade at each hop.
+    ///
+    /// Fails if either token is unknown or blacklisted.
+    pub fn top_paths(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+        max_hops: usize,
+        n: usize,
+    ) -> Result<Vec<PathQuote<'l>>, RouterError> {
+        self.routable_index(input_token)?;
+        self.routable_index(output_token)?;
+        let request = PathSearchRequest {
+            input_token,
+            output_token,
+            input_amount,
+            max_hops,
+            budget: usize::MAX,
+            beam_width: None,
+        };
+        let (mut paths, ..) = self.search_best_paths(&request, n);
+        paths.reverse();
+        Ok(paths)
+    }
+
+    /// Runs the best-first search of [`Router::search_paths`] until the `n` best complete paths
+    /// are proven, returning them by increasing output with the number of paths examined and
+    /// whether the budget or the beam width cut the search short.
+    fn search_best_paths(
+        &self,
+        request: &PathSearchRequest,
+        n: usize,
+    ) -> (Vec<PathQuote<'l>>, usize, bool) {
+        let input_token = self.token_index[request.input_token];
+        let output_token = self.token_index[request.output_token];
+        let pairs = self.aggregated_pairs();
+        let rate_bounds = spot_rate_bounds(&pairs, output_token, request.max_hops);
+
+        let mut arena = vec![Prefix {
+            token: input_token,
+            parent: None,
+            hops: 0,
+            amount: request.input_amount,
+        }];
+        let mut frontier = BinaryHeap::from([Candidate {
+            upper_bound: request.input_amount * rate_bounds[request.max_hops][input_token],
+            prefix: 0,
+        }]);
+        // Best complete paths found so far, by increasing output
+        let mut best: Vec<(usize, f64)> = Vec::new();
+        let threshold = |best: &[(usize, f64)]| match best.len() == n {
+            true => best.first().map(|&(_, amount)| amount),
+            false => None,
+        };
+        let mut paths_examined = 0;
+        let mut possibly_suboptimal = false;
+
+        while n > 0
+            && let Some(candidate) = frontier.pop()
+        {
+            if threshold(&best).is_some_and(|amount| amount >= candidate.upper_bound) {
+                // Nothing left in the frontier can beat the best complete paths
+                break;
+            }
+            if paths_examined == request.budget {
+                possibly_suboptimal = true;
+                break;
+            }
+            paths_examined += 1;
+
+            let Prefix {
+                token,
+                hops,
+                amount,
+                ..
+            } = arena[candidate.prefix];
+            if token == output_token {
+                // Equal outputs rank below the paths found earlier
+                let position = best.partition_point(|&(_, other)| other < amount);
+                best.insert(position, (candidate.prefix, amount));
+                if best.len() > n {
+                    best.remove(0);
+                }
+                continue;
+            }
+            if hops == request.max_hops {
+                continue;
+            }
+
+            let remaining_hops = request.max_hops - hops - 1;
+            for &(next_token, reserve_in, reserve_out) in &pairs[token] {
+                if contains_token(&arena, candidate.prefix, next_token) {
+                    continue;
+                }
+                let upper_bound = rate_bounds[remaining_hops][next_token];
+                if upper_bound == 0.0 {
+                    continue;
+                }
+                let next_amount = amount * reserve_out / (reserve_in + amount);
+                if threshold(&best).is_some_and(|amount| amount >= next_amount * upper_bound) {
+                    continue;
+                }
+                arena.push(Prefix {
+                    token: next_token,
+                    parent: Some(candidate.prefix),
+                    hops: hops + 1,
+                    amount: next_amount,
+                });
+                frontier.push(Candidate {
+                    upper_bound: next_amount * upper_bound,
+                    prefix: arena.len() - 1,
+                });
+            }
+
+            if let Some(beam_width) = request.beam_width
+                && frontier.len() > beam_width
+            {
+                let mut kept = frontier.into_sorted_vec();
+                kept.drain(..kept.len() - beam_width);
+                frontier = kept.into();
+                possibly_suboptimal = true;
+            }
+        }
+
+        let paths = best
+            .into_iter()
+            .map(|(prefix, output_amount)| {
+                let (tokens, amounts) = self.prefix_path(&arena, prefix);
+                PathQuote {
+                    tokens,
+                    amounts,
+                    output_amount,
+                }
+            })
+            .collect();
+        (paths, paths_examined, possibly_suboptimal)
+    }
+
+    /// For each token, the list of `(paired_token, reserve_in, reserve_out)` of the virtual pool
+    /// obtained by summing the reserves of all pools of the pair. Pairs touching a blacklisted
+    /// token are left out.
+    pub(super) fn aggregated_pairs(&self) -> Vec<Vec<(usize, f64, f64)>> {
+        let mut reserves = HashMap::<(usize, usize), (f64, f64)>::new();
+        for pool in &self.pools {
+            let index_0 = self.token_index[pool.token0];
+            let index_1 = self.token_index[pool.token1];
+            if self.token_graph.is_blacklisted(index_0) || self.token_graph.is_blacklisted(index_1)
+            {
+                continue;
+            }
+            let (curve0, curve1) = pool.curve_reserves();
+            let entry = reserves.entry((index_0, index_1)).or_insert((0.0, 0.0));
+            entry.0 += curve0;
+            entry.1 += curve1;
+            let entry = reserves.entry((index_1, index_0)).or_insert((0.0, 0.0));
+            entry.0 += curve1;
+            entry.1 += curve0;
+        }
+
+        let mut pairs = vec![Vec::new(); self.tokens.len()];
+        for ((token_in, token_out), (reserve_in, reserve_out)) in reserves {
+            pairs[token_in].push((token_out, reserve_in, reserve_out));
+        }
+        for neighbors in &mut pairs {
+            neighbors.sort_by_key(|&(token, ..)| token);
+        }
+        pairs
+    }
+
+    /// Returns the tokens of the path ending at `prefix` with the amount held in each.
+    fn prefix_path(&self, arena: &[Prefix], prefix: usize) -> (Vec<&'l str>, Vec<f64>) {
+        let (mut tokens, mut amounts) = (Vec::new(), Vec::new());
+        let mut current = Some(prefix);
+        while let Some(index) = current {
+            tokens.push(self.tokens[arena[index].token]);
+            amounts.push(arena[index].amount);
+            current = arena[index].parent;
+        }
+        tokens.reverse();
+        amounts.reverse();
+        (tokens, amounts)
+    }
+}
+
+/// `bounds[h][t]` is the best product of spot rates over the walks from `t` to `output_token`
+/// using at most `h` hops, which upper-bounds the rate of any simple path with the same budget.
+fn spot_rate_bounds(
+    pairs: &[Vec<(usize, f64, f64)>],
+    output_token: usize,
+    max_hops: usize,
+) -> Vec<Vec<f64>> {
+    let mut bounds = vec![vec![0.0; pairs.len()]];
+    bounds[0][output_token] = 1.0;
+    for hops in 1..=max_hops {
+        let previous = &bounds[hops - 1];
+        let current = pairs
+            .iter()
+            .enumerate()
+            .map(|(token, neighbors)| {
+                neighbors
+                    .iter()
+                    .map(|&(next, reserve_in, reserve_out)| {
+                        reserve_out / reserve_in * previous[next]
+                    })
+                    .fold(previous[token], f64::max)
+            })
+            .collect();
+        bounds.push(current);
+    }
+    bounds
+}
+
+fn contains_token(arena: &[Prefix], prefix: usize, token: usize) -> bool {
+    let mut current = Some(prefix);
+    while let Some(index) = current {
+        if arena[index].token == token {
+            return true;
+        }
+        current = arena[index].parent;
+    }
+    false
+}
@@ -0,0 +1,276 @@
+use crate::{
+    error::RouterError,
+    pool::Pool,
+    router::{Router, capture::CapturedOperation, check_pools, pool_id, token_graph::TokenGraph},
+    uni_v2_pool::{LpShares, PoolId, UniV2Pool},
+};
+
+/// State before a [`Router::add_pool`], restored if the pool is removed before anything else
+/// changed.
+#[derive(Debug, Clone)]
+pub(super) struct PoolAddition {
+    id: PoolId,
+    /// Version of the state right after the addition, see [`Router::publish_version`]
+    state_version: u64,
+    /// Number of tokens before the addition
+    tokens: usize,
+    token_graph: TokenGraph,
+}
+
+impl<'l> Router<'l> {
+    /// Returns the individual pools, with their reserves at the current equilibrium.
+    ///
+    /// After every trade, each pool taking part in the equilibrium holds its share
+    /// `√k · q_u / q_v` of its pair, so that summing the pool reserves of a token gives back its
+    /// total reserve, as returned by [`Router::reserve_by_token`], up to the solver tolerance.
+    pub fn pools(&self) -> &[UniV2Pool] {
+        &self.pools
+    }
+
+    /// Returns the total reserve of every token across all pools, in token index order, without
+    /// their virtual reserves, see [`UniV2Pool::virtual_reserves`].
+    pub fn reserve_by_token(&self) -> impl Iterator<Item = (&'l str, f64)> + '_ {
+        self.tokens
+            .iter()
+            .enumerate()
+            .map(|(index, &token)| (token, self.token_graph.held_reserve(index)))
+    }
+
+    /// Returns the identifiers of the pools, in the same order as the pools themselves.
+    pub fn pool_ids(&self) -> &[PoolId] {
+        &self.pool_ids
+    }
+
+    /// Returns the pool of identifier `id`, if any.
+    pub fn pool_by_id(&self, id: &PoolId) -> Option<&UniV2Pool> {
+        self.pool_position(id)
+            .ok()
+            .map(|position| &self.pools[position])
+    }
+
+    /// Registers a new pool of any type, as its constant-product equivalent, see
+    /// [`Pool::to_uni_v2`], and returns its identifier, its own if set by
+    /// [`UniV2Pool::with_id`]. Tokens not seen before are appended to the token index. Fails with
+    /// [`RouterError::InvalidPool`] if the pool is invalid, see [`UniV2Pool::validate`], and with
+    /// [`RouterError::DuplicatePool`] if another pool has its identifier.
+    pub fn add_pool(&mut self, pool: impl Pool) -> Result<PoolId, RouterError> {
+        let pool = pool.to_uni_v2();
+        let tokens = self.tokens.len();
+        let token_graph = self.token_graph.clone();
+        let id = self.insert_pool(pool)?;
+        self.rebuild_token_graph();
+        self.publish_version();
+        self.last_addition = Some(PoolAddition {
+            id: id.clone(),
+            state_version: self.state_version,
+            tokens,
+            token_graph,
+        });
+        Ok(id)
+    }
+
+    /// Removes a pool. Its tokens keep their index even if no pool references them anymore.
+    ///
+    /// Removing the pool of the last [`Router::add_pool`] before anything else changed restores
+    /// the state from before the addition bit for bit, see [`Router::content_hash`]: the tokens
+    /// the pool introduced are dropped and its identifier is issued again. Re-aggregating the
+    /// pools instead would leave the totals off by the rounding of the trades since the last
+    /// aggregation. With [`RouterConfig::publish_deltas`](crate::router::RouterConfig::publish_deltas),
+    /// replicas that saw the addition receive a delta undoing it.
+    pub fn remove_pool(&mut self, id: &PoolId) -> Result<UniV2Pool, RouterError> {
+        if let Some(addition) = self.last_addition.take()
+            && addition.id == *id
+            && self.pool_ids.last() == Some(id)
+            && addition.state_version == self.state_version
+        {
+            return Ok(self.undo_addition(addition));
+        }
+        let pool = self.take_pool(id)?;
+        self.rebuild_token_graph();
+        self.publish_version();
+        Ok(pool)
+    }
+
+    /// Overwrites the reserves of a pool, typically from an on-chain `Sync` event, dropping its
+    /// raw reserves. Reserves identical to the current ones leave the router untouched. Fails
+    /// with [`RouterError::InvalidPool`] if a reserve is zero, negative or not finite, leaving
+    /// the router untouched.
+    pub fn update_pool_reserves(
+        &mut self,
+        id: &PoolId,
+        reserve0: f64,
+        reserve1: f64,
+    ) -> Result<(), RouterError> {
+        if self.set_pool_reserves(id, reserve0, reserve1)? {
+            self.rebuild_token_graph();
+            self.publish_version();
+        }
+        Ok(())
+    }
+
+    /// Deposits liquidity into the pool `id`, see [`UniV2Pool::add_liquidity`], and returns the
+    /// shares minted. Its price is unchanged; its reserves and its contribution to the liquidity
+    /// of its pair grow with the deposit, the token graph being rebuilt as by
+    /// [`Router::update_pool_reserves`]. Fails with [`RouterError::PoolOperation`] if the pool
+    /// refuses the amounts.
+    pub fn add_liquidity(
+        &mut self,
+        id: &PoolId,
+        amount0: f64,
+        amount1: f64,
+    ) -> Result<LpShares, RouterError> {
+        let mut pool = self.pools[self.pool_position(id)?].clone();
+        let deposit =
+            pool.add_liquidity(amount0, amount1)
+                .map_err(|error| RouterError::PoolOperation {
+                    pool: id.clone(),
+                    error,
+                })?;
+        self.set_pool_liquidity(id, pool)?;
+        Ok(deposit)
+    }
+
+    /// Burns `shares` of the pool `id`, see [`UniV2Pool::remove_liquidity`], and returns the
+    /// amounts withdrawn, rebuilding the token graph as [`Router::add_liquidity`]. Fails with
+    /// [`RouterError::PoolOperation`] if the pool refuses the amount.
+    pub fn remove_liquidity(
+        &mut self,
+        id: &PoolId,
+        shares: f64,
+    ) -> Result<(f64, f64), RouterError> {
+        let mut pool = self.pools[self.pool_position(id)?].clone();
+        let amounts =
+            pool.remove_liquidity(shares)
+                .map_err(|error| RouterError::PoolOperation {
+                    pool: id.clone(),
+                    error,
+                })?;
+        self.set_pool_liquidity(id, pool)?;
+        Ok(amounts)
+    }
+
+    /// Moves the pool `id` to the reserves and shares of `pool` after a change of its liquidity.
+    fn set_pool_liquidity(&mut self, id: &PoolId, pool: UniV2Pool) -> Result<(), RouterError> {
+        self.update_pool_reserves(id, pool.reserve0, pool.reserve1)?;
+        let position = self.pool_position(id)?;
+        self.pools[position].total_shares = pool.total_shares;
+        Ok(())
+    }
+
+    /// Appends a pool without rebuilding the token graph, unless it is invalid.
+    pub(super) fn insert_pool(&mut self, pool: UniV2Pool) -> Result<PoolId, RouterError> {
+        check_pools(
+            std::slice::from_ref(&pool),
+            &self.pool_ids,
+            self.next_pool_id,
+        )?;
+        let id = pool_id(&pool, self.next_pool_id);
+        self.record_operation(|| CapturedOperation::AddPool((&id, &pool).into()));
+        Ok(self.register_pool(pool))
+    }
+
+    /// Appends a pool without rebuilding the token graph nor recording it in the session
+    /// capture.
+    pub(super) fn register_pool(&mut self, pool: UniV2Pool) -> PoolId {
+        for token in [pool.token0, pool.token1] {
+            if !self.token_index.contains_key(token) {
+                self.token_index.insert(token, self.tokens.len());
+                self.tokens.push(token);
+            }
+        }
+        let id = pool_id(&pool, self.next_pool_id);
+        self.next_pool_id += 1;
+        self.pools.push(pool);
+        self.pool_ids.push(id.clone());
+        id
+    }
+
+    /// Removes a pool without rebuilding the token graph.
+    pub(super) fn take_pool(&mut self, id: &PoolId) -> Result<UniV2Pool, RouterError> {
+        let position = self.pool_position(id)?;
+        self.record_operation(|| CapturedOperation::RemovePool { pool: id.clone() });
+        self.pool_ids.remove(position);
+        Ok(self.pools.remove(position))
+    }
+
+    /// Overwrites the reserves of a pool without rebuilding the token graph, returning whether
+    /// they changed. Fails with [`RouterError::InvalidPool`] if the pool would not enter the
+    /// router with these reserves, see [`UniV2Pool::validate`].
+    pub(super) fn set_pool_reserves(
+        &mut self,
+        id: &PoolId,
+        reserve0: f64,
+        reserve1: f64,
+    ) -> Result<bool, RouterError> {
+        let position = self.pool_position(id)?;
+        let mut pool = self.pools[position].clone();
+        pool.reserve0 = reserve0;
+        pool.reserve1 = reserve1;
+        pool.raw_reserves = None;
+        pool.validate().map_err(|error| RouterError::InvalidPool {
+            pool: id.clone(),
+            error,
+        })?;
+        if self.has_reserves(id, reserve0, reserve1) {
+            return Ok(false);
+        }
+        self.record_operation(|| CapturedOperation::UpdateReserves {
+            pool: id.clone(),
+            reserve0,
+            reserve1,
+        });
+        self.pools[position] = pool;
+        Ok(true)
+    }
+
+    /// Re-aggregates the token graph from the pools after a change of the pool set, keeping the
+    /// current prices as warm start.
+    pub(super) fn rebuild_token_graph(&mut self) {
+        let mut token_graph = TokenGraph::from_pools(
+            &self.pools,
+            &self.token_index,
+            self.config.liquidity_storage,
+        );
+        token_graph.inherit_prices(&self.token_graph);
+        self.token_graph = token_graph;
+        self.apply_blacklist();
+    }
+
+    /// Returns whether the pool `id` exists and holds exactly these reserves.
+    pub(super) fn has_reserves(&self, id: &PoolId, reserve0: f64, reserve1: f64) -> bool {
+        self.pool_position(id).is_ok_and(|position| {
+            let pool = &self.pools[position];
+            pool.reserve0.to_bits() == reserve0.to_bits()
+                && pool.reserve1.to_bits() == reserve1.to_bits()
+        })
+    }
+
+    /// Removes the pool of the last addition, restoring the state from before it.
+    fn undo_addition(&mut self, addition: PoolAddition) -> UniV2Pool {
+        self.record_operation(|| CapturedOperation::RemovePool {
+            pool: addition.id.clone(),
+        });
+        self.pool_ids.pop();
+        let pool = self.pools.pop().expect("the added pool is the last one");
+        self.next_pool_id -= 1;
+        for token in self.tokens.drain(addition.tokens..) {
+            self.token_index.remove(token);
+        }
+        self.token_graph = addition.token_graph;
+        self.publish_version();
+        pool
+    }
+
+    fn record_operation(&mut self, operation: impl FnOnce() -> CapturedOperation) {
+        if let Some(capture) = &mut self.capture {
+            capture.push(operation());
+        }
+    }
+
+    fn pool_position(&self, id: &PoolId) -> Result<usize, RouterError> {
+        self.pool_ids
+            .iter()
+            .position(|pool_id| pool_id == id)
+            .ok_or_else(|| RouterError::UnknownPool(id.clone()))
+    }
+}
@@ -0,0 +1,328 @@
+use crate::router::{
+    AutoSettle, BandSelection, HistoryConfig, RouterConfig, SolverConfig, TradeSizePolicy,
+};
+
+use std::fmt;
+
+/// Named configurations for the common uses of the router, see [`RouterConfig::profile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Profile {
+    /// Interactive quotes: fast answers at a precision far beyond what a user interface shows
+    UiQuoting,
+    /// Trades that are executed: precision first, every change audited after the fact
+    Settlement,
+    /// Offline analysis: precision, and a full record of the session to replay it
+    Research,
+    /// Constrained environments: bounded memory, modest iteration budget
+    Embedded,
+}
+
+/// Fields on which two configurations differ, see [`RouterConfig::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiff {
+    pub differences: Vec<FieldDifference>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDifference {
+    pub field: &'static str,
+    /// Value in the configuration `diff` was called on
+    pub value: String,
+    /// Value in the configuration it was compared to
+    pub other_value: String,
+}
+
+impl ConfigDiff {
+    /// Returns whether the configurations are identical.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.differences.is_empty() {
+            return writeln!(f, "no difference");
+        }
+        for difference in &self.differences {
+            writeln!(
+                f,
+                "{}: {} instead of {}",
+                difference.field, difference.value, difference.other_value
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl RouterConfig {
+    /// Returns the fully specified configuration of `profile`. The reference token and the
+    /// blacklist depend on the pools and are left empty.
+    pub fn profile(profile: Profile) -> Self {
+        match profile {
+            Profile::UiQuoting => Self {
+                // Quotes are discarded, nobody reads them back
+                history: HistoryConfig::Disabled,
+                capture_session: false,
+                // Six significant digits are displayed at most; the bands keep that precision
+                // across trade sizes at a fraction of the default budget
+                solver: SolverConfig {
+                    tolerance: 1e-10,
+                    absolute_tolerance: 0.0,
+                    output_accuracy: None,
+                    max_iterations: 2_000,
+                },
+                trade_size_policy: TradeSizePolicy {
+                    selection: BandSelection::Automatic,
+                    ..TradeSizePolicy::default()
+                },
+                // A quote must not show the arbitrage opened by the latest blocks, but settling
+                // every batch of a busy feed would cost more than settling once per quote
+                auto_settle: AutoSettle::OnNextSolve,
+                staleness_threshold: 1e-3,
+                ..Self::default()
+            },
+            Profile::Settlement => Self {
+                // Every executed trade is kept for reconciliation
+                history: HistoryConfig::Unbounded,
+                capture_session: false,
+                // Tight tolerance and a budget large enough for slowly converging components
+                solver: SolverConfig {
+                    tolerance: 1e-14,
+                    absolute_tolerance: 0.0,
+                    output_accuracy: None,
+                    max_iterations: 200_000,
+                },
+                trade_size_policy: TradeSizePolicy {
+                    selection: BandSelection::Disabled,
+                    ..TradeSizePolicy::default()
+                },
+                // Arbitrage is absorbed as soon as it appears, never attributed to a trade
+                auto_settle: AutoSettle::Eager,
+                staleness_threshold: 1e-6,
+                // Deep reorgs must stay recoverable and redelivered events skipped
+                event_dedup_window: 16_384,
+                retained_snapshots: 64,
+                ..Self::default()
+            },
+            Profile::Research => Self {
+                history: HistoryConfig::Unbounded,
+                // Sessions are replayed and bisected offline
+                capture_session: true,
+                solver: SolverConfig {
+                    tolerance: 1e-13,
+                    absolute_tolerance: 0.0,
+                    output_accuracy: None,
+                    max_iterations: 100_000,
+                },
+                trade_size_policy: TradeSizePolicy {
+                    selection: BandSelection::Automatic,
+                    ..TradeSizePolicy::default()
+                },
+                // Arbitrage is part of what is studied, it is left in the pools
+                auto_settle: AutoSettle::Off,
+                // No amount is hidden as dust
+                dust_tolerance: 0.0,
+                ..Self::default()
+            },
+            Profile::Embedded => Self {
+                // Nothing grows with the length of the session
+                history: HistoryConfig::Disabled,
+                capture_session: false,
+                event_dedup_window: 64,
+                retained_snapshots: 1,
+                solver: SolverConfig {
+                    tolerance: 1e-10,
+                    absolute_tolerance: 0.0,
+                    output_accuracy: None,
+                    max_iterations: 5_000,
+                },
+                trade_size_policy: TradeSizePolicy {
+                    selection: BandSelection::Disabled,
+                    bands: Vec::new(),
+                },
+                auto_settle: AutoSettle::OnNextSolve,
+                staleness_threshold: 1e-3,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Lists the fields on which this configuration departs from `other`, typically a
+    /// [`RouterConfig::profile`].
+    pub fn diff(&self, other: &RouterConfig) -> ConfigDiff {
+        // Destructured so that a new field cannot be forgotten here
+        let RouterConfig {
+            history,
+            reference_token,
+            capture_session,
+            blacklisted_tokens,
+            dust_tolerance,
+            warm_start,
+            event_dedup_window,
+            retained_snapshots,
+            solver,
+            solver_domain,
+            relaxation,
+            acceleration,
+            sweep_mode,
+            parallel_sweep_threshold,
+            solver_kind,
+            fallback,
+            price_normalization,
+            fee_model,
+            strict_math,
+            trade_size_policy,
+            auto_settle,
+            staleness_threshold,
+            publish_deltas,
+            plan_depth_warning,
+            liquidity_storage,
+            quote_cache_capacity,
+        } = self;
+        let fields = [
+            (
+                "history",
+                format!("{history:?}"),
+                format!("{:?}", other.history),
+            ),
+            (
+                "reference_token",
+                format!("{reference_token:?}"),
+                format!("{:?}", other.reference_token),
+            ),
+            (
+                "capture_session",
+                format!("{capture_session:?}"),
+                format!("{:?}", other.capture_session),
+            ),
+            (
+                "blacklisted_tokens",
+                format!("{blacklisted_tokens:?}"),
+                format!("{:?}", other.blacklisted_tokens),
+            ),
+            (
+                "dust_tolerance",
+                format!("{dust_tolerance:?}"),
+                format!("{:?}", other.dust_tolerance),
+            ),
+            (
+                "warm_start",
+                format!("{warm_start:?}"),
+                format!("{:?}", other.warm_start),
+            ),
+            (
+                "event_dedup_window",
+                format!("{event_dedup_window:?}"),
+                format!("{:?}", other.event_dedup_window),
+            ),
+            (
+                "retained_snapshots",
+                format!("{retained_snapshots:?}"),
+                format!("{:?}", other.retained_snapshots),
+            ),
+            (
+                "solver",
+                format!("{solver:?}"),
+                format!("{:?}", other.solver),
+            ),
+            (
+                "solver_domain",
+                format!("{solver_domain:?}"),
+                format!("{:?}", other.solver_domain),
+            ),
+            (
+                "relaxation",
+                format!("{relaxation:?}"),
+                format!("{:?}", other.relaxation),
+            ),
+            (
+                "acceleration",
+                format!("{acceleration:?}"),
+                format!("{:?}", other.acceleration),
+            ),
+            (
+                "sweep_mode",
+                format!("{sweep_mode:?}"),
+                format!("{:?}", other.sweep_mode),
+            ),
+            (
+                "parallel_sweep_threshold",
+                format!("{parallel_sweep_threshold:?}"),
+                format!("{:?}", other.parallel_sweep_threshold),
+            ),
+            (
+                "solver_kind",
+                format!("{solver_kind:?}"),
+                format!("{:?}", other.solver_kind),
+            ),
+            (
+                "fallback",
+                format!("{fallback:?}"),
+                format!("{:?}", other.fallback),
+            ),
+            (
+                "price_normalization",
+                format!("{price_normalization:?}"),
+                format!("{:?}", other.price_normalization),
+            ),
+            (
+                "fee_model",
+                format!("{fee_model:?}"),
+                format!("{:?}", other.fee_model),
+            ),
+            (
+                "strict_math",
+                format!("{strict_math:?}"),
+                format!("{:?}", other.strict_math),
+            ),
+            (
+                "trade_size_policy",
+                format!("{trade_size_policy:?}"),
+                format!("{:?}", other.trade_size_policy),
+            ),
+            (
+                "auto_settle",
+                format!("{auto_settle:?}"),
+                format!("{:?}", other.auto_settle),
+            ),
+            (
+                "staleness_threshold",
+                format!("{staleness_threshold:?}"),
+                format!("{:?}", other.staleness_threshold),
+            ),
+            (
+                "publish_deltas",
+                format!("{publish_deltas:?}"),
+                format!("{:?}", other.publish_deltas),
+            ),
+            (
+                "plan_depth_warning",
+                format!("{plan_depth_warning:?}"),
+                format!("{:?}", other.plan_depth_warning),
+            ),
+            (
+                "liquidity_storage",
+                format!("{liquidity_storage:?}"),
+                format!("{:?}", other.liquidity_storage),
+            ),
+            (
+                "quote_cache_capacity",
+                format!("{quote_cache_capacity:?}"),
+                format!("{:?}", other.quote_cache_capacity),
+            ),
+        ];
+        ConfigDiff {
+            differences: fields
+                .into_iter()
+                .filter(|(_, value, other_value)| value != other_value)
+                .map(|(field, value, other_value)| FieldDifference {
+                    field,
+                    value,
+                    other_value,
+                })
+                .collect(),
+        }
+    }
+}
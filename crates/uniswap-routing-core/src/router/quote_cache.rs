@@ -0,0 +1,133 @@
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{error::RouterError, router::Router};
+
+/// Number of low bits of the mantissa of an input amount dropped by the key of the quote cache:
+/// amounts within a relative `2⁻⁴¹` of each other share their quote, see
+/// [`Router::quote_cached`].
+const DROPPED_MANTISSA_BITS: u32 = 12;
+
+/// Quotes of the current state of a router, see [`Router::quote_cached`].
+///
+/// The quotes are keyed by input token, output token and quantized input amount, and tagged with
+/// the state version they were computed at, see [`Router::publish_version`]: the first lookup
+/// at a later version drops them all. At capacity, the least recently used quote makes room for
+/// the new one; the cache is meant to be small, and is scanned linearly.
+///
+/// Lookups take `&self`, the lock serializing them; the quotes are computed outside of it. A copy
+/// of the router starts with an empty cache, see the [`Clone`] implementation.
+#[derive(Debug, Default)]
+pub(super) struct QuoteCache(Mutex<CachedQuotes>);
+
+#[derive(Debug, Default)]
+struct CachedQuotes {
+    /// State version of the quotes
+    version: u64,
+    /// Quotes by key, from the least to the most recently used
+    quotes: VecDeque<(QuoteKey, f64)>,
+}
+
+/// Input token index, output token index and quantized input amount, see [`quantize`].
+type QuoteKey = (usize, usize, u64);
+
+impl Clone for QuoteCache {
+    /// Returns an empty cache: the copy is meant to change its state, which would drop the quotes
+    /// at the first lookup anyway.
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl QuoteCache {
+    /// Returns the quote of `key` at `version`, marking it as the most recently used.
+    fn get(&self, key: QuoteKey, version: u64) -> Option<f64> {
+        let mut cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if cached.version != version {
+            cached.version = version;
+            cached.quotes.clear();
+            return None;
+        }
+        let position = cached.quotes.iter().position(|&(k, _)| k == key)?;
+        let entry = cached.quotes.remove(position)?;
+        cached.quotes.push_back(entry);
+        Some(entry.1)
+    }
+
+    /// Stores the quote of `key` computed at `version`, evicting the least recently used quote
+    /// beyond `capacity`. Quotes of a past version are not stored.
+    fn insert(&self, key: QuoteKey, output_amount: f64, version: u64, capacity: usize) {
+        let mut cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if cached.version != version {
+            return;
+        }
+        // A concurrent lookup of the same key may have stored it in the meantime
+        if cached.quotes.iter().any(|&(k, _)| k == key) {
+            return;
+        }
+        if cached.quotes.len() >= capacity {
+            cached.quotes.pop_front();
+        }
+        cached.quotes.push_back((key, output_amount));
+    }
+
+    /// Returns the number of quotes held.
+    pub(super) fn len(&self) -> usize {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .quotes
+            .len()
+    }
+}
+
+/// Rounds the bits of a non-negative `amount` to the nearest multiple of
+/// `2^DROPPED_MANTISSA_BITS`, the carry moving on to the exponent.
+fn quantize(amount: f64) -> u64 {
+    (amount.to_bits() + (1 << (DROPPED_MANTISSA_BITS - 1))) >> DROPPED_MANTISSA_BITS
+}
+
+/// Returns the amount [`Router::quote_cached`] quotes for a non-negative `amount`.
+pub(super) fn rounded_amount(amount: f64) -> f64 {
+    f64::from_bits(quantize(amount) << DROPPED_MANTISSA_BITS)
+}
+
+impl<'l> Router<'l> {
+    /// Returns the output amount [`Router::quote`] returns for the trade, from a cache of the
+    /// quotes of the current state of the router when enabled, see
+    /// [`RouterConfig::quote_cache_capacity`](crate::router::RouterConfig::quote_cache_capacity).
+    ///
+    /// The input amount is rounded to 41 significant bits, a relative change of at most `2⁻⁴¹`,
+    /// and the trade quoted for the rounded amount: amounts rounding alike share the same quote,
+    /// the same bit for bit whichever was requested first. Every operation that may change the
+    /// state of the router invalidates the cache: trades, pool updates, events, settlements,
+    /// blacklisting, compactions and changes of the reference token. Failed quotes are not
+    /// cached. Without a cache, every call quotes the trade.
+    ///
+    /// Fails as [`Router::solve`] does.
+    pub fn quote_cached(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<f64, RouterError> {
+        let capacity = self.config.quote_cache_capacity;
+        if capacity == 0 || !(input_amount >= 0.0 && input_amount.is_finite()) {
+            return self.quote(input_token, output_token, input_amount);
+        }
+        let key = (
+            self.routable_index(input_token)?,
+            self.routable_index(output_token)?,
+            quantize(input_amount),
+        );
+        if let Some(output_amount) = self.quote_cache.get(key, self.state_version) {
+            return Ok(output_amount);
+        }
+        let output_amount = self.quote(input_token, output_token, rounded_amount(input_amount))?;
+        self.quote_cache
+            .insert(key, output_amount, self.state_version, capacity);
+        Ok(output_amount)
+    }
+}
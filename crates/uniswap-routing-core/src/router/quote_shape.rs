@@ -0,0 +1,121 @@
+use crate::{
+    error::RouterError,
+    router::{Router, parallel::map_ordered},
+};
+
+/// Relative deviation, of the outputs involved, below which a shape violation is treated as
+/// floating-point noise.
+const SHAPE_TOLERANCE: f64 = 1e-9;
+
+/// Outcome of [`Router::check_quote_shape`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShapeReport {
+    /// Sampled quotes by increasing input amount, starting at the empty trade
+    pub samples: Vec<ShapeSample>,
+    /// First violation along the curve, `None` if the quotes are non-decreasing and concave
+    pub violation: Option<ShapeViolation>,
+}
+
+/// A quote of the sampled curve with the convergence of its solve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShapeSample {
+    pub input_amount: f64,
+    pub output_amount: f64,
+    /// Number of fixed-point sweeps of the solve, zero for the empty trade
+    pub iterations: usize,
+    /// Largest relative price change of the last sweep
+    pub residual: f64,
+}
+
+/// Two consecutive samples between which the quote curve loses its shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShapeViolation {
+    pub kind: ShapeViolationKind,
+    pub lower: ShapeSample,
+    pub upper: ShapeSample,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ShapeViolationKind {
+    /// The output decreases from `lower` to `upper`
+    Decreasing,
+    /// The marginal output between `lower` and `upper` exceeds the one of the previous pair
+    NotConcave,
+}
+
+impl Router<'_> {
+    /// Samples the quote curve at `samples` evenly spaced input amounts up to `max_amount`, each
+    /// solved from the current state as [`Router::solve`] would, without modifying the router,
+    /// and checks that the output is non-decreasing and concave in the input.
+    ///
+    /// The output of a fee-free constant-product network is both, so a violation points at a
+    /// solve that stopped short of the equilibrium, typically with a tolerance too loose for
+    /// the trade size. The first violating pair of consecutive samples is reported, deviations
+    /// within a relative `1e-9` of the outputs being ignored: with evenly spaced samples, the
+    /// curve is concave when no output falls below the midpoint of its neighbours.
+    ///
+    /// Fails if either token is unknown or blacklisted, if the tokens are not connected, see
+    /// [`Router::solve`], and with [`RouterError::InvalidAmount`]
+    /// if `max_amount` is not positive and finite.
+    pub fn check_quote_shape(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        max_amount: f64,
+        samples: usize,
+    ) -> Result<ShapeReport, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        self.check_connected(input_index, output_index)?;
+        if !(max_amount.is_finite() && max_amount > 0.0) {
+            return Err(RouterError::InvalidAmount(max_amount));
+        }
+        let mut scratch = self.scratch_copy();
+        scratch.settle_if_pending();
+        let (scratch, base) = (&scratch, &scratch.token_graph);
+
+        let amounts = (1..=samples)
+            .map(|sample| max_amount * sample as f64 / samples as f64)
+            .collect::<Vec<_>>();
+        let quotes = map_ordered(amounts, |input_amount| {
+            let mut graph = base.clone();
+            let (_, _, solver) = scratch.trade_solver(input_index, input_amount);
+            graph.set_solver(solver);
+            let outcome = graph.apply_trade_and_solve(input_index, output_index, input_amount);
+            ShapeSample {
+                input_amount,
+                output_amount: outcome.output_amount,
+                iterations: outcome.iterations,
+                residual: outcome.residual,
+            }
+        });
+        let empty_trade = ShapeSample {
+            input_amount: 0.0,
+            output_amount: 0.0,
+            iterations: 0,
+            residual: 0.0,
+        };
+        let samples = [empty_trade].into_iter().chain(quotes).collect::<Vec<_>>();
+
+        let violation = samples.windows(2).enumerate().find_map(|(position, pair)| {
+            let (lower, upper) = (pair[0], pair[1]);
+            let noise = SHAPE_TOLERANCE * upper.output_amount.abs();
+            let kind = if upper.output_amount < lower.output_amount - noise {
+                ShapeViolationKind::Decreasing
+            } else if position > 0
+                && upper.output_amount - lower.output_amount
+                    > lower.output_amount - samples[position - 1].output_amount + noise
+            {
+                ShapeViolationKind::NotConcave
+            } else {
+                return None;
+            };
+            Some(ShapeViolation { kind, lower, upper })
+        });
+        Ok(ShapeReport { samples, violation })
+    }
+}
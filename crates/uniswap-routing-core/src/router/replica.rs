@@ -0,0 +1,412 @@
+use crate::{
+    error::RouterError,
+    router::{CapturedPool, HistoryConfig, Router, RouterConfig},
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
+
+/// Full state of a router at a version, from which a [`ReplicaRouter`] is built or resynced.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplicaSnapshot {
+    /// Number of committed operations that changed the state, see [`Router::publish`]
+    pub version: u64,
+    pub config: RouterConfig,
+    /// Tokens in the order of the router, including those left without pools
+    pub tokens: Vec<String>,
+    pub reference_token: String,
+    pub pools: Vec<CapturedPool>,
+    /// Price `q` of every token, in the order of `tokens`
+    pub prices: Vec<f64>,
+    /// Total reserve of every token, in the order of `tokens`, as accounted by the router: the
+    /// pools, whose reserves are recomputed from the prices, hold it up to the rounding of the
+    /// trades
+    pub totals: Vec<f64>,
+    /// Liquidity `(u, v, K(u, v))` of every pair of token indices, as aggregated at the last
+    /// change of the pools, from which the pool reserves drift by the rounding of the trades
+    pub liquidities: Vec<(usize, usize, f64)>,
+    /// Whether an event batch left a settlement for the next solve, see
+    /// [`AutoSettle::OnNextSolve`](crate::router::AutoSettle::OnNextSolve)
+    pub settle_pending: bool,
+    pub initial_arbitrage_value: Option<f64>,
+}
+
+/// Changes of the state of a router between two consecutive versions, see [`Router::publish`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateDelta {
+    /// Version the delta applies to
+    pub from_version: u64,
+    /// Version the delta leads to, `from_version + 1`
+    pub version: u64,
+    /// New configuration, when it changed (blacklist, reference token)
+    pub config: Option<RouterConfig>,
+    /// New token list, when tokens were added or compacted away
+    pub tokens: Option<Vec<String>>,
+    pub reference_token: Option<String>,
+    /// Pools added or whose reserves changed
+    pub updated_pools: Vec<CapturedPool>,
+    pub removed_pools: Vec<PoolId>,
+    /// Tokens whose price changed, with their new price
+    pub prices: Vec<(String, f64)>,
+    /// Tokens whose total reserve changed, with their new total
+    pub totals: Vec<(String, f64)>,
+    /// New liquidities, when the pools were aggregated again
+    pub liquidities: Option<Vec<(usize, usize, f64)>>,
+    pub settle_pending: bool,
+    pub initial_arbitrage_value: Option<f64>,
+}
+
+/// Last published state of a router, see [`RouterConfig::publish_deltas`].
+#[derive(Debug, Clone)]
+pub(super) struct Replication {
+    state: ReplicaSnapshot,
+    delta: Option<StateDelta>,
+}
+
+impl<'l> Router<'l> {
+    /// Returns the delta of the latest committed operation that changed the state, `None` until
+    /// one did or without [`RouterConfig::publish_deltas`].
+    ///
+    /// Every solve, settlement, event batch, pool update, blacklist change and compaction
+    /// publishes a new version when it changes the state; the delta holds the reserves, prices
+    /// and token totals it changed, and the new token list and pair liquidities if the pools
+    /// changed. Publishing compares
+    /// the whole state to the previous version, which costs a pass over the pools and tokens per
+    /// operation.
+    pub fn publish(&self) -> Option<&StateDelta> {
+        self.replication
+            .as_ref()
+            .and_then(|replication| replication.delta.as_ref())
+    }
+
+    /// Returns the full state of the router at the latest published version, to start or
+    /// resync a [`ReplicaRouter`]. Without [`RouterConfig::publish_deltas`], the current state
+    /// is returned at version 0.
+    pub fn replica_snapshot(&self) -> ReplicaSnapshot {
+        match &self.replication {
+            Some(replication) => replication.state.clone(),
+            None => self.current_snapshot(0),
+        }
+    }
+
+    pub(super) fn start_replication(&mut self) {
+        if self.config.publish_deltas {
+            self.replication = Some(Replication {
+                state: self.current_snapshot(0),
+                delta: None,
+            });
+        }
+    }
+
+    /// Publishes a new version if the operation that just committed changed the state, and
+    /// invalidates the quotes cached at the previous one, see [`Router::quote_cached`].
+    pub(super) fn publish_version(&mut self) {
+        self.state_version += 1;
+        let Some(replication) = &self.replication else {
+            return;
+        };
+        let previous = &replication.state;
+        let state = self.current_snapshot(previous.version + 1);
+        let Some(delta) = state.delta_from(previous) else {
+            return;
+        };
+        self.replication = Some(Replication {
+            state,
+            delta: Some(delta),
+        });
+    }
+
+    fn current_snapshot(&self, version: u64) -> ReplicaSnapshot {
+        ReplicaSnapshot {
+            version,
+            config: self.config.clone(),
+            tokens: self.tokens.iter().map(|token| token.to_string()).collect(),
+            reference_token: self.tokens[self.token_graph.reference_token()].to_string(),
+            pools: self
+                .pool_ids
+                .iter()
+                .zip(&self.pools)
+                .map(CapturedPool::from)
+                .collect(),
+            prices: (0..self.token_graph.len())
+                .map(|token| self.token_graph.price(token))
+                .collect(),
+            totals: (0..self.token_graph.len())
+                .map(|token| self.token_graph.total_reserve(token))
+                .collect(),
+            liquidities: self.token_graph.sorted_edges(),
+            settle_pending: self.settle_pending,
+            initial_arbitrage_value: self.initial_arbitrage_value,
+        }
+    }
+}
+
+impl ReplicaSnapshot {
+    /// Returns the changes from `previous`, `None` if there are none.
+    fn delta_from(&self, previous: &ReplicaSnapshot) -> Option<StateDelta> {
+        let previous_pools = previous
+            .pools
+            .iter()
+            .map(|pool| (&pool.id, pool))
+            .collect::<HashMap<_, _>>();
+        let pool_ids = self
+            .pools
+            .iter()
+            .map(|pool| &pool.id)
+            .collect::<HashSet<_>>();
+        let previous_prices = by_token(&previous.tokens, &previous.prices);
+        let previous_totals = by_token(&previous.tokens, &previous.totals);
+
+        let delta = StateDelta {
+            from_version: previous.version,
+            version: self.version,
+            config: (self.config != previous.config).then(|| self.config.clone()),
+            tokens: (self.tokens != previous.tokens).then(|| self.tokens.clone()),
+            reference_token: (self.reference_token != previous.reference_token)
+                .then(|| self.reference_token.clone()),
+            updated_pools: self
+                .pools
+                .iter()
+                .filter(|pool| previous_pools.get(&pool.id) != Some(pool))
+                .cloned()
+                .collect(),
+            removed_pools: previous
+                .pools
+                .iter()
+                .filter(|pool| !pool_ids.contains(&pool.id))
+                .map(|pool| pool.id.clone())
+                .collect(),
+            prices: changed(&self.tokens, &self.prices, &previous_prices),
+            totals: changed(&self.tokens, &self.totals, &previous_totals),
+            liquidities: (self.liquidities != previous.liquidities)
+                .then(|| self.liquidities.clone()),
+            settle_pending: self.settle_pending,
+            initial_arbitrage_value: self.initial_arbitrage_value,
+        };
+        let unchanged = delta.config.is_none()
+            && delta.tokens.is_none()
+            && delta.reference_token.is_none()
+            && delta.updated_pools.is_empty()
+            && delta.removed_pools.is_empty()
+            && delta.prices.is_empty()
+            && delta.totals.is_empty()
+            && delta.liquidities.is_none()
+            && self.settle_pending == previous.settle_pending
+            && self.initial_arbitrage_value == previous.initial_arbitrage_value;
+        (!unchanged).then_some(delta)
+    }
+
+    /// Applies `delta`, which must start at the version of the snapshot.
+    fn apply(&mut self, delta: &StateDelta) {
+        let previous_prices = owned(by_token(&self.tokens, &self.prices));
+        let previous_totals = owned(by_token(&self.tokens, &self.totals));
+
+        self.version = delta.version;
+        if let Some(config) = &delta.config {
+            self.config = config.clone();
+        }
+        if let Some(tokens) = &delta.tokens {
+            self.tokens = tokens.clone();
+        }
+        if let Some(reference_token) = &delta.reference_token {
+            self.reference_token = reference_token.clone();
+        }
+        self.pools
+            .retain(|pool| !delta.removed_pools.contains(&pool.id));
+        for updated in &delta.updated_pools {
+            match self.pools.iter_mut().find(|pool| pool.id == updated.id) {
+                Some(pool) => *pool = updated.clone(),
+                None => self.pools.push(updated.clone()),
+            }
+        }
+        self.prices = merged(&self.tokens, &delta.prices, &previous_prices, 1.0);
+        self.totals = merged(&self.tokens, &delta.totals, &previous_totals, 0.0);
+        if let Some(liquidities) = &delta.liquidities {
+            self.liquidities = liquidities.clone();
+        }
+        self.settle_pending = delta.settle_pending;
+        self.initial_arbitrage_value = delta.initial_arbitrage_value;
+    }
+}
+
+/// Maps every token of `tokens` to its value in `values`, index-aligned.
+fn by_token<'a>(tokens: &'a [String], values: &[f64]) -> HashMap<&'a str, f64> {
+    tokens
+        .iter()
+        .map(String::as_str)
+        .zip(values.iter().copied())
+        .collect()
+}
+
+fn owned(values: HashMap<&str, f64>) -> HashMap<String, f64> {
+    values
+        .into_iter()
+        .map(|(token, value)| (token.to_string(), value))
+        .collect()
+}
+
+/// Returns the tokens of `tokens` whose value in `values`, index-aligned, differs from the one
+/// in `previous` bit for bit, with their value.
+fn changed(tokens: &[String], values: &[f64], previous: &HashMap<&str, f64>) -> Vec<(String, f64)> {
+    tokens
+        .iter()
+        .zip(values)
+        .filter(|&(token, value)| {
+            previous.get(token.as_str()).map(|value| value.to_bits()) != Some(value.to_bits())
+        })
+        .map(|(token, &value)| (token.clone(), value))
+        .collect()
+}
+
+/// Returns the value of every token of `tokens`, from `changes` if it changed, else from
+/// `previous`, else `default`.
+fn merged(
+    tokens: &[String],
+    changes: &[(String, f64)],
+    previous: &HashMap<String, f64>,
+    default: f64,
+) -> Vec<f64> {
+    let changes = changes
+        .iter()
+        .map(|(token, value)| (token.as_str(), *value))
+        .collect::<HashMap<_, _>>();
+    tokens
+        .iter()
+        .map(|token| {
+            changes
+                .get(token.as_str())
+                .or_else(|| previous.get(token))
+                .copied()
+                .unwrap_or(default)
+        })
+        .collect()
+}
+
+/// Quote-only copy of a router following a primary through its [`StateDelta`]s.
+///
+/// A replica that applied the delta of version `n` answers every query exactly as the primary
+/// did at version `n`, bit for bit: it takes the token totals and the pair liquidities of the
+/// primary as they are, rather than aggregating them again from the pools. It dereferences to the read-only
+/// API of [`Router`]; quotes are solved on a scratch copy and leave it unchanged. Token names are
+/// leaked once per distinct name, as by [`SessionCapture::restore`](crate::router::SessionCapture::restore).
+pub struct ReplicaRouter {
+    router: Router<'static>,
+    state: ReplicaSnapshot,
+    names: HashMap<String, &'static str>,
+}
+
+impl ReplicaRouter {
+    pub fn new(snapshot: ReplicaSnapshot) -> Result<Self, RouterError> {
+        let mut names = HashMap::new();
+        let router = Self::build(&snapshot, &mut names)?;
+        Ok(Self {
+            router,
+            state: snapshot,
+            names,
+        })
+    }
+
+    /// Version of the primary state the replica reflects.
+    pub fn version(&self) -> u64 {
+        self.state.version
+    }
+
+    /// Brings the replica to the version of `delta`.
+    ///
+    /// Deltas the replica already reflects are ignored, so redelivery is harmless. A delta that
+    /// does not start at the version of the replica means some were missed: it fails with
+    /// [`RouterError::VersionGap`] and leaves the replica unchanged until a
+    /// [`ReplicaRouter::resync`].
+    pub fn apply_delta(&mut self, delta: &StateDelta) -> Result<(), RouterError> {
+        if delta.version <= self.state.version {
+            return Ok(());
+        }
+        if delta.from_version != self.state.version {
+            return Err(RouterError::VersionGap {
+                expected: self.state.version,
+                found: delta.from_version,
+            });
+        }
+        let mut state = self.state.clone();
+        state.apply(delta);
+        self.router = Self::build(&state, &mut self.names)?;
+        self.state = state;
+        Ok(())
+    }
+
+    /// Replaces the state of the replica with a full snapshot of the primary, see
+    /// [`Router::replica_snapshot`].
+    pub fn resync(&mut self, snapshot: ReplicaSnapshot) -> Result<(), RouterError> {
+        self.router = Self::build(&snapshot, &mut self.names)?;
+        self.state = snapshot;
+        Ok(())
+    }
+
+    /// Returns the output amount the primary would have returned for the trade at the version
+    /// of the replica, see [`Router::quote`].
+    pub fn quote(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<f64, RouterError> {
+        self.router.quote(input_token, output_token, input_amount)
+    }
+
+    fn build(
+        snapshot: &ReplicaSnapshot,
+        names: &mut HashMap<String, &'static str>,
+    ) -> Result<Router<'static>, RouterError> {
+        let mut name = |token: &String| -> &'static str {
+            names
+                .entry(token.clone())
+                .or_insert_with(|| Box::leak(token.clone().into_boxed_str()))
+        };
+        let tokens = snapshot.tokens.iter().map(&mut name).collect::<Vec<_>>();
+        let pools = snapshot
+            .pools
+            .iter()
+            .map(|pool| {
+                UniV2Pool::new(
+                    name(&pool.token0),
+                    name(&pool.token1),
+                    pool.reserve0,
+                    pool.reserve1,
+                )
+                .with_fee(pool.fee)
+                .with_virtual_reserves(pool.virtual_reserves.0, pool.virtual_reserves.1)
+            })
+            .collect();
+
+        let mut config = snapshot.config.clone();
+        config.reference_token = Some(snapshot.reference_token.clone());
+        config.capture_session = false;
+        config.history = HistoryConfig::Disabled;
+        config.publish_deltas = false;
+        let mut router = Router::from_tokens(tokens, pools, config)?;
+        router.pool_ids = snapshot.pools.iter().map(|pool| pool.id.clone()).collect();
+        for (token, &q) in snapshot.prices.iter().enumerate() {
+            router.token_graph.set_price(token, q);
+        }
+        for (token, &total_reserve) in snapshot.totals.iter().enumerate() {
+            router.token_graph.set_total_reserve(token, total_reserve);
+        }
+        router.token_graph.set_liquidities(&snapshot.liquidities);
+        router.settle_pending = snapshot.settle_pending;
+        router.initial_arbitrage_value = snapshot.initial_arbitrage_value;
+        Ok(router)
+    }
+}
+
+impl Deref for ReplicaRouter {
+    type Target = Router<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.router
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{error::RouterError, router::Router};
+
+/// Largest gain of a round trip, relative to the reserve of its token, attributed to the
+/// tolerance of the solver rather than to an inconsistency.
+const ROUND_TRIP_TOLERANCE: f64 = 1e-9;
+
+impl Router<'_> {
+    /// Returns the relative loss `1 - returned / amount` of selling `amount` of `token_a` for
+    /// `token_b`, then selling the proceeds back for `token_a`. Both trades are solved on a
+    /// scratch copy; the router is left untouched.
+    ///
+    /// Fee-free constant-product swaps are reversible, and so is the equilibrium of an
+    /// equilibrated graph: the cost is then zero up to the absolute error of the solver on the
+    /// output amounts, so it mostly shows up on small amounts. A graph out of equilibrium can
+    /// yield a gain, from the arbitrage captured by the first trade.
+    ///
+    /// Fails if either token is unknown or blacklisted, or with
+    /// [`RouterError::ModelInconsistency`] if the round trip gains more than `1e-9` of the reserve
+    /// of `token_a`.
+    pub fn round_trip_cost(
+        &self,
+        token_a: &str,
+        token_b: &str,
+        amount: f64,
+    ) -> Result<f64, RouterError> {
+        let mut copy = self.scratch_copy();
+        let proceeds = copy.solve(token_a, token_b, amount)?;
+        let returned = copy.solve(token_b, token_a, proceeds)?;
+        let cost = 1.0 - returned / amount;
+        let reserve = self.token_graph.active_reserve(self.index_of(token_a)?);
+        match returned - amount <= ROUND_TRIP_TOLERANCE * reserve {
+            true => Ok(cost),
+            false => Err(RouterError::ModelInconsistency(format!(
+                "selling {amount} {token_a} for {token_b} and back returns {returned}"
+            ))),
+        }
+    }
+}
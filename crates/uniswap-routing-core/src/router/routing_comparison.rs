@@ -0,0 +1,60 @@
+use crate::{error::RouterError, router::Router};
+
+/// Outputs of a trade routed through the direct pools only and through the whole graph, see
+/// [`Router::compare_routing`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RoutingComparison {
+    /// Output of the optimal split across the pools of the pair, `None` if no pool links the
+    /// tokens directly
+    pub direct_output_amount: Option<f64>,
+    /// Output of the equilibrium across every pool
+    pub graph_output_amount: f64,
+    /// `graph / direct − 1`, `None` when the direct route outputs nothing
+    pub relative_improvement: Option<f64>,
+}
+
+impl Router<'_> {
+    /// Compares the output of a trade routed through the direct pools of the pair, split in
+    /// closed form by [`Router::optimal_direct_split`], with the output of the equilibrium
+    /// across the whole graph as [`Router::solve`] would return it, without modifying the
+    /// router.
+    ///
+    /// Both are computed on a settled copy of the pools, see [`Router::settle`]: out of
+    /// equilibrium the solver would also capture the arbitrage left in the pools, crediting
+    /// multi-hop routing with gains unrelated to the trade. The graph output is then at least
+    /// the direct output, up to the solver tolerance.
+    ///
+    /// Fails if either token is unknown or blacklisted, with [`RouterError::InvalidAmount`] if
+    /// `input_amount` is negative or not finite, and with [`RouterError::DidNotConverge`] if the
+    /// equilibrium solve does not converge.
+    pub fn compare_routing(
+        &self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<RoutingComparison, RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        let mut scratch = self.scratch_copy();
+        scratch.settle_components();
+
+        let direct_output_amount =
+            match scratch.optimal_direct_split(input_token, output_token, input_amount) {
+                Ok(swaps) => Some(swaps.iter().fold(0.0, |sum, swap| sum + swap.output_amount)),
+                Err(RouterError::NoDirectPool { .. }) => None,
+                Err(error) => return Err(error),
+            };
+        let graph_output_amount = scratch
+            .solve_trade(input_index, output_index, input_amount)?
+            .output_amount;
+
+        Ok(RoutingComparison {
+            direct_output_amount,
+            graph_output_amount,
+            relative_improvement: direct_output_amount
+                .filter(|&direct| direct > 0.0)
+                .map(|direct| graph_output_amount / direct - 1.0),
+        })
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+use crate::router::Router;
+
+use std::collections::VecDeque;
+
+/// Buffers of the solver, kept from one trade to the next so that the solves of a router reuse
+/// them instead of allocating their own, see
+/// [`TokenGraph`](crate::router::token_graph::TokenGraph).
+///
+/// Every buffer is cleared before use and holds nothing between two solves: only its capacity
+/// carries over, which the first solves grow to that of the largest component. The scratch
+/// belongs to its graph and is only reached through `&mut` access to it, so that the solves of
+/// one router are sequential and never share it. A copy of the graph starts with empty buffers of
+/// its own, see the [`Clone`] implementation, and the routers quoting in parallel on copies of a
+/// router, one per thread, each grow their own: the scratch is plain owned buffers, adding no
+/// synchronization and no state shared between threads.
+#[derive(Debug, Default)]
+pub(super) struct SolveScratch {
+    /// Tokens of the connected component of the trade, by token index
+    pub(super) component: Vec<usize>,
+    /// Root prices of every token before the trade, to undo it, see
+    /// [`TokenGraph::checkpoint`](crate::router::token_graph::TokenGraph::checkpoint)
+    pub(super) prices: Vec<f64>,
+    /// Buffers of the fixed-point sweeps
+    pub(super) sweeps: SweepScratch,
+}
+
+/// Buffers of the fixed-point iteration, see [`SolveScratch`].
+#[derive(Debug, Default)]
+pub(super) struct SweepScratch {
+    /// Logarithms of the prices, in the log domain
+    pub(super) log_q: Vec<f64>,
+    /// Updates of a Jacobi sweep, applied once the sweep is over: token, price, log price
+    pub(super) jacobi_updates: Vec<(usize, f64, f64)>,
+    /// Tokens of a Jacobi sweep whose denominators are computed at once in parallel
+    pub(super) parallel_tokens: Vec<usize>,
+    /// Denominators of the updates of `parallel_tokens`, in the same order
+    pub(super) denominators: Vec<f64>,
+    /// Tokens waiting for an update of the active-set iteration, first in first out
+    pub(super) active_tokens: VecDeque<usize>,
+    /// Whether every token, by token index, is in `active_tokens`
+    pub(super) is_active: Vec<bool>,
+}
+
+impl SolveScratch {
+    /// Returns the number of values the buffers can hold without reallocating, all together.
+    #[cfg(test)]
+    pub(super) fn capacity(&self) -> usize {
+        let SweepScratch {
+            log_q,
+            jacobi_updates,
+            parallel_tokens,
+            denominators,
+            active_tokens,
+            is_active,
+        } = &self.sweeps;
+        self.component.capacity()
+            + self.prices.capacity()
+            + log_q.capacity()
+            + jacobi_updates.capacity()
+            + parallel_tokens.capacity()
+            + denominators.capacity()
+            + active_tokens.capacity()
+            + is_active.capacity()
+    }
+}
+
+impl Clone for SolveScratch {
+    /// Returns empty buffers: those of the copied graph hold nothing between two solves, and the
+    /// copy grows its own as it solves.
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+impl Router<'_> {
+    /// Returns the number of values the buffers reused from one solve to the next hold without
+    /// reallocating: those of the token graph, and the pools of the last trade with their
+    /// identifiers, see [`Router::write_back_pool_reserves`].
+    fn scratch_capacity(&self) -> usize {
+        let traded_pools = self.last_trade.iter().flat_map(|trade| &trade.pools);
+        self.token_graph.scratch_capacity()
+            + self
+                .last_trade
+                .as_ref()
+                .map_or(0, |trade| trade.pools.capacity())
+            + traded_pools.map(|pool| pool.id.0.capacity()).sum::<usize>()
+            + self.spare_pool_ids.capacity()
+            + self
+                .spare_pool_ids
+                .iter()
+                .map(|id| id.0.capacity())
+                .sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        router::{FeeModel, Router, SolverDomain, SolverKind, SweepMode},
+        test_utils::synthetic_pools,
+    };
+
+    /// Number of trades after the first round trip, which grows the buffers.
+    const TRADES: usize = 1_000;
+
+    #[test]
+    fn solves_keep_the_capacity_of_their_buffers() {
+        let pools = synthetic_pools(12, 30, 353);
+        let builders = [
+            Router::builder(pools.clone()),
+            Router::builder(pools.clone()).sweep_mode(SweepMode::Jacobi),
+            Router::builder(pools.clone()).solver_domain(SolverDomain::Log),
+            Router::builder(pools.clone()).solver_kind(SolverKind::ActiveSet),
+        ];
+        for builder in builders {
+            let mut router = builder.fee_model(FeeModel::FeeFree).build().unwrap();
+            // Back and forth between the same tokens, selling the output of each trade back
+            let mut amount = 1e-3 * router.reserve_by_token().next().unwrap().1;
+            let mut trade = |router: &mut Router, n: usize| {
+                let (input, output) = match n % 2 {
+                    0 => ("T0", "T1"),
+                    _ => ("T1", "T0"),
+                };
+                amount = router.solve(input, output, amount).unwrap();
+            };
+            for n in 0..2 {
+                trade(&mut router, n);
+            }
+            let capacity = router.scratch_capacity();
+            for n in 0..TRADES {
+                trade(&mut router, n);
+            }
+            assert_eq!(router.scratch_capacity(), capacity, "{:?}", router.config);
+        }
+    }
+}
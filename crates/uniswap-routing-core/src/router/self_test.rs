@@ -0,0 +1,457 @@
+use crate::router::{BandSelection, Router, SolverConfig, quote_cache::rounded_amount};
+
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+/// Checks run by [`Router::self_test`], each of which can be turned off, and their parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestConfig {
+    /// Consistency of the state, see [`Router::audit`]
+    pub audit: bool,
+    /// Convergence outlook of every component, see [`Router::conditioning_report`]
+    pub conditioning: bool,
+    /// Convergence of a settlement, see [`Router::settle`]
+    pub settle: bool,
+    /// Quotes of the most liquid pairs, of a leaf pair, and of a pair spanning two components,
+    /// which must yield nothing
+    pub probe_quotes: bool,
+    /// Agreement of a quote with its re-solve at a tight tolerance
+    pub precision: bool,
+    /// Quotes of a copy caching them, see [`Router::quote_cached`]: a repeated quote must return
+    /// the cached one bit for bit, and a trade must invalidate it
+    pub quote_cache: bool,
+    /// Agreement, bit for bit, of the quotes of the probe pairs, both ways, made from
+    /// `concurrency_threads` threads sharing the router, see [`Router::quote`], with those made
+    /// from one thread
+    pub concurrency: bool,
+    /// Agreement of a quote with the execution of its swap plan, see [`Router::solve_with_plan`]
+    pub fill: bool,
+    /// Number of most liquid pairs quoted by the probe quotes
+    pub probe_pairs: usize,
+    /// Input amount of the probe quotes, relative to the reserve of the input token
+    pub probe_size_ratio: f64,
+    /// Largest relative difference tolerated by the precision check
+    pub precision_tolerance: f64,
+    /// Largest relative difference tolerated by the fill check, between the output of the plan
+    /// and the quote, and between the output of each swap and the reserve of its pool
+    pub fill_tolerance: f64,
+    /// Number of threads of the concurrency check
+    pub concurrency_threads: usize,
+}
+
+impl Default for SelfTestConfig {
+    fn default() -> Self {
+        Self {
+            audit: true,
+            conditioning: true,
+            settle: true,
+            probe_quotes: true,
+            precision: true,
+            quote_cache: true,
+            concurrency: true,
+            fill: true,
+            probe_pairs: 3,
+            probe_size_ratio: 1e-3,
+            precision_tolerance: 1e-6,
+            fill_tolerance: 1e-9,
+            concurrency_threads: 8,
+        }
+    }
+}
+
+/// Outcome of [`Router::self_test`], one entry per check in the order of [`SelfTestConfig`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub outcome: CheckOutcome,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckOutcome {
+    /// The check passed, with a summary of what it observed
+    Passed(String),
+    /// The check failed, with the reason
+    Failed(String),
+    /// The check was turned off, or the state has nothing for it to exercise
+    Skipped(String),
+}
+
+impl SelfTestReport {
+    /// Returns whether no check failed.
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| !matches!(check.outcome, CheckOutcome::Failed(_)))
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let (status, detail) = match &check.outcome {
+                CheckOutcome::Passed(detail) => ("PASS", detail),
+                CheckOutcome::Failed(reason) => ("FAIL", reason),
+                CheckOutcome::Skipped(reason) => ("SKIP", reason),
+            };
+            writeln!(
+                f,
+                "{status} {:<14} {:>10.3?}  {detail}",
+                check.name, check.elapsed
+            )?;
+        }
+        let failed = self
+            .checks
+            .iter()
+            .filter(|check| matches!(check.outcome, CheckOutcome::Failed(_)))
+            .count();
+        match failed {
+            0 => writeln!(f, "PASS"),
+            _ => writeln!(f, "FAIL: {failed} failed checks"),
+        }
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Runs a health check of the router, without modifying it: every check quoting or settling
+    /// works on a scratch copy.
+    pub fn self_test(&self, config: &SelfTestConfig) -> SelfTestReport {
+        let pairs = self.most_liquid_pairs(config.probe_pairs.max(2));
+        let checks = vec![
+            run_check("audit", config.audit, || {
+                let violations = self.audit();
+                match violations.is_empty() {
+                    true => Ok("state consistent".to_string()),
+                    false => Err(format!(
+                        "{} violations, first: {}",
+                        violations.len(),
+                        violations[0]
+                    )),
+                }
+            }),
+            run_check("conditioning", config.conditioning, || {
+                let report = self.conditioning_report();
+                let radii = report
+                    .components
+                    .iter()
+                    .map(|component| component.spectral_radius);
+                match radii
+                    .clone()
+                    .find(|radius| radius.is_nan() || *radius >= 1.0)
+                {
+                    Some(radius) => Err(format!("spectral radius of {radius}, cannot converge")),
+                    None => Ok(format!(
+                        "{} components, largest spectral radius {:.6}",
+                        report.components.len(),
+                        radii.fold(0.0, f64::max)
+                    )),
+                }
+            }),
+            run_check("settle", config.settle, || {
+                let settlement = self.scratch_copy().settle();
+                let max_iterations = self.config.solver.max_iterations;
+                let unconverged = settlement
+                    .components
+                    .iter()
+                    .filter(|component| {
+                        component.iterations >= max_iterations
+                            || !component.extracted_amount.is_finite()
+                    })
+                    .count();
+                match unconverged {
+                    0 => Ok(format!(
+                        "{} components settled",
+                        settlement.components.len()
+                    )),
+                    _ => Err(format!(
+                        "{unconverged} of {} components did not converge",
+                        settlement.components.len()
+                    )),
+                }
+            }),
+            run_check("probe quotes", config.probe_quotes, || {
+                self.probe_quotes(&pairs[..pairs.len().min(config.probe_pairs)], config)
+            }),
+            run_check("precision", config.precision, || {
+                let &(input, output) = pairs.first().ok_or("no pair to quote")?;
+                let amount = config.probe_size_ratio * self.token_graph.active_reserve(input);
+                let quoted = self.scratch_copy().solve_between(input, output, amount)?;
+                let mut tight = self.scratch_copy();
+                tight.config.trade_size_policy.selection = BandSelection::Disabled;
+                tight.config.solver = SolverConfig {
+                    tolerance: 1e-14,
+                    absolute_tolerance: 0.0,
+                    output_accuracy: None,
+                    max_iterations: 10 * self.config.solver.max_iterations,
+                };
+                let reference = tight.solve_between(input, output, amount)?;
+                let difference = relative_difference(quoted, reference);
+                match difference <= config.precision_tolerance {
+                    true => Ok(format!("relative difference {difference:e}")),
+                    false => Err(format!(
+                        "quote {quoted} differs from {reference} by a relative {difference:e}"
+                    )),
+                }
+            }),
+            run_check("quote cache", config.quote_cache, || {
+                let &(input, output) = pairs.first().ok_or("no pair to quote")?;
+                self.check_quote_cache(input, output, config)
+            }),
+            run_check("concurrency", config.concurrency, || {
+                self.check_concurrency(&pairs[..pairs.len().min(config.probe_pairs)], config)
+            }),
+            run_check("fill", config.fill, || {
+                let &(input, output) = pairs.get(1).or(pairs.first()).ok_or("no pair to quote")?;
+                self.check_fill(input, output, config)
+            }),
+        ];
+        SelfTestReport { checks }
+    }
+
+    fn solve_between(&mut self, input: usize, output: usize, amount: f64) -> Result<f64, String> {
+        self.solve(self.tokens[input], self.tokens[output], amount)
+            .map_err(|error| error.to_string())
+    }
+
+    /// Returns up to `count` pairs, most liquid first, as `(input, output)` token indices.
+    fn most_liquid_pairs(&self, count: usize) -> Vec<(usize, usize)> {
+        let mut edges = self.token_graph.sorted_edges();
+        edges.sort_by(|(.., liquidity), (.., other)| other.total_cmp(liquidity));
+        edges
+            .into_iter()
+            .take(count)
+            .map(|(u, v, _)| (u, v))
+            .collect()
+    }
+
+    fn probe_quotes(
+        &self,
+        pairs: &[(usize, usize)],
+        config: &SelfTestConfig,
+    ) -> Result<String, String> {
+        let graph = &self.token_graph;
+        let leaf_pair = (0..self.tokens.len())
+            .filter(|&token| graph.neighbors(token).count() == 1)
+            .find_map(|token| {
+                graph
+                    .neighbors(token)
+                    .next()
+                    .map(|(paired, _)| (token, paired))
+            });
+        let mut quoted = 0;
+        for &(input, output) in pairs.iter().chain(&leaf_pair) {
+            let amount = config.probe_size_ratio * graph.active_reserve(input);
+            let output_amount = self.scratch_copy().solve_between(input, output, amount)?;
+            if !(output_amount.is_finite() && output_amount > 0.0) {
+                return Err(format!(
+                    "{amount} {} to {} yields {output_amount}",
+                    self.tokens[input], self.tokens[output]
+                ));
+            }
+            quoted += 1;
+        }
+
+        let components = graph
+            .connected_components()
+            .into_iter()
+            .filter(|component| component.len() >= 2)
+            .collect::<Vec<_>>();
+        let cross_component = match components.as_slice() {
+            [first, second, ..] => {
+                let (input, output) = (first[0], second[0]);
+                let amount = config.probe_size_ratio * graph.active_reserve(input);
+                match self.scratch_copy().solve_between(input, output, amount) {
+                    Ok(output_amount) if output_amount != 0.0 => {
+                        return Err(format!(
+                            "{} to {}, in distinct components, yields {output_amount}",
+                            self.tokens[input], self.tokens[output]
+                        ));
+                    }
+                    _ => "cross-component quote refused",
+                }
+            }
+            _ => "no cross-component pair",
+        };
+        Ok(format!("{quoted} quotes, {cross_component}"))
+    }
+
+    /// Quotes `input` to `output` twice on a copy caching quotes, then trades and quotes again:
+    /// the second quote must be the first, and the last that of the state after the trade.
+    fn check_quote_cache(
+        &self,
+        input: usize,
+        output: usize,
+        config: &SelfTestConfig,
+    ) -> Result<String, String> {
+        let mut copy = self.scratch_copy();
+        copy.config.quote_cache_capacity = copy.config.quote_cache_capacity.max(1);
+        let (input_token, output_token) = (self.tokens[input], self.tokens[output]);
+        let amount = config.probe_size_ratio * copy.token_graph.active_reserve(input);
+        let cached_quote = |copy: &Router<'l>| {
+            copy.quote_cached(input_token, output_token, amount)
+                .map_err(|error| error.to_string())
+        };
+        let fresh_quote = |copy: &Router<'l>| {
+            copy.scratch_copy()
+                .solve_between(input, output, rounded_amount(amount))
+        };
+
+        let first = cached_quote(&copy)?;
+        let hit = cached_quote(&copy)?;
+        if hit.to_bits() != first.to_bits() || copy.quote_cache.len() != 1 {
+            return Err(format!(
+                "repeated quote {hit} instead of the cached {first}"
+            ));
+        }
+        let fresh = fresh_quote(&copy)?;
+        if fresh.to_bits() != first.to_bits() {
+            return Err(format!("cached quote {first} instead of {fresh}"));
+        }
+        copy.solve_between(input, output, amount)?;
+        let after_trade = cached_quote(&copy)?;
+        let fresh = fresh_quote(&copy)?;
+        match after_trade.to_bits() == fresh.to_bits() && after_trade != first {
+            true => Ok(format!("hit {first}, {after_trade} after a trade")),
+            false => Err(format!(
+                "quote {after_trade} after a trade instead of {fresh}, {first} before"
+            )),
+        }
+    }
+
+    /// Quotes `pairs` both ways from one thread, then from `config.concurrency_threads` threads
+    /// at once, each starting from its own trade so that distinct trades overlap.
+    fn check_concurrency(
+        &self,
+        pairs: &[(usize, usize)],
+        config: &SelfTestConfig,
+    ) -> Result<String, String> {
+        let trades = pairs
+            .iter()
+            .flat_map(|&(u, v)| [(u, v), (v, u)])
+            .map(|(input, output)| {
+                let amount = config.probe_size_ratio * self.token_graph.active_reserve(input);
+                (self.tokens[input], self.tokens[output], amount)
+            })
+            .collect::<Vec<_>>();
+        let quote = |n: usize| {
+            let (input_token, output_token, amount) = trades[n];
+            self.quote(input_token, output_token, amount)
+                .map(f64::to_bits)
+        };
+        let expected = (0..trades.len()).map(quote).collect::<Vec<_>>();
+        let mismatches = thread::scope(|scope| {
+            let threads = (0..config.concurrency_threads)
+                .map(|thread| {
+                    let (trades, quote, expected) = (&trades, &quote, &expected);
+                    scope.spawn(move || {
+                        (0..trades.len())
+                            .map(|n| (thread + n) % trades.len())
+                            .filter(|&n| quote(n) != expected[n])
+                            .count()
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().map_err(|_| "a quoting thread panicked"))
+                .sum::<Result<usize, _>>()
+        })?;
+        match mismatches {
+            0 => Ok(format!(
+                "{} quotes on {} threads, all identical",
+                trades.len() * config.concurrency_threads,
+                config.concurrency_threads
+            )),
+            _ => Err(format!(
+                "{mismatches} of {} quotes on {} threads differ from the single-threaded ones",
+                trades.len() * config.concurrency_threads,
+                config.concurrency_threads
+            )),
+        }
+    }
+
+    fn check_fill(
+        &self,
+        input: usize,
+        output: usize,
+        config: &SelfTestConfig,
+    ) -> Result<String, String> {
+        let mut copy = self.scratch_copy();
+        let amount = config.probe_size_ratio * copy.token_graph.active_reserve(input);
+        let (input_token, output_token) = (self.tokens[input], self.tokens[output]);
+        let (output_amount, plan) = copy
+            .solve_with_plan(input_token, output_token, amount)
+            .map_err(|error| error.to_string())?;
+
+        let mut filled = 0.0;
+        for swap in &plan {
+            let position = self
+                .pool_ids
+                .iter()
+                .position(|id| *id == swap.pool)
+                .ok_or_else(|| format!("the plan swaps against unknown pool {}", swap.pool))?;
+            let pool = &self.pools[position];
+            let received = pool
+                .output_amount_with_fee(swap.input_token, swap.input_amount, self.pool_fee(pool))
+                .map_err(|error| error.to_string())?;
+            // Tiny swaps are compared against the size of the pool rather than their own
+            let reserve_out = match swap.output_token == pool.token0 {
+                true => pool.reserve0,
+                false => pool.reserve1,
+            };
+            if (received - swap.output_amount).abs() > config.fill_tolerance * reserve_out {
+                return Err(format!(
+                    "pool {} returns {received} instead of {}",
+                    swap.pool, swap.output_amount
+                ));
+            }
+            if swap.output_token == output_token {
+                filled += received;
+            }
+            if swap.input_token == output_token {
+                filled -= swap.input_amount;
+            }
+        }
+        let difference = relative_difference(filled, output_amount);
+        match difference <= config.fill_tolerance {
+            true => Ok(format!(
+                "{} swaps, relative difference {difference:e}",
+                plan.len()
+            )),
+            false => Err(format!(
+                "the plan fills {filled} instead of {output_amount}"
+            )),
+        }
+    }
+}
+
+fn run_check(
+    name: &'static str,
+    enabled: bool,
+    check: impl FnOnce() -> Result<String, String>,
+) -> SelfTestCheck {
+    let start = Instant::now();
+    let outcome = match enabled {
+        false => CheckOutcome::Skipped("turned off".to_string()),
+        true => match check() {
+            Ok(detail) => CheckOutcome::Passed(detail),
+            Err(reason) => CheckOutcome::Failed(reason),
+        },
+    };
+    SelfTestCheck {
+        name,
+        outcome,
+        elapsed: start.elapsed(),
+    }
+}
+
+fn relative_difference(actual: f64, expected: f64) -> f64 {
+    (actual - expected).abs() / expected.abs().max(f64::MIN_POSITIVE)
+}
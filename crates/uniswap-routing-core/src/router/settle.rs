@@ -0,0 +1,90 @@
+use crate::router::{Router, capture::CapturedOperation};
+
+/// Outcome of [`Router::settle`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settlement<'l> {
+    /// One entry per connected component of at least two tokens, ordered by smallest token
+    pub components: Vec<ComponentSettlement<'l>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentSettlement<'l> {
+    /// Token in which the arbitrage of the component was extracted
+    pub sink_token: &'l str,
+    /// Arbitrage value extracted from the component, in `sink_token`
+    pub extracted_amount: f64,
+    /// Number of fixed-point sweeps the component needed
+    pub iterations: usize,
+}
+
+impl<'l> Router<'l> {
+    /// Brings every pool to the no-arbitrage equilibrium without trading, extracting the
+    /// arbitrage left in each connected component in its reference token (the reference token
+    /// of the router where it belongs to the component, its smallest token otherwise).
+    ///
+    /// Pools fresh from on-chain snapshots are usually slightly out of equilibrium; settling them
+    /// once makes the following quotes reflect only the traded amount. A component whose
+    /// iteration drives a price to zero or to a non-finite value, from a non-finite reserve for
+    /// instance, is left untouched and extracts nothing.
+    pub fn settle(&mut self) -> Settlement<'l> {
+        let settlement = self.settle_components();
+        self.publish_version();
+        #[cfg(feature = "paranoid")]
+        self.assert_invariants();
+        settlement
+    }
+
+    /// Settles like [`Router::settle`], as part of a larger operation publishing its own version.
+    pub(super) fn settle_components(&mut self) -> Settlement<'l> {
+        let outcomes = self.token_graph.settle();
+        self.write_back_pool_reserves(None, &mut Vec::new());
+        self.settle_pending = false;
+        if let Some(capture) = &mut self.capture {
+            capture.push(CapturedOperation::Settle);
+        }
+
+        let settlement = Settlement {
+            components: outcomes
+                .into_iter()
+                .map(|(sink, outcome)| ComponentSettlement {
+                    sink_token: self.tokens[sink],
+                    extracted_amount: outcome.output_amount,
+                    iterations: outcome.iterations,
+                })
+                .collect(),
+        };
+        if self.initial_arbitrage_value.is_none() {
+            self.initial_arbitrage_value = Some(settlement.reference_value(self.reference_token()));
+        }
+        settlement
+    }
+
+    /// Returns the arbitrage value the pools held before their first equilibration, by the
+    /// first [`Router::settle`] or the first trade, in the reference token: the gain of
+    /// rebalancing the reserves from their raw, mutually inconsistent spot prices to the
+    /// equilibrium, which the first trade would otherwise capture into its output.
+    ///
+    /// Only the component of the reference token is valued, the others having no price in it.
+    /// `None` before the first equilibration.
+    pub fn initial_arbitrage_value(&self) -> Option<f64> {
+        self.initial_arbitrage_value
+    }
+
+    /// Measures [`Router::initial_arbitrage_value`] on a scratch copy, ahead of the first trade.
+    pub(super) fn measure_initial_arbitrage(&mut self) {
+        if self.initial_arbitrage_value.is_none() {
+            let settlement = self.scratch_copy().settle_components();
+            self.initial_arbitrage_value = Some(settlement.reference_value(self.reference_token()));
+        }
+    }
+}
+
+impl Settlement<'_> {
+    /// Returns the amount extracted in `reference_token`, zero if no component sank there.
+    fn reference_value(&self, reference_token: &str) -> f64 {
+        self.components
+            .iter()
+            .find(|component| component.sink_token == reference_token)
+            .map_or(0.0, |component| component.extracted_amount)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::router::{AutoSettle, Router};
+
+impl Router<'_> {
+    /// Returns how far the pools are from the equilibrium of the current prices: the largest
+    /// `|ln|` ratio, over the pools of the routable pairs, between the spot price of a pool and
+    /// the price `(q_1 / q_0)²` the equilibrium implies for its pair.
+    ///
+    /// Trades and settlements leave it at zero up to the solver tolerance; pool events raise it
+    /// by the price moves they bring, roughly `0.05` for a pool moved by 5%. A stale router
+    /// hands the arbitrage opened by the events to the next trade, see [`AutoSettle`].
+    pub fn staleness(&self) -> f64 {
+        self.pools
+            .iter()
+            .filter_map(|pool| {
+                let index_0 = self.token_index[pool.token0];
+                let index_1 = self.token_index[pool.token1];
+                self.token_graph
+                    .is_pool_active(pool, index_0, index_1)
+                    .then(|| {
+                        let implied =
+                            self.token_graph.price(index_1) / self.token_graph.price(index_0);
+                        let (curve0, curve1) = pool.curve_reserves();
+                        ((curve1 / curve0).ln() - 2.0 * implied.ln()).abs()
+                    })
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Applies [`RouterConfig::auto_settle`](crate::router::RouterConfig::auto_settle) after an
+    /// event batch, returning the staleness it found and whether it settled.
+    pub(super) fn apply_auto_settle(&mut self) -> (f64, bool) {
+        let staleness = self.staleness();
+        let stale = staleness.is_nan() || staleness > self.config.staleness_threshold;
+        match self.config.auto_settle {
+            AutoSettle::Off => (staleness, false),
+            AutoSettle::Eager if stale => {
+                self.settle_components();
+                (staleness, true)
+            }
+            AutoSettle::Eager => (staleness, false),
+            AutoSettle::OnNextSolve => {
+                self.settle_pending |= stale;
+                (staleness, false)
+            }
+        }
+    }
+
+    /// Settles before a trade if an event batch left the router stale, so that the trade does
+    /// not capture the arbitrage the events opened.
+    pub(super) fn settle_if_pending(&mut self) {
+        if self.settle_pending {
+            self.settle_components();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    error::RouterError,
+    router::{Router, check_trade_amount, exact::ExactSwap},
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+use std::collections::HashMap;
+
+/// A single swap against one pool, as part of the execution plan of a solve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolSwap<'l> {
+    pub pool: PoolId,
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    /// Exact amount of `input_token` to send to the pool
+    pub input_amount: f64,
+    /// Amount of `output_token` the pool returns for `input_amount`, given its reserves
+    pub output_amount: f64,
+    /// The swap requoted through the exact integer math of the pool, see
+    /// [`UniV2Pool::get_output_amount_exact`](crate::UniV2Pool::get_output_amount_exact), if it
+    /// has raw reserves
+    pub exact: Option<ExactSwap>,
+}
+
+impl<'l> Router<'l> {
+    /// Like [`Router::solve`], but also returns the swaps realizing the trade on the individual
+    /// pools: executing them in the listed order against constant-product pools holding the
+    /// reserves of the router before the solve yields the returned output amount.
+    ///
+    /// Each pool whose reserves moved contributes one swap, from its pre-trade reserves to its
+    /// share `√k · q_u / q_v` of the new equilibrium, which preserves its invariant, or under
+    /// [`FeeModel::Charged`](crate::router::FeeModel::Charged) grows it by its fee. Swaps are
+    /// listed by decreasing relative change of the root price `q` of their input token: starting
+    /// from an equilibrium, the price changes of a trade are monotonic along its flows, so every
+    /// intermediate token is received before being sent onward. Swaps of pools left out of
+    /// equilibrium beforehand may capture the arbitrage of cycles, and need the pre-funding of
+    /// some intermediate token until the cycle closes.
+    ///
+    /// With [`RouterConfig::plan_depth_warning`](crate::router::RouterConfig::plan_depth_warning),
+    /// a warning is logged for every swap giving a pool, at its reserves before the solve, more
+    /// input than its depth at that price impact, see [`UniV2Pool::max_input_for_impact`].
+    ///
+    /// Swaps of pools with raw reserves are also requoted through their exact math, which rounds
+    /// as the pair contracts do and charges their fee even under
+    /// [`FeeModel::FeeFree`](crate::router::FeeModel::FeeFree); their raw reserves move by the
+    /// exact amounts.
+    ///
+    /// Fails in the same cases as [`Router::solve`], a zero input amount returning an empty plan.
+    pub fn solve_with_plan(
+        &mut self,
+        input_token: &str,
+        output_token: &str,
+        input_amount: f64,
+    ) -> Result<(f64, Vec<PoolSwap<'l>>), RouterError> {
+        let input_index = self.routable_index(input_token)?;
+        let output_index = self.routable_index(output_token)?;
+        if !check_trade_amount(input_amount)? {
+            return Ok((0.0, Vec::new()));
+        }
+        self.settle_if_pending();
+
+        let previous_prices = (0..self.tokens.len())
+            .map(|token| self.token_graph.price(token))
+            .collect::<Vec<_>>();
+
+        let outcome = self.solve_trade(input_index, output_index, input_amount)?;
+        let trade = self.commit_trade(input_index, output_index, input_amount, outcome);
+
+        let price_change =
+            |token: usize| (self.token_graph.price(token) / previous_prices[token]).ln();
+        let traded_pools = self.last_trade.iter().flat_map(|trade| &trade.pools);
+        let positions = self.config.plan_depth_warning.map(|_| {
+            self.pool_ids
+                .iter()
+                .enumerate()
+                .map(|(position, id)| (id, position))
+                .collect::<HashMap<_, _>>()
+        });
+        let mut swaps = Vec::new();
+        for pool in traded_pools {
+            let ((reserve0, reserve1), (new_reserve0, new_reserve1)) = (pool.before, pool.after);
+            let (input_token, output_token, input_amount, output_amount) =
+                match new_reserve0 > reserve0 {
+                    true => (
+                        pool.token0,
+                        pool.token1,
+                        new_reserve0 - reserve0,
+                        reserve1 - new_reserve1,
+                    ),
+                    false => (
+                        pool.token1,
+                        pool.token0,
+                        new_reserve1 - reserve1,
+                        reserve0 - new_reserve0,
+                    ),
+                };
+            if input_amount <= 0.0 {
+                continue;
+            }
+            if let (Some(max_impact), Some(positions)) =
+                (self.config.plan_depth_warning, &positions)
+            {
+                let before = UniV2Pool {
+                    reserve0,
+                    reserve1,
+                    ..self.pools[positions[&pool.id]].clone()
+                };
+                let depth = before.max_input_for_impact(input_token, max_impact);
+                if input_amount > depth {
+                    log::warn!(
+                        "the plan sends {input_amount} {input_token} to pool {}, beyond its depth \
+                         of {depth} at a price impact of {max_impact}",
+                        pool.id
+                    );
+                }
+            }
+            swaps.push((
+                price_change(self.token_index[input_token]),
+                PoolSwap {
+                    pool: pool.id.clone(),
+                    input_token,
+                    output_token,
+                    input_amount,
+                    output_amount,
+                    exact: pool.exact,
+                },
+            ));
+        }
+        swaps.sort_by(|(change, _), (other_change, _)| other_change.total_cmp(change));
+
+        Ok((
+            trade.output_amount,
+            swaps.into_iter().map(|(_, swap)| swap).collect(),
+        ))
+    }
+}
@@ -0,0 +1,2579 @@
+use crate::{
+    router::{
+        FallbackStrategy, LiquidityStorage, PriceNormalization, SolverAcceleration, SolverConfig,
+        SolverDomain, SolverKind, SweepMode,
+        anderson::Anderson,
+        linear_system::solve_linear_system,
+        liquidities::LazyLiquidities,
+        parallel::{extend_ordered, map_ordered},
+        scratch::{SolveScratch, SweepScratch},
+    },
+    uni_v2_pool::UniV2Pool,
+};
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    time::Instant,
+};
+
+/// Largest number of steps of Newton's method, see [`TokenGraph::newton`].
+const MAX_NEWTON_STEPS: usize = 100;
+
+/// Smallest fraction of a Newton step tried before giving the method up.
+const MIN_NEWTON_FRACTION: f64 = 1e-10;
+
+/// Number of sweeps between two readings of the clock against the deadline of the iteration,
+/// see [`TokenGraph::set_deadline`].
+const DEADLINE_CHECK_INTERVAL: usize = 64;
+
+/// Number of sweeps without improvement of the largest price change after which the
+/// fixed-point iteration halves its damping factor, see [`TokenGraph::fixed_point`].
+const STALL_WINDOW: usize = 20;
+
+/// Relative price change below which a stalled iteration is left undamped: changes of a few
+/// ulps are the rounding of the updates, which damping would keep from ever settling.
+const STALL_FLOOR: f64 = 1024.0 * f64::EPSILON;
+
+/// Number of sweeps over which the rate of convergence of the output amount is averaged.
+const RATE_WINDOW: usize = 8;
+
+/// Multiple of the sweep budget of the solver that the bisection fallback may use, its sweeps
+/// converging no faster than those of the solver it takes over from.
+const BISECTION_SWEEP_FACTOR: usize = 100;
+
+/// Spread of the ratios of the updates to the prices below which the sign of `λ − 1` is lost in
+/// rounding, see [`TokenGraph::spectral_sign`].
+const SPECTRAL_FLOOR: f64 = 4.0 * f64::EPSILON;
+
+/// Result of a single equilibrium computation.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SolveOutcome {
+    /// Amount of the output token extracted by the equilibrium
+    pub(super) output_amount: f64,
+    /// Number of fixed-point sweeps performed
+    pub(super) iterations: usize,
+    /// Largest relative price change of the last sweep
+    pub(super) residual: f64,
+    /// Whether the residual fell below the tolerance within the maximum number of sweeps
+    pub(super) converged: bool,
+    /// Token whose update produced a square-root price that is not positive and finite, with
+    /// that price, which stopped the iteration before the equilibrium was applied
+    pub(super) invalid_price: Option<(usize, f64)>,
+    /// Number of times the iteration stalled and halved its damping factor
+    pub(super) damping_events: usize,
+    /// Damping factor of the last sweep, 1.0 for undamped sweeps
+    pub(super) final_damping: f64,
+    /// Token whose update divided by, or whose extraction produced, a value that is not finite,
+    /// with that value, which stopped the solve before the equilibrium was applied; the
+    /// denominators are checked with strict math only, see [`TokenGraph::set_strict_math`]
+    pub(super) numerical_error: Option<(usize, f64)>,
+    /// Whether the iteration stopped unconverged at its deadline, see [`TokenGraph::set_deadline`]
+    pub(super) deadline_reached: bool,
+    /// Whether the output was bisected after the solver exhausted its budget, see
+    /// [`TokenGraph::bisect_output`]
+    pub(super) bisected: bool,
+    /// Whether the prices were left unnormalized, their normalization factor being zero or not
+    /// finite, see [`TokenGraph::normalize_prices`]
+    pub(super) normalization_skipped: bool,
+    /// Number of single-token price updates computed, see
+    /// [`SolveStats::token_updates`](crate::router::SolveStats::token_updates)
+    pub(super) token_updates: usize,
+}
+
+/// Totals of a graph before a trade, to undo it along with the prices saved in its scratch, see
+/// [`TokenGraph::checkpoint`].
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TradeCheckpoint {
+    totals: [(usize, f64); 2],
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct TokenGraph {
+    nodes: Vec<TokenNode>,
+    /// Aggregated geometric liquidity `K(u, v)` of every pair, see [`TokenNode`]
+    liquidities: LazyLiquidities,
+    /// Layout requested for the liquidities, kept for the graphs derived from this one
+    storage: LiquidityStorage,
+    /// Tokens excluded from routing, whose edges count as zero liquidity in the equilibrium
+    blacklisted: Vec<bool>,
+    /// Restriction of the equilibrium to the edges near the traded pair, for the current trade
+    hop_limit: Option<HopLimit>,
+    /// Time at which the iteration of the current trade stops, converged or not
+    deadline: Option<Instant>,
+    /// Extracted amounts within this fraction of the output token total are floating-point
+    /// noise, reported and applied as zero
+    dust_tolerance: f64,
+    /// Numéraire of the reported prices, whose price `q` is kept at 1.0 by the reference token
+    /// normalization
+    reference_token: usize,
+    /// Convergence criterion and budget of the fixed-point iteration
+    solver: SolverConfig,
+    /// Representation of the prices during the fixed-point iteration
+    domain: SolverDomain,
+    /// Relaxation factor `ω` of the fixed-point update
+    relaxation: f64,
+    /// Acceleration of the fixed-point iteration
+    acceleration: SolverAcceleration,
+    /// Order of the updates within a sweep
+    sweep_mode: SweepMode,
+    /// Number of tokens from which the denominators of a Jacobi sweep are computed in parallel
+    parallel_sweep_threshold: usize,
+    /// Method solving the equilibrium
+    kind: SolverKind,
+    /// What a trade does when the solver exhausts its budget unconverged
+    fallback: FallbackStrategy,
+    /// Whether the denominators of the updates and the extracted amounts are checked finite
+    strict_math: bool,
+    /// How the prices are rescaled after every solve
+    normalization: PriceNormalization,
+    /// Connected component of every token through the active edges, numbered as in
+    /// [`TokenGraph::connected_components`], kept up to date with the edges
+    component_of: Vec<usize>,
+    /// Power of two `sₜ` of every token, see [`TokenGraph::from_pools`]
+    scales: Vec<f64>,
+    /// Virtual reserves of every token across its pools, unscaled, part of its total reserve
+    /// without being held, see [`UniV2Pool::virtual_reserves`]
+    virtual_reserves: Vec<f64>,
+    /// Buffers of the solves, reused from one trade to the next
+    scratch: SolveScratch,
+}
+
+/// Represents a node in the token–liquidity graph used by the router.
+///
+/// Each `TokenNode` corresponds to a single token in the system and carries:
+/// - its **total reserve** across all pools in which it appears.
+/// - and its **square-root price variable** `q` used in the equilibrium solver,
+///
+/// its **adjacent tokens**, with their aggregated *geometric liquidities*, being stored apart
+/// in the [`LazyLiquidities`] of the graph.
+///
+/// ### Pool aggregation
+///
+/// All Uniswap-V2 pools connecting the same token pair `(u,v)` are collapsed
+/// into a single effective edge in the graph, whose liquidity is the sum of the
+/// square roots of their individual invariants:
+///
+/// `K(u, v) = Σ √kᵢ`, over all pools `i` linking `u` and `v`,
+///
+/// The resulting structure is a **aggregated, undirected, and weighted graph of
+/// tokens**, where each edge encodes the combined liquidity between two tokens.
+///
+/// The aggregation is exact, even for pools of a pair trading at different prices: at the
+/// equilibrium every pool `i` holds `√kᵢ · q_u / q_v` of `u`, so the conservation of `u` over
+/// the individual pools, `Σᵢ √kᵢ · q_u / q_v = T_u`, is the aggregated one. The price gaps
+/// between the pools are arbitrage the equilibrium closes, extracted in the output token along
+/// with the trade.
+///
+/// Amounts, prices and liquidities are stored scaled by the token scales, see
+/// [`TokenGraph::from_pools`].
+#[derive(Debug, Clone)]
+pub(super) struct TokenNode {
+    /// Total amount of the token across all pools
+    total_reserve: f64,
+    /// Part of `total_reserve` held in pools out of the equilibrium, because they touch a
+    /// blacklisted token, lie beyond the hop limit or are empty
+    frozen_reserve: f64,
+    /// Square-root price of the token, used for equilibrium computation
+    q: f64,
+}
+
+/// Edges lying on some path of at most `max_hops` edges between the input and the output tokens.
+#[derive(Debug, Clone)]
+pub(super) struct HopLimit {
+    /// Number of edges from the input token to every token
+    from_input: Vec<usize>,
+    /// Number of edges from every token to the output token
+    to_output: Vec<usize>,
+    pub(super) max_hops: usize,
+}
+
+impl HopLimit {
+    pub(super) fn new(
+        graph: &TokenGraph,
+        input_token: usize,
+        output_token: usize,
+        max_hops: usize,
+    ) -> Self {
+        Self {
+            from_input: graph.hop_distances(input_token),
+            to_output: graph.hop_distances(output_token),
+            max_hops,
+        }
+    }
+
+    fn allows(&self, u: usize, v: usize) -> bool {
+        let hops = |a: usize, b: usize| {
+            self.from_input[a]
+                .saturating_add(1)
+                .saturating_add(self.to_output[b])
+        };
+        hops(u, v).min(hops(v, u)) <= self.max_hops
+    }
+}
+
+impl TokenGraph {
+    /// Initializes the aggregated token graph from pools:
+    /// - accumulates Tₜ (totals per token),
+    /// - sums K(u, v) = Σ √kᵢ.
+    /// - sets initial prices `q` to 1.0 for all tokens (those prices will be updated during first
+    ///   equilibrium computation).
+    ///
+    /// Reserves quoted in raw units reach `1e27` and beyond, where `√(r₀ · r₁)` and the ratios of
+    /// the solver approach the limits of `f64`. Every token `t` is hence given a scale `sₜ`, the
+    /// power of two closest to `√Tₜ`, and the graph stores its amounts divided by `sₜ²`, its
+    /// square-root price divided by `sₜ`, and the liquidity of each pair `(u, v)` divided by
+    /// `sᵤ · sᵥ`: the solver then runs on numbers of order one, and the scaled equations are
+    /// those of the original ones. The scales being powers of two, the scaling is exact and the
+    /// results are bitwise those of an unscaled solve wherever the latter does not overflow.
+    /// Accessors take and return unscaled values.
+    ///
+    /// The liquidities are laid out as `storage` picks for the number of tokens, see
+    /// [`LiquidityStorage`].
+    pub(super) fn from_pools(
+        pools: &[UniV2Pool],
+        token_index: &HashMap<&str, usize>,
+        storage: LiquidityStorage,
+    ) -> Self {
+        // Summed in an order that does not depend on the order of the pools, nor on the numbering
+        // of the tokens that follows from it
+        let pools = aggregation_order(pools);
+        let mut totals = vec![0.0; token_index.len()];
+        let mut virtual_reserves = vec![0.0; token_index.len()];
+        for pool in &pools {
+            let (curve0, curve1) = pool.curve_reserves();
+            totals[token_index[pool.token0]] += curve0;
+            totals[token_index[pool.token1]] += curve1;
+            virtual_reserves[token_index[pool.token0]] += pool.virtual_reserves.0;
+            virtual_reserves[token_index[pool.token1]] += pool.virtual_reserves.1;
+        }
+        let scales = totals
+            .iter()
+            .map(|&total| token_scale(total))
+            .collect::<Vec<_>>();
+        let mut nodes = scales
+            .iter()
+            .map(|&scale| TokenNode {
+                total_reserve: 0.0,
+                frozen_reserve: 0.0,
+                q: 1.0 / scale,
+            })
+            .collect::<Vec<_>>();
+
+        let mut pairs = Vec::with_capacity(pools.len());
+        for pool in &pools {
+            let index_0 = token_index[pool.token0];
+            let index_1 = token_index[pool.token1];
+            let (curve0, curve1) = pool.curve_reserves();
+            let reserve0 = curve0 / (scales[index_0] * scales[index_0]);
+            let reserve1 = curve1 / (scales[index_1] * scales[index_1]);
+            nodes[index_0].total_reserve += reserve0;
+            nodes[index_1].total_reserve += reserve1;
+            let liquidity = (reserve0 * reserve1).sqrt();
+            // Empty pools add no edge, so that a token held only in them stays isolated
+            if liquidity.is_nan() || liquidity == 0.0 {
+                continue;
+            }
+            pairs.push((index_0, index_1, liquidity));
+        }
+        let liquidities = LazyLiquidities::new(nodes.len(), storage, pairs);
+
+        let mut graph = Self {
+            liquidities,
+            storage,
+            blacklisted: vec![false; nodes.len()],
+            hop_limit: None,
+            deadline: None,
+            dust_tolerance: 0.0,
+            nodes,
+            reference_token: 0,
+            solver: SolverConfig::default(),
+            domain: SolverDomain::default(),
+            relaxation: 1.0,
+            acceleration: SolverAcceleration::default(),
+            sweep_mode: SweepMode::default(),
+            parallel_sweep_threshold: usize::MAX,
+            kind: SolverKind::default(),
+            fallback: FallbackStrategy::default(),
+            strict_math: false,
+            normalization: PriceNormalization::default(),
+            component_of: Vec::new(),
+            scales,
+            virtual_reserves,
+            scratch: SolveScratch::default(),
+        };
+        graph.update_components();
+        graph
+    }
+
+    /// Computes the maximum amount of `output_token` obtainable by swapping
+    /// `input_amount` of `input_token`, and updates the router’s internal state.
+    ///
+    /// Conceptually, this adds `input_amount` to the total reserves of `input_token`,
+    /// then resolves the new no-arbitrage equilibrium of the connected component of the
+    /// trade, which both tokens must belong to. The difference in `output_token` reserves
+    /// before and after equilibrium represents the extractable output amount.
+    pub(super) fn apply_trade_and_solve(
+        &mut self,
+        input_token: usize,
+        output_token: usize,
+        input_amount: f64,
+    ) -> SolveOutcome {
+        self.liquidities.lay_out();
+        self.nodes[input_token].total_reserve += input_amount / self.reserve_scale(input_token);
+        self.no_arbitrage_equilibrium(output_token)
+    }
+
+    /// Commits a trade whose equilibrium prices were computed elsewhere: adds `input_amount` to
+    /// the input token, adopts `prices` as the new `q` vector, and extracts the output token down
+    /// to the total implied by those prices. Returns the extracted amount.
+    pub(super) fn apply_trade_with_prices(
+        &mut self,
+        input_token: usize,
+        output_token: usize,
+        input_amount: f64,
+        prices: &[f64],
+    ) -> f64 {
+        self.nodes[input_token].total_reserve += input_amount / self.reserve_scale(input_token);
+        for ((node, &q), &scale) in self.nodes.iter_mut().zip(prices).zip(&self.scales) {
+            node.q = q / scale;
+        }
+        self.normalize_prices();
+        self.extract_output(output_token)
+    }
+
+    /// Excludes the flagged tokens from the equilibrium, freezing the reserves of every pool that
+    /// touches one of them. `pools` must be the pools the graph was built from.
+    pub(super) fn set_blacklist(
+        &mut self,
+        blacklisted: Vec<bool>,
+        pools: &[UniV2Pool],
+        token_index: &HashMap<&str, usize>,
+    ) {
+        self.blacklisted = blacklisted;
+        self.freeze_inactive_pools(pools, token_index);
+    }
+
+    /// Restricts the equilibrium to the edges allowed by `hop_limit`, or lifts the restriction,
+    /// freezing the reserves of the excluded pools. `pools` must be the pools the graph was built
+    /// from.
+    pub(super) fn set_hop_limit(
+        &mut self,
+        hop_limit: Option<HopLimit>,
+        pools: &[UniV2Pool],
+        token_index: &HashMap<&str, usize>,
+    ) {
+        self.hop_limit = hop_limit;
+        self.freeze_inactive_pools(pools, token_index);
+    }
+
+    /// Sets the fraction of the output token total below which extracted amounts are dust.
+    pub(super) fn set_dust_tolerance(&mut self, dust_tolerance: f64) {
+        self.dust_tolerance = dust_tolerance;
+    }
+
+    /// Returns the fraction of the output token total below which extracted amounts are dust.
+    pub(super) fn dust_tolerance(&self) -> f64 {
+        self.dust_tolerance
+    }
+
+    /// Sets the convergence criterion and budget of the next solves.
+    pub(super) fn set_solver(&mut self, solver: SolverConfig) {
+        self.solver = solver;
+    }
+
+    /// Sets the representation of the prices of the next solves.
+    pub(super) fn set_domain(&mut self, domain: SolverDomain) {
+        self.domain = domain;
+    }
+
+    /// Sets the time at which the iterations of the next solves stop, converged or not, checked
+    /// every `DEADLINE_CHECK_INTERVAL` sweeps and after every Newton step.
+    pub(super) fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Returns the time at which the iterations stop, see [`TokenGraph::set_deadline`].
+    pub(super) fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Sets the relaxation factor of the next solves.
+    pub(super) fn set_relaxation(&mut self, relaxation: f64) {
+        self.relaxation = relaxation;
+    }
+
+    /// Sets the acceleration of the next solves.
+    pub(super) fn set_acceleration(&mut self, acceleration: SolverAcceleration) {
+        self.acceleration = acceleration;
+    }
+
+    /// Sets the order of the updates of the next solves.
+    pub(super) fn set_sweep_mode(&mut self, sweep_mode: SweepMode) {
+        self.sweep_mode = sweep_mode;
+    }
+
+    /// Sets the number of tokens from which the denominators of the Jacobi sweeps are computed
+    /// in parallel, with the `parallel` feature.
+    pub(super) fn set_parallel_sweep_threshold(&mut self, threshold: usize) {
+        self.parallel_sweep_threshold = threshold;
+    }
+
+    /// Sets the method solving the equilibrium of the next solves.
+    pub(super) fn set_kind(&mut self, kind: SolverKind) {
+        self.kind = kind;
+    }
+
+    /// Sets what the next trades do when the solver exhausts its budget unconverged.
+    pub(super) fn set_fallback(&mut self, fallback: FallbackStrategy) {
+        self.fallback = fallback;
+    }
+
+    /// Sets whether the next solves check the denominators of the updates finite, reporting them
+    /// as [`SolveOutcome::numerical_error`]; the extracted amounts are checked whatever this flag.
+    pub(super) fn set_strict_math(&mut self, strict_math: bool) {
+        self.strict_math = strict_math;
+    }
+
+    /// Sets how the next solves rescale the prices.
+    pub(super) fn set_normalization(&mut self, normalization: PriceNormalization) {
+        self.normalization = normalization;
+    }
+
+    /// Returns the current hop limit, if any.
+    pub(super) fn hop_limit(&self) -> Option<&HopLimit> {
+        self.hop_limit.as_ref()
+    }
+
+    fn freeze_inactive_pools(&mut self, pools: &[UniV2Pool], token_index: &HashMap<&str, usize>) {
+        for node in &mut self.nodes {
+            node.frozen_reserve = 0.0;
+        }
+        for pool in aggregation_order(pools) {
+            let index_0 = token_index[pool.token0];
+            let index_1 = token_index[pool.token1];
+            if !self.is_pool_active(pool, index_0, index_1) {
+                let (curve0, curve1) = pool.curve_reserves();
+                self.nodes[index_0].frozen_reserve += curve0 / self.reserve_scale(index_0);
+                self.nodes[index_1].frozen_reserve += curve1 / self.reserve_scale(index_1);
+            }
+        }
+        self.update_components();
+    }
+
+    /// Recomputes the connected component of every token after a change of the active edges.
+    fn update_components(&mut self) {
+        let mut component_of = vec![0; self.nodes.len()];
+        for (position, component) in self.connected_components().into_iter().enumerate() {
+            for token in component {
+                component_of[token] = position;
+            }
+        }
+        self.component_of = component_of;
+    }
+
+    /// Returns whether `u` and `v` are linked by a path of active edges.
+    pub(super) fn are_connected(&self, u: usize, v: usize) -> bool {
+        self.component_of[u] == self.component_of[v]
+    }
+
+    /// Returns the tokens of the connected component of `token`, by token index.
+    pub(super) fn component_tokens(&self, token: usize) -> Vec<usize> {
+        let component = self.component_of[token];
+        (0..self.nodes.len())
+            .filter(|&other| self.component_of[other] == component)
+            .collect()
+    }
+
+    /// Returns whether `pool`, linking the tokens `index_0` and `index_1`, takes part in the
+    /// equilibrium: its edge is active and it holds liquidity. The reserves of an empty pool,
+    /// one of them being zero, are frozen like those of an inactive edge.
+    pub(super) fn is_pool_active(&self, pool: &UniV2Pool, index_0: usize, index_1: usize) -> bool {
+        self.is_edge_active(index_0, index_1) && pool.reserve0 > 0.0 && pool.reserve1 > 0.0
+    }
+
+    /// Returns the curve reserves of `pool`, linking the tokens `index_0` and `index_1`, once
+    /// brought to the current prices along its constant-product curve: `√k · q₀ / q₁` and
+    /// `√k · q₁ / q₀`, computed on the scaled reserves so that `k` cannot overflow. Its reserves
+    /// are these less its virtual ones, see [`UniV2Pool::virtual_reserves`].
+    pub(super) fn equilibrium_reserves(
+        &self,
+        pool: &UniV2Pool,
+        index_0: usize,
+        index_1: usize,
+    ) -> (f64, f64) {
+        let scale_0 = self.reserve_scale(index_0);
+        let scale_1 = self.reserve_scale(index_1);
+        let (curve0, curve1) = pool.curve_reserves();
+        let sqrt_k = (curve0 / scale_0 * (curve1 / scale_1)).sqrt();
+        let q0 = self.nodes[index_0].q;
+        let q1 = self.nodes[index_1].q;
+        (sqrt_k * q0 / q1 * scale_0, sqrt_k * q1 / q0 * scale_1)
+    }
+
+    /// Returns whether `token` has no pool holding liquidity with a routable token, ignoring
+    /// the hop limit: it cannot take part in any trade.
+    pub(super) fn is_isolated(&self, token: usize) -> bool {
+        self.blacklisted[token]
+            || self
+                .liquidities
+                .neighbors(token)
+                .all(|(paired_token, _)| self.blacklisted[paired_token])
+    }
+
+    /// Returns whether `token` is excluded from routing.
+    pub(super) fn is_blacklisted(&self, token: usize) -> bool {
+        self.blacklisted[token]
+    }
+
+    /// Returns whether the pools between `u` and `v` take part in the equilibrium.
+    pub(super) fn is_edge_active(&self, u: usize, v: usize) -> bool {
+        !self.blacklisted[u]
+            && !self.blacklisted[v]
+            && self
+                .hop_limit
+                .as_ref()
+                .is_none_or(|limit| limit.allows(u, v))
+    }
+
+    /// Returns the number of edges between `from` and every token, `usize::MAX` for the
+    /// unreachable ones, through the active edges only.
+    pub(super) fn hop_distances(&self, from: usize) -> Vec<usize> {
+        let mut distances = vec![usize::MAX; self.nodes.len()];
+        distances[from] = 0;
+        let mut queue = VecDeque::from([from]);
+        while let Some(token) = queue.pop_front() {
+            for (paired_token, _) in self.scaled_neighbors(token) {
+                if distances[paired_token] == usize::MAX {
+                    distances[paired_token] = distances[token] + 1;
+                    queue.push_back(paired_token);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Takes over the prices, the reference token, the dust tolerance, the solver configuration,
+    /// the solver domain, the relaxation factor, the acceleration, the sweep mode and its
+    /// parallel threshold, the solver kind, the fallback, the strict math flag and the normalization of `previous`, a graph over a prefix of the same tokens, as warm start after the pool set changed. The blacklist is not inherited.
+    pub(super) fn inherit_prices(&mut self, previous: &TokenGraph) {
+        for (token, previous_node) in previous.nodes.iter().enumerate().take(self.nodes.len()) {
+            self.nodes[token].q = previous_node.q * previous.scales[token] / self.scales[token];
+        }
+        self.reference_token = previous.reference_token;
+        self.dust_tolerance = previous.dust_tolerance;
+        self.solver = previous.solver;
+        self.domain = previous.domain;
+        self.relaxation = previous.relaxation;
+        self.acceleration = previous.acceleration;
+        self.sweep_mode = previous.sweep_mode;
+        self.parallel_sweep_threshold = previous.parallel_sweep_threshold;
+        self.kind = previous.kind;
+        self.fallback = previous.fallback;
+        self.strict_math = previous.strict_math;
+        self.normalization = previous.normalization;
+    }
+
+    /// Renumbers the tokens, `new_indices[old]` being the new index of each token or `None` for
+    /// the dropped ones, which must be isolated. A dropped reference token is replaced by the
+    /// first token.
+    pub(super) fn compact(&mut self, new_indices: &[Option<usize>]) {
+        let mut nodes = Vec::new();
+        let mut pairs = Vec::new();
+        let mut blacklisted = Vec::new();
+        let mut scales = Vec::new();
+        for (token, (node, &new_index)) in self.nodes.iter().zip(new_indices).enumerate() {
+            let Some(new_index) = new_index else {
+                continue;
+            };
+            for (paired_token, liquidity) in self.liquidities.neighbors(token) {
+                if let Some(paired_token) = new_indices[paired_token]
+                    && new_index < paired_token
+                {
+                    pairs.push((new_index, paired_token, liquidity));
+                }
+            }
+            nodes.push(node.clone());
+        }
+        let liquidities = LazyLiquidities::new(nodes.len(), self.storage, pairs);
+        for ((&flag, &scale), &new_index) in
+            self.blacklisted.iter().zip(&self.scales).zip(new_indices)
+        {
+            if new_index.is_some() {
+                blacklisted.push(flag);
+                scales.push(scale);
+            }
+        }
+        self.reference_token = new_indices[self.reference_token].unwrap_or(0);
+        self.nodes = nodes;
+        self.liquidities = liquidities;
+        self.blacklisted = blacklisted;
+        self.scales = scales;
+        self.update_components();
+    }
+
+    /// Feeds the totals, prices, liquidities, scales, blacklist and reference token to `state`,
+    /// floats bit for bit and edges by token index.
+    pub(super) fn hash_content(&self, state: &mut impl Hasher) {
+        for (token, (node, scale)) in self.nodes.iter().zip(&self.scales).enumerate() {
+            scale.to_bits().hash(state);
+            node.total_reserve.to_bits().hash(state);
+            node.frozen_reserve.to_bits().hash(state);
+            node.q.to_bits().hash(state);
+            self.liquidities.neighbors(token).count().hash(state);
+            for (paired_token, liquidity) in self.liquidities.neighbors(token) {
+                (paired_token, liquidity.to_bits()).hash(state);
+            }
+        }
+        self.blacklisted.hash(state);
+        self.reference_token.hash(state);
+        self.dust_tolerance.to_bits().hash(state);
+    }
+
+    /// Returns the current square-root price variable `q` of `token`.
+    pub(super) fn price(&self, token: usize) -> f64 {
+        self.nodes[token].q * self.scales[token]
+    }
+
+    /// Overrides the square-root price of `token`, as a warm start for the next solve.
+    pub(super) fn set_price(&mut self, token: usize, q: f64) {
+        self.nodes[token].q = q / self.scales[token];
+    }
+
+    /// Overrides the total reserve of `token`, as accounted by another router, see
+    /// [`ReplicaRouter`](crate::router::ReplicaRouter).
+    pub(super) fn set_total_reserve(&mut self, token: usize, total_reserve: f64) {
+        self.nodes[token].total_reserve = total_reserve / self.reserve_scale(token);
+    }
+
+    /// Replaces the liquidities with `edges`, unscaled `(u, v, K(u, v))` as returned by
+    /// [`TokenGraph::sorted_edges`], in the layout of the graph.
+    pub(super) fn set_liquidities(&mut self, edges: &[(usize, usize, f64)]) {
+        self.liquidities = LazyLiquidities::new(
+            self.nodes.len(),
+            self.storage,
+            edges
+                .iter()
+                .map(|&(u, v, liquidity)| (u, v, liquidity / (self.scales[u] * self.scales[v]))),
+        );
+        self.update_components();
+    }
+
+    /// Returns the sum of the geometric liquidities of the active edges of `token`.
+    pub(super) fn total_liquidity(&self, token: usize) -> f64 {
+        self.neighbors(token).map(|(_, liquidity)| liquidity).sum()
+    }
+
+    /// Returns the total reserve of `token` across all pools, their virtual reserves included.
+    pub(super) fn total_reserve(&self, token: usize) -> f64 {
+        self.nodes[token].total_reserve * self.reserve_scale(token)
+    }
+
+    /// Returns the reserve of `token` its pools hold, its total reserve less their virtual
+    /// reserves, see [`UniV2Pool::virtual_reserves`].
+    pub(super) fn held_reserve(&self, token: usize) -> f64 {
+        self.total_reserve(token) - self.virtual_reserves[token]
+    }
+
+    /// Returns the token used as numéraire by the price normalization.
+    pub(super) fn reference_token(&self) -> usize {
+        self.reference_token
+    }
+
+    /// Selects the numéraire of the price normalization, effective from the next solve.
+    pub(super) fn set_reference_token(&mut self, token: usize) {
+        self.reference_token = token;
+    }
+
+    /// Returns the part of the total reserve of `token` taking part in the equilibrium, that is
+    /// outside of the pools touching a blacklisted token.
+    pub(super) fn active_reserve(&self, token: usize) -> f64 {
+        (self.nodes[token].total_reserve - self.nodes[token].frozen_reserve)
+            * self.reserve_scale(token)
+    }
+
+    /// Returns the factor `sₜ²` by which the amounts of `token` are scaled down in the graph.
+    fn reserve_scale(&self, token: usize) -> f64 {
+        self.scales[token] * self.scales[token]
+    }
+
+    /// Returns the number of tokens in the graph.
+    pub(super) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the neighbors of `token` with their geometric liquidities, sorted by token index.
+    pub(super) fn sorted_neighbors(&self, token: usize) -> Vec<(usize, f64)> {
+        let mut neighbors = self.neighbors(token).collect::<Vec<_>>();
+        neighbors.sort_by_key(|&(paired_token, _)| paired_token);
+        neighbors
+    }
+
+    /// Partitions the tokens into connected components, each sorted by token index, the
+    /// components themselves being ordered by their smallest token.
+    pub(super) fn connected_components(&self) -> Vec<Vec<usize>> {
+        // Union–find over the active pairs, which does not lay the liquidities out, rooting every
+        // set at its smallest token
+        fn root(parents: &mut [usize], mut token: usize) -> usize {
+            while parents[token] != token {
+                parents[token] = parents[parents[token]];
+                token = parents[token];
+            }
+            token
+        }
+        let mut parents = (0..self.nodes.len()).collect::<Vec<_>>();
+        for (u, v) in self.liquidities.linked_pairs() {
+            if self.is_edge_active(u, v) {
+                let (root_u, root_v) = (root(&mut parents, u), root(&mut parents, v));
+                parents[root_u.max(root_v)] = root_u.min(root_v);
+            }
+        }
+        let mut position_of = vec![usize::MAX; self.nodes.len()];
+        let mut components = Vec::<Vec<usize>>::new();
+        for token in 0..self.nodes.len() {
+            let root = root(&mut parents, token);
+            if position_of[root] == usize::MAX {
+                position_of[root] = components.len();
+                components.push(Vec::new());
+            }
+            components[position_of[root]].push(token);
+        }
+        components
+    }
+
+    /// Returns every edge `(u, v, K(u, v))` once, with `u < v`, sorted by `(u, v)`.
+    pub(super) fn sorted_edges(&self) -> Vec<(usize, usize, f64)> {
+        let mut edges = self.iter_nonzero().collect::<Vec<_>>();
+        edges.sort_by_key(|&(u, v, _)| (u, v));
+        edges
+    }
+
+    /// Iterates over every pair with non-zero liquidity `(u, v, K(u, v))` once, with `u < v`.
+    pub(super) fn iter_nonzero(&self) -> impl Iterator<Item = (usize, usize, f64)> {
+        (0..self.nodes.len()).flat_map(|token| {
+            self.neighbors(token)
+                .filter(move |&(paired_token, _)| token < paired_token)
+                .map(move |(paired_token, liquidity)| (token, paired_token, liquidity))
+        })
+    }
+}
+
+impl TokenGraph {
+    /// Fixed-point no-arbitrage solver (Gauss–Seidel), computing the maximum amount of
+    /// `output_token` `f` that can be extracted in the process.
+    ///
+    /// The system enforces the conservation of total token balances:
+    ///
+    /// ```text
+    ///   ∑ K(u, v) * (q_u / q_v) = T_u,   for all u ≠ output_token
+    /// ```
+    ///
+    /// where:
+    /// - `K(u, v) = Σ √kᵢ`  is the geometric liquidity between `u` and `v` (sum over all pools i
+    ///   linking `u` and `v`)
+    /// - `T_u` is the total reserve of token `u` across all pools
+    /// - `q_u` is the sqrt-price variable for token `u`
+    ///
+    /// The fixed-point iteration updates until convergence (`max_relative_change` below the
+    /// solver tolerance):
+    ///
+    /// ```text
+    ///   q_u ← T_u / ( ∑ K(u, v) / q_v ),   for all u ≠ output_token
+    /// ```
+    ///
+    /// Once the prices `q` have converged, the post-equilibrium total of the `output_token` is:
+    ///
+    /// ```text
+    ///   T'_f = ∑ K(f, v) * (q_f / q_v),
+    /// ```
+    ///
+    /// and the amount of `output_token` that can be extracted is:
+    ///
+    /// ```text
+    ///   Δf = T_f − T'_f.
+    /// ```
+    ///
+    /// Complexity:  `O(max_iterations × E)`, where E is the number of edges in the token graph.
+    fn no_arbitrage_equilibrium(&mut self, output_token: usize) -> SolveOutcome {
+        // Only the component of the trade moves, the others keep their state bit for bit
+        let mut component = std::mem::take(&mut self.scratch.component);
+        component.clear();
+        let index = self.component_of[output_token];
+        component.extend((0..self.nodes.len()).filter(|&other| self.component_of[other] == index));
+        let outcome = self.component_equilibrium(&component, output_token);
+        self.scratch.component = component;
+        outcome
+    }
+
+    /// Solves the equilibrium of `component`, the tokens of the connected component of
+    /// `output_token`, see [`TokenGraph::no_arbitrage_equilibrium`].
+    fn component_equilibrium(&mut self, component: &[usize], output_token: usize) -> SolveOutcome {
+        let mut outcome = self.equilibrate(component.iter().copied(), output_token);
+        if outcome.invalid_price.is_some() || outcome.numerical_error.is_some() {
+            return outcome;
+        }
+        if !outcome.converged
+            && !outcome.deadline_reached
+            && self.fallback == FallbackStrategy::Bisection
+        {
+            outcome = self.bisect_output(component, output_token, outcome);
+        }
+
+        // Renormalization of the component, which the reference token may lie outside of
+        if self.normalization == PriceNormalization::GeometricMean
+            || self.are_connected(output_token, self.reference_token)
+        {
+            outcome.normalization_skipped = !self.normalize_prices_of(component);
+        }
+
+        // Compute and update the output reserve based after equilibrium, unless bisected
+        if !outcome.bisected {
+            outcome.numerical_error = self.check_output(output_token);
+            if outcome.numerical_error.is_some() {
+                return outcome;
+            }
+            outcome.output_amount = self.extract_output(output_token);
+        }
+        // The output price was pinned during the iteration; derived again from the new total, it
+        // stays consistent with it even when the extraction was dust and left the total unchanged
+        self.refresh_price(output_token);
+        outcome
+    }
+
+    /// Settles every connected component on its own: the arbitrage left in the pools is
+    /// extracted in the reference token for its component, and in the smallest token of the
+    /// component for the others. Isolated tokens are skipped, and so are the components whose
+    /// iteration produced an invalid price or a numerical error, see
+    /// [`SolveOutcome::invalid_price`] and [`SolveOutcome::numerical_error`], which keep their
+    /// state and extract nothing.
+    ///
+    /// Each component is solved on a graph of its own, in parallel with the `parallel` feature,
+    /// and merged back in component order; both paths perform the very same arithmetic, so their
+    /// results are bitwise equal.
+    ///
+    /// Returns the sink token and the outcome of each settled component, in component order.
+    pub(super) fn settle(&mut self) -> Vec<(usize, SolveOutcome)> {
+        self.liquidities.lay_out();
+        let settled = map_ordered(self.component_graphs(), |(component, mut graph)| {
+            let sink = graph.reference_token;
+            // The settled prices are the starting point of every later trade, whatever their size
+            graph.solver.output_accuracy = None;
+            let mut outcome = graph.equilibrate(0..graph.len(), sink);
+            if outcome.invalid_price.is_none() && outcome.numerical_error.is_none() {
+                outcome.numerical_error = graph.check_output(sink);
+            }
+            if outcome.invalid_price.is_none() && outcome.numerical_error.is_none() {
+                outcome.output_amount = graph.extract_output(sink);
+            }
+            (component, graph, outcome)
+        });
+
+        let mut outcomes = Vec::with_capacity(settled.len());
+        for (component, graph, mut outcome) in settled {
+            if let Some((token, q)) = outcome.invalid_price {
+                outcome.invalid_price = Some((component[token], q));
+                outcomes.push((component[graph.reference_token], outcome));
+                continue;
+            }
+            if let Some((token, value)) = outcome.numerical_error {
+                outcome.numerical_error = Some((component[token], value));
+                outcomes.push((component[graph.reference_token], outcome));
+                continue;
+            }
+            for (node, &token) in graph.nodes.into_iter().zip(&component) {
+                self.nodes[token].q = node.q;
+                self.nodes[token].total_reserve = node.total_reserve;
+            }
+            outcomes.push((component[graph.reference_token], outcome));
+        }
+        self.normalize_prices();
+        outcomes
+    }
+
+    /// Extracts the graph of every connected component of at least two tokens, along with the
+    /// component, in component order. The reference token of each graph is that of this graph
+    /// for its component and the smallest token of the component for the others.
+    pub(super) fn component_graphs(&self) -> Vec<(Vec<usize>, TokenGraph)> {
+        self.connected_components()
+            .into_iter()
+            .filter(|component| component.len() >= 2)
+            .map(|component| {
+                let reference_token = component.binary_search(&self.reference_token).unwrap_or(0);
+                let graph = self.component_graph(&component, reference_token);
+                (component, graph)
+            })
+            .collect()
+    }
+
+    /// Saves the prices and the totals of the tokens of a trade, which are all a trade between
+    /// them changes. The prices are copied into the scratch of the graph, which holds those of a
+    /// single checkpoint: the next one overwrites them.
+    pub(super) fn checkpoint(
+        &mut self,
+        input_token: usize,
+        output_token: usize,
+    ) -> TradeCheckpoint {
+        self.scratch.prices.clear();
+        self.scratch
+            .prices
+            .extend(self.nodes.iter().map(|node| node.q));
+        TradeCheckpoint {
+            totals: [input_token, output_token]
+                .map(|token| (token, self.nodes[token].total_reserve)),
+        }
+    }
+
+    /// Undoes a trade, restoring the state saved by [`TokenGraph::checkpoint`] before it, the
+    /// last checkpoint taken.
+    pub(super) fn restore_checkpoint(&mut self, checkpoint: TradeCheckpoint) {
+        for (node, &q) in self.nodes.iter_mut().zip(&self.scratch.prices) {
+            node.q = q;
+        }
+        for (token, total_reserve) in checkpoint.totals {
+            self.nodes[token].total_reserve = total_reserve;
+        }
+    }
+
+    /// Restores the prices and the totals of `baseline`, a copy of this graph taken before
+    /// trades, in place.
+    pub(super) fn restore_state(&mut self, baseline: &TokenGraph) {
+        for (node, baseline_node) in self.nodes.iter_mut().zip(&baseline.nodes) {
+            node.q = baseline_node.q;
+            node.total_reserve = baseline_node.total_reserve;
+        }
+    }
+
+    /// Extracts the graph of `component`, a connected component sorted by token index, with its
+    /// tokens renumbered by their position in it and `reference_token` as reference.
+    ///
+    /// The renumbering preserves the order of the tokens, so that the sweeps over the extracted
+    /// graph sum over the neighbors in the same order as over the original one.
+    fn component_graph(&self, component: &[usize], reference_token: usize) -> TokenGraph {
+        let nodes = component
+            .iter()
+            .map(|&token| self.nodes[token].clone())
+            .collect::<Vec<_>>();
+        let mut pairs = Vec::new();
+        for (local, &token) in component.iter().enumerate() {
+            for (paired_token, liquidity) in self.scaled_neighbors(token) {
+                let paired_local = component
+                    .binary_search(&paired_token)
+                    .expect("active neighbors belong to the same component");
+                if local < paired_local {
+                    pairs.push((local, paired_local, liquidity));
+                }
+            }
+        }
+        let liquidities = LazyLiquidities::new(nodes.len(), self.storage, pairs);
+
+        TokenGraph {
+            liquidities,
+            storage: self.storage,
+            blacklisted: vec![false; nodes.len()],
+            hop_limit: None,
+            deadline: None,
+            dust_tolerance: self.dust_tolerance,
+            component_of: vec![0; nodes.len()],
+            nodes,
+            reference_token,
+            solver: self.solver,
+            domain: self.domain,
+            relaxation: self.relaxation,
+            acceleration: self.acceleration,
+            sweep_mode: self.sweep_mode,
+            parallel_sweep_threshold: self.parallel_sweep_threshold,
+            kind: self.kind,
+            fallback: self.fallback,
+            strict_math: self.strict_math,
+            normalization: self.normalization,
+            scales: component.iter().map(|&token| self.scales[token]).collect(),
+            virtual_reserves: component
+                .iter()
+                .map(|&token| self.virtual_reserves[token])
+                .collect(),
+            scratch: SolveScratch::default(),
+        }
+    }
+
+    /// Brings the prices of `tokens`, `pinned` excepted, to the equilibrium with the solver kind
+    /// of the graph, see [`TokenGraph::fixed_point`] and [`TokenGraph::newton`].
+    ///
+    /// Two tokens are solved in closed form whatever the solver kind: their pools behave as a
+    /// single constant-product pool of invariant `K² = (∑ √k)²`, and the free price is the fixed
+    /// point of its own update, `q_u = T_u · q_f / K`, reported as a single converged sweep.
+    fn equilibrate(
+        &mut self,
+        tokens: impl Iterator<Item = usize> + Clone,
+        pinned: usize,
+    ) -> SolveOutcome {
+        let mut first_tokens = tokens.clone();
+        if let (Some(first), Some(second), None) = (
+            first_tokens.next(),
+            first_tokens.next(),
+            first_tokens.next(),
+        ) && (first == pinned || second == pinned)
+        {
+            let free = if first == pinned { second } else { first };
+            let mut outcome = SolveOutcome {
+                output_amount: 0.0,
+                iterations: 1,
+                residual: 0.0,
+                converged: true,
+                invalid_price: None,
+                numerical_error: None,
+                damping_events: 0,
+                final_damping: 1.0,
+                deadline_reached: false,
+                bisected: false,
+                normalization_skipped: false,
+                token_updates: 1,
+            };
+            match self.plain_update(free) {
+                _ if self.blacklisted[free] => {}
+                Some(q) if !q.is_finite() || q <= 0.0 => {
+                    outcome.residual = f64::INFINITY;
+                    outcome.converged = false;
+                    outcome.invalid_price = Some((free, q * self.scales[free]));
+                }
+                Some(q) => self.nodes[free].q = q,
+                // A token without active edge has no price to find
+                None => {}
+            }
+            return outcome;
+        }
+
+        match self.kind {
+            SolverKind::FixedPoint => self.fixed_point(tokens, pinned),
+            SolverKind::Newton => match self.newton(tokens.clone(), pinned) {
+                Ok(outcome) => outcome,
+                // The fixed-point iteration starts over from the prices Newton's method started from
+                Err(steps) => {
+                    let mut outcome = self.fixed_point(tokens, pinned);
+                    outcome.iterations += steps;
+                    outcome
+                }
+            },
+            SolverKind::ActiveSet => self.active_set(tokens, pinned),
+            SolverKind::ConjugateGradient => {
+                match self.conjugate_gradient(tokens.clone(), pinned) {
+                    Ok(outcome) => outcome,
+                    // Likewise from the prices the conjugate gradients started from
+                    Err(iterations) => {
+                        let mut outcome = self.fixed_point(tokens, pinned);
+                        outcome.iterations += iterations;
+                        outcome
+                    }
+                }
+            }
+        }
+    }
+
+    /// Solves the equilibrium conditions of `tokens`, `pinned` excepted, by preconditioned
+    /// conjugate gradients, and returns the number of iterations, the largest relative price
+    /// change of the plain updates at the solution and whether it converged, with no output
+    /// extracted.
+    ///
+    /// In the inverses `y_u = 1 / q_u` of the prices, the conditions `∑ K(u, v) · q_u / q_v = T_u`
+    /// read `T_u · y_u − ∑ K(u, v) · y_v = 0`: a linear system, whose matrix `T − K` restricted
+    /// to the free tokens is symmetric, the price of `pinned` entering its right-hand side. The
+    /// fixed-point sweeps are the Jacobi and Gauss–Seidel iterations of that system, and their
+    /// convergence, a spectral radius below 1, see [`TokenGraph::spectral_sign`], makes the
+    /// matrix positive definite. Conjugate gradients preconditioned by the diagonal `T` then
+    /// converge in about the square root of the number of sweeps, each iteration summing over
+    /// the edges of the tokens once, like a sweep: only the vectors of the iteration are
+    /// allocated, in the number of tokens of the graph.
+    ///
+    /// The residual of the system is that of the plain updates, `ŷ_u = y_u + r_u / T_u`, so that
+    /// the iteration stops on the criterion of [`TokenGraph::fixed_point`], the output accuracy
+    /// of the solver excepted, checked again on the residual recomputed from the prices, which
+    /// the updated one drifts from in rounding.
+    ///
+    /// Fails with the number of iterations, the prices left untouched, if the matrix is not
+    /// positive definite along a search direction, if the iterations exhaust the budget of the
+    /// solver, or if the solution holds a price that is not positive and finite. Past the
+    /// deadline of the graph, the prices of the last iteration are kept unconverged.
+    fn conjugate_gradient(
+        &mut self,
+        tokens: impl Iterator<Item = usize>,
+        pinned: usize,
+    ) -> Result<SolveOutcome, usize> {
+        // Tokens without active edge have no price to find
+        let free_tokens = tokens
+            .filter(|&token| {
+                token != pinned
+                    && !self.blacklisted[token]
+                    && self.scaled_neighbors(token).next().is_some()
+            })
+            .collect::<Vec<_>>();
+        let n = self.nodes.len();
+        let mut is_free = vec![false; n];
+        for &token in &free_tokens {
+            is_free[token] = true;
+        }
+        let totals = free_tokens
+            .iter()
+            .map(|&token| self.nodes[token].total_reserve - self.nodes[token].frozen_reserve)
+            .collect::<Vec<_>>();
+        let mut y = self
+            .nodes
+            .iter()
+            .map(|node| 1.0 / node.q)
+            .collect::<Vec<_>>();
+        // Residuals `r_u = ∑ K(u, v) · y_v − T_u · y_u`, from the prices
+        let residuals = |graph: &Self, y: &[f64]| -> Vec<f64> {
+            free_tokens
+                .iter()
+                .zip(&totals)
+                .map(|(&token, &total)| {
+                    compensated_sum(
+                        graph
+                            .scaled_neighbors(token)
+                            .map(|(paired_token, liquidity)| liquidity * y[paired_token]),
+                    ) - total * y[token]
+                })
+                .collect()
+        };
+        // Largest relative change `|q̂ − q| / q` of the plain updates, and whether they all meet
+        // the tolerances
+        let plain_changes = |graph: &Self, y: &[f64], r: &[f64]| -> (f64, bool) {
+            let mut max_relative_change = 0.0_f64;
+            let mut converged = true;
+            for ((&token, &total), &residual) in free_tokens.iter().zip(&totals).zip(r) {
+                let q = 1.0 / y[token];
+                let plain_q = 1.0 / (y[token] + residual / total);
+                let change = (plain_q - q).abs();
+                max_relative_change = max_relative_change.max(change / q.abs());
+                if change.is_nan()
+                    || change
+                        >= graph.solver.absolute_tolerance / graph.scales[token]
+                            + graph.solver.tolerance * q.abs()
+                {
+                    converged = false;
+                }
+            }
+            (max_relative_change, converged)
+        };
+        let mut r = residuals(self, &y);
+        let mut z = r
+            .iter()
+            .zip(&totals)
+            .map(|(r, t)| r / t)
+            .collect::<Vec<_>>();
+        let mut rho = r.iter().zip(&z).map(|(r, z)| r * z).sum::<f64>();
+        // The search direction, by token so that the products read it by neighbor
+        let mut p = vec![0.0; n];
+        for (&token, &z) in free_tokens.iter().zip(&z) {
+            p[token] = z;
+        }
+        let mut iterations = 0;
+        // The largest relative change and whether it converged, `None` on failure
+        let stop = loop {
+            let (change, converged) = plain_changes(self, &y, &r);
+            if converged {
+                // Confirmed on the residuals of the prices, which the updated ones drift from
+                r = residuals(self, &y);
+                let (change, converged) = plain_changes(self, &y, &r);
+                if converged {
+                    break Some((change, true));
+                }
+                // Restarting from the recomputed residuals
+                for ((&token, z), (r, t)) in
+                    free_tokens.iter().zip(&mut z).zip(r.iter().zip(&totals))
+                {
+                    *z = r / t;
+                    p[token] = *z;
+                }
+                rho = r.iter().zip(&z).map(|(r, z)| r * z).sum::<f64>();
+            }
+            if iterations == self.solver.max_iterations {
+                break None;
+            }
+            if iterations > 0
+                && iterations % DEADLINE_CHECK_INTERVAL == 0
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                break Some((change, false));
+            }
+            iterations += 1;
+
+            // `(T − K) · p`, the pinned token and the fixed ones not moving
+            let product = free_tokens
+                .iter()
+                .zip(&totals)
+                .map(|(&token, &total)| {
+                    total * p[token]
+                        - compensated_sum(
+                            self.scaled_neighbors(token)
+                                .filter(|&(paired_token, _)| is_free[paired_token])
+                                .map(|(paired_token, liquidity)| liquidity * p[paired_token]),
+                        )
+                })
+                .collect::<Vec<_>>();
+            let curvature = free_tokens
+                .iter()
+                .zip(&product)
+                .map(|(&token, product)| p[token] * product)
+                .sum::<f64>();
+            if !(curvature > 0.0 && curvature.is_finite()) {
+                break None;
+            }
+            let alpha = rho / curvature;
+            for (((&token, r), z), (product, total)) in free_tokens
+                .iter()
+                .zip(&mut r)
+                .zip(&mut z)
+                .zip(product.iter().zip(&totals))
+            {
+                y[token] += alpha * p[token];
+                *r -= alpha * product;
+                *z = *r / total;
+            }
+            let next_rho = r.iter().zip(&z).map(|(r, z)| r * z).sum::<f64>();
+            let beta = next_rho / rho;
+            rho = next_rho;
+            for (&token, &z) in free_tokens.iter().zip(&z) {
+                p[token] = z + beta * p[token];
+            }
+        };
+
+        let Some((change, converged)) = stop else {
+            return Err(iterations);
+        };
+        if !free_tokens
+            .iter()
+            .all(|&token| y[token] > 0.0 && y[token].is_finite())
+        {
+            return Err(iterations);
+        }
+        for &token in &free_tokens {
+            self.nodes[token].q = 1.0 / y[token];
+        }
+        Ok(SolveOutcome {
+            output_amount: 0.0,
+            iterations,
+            residual: change,
+            converged,
+            invalid_price: None,
+            numerical_error: None,
+            damping_events: 0,
+            final_damping: 1.0,
+            deadline_reached: !converged,
+            bisected: false,
+            normalization_skipped: false,
+            token_updates: 0,
+        })
+    }
+
+    /// Solves the equilibrium conditions `F_u = log ∑ K(u, v) · q_u / q_v − log T_u = 0` of
+    /// `tokens`, `pinned` excepted, by Newton's method on the logarithms of the prices, and
+    /// returns the number of steps, the largest relative price change of the last one and
+    /// whether it converged, with no output extracted.
+    ///
+    /// In logarithms, the conditions are nearly linear far from the equilibrium, and their
+    /// Jacobian is `I − W`, with `W_uv = (K(u, v) / q_v) / ∑ K(u, w) / q_w` the weights of the
+    /// fixed-point update. Each step solves its linear system by a dense LU factorization, in
+    /// `O(n³)` for `n` tokens, and is halved until it reduces the residuals, unless it already
+    /// meets the tolerances, which apply as in [`TokenGraph::fixed_point`]. The logarithms keep
+    /// every price positive.
+    ///
+    /// Fails with the number of steps taken, the prices being restored, if the Jacobian is
+    /// singular, no fraction of a step reduces the residuals or the steps do not converge within
+    /// `MAX_NEWTON_STEPS`. Past the deadline of the graph, the prices of the last step are kept
+    /// unconverged.
+    fn newton(
+        &mut self,
+        tokens: impl Iterator<Item = usize>,
+        pinned: usize,
+    ) -> Result<SolveOutcome, usize> {
+        // Tokens without active edge have no price to find
+        let free_tokens = tokens
+            .filter(|&token| {
+                token != pinned
+                    && !self.blacklisted[token]
+                    && self.scaled_neighbors(token).next().is_some()
+            })
+            .collect::<Vec<_>>();
+        let mut row_of = vec![usize::MAX; self.nodes.len()];
+        for (row, &token) in free_tokens.iter().enumerate() {
+            row_of[token] = row;
+        }
+        let initial_q = free_tokens
+            .iter()
+            .map(|&token| self.nodes[token].q)
+            .collect::<Vec<_>>();
+        let fail = |graph: &mut Self, steps: usize| {
+            for (&token, &q) in free_tokens.iter().zip(&initial_q) {
+                graph.nodes[token].q = q;
+            }
+            Err(steps)
+        };
+        let size = free_tokens.len();
+
+        let mut residuals = self.relative_residuals(&free_tokens);
+        let max_steps = MAX_NEWTON_STEPS.min(self.solver.max_iterations);
+        for steps in 1..=max_steps {
+            let mut rows = vec![vec![0.0; size + 1]; size];
+            for (row, &token) in free_tokens.iter().enumerate() {
+                let denom = compensated_sum(
+                    self.scaled_neighbors(token)
+                        .map(|(paired_token, liquidity)| liquidity / self.nodes[paired_token].q),
+                );
+                rows[row][row] = 1.0;
+                for (paired_token, liquidity) in self.scaled_neighbors(token) {
+                    if row_of[paired_token] != usize::MAX {
+                        rows[row][row_of[paired_token]] -=
+                            liquidity / self.nodes[paired_token].q / denom;
+                    }
+                }
+                rows[row][size] = -residuals[row];
+            }
+            let Some(step) = solve_linear_system(rows) else {
+                return fail(self, steps);
+            };
+
+            let start_q = free_tokens
+                .iter()
+                .map(|&token| self.nodes[token].q)
+                .collect::<Vec<_>>();
+            let merit = residuals.iter().map(|r| r * r).sum::<f64>();
+            let mut fraction = 1.0;
+            let step_change = loop {
+                let mut max_relative_change = 0.0_f64;
+                let mut step_converged = true;
+                for ((&token, &q), &log_change) in free_tokens.iter().zip(&start_q).zip(&step) {
+                    let relative_change = (fraction * log_change).exp_m1();
+                    self.nodes[token].q = q + q * relative_change;
+                    max_relative_change = max_relative_change.max(relative_change.abs());
+                    // The criterion of the fixed-point iteration, on the scaled prices
+                    if (q * relative_change).abs()
+                        >= self.solver.absolute_tolerance / self.scales[token]
+                            + self.solver.tolerance * q.abs()
+                    {
+                        step_converged = false;
+                    }
+                }
+                if fraction == 1.0 && step_converged {
+                    return Ok(SolveOutcome {
+                        output_amount: 0.0,
+                        iterations: steps,
+                        residual: max_relative_change,
+                        converged: true,
+                        invalid_price: None,
+                        numerical_error: None,
+                        damping_events: 0,
+                        final_damping: 1.0,
+                        deadline_reached: false,
+                        bisected: false,
+                        normalization_skipped: false,
+                        token_updates: 0,
+                    });
+                }
+                let next_residuals = self.relative_residuals(&free_tokens);
+                let next_merit = next_residuals.iter().map(|r| r * r).sum::<f64>();
+                if next_merit < merit {
+                    residuals = next_residuals;
+                    break max_relative_change;
+                }
+                fraction /= 2.0;
+                if fraction < MIN_NEWTON_FRACTION {
+                    return fail(self, steps);
+                }
+            };
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Ok(SolveOutcome {
+                    output_amount: 0.0,
+                    iterations: steps,
+                    residual: step_change,
+                    converged: false,
+                    invalid_price: None,
+                    numerical_error: None,
+                    damping_events: 0,
+                    final_damping: 1.0,
+                    deadline_reached: true,
+                    bisected: false,
+                    normalization_skipped: false,
+                    token_updates: 0,
+                });
+            }
+        }
+        fail(self, max_steps)
+    }
+
+    /// Returns the residuals `F_u` of the equilibrium conditions of `tokens`, see
+    /// [`TokenGraph::newton`].
+    fn relative_residuals(&self, tokens: &[usize]) -> Vec<f64> {
+        tokens
+            .iter()
+            .map(|&token| self.reserve_ratio(token).ln())
+            .collect()
+    }
+
+    /// Returns the relative imbalance `(∑ K(u, v) q_u / q_v − T_u) / T_u` of the active total of
+    /// `token` against the total its current price implies, zero at the equilibrium, or `None`
+    /// for a blacklisted token or a token without active edge, which has no equilibrium
+    /// condition. Nothing is iterated.
+    pub(super) fn relative_imbalance(&self, token: usize) -> Option<f64> {
+        let has_condition =
+            !self.blacklisted[token] && self.scaled_neighbors(token).next().is_some();
+        has_condition.then(|| self.reserve_ratio(token) - 1.0)
+    }
+
+    /// Returns the ratio of the total of `token` its current price implies to its active total.
+    fn reserve_ratio(&self, token: usize) -> f64 {
+        let node = &self.nodes[token];
+        let active_reserve = node.total_reserve - node.frozen_reserve;
+        let implied_reserve = compensated_sum(
+            self.scaled_neighbors(token)
+                .map(|(paired_token, liquidity)| liquidity * node.q / self.nodes[paired_token].q),
+        );
+        implied_reserve / active_reserve
+    }
+
+    /// Runs fixed-point sweeps over `tokens`, `pinned` excepted, until the prices stop moving,
+    /// and returns the number of sweeps, the largest relative price change of the last one and
+    /// whether the iteration converged, with no output extracted.
+    ///
+    /// A sweep converges when the change `Δq = q̂ − q` of every plain update `q̂`, before any
+    /// relaxation, satisfies `|Δq| < atol + rtol · |q|` on the unscaled prices, with the absolute and relative tolerances of the solver configuration. With an
+    /// output accuracy, the sweep converges instead when the amount of `pinned` the prices would
+    /// extract is estimated within that fraction of its limit, from its last change and the
+    /// average rate at which the changes shrink; the tolerances apply only once the amount stops
+    /// changing. The reported relative change is taken against `max(|q|, f64::MIN_POSITIVE)`, so
+    /// that a zero price cannot turn it into a division by zero. An update producing a price that
+    /// is not positive and finite stops the iteration at once, the prices being left mid-sweep:
+    /// every later update would build on it. Gauss–Seidel sweeps update the prices in place, in
+    /// the order of `tokens`, Jacobi sweeps only once all updates are computed. With Anderson
+    /// acceleration, the prices a sweep leaves unconverged are replaced by their mixing with the
+    /// previous sweeps. Past the deadline of the graph, see [`TokenGraph::set_deadline`], the
+    /// iteration stops unconverged with the prices of the last sweep.
+    ///
+    /// When the largest relative change has not improved on its best value for `STALL_WINDOW`
+    /// sweeps while above `STALL_FLOOR`, the iteration is taken to oscillate: the relaxation factor is multiplied by a
+    /// damping factor, halved at every such stall and reset to 1 as soon as the change improves
+    /// again, Anderson acceleration excepted. Judged on the plain updates, the convergence
+    /// cannot come from the damping of the steps alone.
+    fn fixed_point(
+        &mut self,
+        tokens: impl Iterator<Item = usize> + Clone,
+        pinned: usize,
+    ) -> SolveOutcome {
+        let mut scratch = std::mem::take(&mut self.scratch.sweeps);
+        let outcome = self.fixed_point_in(tokens, pinned, &mut scratch);
+        self.scratch.sweeps = scratch;
+        outcome
+    }
+
+    /// Runs [`TokenGraph::fixed_point`] in the buffers of `scratch`.
+    fn fixed_point_in(
+        &mut self,
+        tokens: impl Iterator<Item = usize> + Clone,
+        pinned: usize,
+        scratch: &mut SweepScratch,
+    ) -> SolveOutcome {
+        let SweepScratch {
+            log_q,
+            jacobi_updates,
+            parallel_tokens,
+            denominators,
+            ..
+        } = scratch;
+        // In the log domain, the logarithms of the prices are the iterated variables
+        log_q.clear();
+        if self.domain == SolverDomain::Log {
+            log_q.extend(self.nodes.iter().map(|node| node.q.ln()));
+        }
+        let mut extracted_amount = match self.solver.output_accuracy {
+            Some(_) => self.pending_output(pinned),
+            None => 0.0,
+        };
+        let mut amount_change = f64::INFINITY;
+        let (mut smoothed_rate, mut rate_samples) = (0.0, 0);
+        // With Anderson mixing, the iterated variables of the free tokens before the sweep
+        let mut anderson = match self.acceleration {
+            SolverAcceleration::None => None,
+            SolverAcceleration::Anderson { memory } => Some(Anderson::new(memory)),
+        };
+        let free_tokens = match anderson {
+            Some(_) => tokens
+                .clone()
+                .filter(|&token| token != pinned && !self.blacklisted[token])
+                .collect(),
+            None => Vec::new(),
+        };
+        let variables = |graph: &Self, log_q: &[f64]| -> Vec<f64> {
+            free_tokens
+                .iter()
+                .map(|&token| match graph.domain {
+                    SolverDomain::Linear => graph.nodes[token].q,
+                    SolverDomain::Log => log_q[token],
+                })
+                .collect()
+        };
+        let mut iterate = Vec::new();
+        jacobi_updates.clear();
+        // Above the threshold, the tokens of a Jacobi sweep, whose denominators are computed at
+        // once in parallel: each reads the prices of the previous sweep only
+        parallel_tokens.clear();
+        if cfg!(feature = "parallel")
+            && self.sweep_mode == SweepMode::Jacobi
+            && tokens.clone().count() >= self.parallel_sweep_threshold
+        {
+            parallel_tokens.extend(tokens.clone());
+        }
+
+        // The damping factor, halved whenever the changes stop improving
+        let (mut damping, mut damping_events) = (1.0, 0);
+        let (mut best_change, mut stalled_sweeps) = (f64::INFINITY, 0);
+
+        let (mut iterations, mut token_updates) = (0, 0);
+        let mut max_relative_change = 0.0;
+        let (mut converged, mut deadline_reached) = (false, false);
+        while iterations < self.solver.max_iterations {
+            iterations += 1;
+            max_relative_change = 0.0;
+            let mut sweep_converged = true;
+            let relaxation = self.relaxation * damping;
+            if anderson.is_some() {
+                iterate = variables(self, log_q);
+            }
+
+            if !parallel_tokens.is_empty() {
+                denominators.clear();
+                extend_ordered(denominators, parallel_tokens, |token| {
+                    self.sweep_denominator(token, log_q)
+                });
+            }
+            for (position, token) in tokens.clone().enumerate() {
+                // Skip the pinned token and the blacklisted ones
+                if token == pinned || self.blacklisted[token] {
+                    continue;
+                }
+                let q = self.nodes[token].q;
+                let node = &self.nodes[token];
+                let active_reserve = node.total_reserve - node.frozen_reserve;
+
+                // The convergence is judged on the plain update `q̂`, whatever the relaxation
+                let (updated_q, updated_log_q, plain_q, relative_change, denom) = match self.domain
+                {
+                    SolverDomain::Linear => {
+                        // Update q_u ← T_u / ( ∑ K(u, v) / q_v )
+                        let denom = match parallel_tokens.is_empty() {
+                            true => self.sweep_denominator(token, log_q),
+                            false => denominators[position],
+                        };
+                        // A token without active edge has no price to find
+                        if denom == 0.0 {
+                            continue;
+                        }
+                        // Relaxed as q ← (1 − ω) · q + ω · q̂
+                        let plain_q = active_reserve / denom;
+                        let updated_q = (1.0 - relaxation) * q + relaxation * plain_q;
+                        (
+                            updated_q,
+                            f64::NAN,
+                            plain_q,
+                            (plain_q - q).abs() / q.abs().max(f64::MIN_POSITIVE),
+                            denom,
+                        )
+                    }
+                    SolverDomain::Log => {
+                        // Update log q_u ← log T_u − log ∑ exp(log K(u, v) − log q_v), relaxed alike
+                        let log_denom = match parallel_tokens.is_empty() {
+                            true => self.sweep_denominator(token, log_q),
+                            false => denominators[position],
+                        };
+                        if log_denom == f64::NEG_INFINITY {
+                            continue;
+                        }
+                        let plain_log_q = active_reserve.ln() - log_denom;
+                        let updated_log_q =
+                            (1.0 - relaxation) * log_q[token] + relaxation * plain_log_q;
+                        // The relative change of the price, from the change of its logarithm
+                        (
+                            updated_log_q.exp(),
+                            updated_log_q,
+                            plain_log_q.exp(),
+                            (plain_log_q - log_q[token]).exp_m1().abs(),
+                            log_denom.exp(),
+                        )
+                    }
+                };
+                token_updates += 1;
+                if self.strict_math && !denom.is_finite() {
+                    return SolveOutcome {
+                        output_amount: 0.0,
+                        iterations,
+                        residual: f64::INFINITY,
+                        converged: false,
+                        invalid_price: None,
+                        numerical_error: Some((token, denom)),
+                        damping_events,
+                        final_damping: damping,
+                        deadline_reached: false,
+                        bisected: false,
+                        normalization_skipped: false,
+                        token_updates,
+                    };
+                }
+                if !updated_q.is_finite() || updated_q <= 0.0 {
+                    return SolveOutcome {
+                        output_amount: 0.0,
+                        iterations,
+                        residual: f64::INFINITY,
+                        converged: false,
+                        invalid_price: Some((token, updated_q * self.scales[token])),
+                        numerical_error: None,
+                        damping_events,
+                        final_damping: damping,
+                        deadline_reached: false,
+                        bisected: false,
+                        normalization_skipped: false,
+                        token_updates,
+                    };
+                }
+
+                if relative_change > max_relative_change {
+                    max_relative_change = relative_change;
+                }
+                // Compared on the scaled prices, the absolute tolerance being scaled alike
+                if (plain_q - q).abs()
+                    >= self.solver.absolute_tolerance / self.scales[token]
+                        + self.solver.tolerance * q.abs()
+                {
+                    sweep_converged = false;
+                }
+
+                match self.sweep_mode {
+                    SweepMode::GaussSeidel => {
+                        self.nodes[token].q = updated_q;
+                        if self.domain == SolverDomain::Log {
+                            log_q[token] = updated_log_q;
+                        }
+                    }
+                    SweepMode::Jacobi => jacobi_updates.push((token, updated_q, updated_log_q)),
+                }
+            }
+            // The Jacobi updates all read the prices of the previous sweep
+            for (token, updated_q, updated_log_q) in jacobi_updates.drain(..) {
+                self.nodes[token].q = updated_q;
+                if self.domain == SolverDomain::Log {
+                    log_q[token] = updated_log_q;
+                }
+            }
+
+            if let Some(accuracy) = self.solver.output_accuracy {
+                let previous_amount = extracted_amount;
+                extracted_amount = self.pending_output(pinned);
+                let previous_change = amount_change;
+                amount_change = (extracted_amount - previous_amount).abs();
+                // The changes shrink geometrically at the rate `ρ` of the sweeps, leaving
+                // `change · ρ / (1 − ρ)` to come. Successive ratios are noisy once the changes
+                // near the rounding of the totals, hence a running average of them
+                let rate = amount_change / previous_change;
+                if rate.is_finite() && amount_change > 0.0 {
+                    rate_samples += 1;
+                    smoothed_rate += (rate - smoothed_rate) / rate_samples.min(RATE_WINDOW) as f64;
+                }
+                sweep_converged = match amount_change > 0.0 {
+                    // The amount no longer moves in floating point: as accurate as it gets
+                    false => sweep_converged,
+                    // Until the rate is established, and as long as the changes do not shrink
+                    true if rate_samples < RATE_WINDOW || smoothed_rate >= 1.0 => false,
+                    true => {
+                        let remaining = amount_change * smoothed_rate / (1.0 - smoothed_rate);
+                        remaining < accuracy * extracted_amount.abs()
+                    }
+                };
+            }
+            if sweep_converged {
+                converged = true;
+                break;
+            }
+            if iterations % DEADLINE_CHECK_INTERVAL == 0
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                deadline_reached = true;
+                break;
+            }
+
+            // Changes that no longer improve are taken for an oscillation, damped until they
+            // improve again, unless they are down to the rounding of the updates. The changes
+            // of Anderson mixing do not decrease steadily, they are left alone
+            if max_relative_change < best_change {
+                best_change = max_relative_change;
+                stalled_sweeps = 0;
+                damping = 1.0;
+            } else if max_relative_change > STALL_FLOOR && anderson.is_none() {
+                stalled_sweeps += 1;
+                if stalled_sweeps == STALL_WINDOW {
+                    damping /= 2.0;
+                    damping_events += 1;
+                    stalled_sweeps = 0;
+                }
+            }
+
+            if let Some(anderson) = &mut anderson
+                && let Some(mixed) = anderson.mix(&iterate, &variables(self, log_q))
+            {
+                let prices = mixed.iter().map(|&variable| match self.domain {
+                    SolverDomain::Linear => variable,
+                    SolverDomain::Log => variable.exp(),
+                });
+                match prices.clone().all(|q| q.is_finite() && q > 0.0) {
+                    true => {
+                        for ((&token, q), variable) in free_tokens.iter().zip(prices).zip(&mixed) {
+                            self.nodes[token].q = q;
+                            if self.domain == SolverDomain::Log {
+                                log_q[token] = *variable;
+                            }
+                        }
+                    }
+                    // Keeping the plain sweep, and starting the mixing over from it
+                    false => anderson.reset(),
+                }
+            }
+        }
+        SolveOutcome {
+            output_amount: 0.0,
+            iterations,
+            residual: max_relative_change,
+            converged,
+            invalid_price: None,
+            numerical_error: None,
+            damping_events,
+            final_damping: damping,
+            deadline_reached,
+            bisected: false,
+            normalization_skipped: false,
+            token_updates,
+        }
+    }
+
+    /// Runs Gauss–Seidel updates over the active tokens of `tokens`, `pinned` excepted, until
+    /// none is left, and returns the number of updates in sweeps of as many tokens, rounded up,
+    /// the largest relative change of the plain updates at the end and whether the iteration
+    /// converged, with no output extracted, see [`SolverKind::ActiveSet`].
+    ///
+    /// A pass computes the plain update of every token without applying it, and activates those
+    /// moving beyond the tolerances of [`TokenGraph::fixed_point`], in the order of `tokens`.
+    /// Active tokens are then updated first in, first out: every update is applied, and one
+    /// moving beyond the tolerances activates the neighbors of its token that are not already.
+    /// Once no token is left, another pass checks the prices: the changes of the neighbors since
+    /// the last update of a token were all within the tolerances, but several of them may add up
+    /// beyond. The iteration converges on a pass activating no token, the criterion of the last
+    /// sweep of the fixed-point iteration on prices that pass leaves unchanged.
+    ///
+    /// The iteration fails to converge past `max_iterations` times as many updates as there are
+    /// tokens, passes excepted, and stops at the deadline of the graph, checked every
+    /// `DEADLINE_CHECK_INTERVAL` times as many updates. An update producing a price that is not
+    /// positive and finite stops it at once, as in a sweep.
+    fn active_set(
+        &mut self,
+        tokens: impl Iterator<Item = usize> + Clone,
+        pinned: usize,
+    ) -> SolveOutcome {
+        let mut scratch = std::mem::take(&mut self.scratch.sweeps);
+        let outcome = self.active_set_in(tokens, pinned, &mut scratch);
+        self.scratch.sweeps = scratch;
+        outcome
+    }
+
+    /// Runs [`TokenGraph::active_set`] in the buffers of `scratch`.
+    fn active_set_in(
+        &mut self,
+        tokens: impl Iterator<Item = usize> + Clone,
+        pinned: usize,
+        scratch: &mut SweepScratch,
+    ) -> SolveOutcome {
+        let SweepScratch {
+            active_tokens,
+            is_active,
+            ..
+        } = scratch;
+        active_tokens.clear();
+        is_active.clear();
+        is_active.resize(self.nodes.len(), false);
+        let mut outcome = SolveOutcome {
+            output_amount: 0.0,
+            iterations: 0,
+            residual: 0.0,
+            converged: false,
+            invalid_price: None,
+            numerical_error: None,
+            damping_events: 0,
+            final_damping: 1.0,
+            deadline_reached: false,
+            bisected: false,
+            normalization_skipped: false,
+            token_updates: 0,
+        };
+
+        // The plain update of `token`, its relative change and whether it meets the tolerances,
+        // `None` for a token without active edge, which has no price to find
+        let update = |graph: &Self, token: usize| -> Option<(f64, f64, bool)> {
+            let q = graph.nodes[token].q;
+            let denom = compensated_sum(
+                graph
+                    .scaled_neighbors(token)
+                    .map(|(paired_token, liquidity)| liquidity / graph.nodes[paired_token].q),
+            );
+            if denom == 0.0 {
+                return None;
+            }
+            let node = &graph.nodes[token];
+            let plain_q = (node.total_reserve - node.frozen_reserve) / denom;
+            let met = (plain_q - q).abs()
+                < graph.solver.absolute_tolerance / graph.scales[token]
+                    + graph.solver.tolerance * q.abs();
+            Some((
+                plain_q,
+                (plain_q - q).abs() / q.abs().max(f64::MIN_POSITIVE),
+                met,
+            ))
+        };
+        let free_tokens = tokens
+            .clone()
+            .filter(|&token| token != pinned && !self.blacklisted[token])
+            .count();
+        let max_updates = self.solver.max_iterations.saturating_mul(free_tokens);
+        let check_interval = DEADLINE_CHECK_INTERVAL * free_tokens.max(1);
+
+        loop {
+            outcome.residual = 0.0;
+            for token in tokens.clone() {
+                if token == pinned || self.blacklisted[token] {
+                    continue;
+                }
+                if let Some((_, change, met)) = update(self, token) {
+                    outcome.residual = f64::max(outcome.residual, change);
+                    if !met {
+                        active_tokens.push_back(token);
+                        is_active[token] = true;
+                    }
+                }
+            }
+            if active_tokens.is_empty() {
+                outcome.converged = true;
+                break;
+            }
+
+            while let Some(token) = active_tokens.pop_front() {
+                is_active[token] = false;
+                let Some((plain_q, _, met)) = update(self, token) else {
+                    continue;
+                };
+                outcome.token_updates += 1;
+                if self.strict_math && !plain_q.is_finite() {
+                    outcome.residual = f64::INFINITY;
+                    outcome.numerical_error = Some((token, plain_q));
+                    break;
+                }
+                if !plain_q.is_finite() || plain_q <= 0.0 {
+                    outcome.residual = f64::INFINITY;
+                    outcome.invalid_price = Some((token, plain_q * self.scales[token]));
+                    break;
+                }
+                self.nodes[token].q = plain_q;
+                if !met {
+                    for (paired_token, _) in self.scaled_neighbors(token) {
+                        if paired_token != pinned && !is_active[paired_token] {
+                            active_tokens.push_back(paired_token);
+                            is_active[paired_token] = true;
+                        }
+                    }
+                }
+                if outcome.token_updates >= max_updates {
+                    break;
+                }
+                if outcome.token_updates.is_multiple_of(check_interval)
+                    && self
+                        .deadline
+                        .is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    outcome.deadline_reached = true;
+                    break;
+                }
+            }
+            if outcome.invalid_price.is_some()
+                || outcome.numerical_error.is_some()
+                || outcome.deadline_reached
+                || outcome.token_updates >= max_updates
+            {
+                break;
+            }
+        }
+        outcome.iterations = outcome.token_updates.div_ceil(free_tokens.max(1));
+        outcome
+    }
+
+    /// Returns the denominator of the update of `token` from the current prices, `∑ K(u, v) / q_v`
+    /// in the linear domain, its logarithm in the log domain, where `log_q` holds the logarithms
+    /// of the prices.
+    ///
+    /// In the linear domain, the dense rows holding enough pairs, see
+    /// [`PairwiseLiquidities::full_row`](super::liquidities::PairwiseLiquidities::full_row), are
+    /// summed in full without branching, the pairs without pools adding zero terms `0 / q_v`, the
+    /// prices being positive and finite during the sweeps, and the blacklisted tokens zero terms
+    /// as well: the compensated sum leaves its total and its compensation unchanged, bit for bit,
+    /// on adding zero to positive terms. A hop limit takes the general path.
+    fn sweep_denominator(&self, token: usize, log_q: &[f64]) -> f64 {
+        match self.domain {
+            SolverDomain::Linear => match self.liquidities.full_row(token) {
+                Some(row) if self.hop_limit.is_none() && !self.blacklisted[token] => self
+                    .neighbor_sum(row.iter().zip(&self.nodes).zip(&self.blacklisted).map(
+                        |((&liquidity, node), &blacklisted)| match blacklisted {
+                            true => 0.0,
+                            false => liquidity / node.q,
+                        },
+                    )),
+                _ => self.neighbors_denominator(token),
+            },
+            SolverDomain::Log => {
+                let terms = self
+                    .scaled_neighbors(token)
+                    .map(|(paired_token, liquidity)| liquidity.ln() - log_q[paired_token]);
+                let max = terms.clone().fold(f64::NEG_INFINITY, f64::max);
+                if !max.is_finite() {
+                    return max;
+                }
+                max + self.neighbor_sum(terms.map(|term| (term - max).exp())).ln()
+            }
+        }
+    }
+
+    /// Returns the denominator of the linear update of `token`, summed over its active neighbors
+    /// alone, see [`TokenGraph::sweep_denominator`].
+    fn neighbors_denominator(&self, token: usize) -> f64 {
+        self.neighbor_sum(
+            self.scaled_neighbors(token)
+                .map(|(paired_token, liquidity)| liquidity / self.nodes[paired_token].q),
+        )
+    }
+
+    /// Returns the denominators of the linear updates of every token at the current prices, by
+    /// token index: those of the sweeps if `full_rows`, summing the dense rows in full where
+    /// they do, those summed over the neighbors alone otherwise, see
+    /// [`TokenGraph::sweep_denominator`].
+    #[cfg(feature = "test-utils")]
+    pub(super) fn sweep_denominators(&self, full_rows: bool) -> Vec<f64> {
+        (0..self.nodes.len())
+            .map(|token| match full_rows {
+                true => self.sweep_denominator(token, &[]),
+                false => self.neighbors_denominator(token),
+            })
+            .collect()
+    }
+
+    /// Bisects the amount `Δ` of `output_token` whose removal leaves totals admitting an
+    /// equilibrium of `tokens` with every price free, and extracts it, after `solved`, the
+    /// outcome of a solver that exhausted its budget, see [`FallbackStrategy::Bisection`].
+    ///
+    /// The bracket starts at the amount the current prices would extract and widens by doubling
+    /// steps until the sign flips, then halves until its width is within the tolerance of its
+    /// middle, the reported residual. Each sign comes from [`TokenGraph::spectral_sign`], all
+    /// of them sharing a budget of `BISECTION_SWEEP_FACTOR` times `max_iterations` sweeps; a
+    /// sign lost in rounding ends the bisection at that amount, the spread of the ratios being
+    /// the residual then. Past that budget or the deadline of the graph, `solved` is returned
+    /// with the sweeps of the bisection added, the prices being those of the last sweep.
+    fn bisect_output(
+        &mut self,
+        tokens: &[usize],
+        output_token: usize,
+        solved: SolveOutcome,
+    ) -> SolveOutcome {
+        // Tokens without active edge have no price to find
+        let free_tokens = tokens
+            .iter()
+            .copied()
+            .filter(|&token| {
+                !self.blacklisted[token] && self.scaled_neighbors(token).next().is_some()
+            })
+            .collect::<Vec<_>>();
+        let node = &self.nodes[output_token];
+        let active_total = node.total_reserve - node.frozen_reserve;
+        let tolerance = self.solver.tolerance.max(f64::EPSILON);
+
+        // Amounts known to be below and above the equilibrium one
+        let (mut lower, mut upper) = (None, None);
+        let mut amount = self.pending_output(output_token);
+        let mut step = amount.abs().max(tolerance * active_total);
+        let mut sweeps = 0;
+        let bisected = loop {
+            match self.spectral_sign(
+                &free_tokens,
+                output_token,
+                active_total - amount,
+                &mut sweeps,
+            ) {
+                Some((Ordering::Greater, _)) => lower = Some(amount),
+                Some((Ordering::Less, _)) => upper = Some(amount),
+                Some((Ordering::Equal, spread)) => break Some((amount, spread)),
+                None => break None,
+            }
+            amount = match (lower, upper) {
+                (Some(lower), Some(upper)) => {
+                    let middle = 0.5 * (lower + upper);
+                    let width = (upper - lower) / middle.abs();
+                    if width <= tolerance || middle == lower || middle == upper {
+                        break Some((middle, width));
+                    }
+                    middle
+                }
+                // The output total must stay positive
+                (Some(lower), None) => (lower + step).min(0.5 * (lower + active_total)),
+                (None, Some(upper)) => upper - step,
+                (None, None) => unreachable!("every sign moves a bound"),
+            };
+            step *= 2.0;
+        };
+
+        let Some((amount, width)) = bisected else {
+            return SolveOutcome {
+                iterations: solved.iterations + sweeps,
+                deadline_reached: self.deadline.is_some_and(|d| Instant::now() >= d),
+                ..solved
+            };
+        };
+        let total_reserve = self.nodes[output_token].total_reserve;
+        let output_amount = if amount.abs() <= self.dust_tolerance * total_reserve {
+            0.0
+        } else {
+            self.nodes[output_token].total_reserve -= amount;
+            amount * self.reserve_scale(output_token)
+        };
+        SolveOutcome {
+            output_amount,
+            iterations: solved.iterations + sweeps,
+            residual: width,
+            converged: true,
+            invalid_price: None,
+            numerical_error: None,
+            damping_events: solved.damping_events,
+            final_damping: solved.final_damping,
+            deadline_reached: false,
+            bisected: true,
+            normalization_skipped: false,
+            token_updates: solved.token_updates,
+        }
+    }
+
+    /// Returns on which side of 1 lies the spectral radius `λ` of the fixed-point update of
+    /// `tokens`, every price free and the scaled active total of `output_token` set to
+    /// `output_total`: the update is monotone and homogeneous, so that the totals admit an
+    /// equilibrium for `λ = 1` only, and `λ` decreases with the amount removed from the output
+    /// token.
+    ///
+    /// `λ` lies between the smallest and the largest ratio of an update to its price, which
+    /// damped Jacobi sweeps `q ← √(q · G(q))` narrow down, the price of the output token kept
+    /// in place, until both ratios lie on the same side of 1, or within rounding of each other,
+    /// `Equal` being returned then, along with the spread of the ratios. Returns `None` once
+    /// `sweeps` reaches the budget of the bisection, or past the deadline of the graph.
+    fn spectral_sign(
+        &mut self,
+        tokens: &[usize],
+        output_token: usize,
+        output_total: f64,
+        sweeps: &mut usize,
+    ) -> Option<(Ordering, f64)> {
+        if output_total <= 0.0 {
+            return Some((Ordering::Less, f64::INFINITY));
+        }
+        let mut images = Vec::with_capacity(tokens.len());
+        loop {
+            images.clear();
+            let (mut lowest, mut highest) = (f64::INFINITY, 0.0_f64);
+            for &token in tokens {
+                let node = &self.nodes[token];
+                let total = match token == output_token {
+                    true => output_total,
+                    false => node.total_reserve - node.frozen_reserve,
+                };
+                let denom = compensated_sum(
+                    self.scaled_neighbors(token)
+                        .map(|(paired_token, liquidity)| liquidity / self.nodes[paired_token].q),
+                );
+                let image = total / denom;
+                if !image.is_finite() || image <= 0.0 {
+                    return None;
+                }
+                let ratio = image / node.q;
+                lowest = lowest.min(ratio);
+                highest = highest.max(ratio);
+                images.push(image);
+            }
+            if lowest > 1.0 {
+                return Some((Ordering::Greater, highest - lowest));
+            }
+            if highest < 1.0 {
+                return Some((Ordering::Less, highest - lowest));
+            }
+            if highest - lowest <= SPECTRAL_FLOOR {
+                return Some((Ordering::Equal, highest - lowest));
+            }
+            if *sweeps >= BISECTION_SWEEP_FACTOR * self.solver.max_iterations
+                || (sweeps.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+                    && self.deadline.is_some_and(|d| Instant::now() >= d))
+            {
+                return None;
+            }
+            *sweeps += 1;
+
+            let output_q = self.nodes[output_token].q;
+            let rescale = tokens
+                .iter()
+                .zip(&images)
+                .find(|&(&token, _)| token == output_token)
+                .map_or(1.0, |(_, &image)| (output_q / image).sqrt());
+            for (&token, &image) in tokens.iter().zip(&images) {
+                let q = &mut self.nodes[token].q;
+                *q = (*q * image).sqrt() * rescale;
+            }
+        }
+    }
+
+    /// Returns the scaled amount of `token` the current prices would extract, `T_f − T'_f`.
+    fn pending_output(&self, token: usize) -> f64 {
+        let node = &self.nodes[token];
+        node.total_reserve
+            - (self.scaled_implied_reserve(token, |token| self.nodes[token].q)
+                + node.frozen_reserve)
+    }
+
+    /// Returns `token` and the amount the current prices would extract from it if that amount is
+    /// not finite, one sum over the neighbors of `token` per solve.
+    fn check_output(&self, token: usize) -> Option<(usize, f64)> {
+        let amount = self.pending_output(token);
+        (!amount.is_finite()).then(|| (token, amount * self.reserve_scale(token)))
+    }
+
+    /// Sets the output token total to its post-equilibrium value `T'_f = ∑ K(f, v) * (q_f / q_v)`,
+    /// plus its frozen reserve, and returns the extracted amount `T_f − T'_f`.
+    ///
+    /// An amount within the dust tolerance of `T_f`, positive or negative, is floating-point
+    /// noise: the total is left untouched and zero is returned, so that repeated tiny trades
+    /// cannot drift the reserves. A larger negative amount is returned as is, for the router to
+    /// refuse the trade, see [`RouterError::NegativeOutput`](crate::error::RouterError).
+    fn extract_output(&mut self, output_token: usize) -> f64 {
+        let output_reserve = self.scaled_implied_reserve(output_token, |token| self.nodes[token].q)
+            + self.nodes[output_token].frozen_reserve;
+        let total_reserve = self.nodes[output_token].total_reserve;
+        let extracted_amount = total_reserve - output_reserve;
+        if extracted_amount.abs() <= self.dust_tolerance * total_reserve {
+            return 0.0;
+        }
+        self.nodes[output_token].total_reserve = output_reserve;
+        extracted_amount * self.reserve_scale(output_token)
+    }
+
+    /// Sets the price of `token` to the fixed point of its update, see
+    /// [`TokenGraph::plain_update`]. A token without active edge, or whose update is not a valid
+    /// price, keeps its price.
+    fn refresh_price(&mut self, token: usize) {
+        if let Some(q) = self.plain_update(token)
+            && q.is_finite()
+            && q > 0.0
+        {
+            self.nodes[token].q = q;
+        }
+    }
+
+    /// Returns the scaled price `q_u = T_u / ∑ K(u, v) / q_v` of `token` given its total and the
+    /// prices of its neighbors, or `None` for a token without active edge.
+    fn plain_update(&self, token: usize) -> Option<f64> {
+        let node = &self.nodes[token];
+        let active_reserve = node.total_reserve - node.frozen_reserve;
+        let denom = self.neighbor_sum(
+            self.scaled_neighbors(token)
+                .map(|(paired_token, liquidity)| liquidity / self.nodes[paired_token].q),
+        );
+        (denom != 0.0).then(|| active_reserve / denom)
+    }
+
+    /// Returns the total of `token` implied by the prices `q`: `∑ K(u, v) * (q_u / q_v)`.
+    pub(super) fn implied_reserve(&self, token: usize, q: impl Fn(usize) -> f64) -> f64 {
+        self.scaled_implied_reserve(token, |token| q(token) / self.scales[token])
+            * self.reserve_scale(token)
+    }
+
+    /// Returns the scaled total of `token` implied by the scaled prices `q`.
+    fn scaled_implied_reserve(&self, token: usize, q: impl Fn(usize) -> f64) -> f64 {
+        self.neighbor_sum(
+            self.scaled_neighbors(token)
+                .map(|(paired_token, liquidity)| liquidity * (q(token) / q(paired_token))),
+        )
+    }
+
+    /// Returns an iterator over the neighboring tokens and their associated geometric liquidities.
+    ///
+    /// Only pairs with non-zero liquidity are stored, so the solver never visits empty pairs.
+    /// Inactive edges, touching a blacklisted token or beyond the hop limit, are skipped as if
+    /// their liquidity were zero.
+    pub(super) fn neighbors(&self, token: usize) -> impl Iterator<Item = (usize, f64)> {
+        self.scaled_neighbors(token)
+            .map(move |(paired_token, liquidity)| {
+                (
+                    paired_token,
+                    liquidity * self.scales[token] * self.scales[paired_token],
+                )
+            })
+    }
+
+    /// Returns the layout of the liquidities in use, [`LiquidityStorage::Dense`] or
+    /// [`LiquidityStorage::Sparse`].
+    pub(super) fn liquidity_layout(&self) -> LiquidityStorage {
+        self.liquidities.layout()
+    }
+
+    /// Returns the number of values the solve buffers of the graph hold without reallocating, see
+    /// [`SolveScratch`].
+    #[cfg(test)]
+    pub(super) fn scratch_capacity(&self) -> usize {
+        self.scratch.capacity()
+    }
+
+    /// Returns the geometric liquidity of the pair `(u, v)`, zero if it has no pool or its edge is
+    /// inactive.
+    pub(super) fn liquidity(&self, u: usize, v: usize) -> f64 {
+        match self.is_edge_active(u, v) {
+            true => self.liquidities.get(u, v) * self.scales[u] * self.scales[v],
+            false => 0.0,
+        }
+    }
+
+    /// Iterates over every pair stored for `token` with its geometric liquidity, the inactive
+    /// ones included, for consistency checks.
+    pub(super) fn stored_liquidities(&self, token: usize) -> impl Iterator<Item = (usize, f64)> {
+        self.liquidities
+            .neighbors(token)
+            .map(move |(paired_token, liquidity)| {
+                (
+                    paired_token,
+                    liquidity * self.scales[token] * self.scales[paired_token],
+                )
+            })
+    }
+
+    /// Sums the terms of the neighbors of a token, or of the tokens of a component, in the
+    /// fixed-point sweeps and the extraction: compensated under Gauss–Seidel sweeps, whose
+    /// iterates depend on the numbering of the tokens anyway, and correctly rounded under Jacobi
+    /// sweeps, so that the sums, taken by token index, do not depend on that numbering either.
+    fn neighbor_sum(&self, terms: impl Iterator<Item = f64>) -> f64 {
+        match self.sweep_mode {
+            SweepMode::GaussSeidel => compensated_sum(terms),
+            SweepMode::Jacobi => exact_sum(terms),
+        }
+    }
+
+    /// Iterates over the neighbors of `token` like [`TokenGraph::neighbors`], with their scaled
+    /// geometric liquidities.
+    fn scaled_neighbors(&self, token: usize) -> impl Iterator<Item = (usize, f64)> + Clone {
+        self.liquidities
+            .neighbors(token)
+            .filter(move |&(paired_token, _)| self.is_edge_active(token, paired_token))
+    }
+
+    /// Renormalizes all root prices `q`, see [`PriceNormalization`]: every connected component
+    /// by the geometric mean of its prices, or all of them so that the reference token has price
+    /// 1.0.
+    ///
+    /// The normalization is skipped when the factor is zero or not finite, since dividing by it
+    /// would poison every other price, and only the prices that are positive and finite enter
+    /// the geometric mean. Returns whether every normalization took place.
+    fn normalize_prices(&mut self) -> bool {
+        match self.normalization {
+            PriceNormalization::GeometricMean => {
+                let mut normalized = true;
+                for component in self.connected_components() {
+                    normalized &= self.normalize_prices_of(&component);
+                }
+                normalized
+            }
+            PriceNormalization::ReferenceToken => {
+                self.normalize_prices_of(&(0..self.nodes.len()).collect::<Vec<_>>())
+            }
+        }
+    }
+
+    /// Renormalizes the root prices of `tokens` only, see [`TokenGraph::normalize_prices`], by
+    /// the geometric mean of their valid prices or by the price of the reference token. Returns
+    /// whether the normalization took place.
+    fn normalize_prices_of(&mut self, tokens: &[usize]) -> bool {
+        let factor = match self.normalization {
+            PriceNormalization::GeometricMean => {
+                let valid_prices = tokens
+                    .iter()
+                    .map(|&token| self.price(token))
+                    .filter(|&q| q.is_finite() && q > 0.0);
+                let count = valid_prices.clone().count();
+                (self.neighbor_sum(valid_prices.map(f64::ln)) / count as f64).exp()
+            }
+            PriceNormalization::ReferenceToken => self.price(self.reference_token),
+        };
+        if !(factor.is_finite() && factor > 0.0) {
+            log::warn!("skipping price normalization by a factor of {factor}");
+            return false;
+        }
+        for &token in tokens {
+            self.nodes[token].q /= factor;
+        }
+        true
+    }
+}
+
+/// Sums `terms` with Neumaier's compensated summation, so that the terms of the small pools of a
+/// token are not lost next to those of its large pools.
+///
+/// The rounding error of each addition is carried in a separate compensation, which makes the
+/// error of the sum independent of the number of terms and of their magnitude spread, instead of
+/// growing with both as with a plain `+=`.
+pub(super) fn compensated_sum(terms: impl Iterator<Item = f64>) -> f64 {
+    let mut sum = 0.0;
+    let mut compensation = 0.0;
+    for term in terms {
+        let total = sum + term;
+        compensation += match sum.abs() >= term.abs() {
+            true => (sum - total) + term,
+            false => (term - total) + sum,
+        };
+        sum = total;
+    }
+    sum + compensation
+}
+
+/// Returns `pools` sorted by tokens then reserves and virtual reserves, the order in which the totals and the
+/// liquidities are summed, so that their rounding does not depend on the order of the pools.
+fn aggregation_order(pools: &[UniV2Pool]) -> Vec<&UniV2Pool> {
+    let mut sorted = pools.iter().collect::<Vec<_>>();
+    sorted.sort_by(|pool, other| {
+        (pool.token0, pool.token1)
+            .cmp(&(other.token0, other.token1))
+            .then(pool.reserve0.total_cmp(&other.reserve0))
+            .then(pool.reserve1.total_cmp(&other.reserve1))
+            .then(pool.virtual_reserves.0.total_cmp(&other.virtual_reserves.0))
+            .then(pool.virtual_reserves.1.total_cmp(&other.virtual_reserves.1))
+    });
+    sorted
+}
+
+/// Returns the power of two closest to `√total`, by which the square-root prices of a token
+/// holding `total` across its pools are scaled down, one for an empty or non-finite total.
+fn token_scale(total: f64) -> f64 {
+    if !(total.is_finite() && total > 0.0) {
+        return 1.0;
+    }
+    // Bounded so that the square of the scale stays a normal float
+    let exponent = (total.log2() / 2.0).round().clamp(-511.0, 511.0);
+    2f64.powi(exponent as i32)
+}
+
+/// Largest number of partials of [`exact_sum`]: non-overlapping, they cannot outnumber the
+/// 2098 bits of the exponent range of `f64` divided by the 53 bits of its significand.
+const EXACT_SUM_PARTIALS: usize = 48;
+
+/// Returns the sum of `terms` correctly rounded, which does not depend on their order, by
+/// Shewchuk's algorithm as `math.fsum` of Python: the running sum is kept exactly as
+/// non-overlapping partials, rounded once at the end. Terms that are not finite, or partials
+/// overflowing, give the plain sum instead.
+pub(super) fn exact_sum(terms: impl Iterator<Item = f64>) -> f64 {
+    let mut partials = [0.0_f64; EXACT_SUM_PARTIALS];
+    let (mut len, mut plain_sum) = (0, 0.0);
+    for term in terms {
+        plain_sum += term;
+        let mut x = term;
+        let mut kept = 0;
+        for i in 0..len {
+            let mut y = partials[i];
+            if x.abs() < y.abs() {
+                std::mem::swap(&mut x, &mut y);
+            }
+            let high = x + y;
+            let low = y - (high - x);
+            if low != 0.0 {
+                partials[kept] = low;
+                kept += 1;
+            }
+            x = high;
+        }
+        if kept == EXACT_SUM_PARTIALS {
+            return plain_sum;
+        }
+        partials[kept] = x;
+        len = kept + 1;
+    }
+    if !plain_sum.is_finite() || partials[..len].iter().any(|partial| !partial.is_finite()) {
+        return plain_sum;
+    }
+
+    // Adds the partials from the largest down until the sum is inexact, then rounds half to even
+    // by the sign of the next partial
+    let Some((&last, rest)) = partials[..len].split_last() else {
+        return 0.0;
+    };
+    let (mut high, mut low) = (last, 0.0);
+    let mut remaining = rest.len();
+    while remaining > 0 {
+        let (x, y) = (high, rest[remaining - 1]);
+        remaining -= 1;
+        high = x + y;
+        low = y - (high - x);
+        if low != 0.0 {
+            break;
+        }
+    }
+    if remaining > 0
+        && ((low < 0.0 && rest[remaining - 1] < 0.0) || (low > 0.0 && rest[remaining - 1] > 0.0))
+    {
+        let y = 2.0 * low;
+        let x = high + y;
+        if y == x - high {
+            high = x;
+        }
+    }
+    high
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenGraph;
+    use crate::{
+        router::{FeeModel, LiquidityStorage, Router, SolverConfig},
+        uni_v2_pool::UniV2Pool,
+    };
+    use std::collections::HashMap;
+
+    /// Returns the graph of `pools` between `A` and `B`, solved to `tolerance`.
+    fn two_token_graph(pools: &[UniV2Pool], tolerance: f64) -> TokenGraph {
+        let token_index = HashMap::from([("A", 0), ("B", 1)]);
+        let mut graph = TokenGraph::from_pools(pools, &token_index, LiquidityStorage::Auto);
+        graph.set_solver(SolverConfig {
+            tolerance,
+            ..SolverConfig::default()
+        });
+        graph
+    }
+
+    /// Output of the trade of `amount` of `A` into `B` over `pools`, by the fixed-point iteration
+    /// which the closed form of two tokens bypasses.
+    fn iterative_output(pools: &[UniV2Pool], amount: f64) -> f64 {
+        let mut graph = two_token_graph(pools, 1e-15);
+        graph.nodes[0].total_reserve += amount / graph.reserve_scale(0);
+        assert!(graph.fixed_point(0..2, 1).converged);
+        graph.extract_output(1)
+    }
+
+    /// Output of the same trade in closed form, with the default tolerance.
+    fn closed_form_output(pools: &[UniV2Pool], amount: f64) -> f64 {
+        let outcome = two_token_graph(pools, SolverConfig::default().tolerance)
+            .apply_trade_and_solve(0, 1, amount);
+        assert!(outcome.converged);
+        assert_eq!(outcome.iterations, 1);
+        outcome.output_amount
+    }
+
+    #[test]
+    fn closed_form_of_two_tokens_is_the_combined_pool() {
+        // Two identical 10/40 pools: T_A = 20, T_B = 80, K = 40, and selling 20 A leaves
+        // T'_B = K² / (T_A + 20) = 40
+        let pools = [
+            UniV2Pool::new("A", "B", 10., 40.),
+            UniV2Pool::new("A", "B", 10., 40.),
+        ];
+        assert_eq!(closed_form_output(&pools, 20.), 40.);
+
+        // Parallel pools at different prices, the arbitrage between them going to the trade
+        let pools = [
+            UniV2Pool::new("A", "B", 10., 40.),
+            UniV2Pool::new("A", "B", 20., 30.),
+        ];
+        let k = (400f64).sqrt() + (600f64).sqrt();
+        let expected = 70. - k * k / 35.;
+        assert!((closed_form_output(&pools, 5.) / expected - 1.).abs() < 1e-14);
+    }
+
+    #[test]
+    fn closed_form_of_two_tokens_matches_the_iterative_solver() {
+        let pool_sets = [
+            vec![
+                UniV2Pool::new("A", "B", 10., 40.),
+                UniV2Pool::new("A", "B", 10., 40.),
+            ],
+            vec![
+                UniV2Pool::new("A", "B", 10., 40.),
+                UniV2Pool::new("A", "B", 20., 30.),
+            ],
+            vec![
+                UniV2Pool::new("A", "B", 3e24, 1.2e27),
+                UniV2Pool::new("A", "B", 1e21, 3.9e23),
+                UniV2Pool::new("B", "A", 5e26, 1.3e24),
+            ],
+        ];
+        for pools in &pool_sets {
+            let reserve = pools
+                .iter()
+                .map(|pool| {
+                    if pool.token0 == "A" {
+                        pool.reserve0
+                    } else {
+                        pool.reserve1
+                    }
+                })
+                .sum::<f64>();
+            for size_ratio in [1e-6, 1e-3, 0.1, 1., 10.] {
+                let amount = size_ratio * reserve;
+                let closed_form = closed_form_output(pools, amount);
+                let iterative = iterative_output(pools, amount);
+                assert!(
+                    (iterative / closed_form - 1.).abs() < 1e-9,
+                    "{size_ratio}: {iterative} against {closed_form}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pool_set_changes_lay_the_liquidities_out_on_the_next_solve() {
+        let pools = [
+            UniV2Pool::new("A", "B", 10., 40.),
+            UniV2Pool::new("B", "C", 20., 30.),
+            UniV2Pool::new("A", "C", 5., 10.),
+            UniV2Pool::new("C", "D", 50., 5.),
+        ];
+        // Trades charging fees solve over the pools, without the liquidities
+        let fee_free = |pools: Vec<UniV2Pool>| {
+            Router::builder(pools)
+                .fee_model(FeeModel::FeeFree)
+                .build()
+                .unwrap()
+        };
+        let mut router = fee_free(pools[..2].to_vec());
+        router.solve("A", "B", 1.).unwrap();
+        assert!(router.token_graph.liquidities.is_laid_out());
+
+        for pool in &pools[2..] {
+            router.add_pool(pool.clone()).unwrap();
+            assert!(!router.token_graph.liquidities.is_laid_out());
+        }
+        assert!(router.token_graph.are_connected(0, 3));
+        let id = router.pool_ids()[0].clone();
+        router.remove_pool(&id).unwrap();
+        assert!(!router.token_graph.liquidities.is_laid_out());
+        assert!(router.token_graph.are_connected(0, 3));
+
+        let mut rebuilt = fee_free(router.pools().to_vec());
+        let expected = rebuilt.solve("A", "D", 2.).unwrap();
+        let output_amount = router.solve("A", "D", 2.).unwrap();
+        assert!(router.token_graph.liquidities.is_laid_out());
+        assert!(
+            (output_amount / expected - 1.).abs() < 1e-9,
+            "{output_amount} against {expected}"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use crate::router::Router;
+
+/// Staleness below which the prices are taken as the equilibrium of the pools.
+const EQUILIBRIUM_STALENESS: f64 = 1e-9;
+
+impl<'l> Router<'l> {
+    /// Returns the value locked in each token, its total reserve across the pools, virtual
+    /// reserves left out, times its price in the reference token `(q_ref / q_u)²`, by decreasing value, without modifying the
+    /// router.
+    ///
+    /// Only the tokens connected to the reference token through routable pairs have a price in
+    /// it: tokens of other components and blacklisted tokens are left out rather than valued at
+    /// their stale square-root price. The prices are those of the current equilibrium, or of a
+    /// settled copy of the pools, see [`Router::settle`], when the pools are out of equilibrium
+    /// by more than `1e-9` in [`Router::staleness`], for instance before the first trade.
+    pub fn tvl(&self) -> Vec<(&'l str, f64)> {
+        let scratch;
+        let router = match self.settle_pending || self.staleness() > EQUILIBRIUM_STALENESS {
+            true => {
+                let mut copy = self.scratch_copy();
+                copy.settle_components();
+                scratch = copy;
+                &scratch
+            }
+            false => self,
+        };
+        let graph = &router.token_graph;
+        let reference_token = graph.reference_token();
+        let reference_q = graph.price(reference_token);
+
+        let mut values = graph
+            .connected_components()
+            .into_iter()
+            .find(|component| component.contains(&reference_token))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|token| {
+                let price = (reference_q / graph.price(token)).powi(2);
+                (self.tokens[token], graph.held_reserve(token) * price)
+            })
+            .collect::<Vec<_>>();
+        values.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        values
+    }
+
+    /// Returns the value locked in the pools connected to the reference token, in the reference
+    /// token: the sum of [`Router::tvl`].
+    pub fn total_tvl(&self) -> f64 {
+        self.tvl().into_iter().map(|(_, value)| value).sum()
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    router::{Router, exact::ExactSwap},
+    uni_v2_pool::PoolId,
+};
+
+use std::collections::HashMap;
+
+/// Largest relative deviation tolerated by [`Router::verify_last_solution`], against the
+/// reserves involved.
+const VERIFICATION_TOLERANCE: f64 = 1e-9;
+
+/// The last committed trade and the pools it moved.
+#[derive(Debug, Clone)]
+pub(super) struct LastTrade<'l> {
+    pub(super) input_token: &'l str,
+    pub(super) output_token: &'l str,
+    pub(super) input_amount: f64,
+    pub(super) output_amount: f64,
+    /// Pools whose reserves changed, in pool order
+    pub(super) pools: Vec<TradedPool<'l>>,
+}
+
+/// A pool moved by a trade, with its reserves before and after.
+#[derive(Debug, Clone)]
+pub(super) struct TradedPool<'l> {
+    pub(super) id: PoolId,
+    pub(super) token0: &'l str,
+    pub(super) token1: &'l str,
+    pub(super) before: (f64, f64),
+    pub(super) after: (f64, f64),
+    /// The move requoted through the exact math of the pool, if it has raw reserves
+    pub(super) exact: Option<ExactSwap>,
+}
+
+/// Outcome of [`Router::verify_last_solution`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationReport<'l> {
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    pub input_amount: f64,
+    pub output_amount: f64,
+    /// Number of pools whose reserves the trade moved
+    pub pools_checked: usize,
+    /// Pools whose moves are not a fee-free constant-product swap, in pool order
+    pub pool_violations: Vec<PoolViolation>,
+    /// Tokens whose reserve changes, summed over the pools, differ from the traded amounts, in
+    /// token index order
+    pub token_imbalances: Vec<TokenImbalance<'l>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolViolation {
+    pub pool: PoolId,
+    pub kind: PoolViolationKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoolViolationKind {
+    /// The product of the reserves decreased by this relative amount
+    InvariantDecreased { relative_decrease: f64 },
+    /// Both reserves moved in the same direction, by these amounts
+    NotASwap { delta0: f64, delta1: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenImbalance<'l> {
+    pub token: &'l str,
+    /// Net amount the pools should have received: the input amount for the input token, minus
+    /// the output amount for the output token, zero for the others
+    pub expected: f64,
+    /// Net amount the pools received
+    pub actual: f64,
+}
+
+/// Outcome of [`Router::last_trade_reconciliation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation<'l> {
+    pub input_token: &'l str,
+    pub output_token: &'l str,
+    pub input_amount: f64,
+    pub output_amount: f64,
+    /// Every token of the router in token index order, then the tokens of the trade dropped by
+    /// a compaction since
+    pub tokens: Vec<TokenReconciliation<'l>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenReconciliation<'l> {
+    pub token: &'l str,
+    /// Net amount the pools should have received: the input amount for the input token, minus
+    /// the output amount for the output token, zero for the others
+    pub expected: f64,
+    /// Net change of the reserves of the token, summed over all pools
+    pub actual: f64,
+    /// Reserve of the token across all pools before the trade, against which the difference is
+    /// measured
+    pub reserve: f64,
+}
+
+impl TokenReconciliation<'_> {
+    /// Returns whether the change of the reserves matches the traded amount, within a relative
+    /// `1e-9` of the reserve.
+    pub fn is_balanced(&self) -> bool {
+        (self.actual - self.expected).abs() <= VERIFICATION_TOLERANCE * self.reserve
+    }
+}
+
+impl Reconciliation<'_> {
+    /// Returns whether every token balances.
+    pub fn is_balanced(&self) -> bool {
+        self.tokens.iter().all(TokenReconciliation::is_balanced)
+    }
+
+    /// Iterates over the tokens that do not balance.
+    pub fn imbalances(&self) -> impl Iterator<Item = &TokenReconciliation<'_>> {
+        self.tokens.iter().filter(|token| !token.is_balanced())
+    }
+}
+
+impl VerificationReport<'_> {
+    /// Returns whether the trade passed every check.
+    pub fn is_valid(&self) -> bool {
+        self.pool_violations.is_empty() && self.token_imbalances.is_empty()
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Checks the last committed trade against the individual pools rather than the aggregated
+    /// model: every moved pool must have kept its invariant `x · y` (equality is expected
+    /// without fee, growth under
+    /// [`FeeModel::Charged`](crate::router::FeeModel::Charged)) through a single swap, one reserve
+    /// growing and the other shrinking, and the reserve changes summed per token must match the
+    /// traded amounts.
+    ///
+    /// Deviations within a relative `1e-9` of the reserves involved are floating-point noise and
+    /// are not reported. Returns `None` before the first trade.
+    pub fn verify_last_solution(&self) -> Option<VerificationReport<'l>> {
+        let trade = self.last_trade.as_ref()?;
+
+        let mut pool_violations = Vec::new();
+        // Net received amount and reserve scale of every token; the traded tokens are always
+        // checked, even if no pool absorbed their amounts
+        let mut deltas = [trade.input_token, trade.output_token]
+            .into_iter()
+            .map(|token| (token, (0.0, 0.0)))
+            .collect::<HashMap<&'l str, (f64, f64)>>();
+        for pool in &trade.pools {
+            let (before0, before1) = pool.before;
+            let (after0, after1) = pool.after;
+            let (delta0, delta1) = (after0 - before0, after1 - before1);
+
+            let relative_decrease = 1.0 - (after0 * after1) / (before0 * before1);
+            if relative_decrease > VERIFICATION_TOLERANCE {
+                pool_violations.push(PoolViolation {
+                    pool: pool.id.clone(),
+                    kind: PoolViolationKind::InvariantDecreased { relative_decrease },
+                });
+            }
+            let significant0 = delta0.abs() > VERIFICATION_TOLERANCE * before0;
+            let significant1 = delta1.abs() > VERIFICATION_TOLERANCE * before1;
+            if significant0 && significant1 && delta0.signum() == delta1.signum() {
+                pool_violations.push(PoolViolation {
+                    pool: pool.id.clone(),
+                    kind: PoolViolationKind::NotASwap { delta0, delta1 },
+                });
+            }
+
+            for (token, delta, before) in [
+                (pool.token0, delta0, before0),
+                (pool.token1, delta1, before1),
+            ] {
+                let entry = deltas.entry(token).or_insert((0.0, 0.0));
+                entry.0 += delta;
+                entry.1 += before;
+            }
+        }
+
+        let mut token_imbalances = deltas
+            .into_iter()
+            .filter_map(|(token, (actual, scale))| {
+                let expected = if token == trade.input_token {
+                    trade.input_amount
+                } else if token == trade.output_token {
+                    -trade.output_amount
+                } else {
+                    0.0
+                };
+                ((actual - expected).abs() > VERIFICATION_TOLERANCE * scale).then_some(
+                    TokenImbalance {
+                        token,
+                        expected,
+                        actual,
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        // Tokens dropped by a compaction since the trade come last
+        token_imbalances.sort_by_key(|imbalance| {
+            self.token_index
+                .get(imbalance.token)
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+
+        Some(VerificationReport {
+            input_token: trade.input_token,
+            output_token: trade.output_token,
+            input_amount: trade.input_amount,
+            output_amount: trade.output_amount,
+            pools_checked: trade.pools.len(),
+            pool_violations,
+            token_imbalances,
+        })
+    }
+
+    /// Reconciles the last committed trade with the reserves of the whole router: summed over
+    /// all pools, the input token must have grown by the input amount, the output token shrunk
+    /// by the output amount, and every other token kept its total. Returns `None` before the
+    /// first trade.
+    ///
+    /// With the `paranoid` feature, debug builds assert the reconciliation after every trade.
+    pub fn last_trade_reconciliation(&self) -> Option<Reconciliation<'l>> {
+        let trade = self.last_trade.as_ref()?;
+
+        let mut deltas = HashMap::<&'l str, f64>::new();
+        let mut reserves = HashMap::<&'l str, f64>::new();
+        for pool in &trade.pools {
+            for (token, before, after) in [
+                (pool.token0, pool.before.0, pool.after.0),
+                (pool.token1, pool.before.1, pool.after.1),
+            ] {
+                *deltas.entry(token).or_insert(0.0) += after - before;
+                *reserves.entry(token).or_insert(0.0) += before;
+            }
+        }
+        // Indexed tokens are measured against all their pools rather than the moved ones only
+        let mut pool_sums = vec![0.0; self.tokens.len()];
+        for pool in &self.pools {
+            pool_sums[self.token_index[pool.token0]] += pool.reserve0;
+            pool_sums[self.token_index[pool.token1]] += pool.reserve1;
+        }
+
+        let expected = |token: &str| {
+            if token == trade.input_token {
+                trade.input_amount
+            } else if token == trade.output_token {
+                -trade.output_amount
+            } else {
+                0.0
+            }
+        };
+        let mut tokens = self
+            .tokens
+            .iter()
+            .zip(pool_sums)
+            .map(|(&token, pool_sum)| {
+                let actual = deltas.remove(token).unwrap_or(0.0);
+                TokenReconciliation {
+                    token,
+                    expected: expected(token),
+                    actual,
+                    reserve: pool_sum - actual,
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut dropped = deltas.into_iter().collect::<Vec<_>>();
+        dropped.sort_by_key(|&(token, _)| token);
+        tokens.extend(
+            dropped
+                .into_iter()
+                .map(|(token, actual)| TokenReconciliation {
+                    token,
+                    expected: expected(token),
+                    actual,
+                    reserve: reserves[token],
+                }),
+        );
+
+        Some(Reconciliation {
+            input_token: trade.input_token,
+            output_token: trade.output_token,
+            input_amount: trade.input_amount,
+            output_amount: trade.output_amount,
+            tokens,
+        })
+    }
+
+    /// Asserts in debug builds that the last trade reconciles, see
+    /// [`Router::last_trade_reconciliation`].
+    #[cfg(feature = "paranoid")]
+    pub(super) fn assert_reconciled(&self) {
+        if let Some(reconciliation) = self.last_trade_reconciliation()
+            && let Some(imbalance) = reconciliation.imbalances().next()
+        {
+            debug_assert!(
+                false,
+                "the pools of {} received {} instead of {}",
+                imbalance.token, imbalance.actual, imbalance.expected
+            );
+        }
+    }
+}
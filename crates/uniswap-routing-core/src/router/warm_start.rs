@@ -0,0 +1,314 @@
+use crate::{
+    error::RouterError,
+    pool::Pool,
+    router::{
+        Router, WarmStartPolicy, capture::CapturedOperation, check_pools, parallel::map_ordered,
+        pool_id, token_graph::TokenGraph,
+    },
+};
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// How the prices of a replaced or merged state were initialized, per token of the new state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmStartReport<'l> {
+    /// One entry per token of the new state, in token index order
+    pub tokens: Vec<TokenWarmStart<'l>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenWarmStart<'l> {
+    pub token: &'l str,
+    pub decision: WarmStartDecision,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WarmStartDecision {
+    /// The converged price of the previous state was kept
+    Transferred {
+        /// New over previous sum of the geometric liquidities of the token
+        liquidity_ratio: f64,
+        /// Jaccard index of the previous and new sets of paired tokens
+        neighbor_overlap: f64,
+    },
+    /// The neighborhood changed too much for the previous price to be trusted, the price was
+    /// estimated from the spot prices along a spanning tree
+    Reseeded {
+        liquidity_ratio: f64,
+        neighbor_overlap: f64,
+    },
+    /// The token is new, its price was estimated from the spot prices along a spanning tree
+    New,
+}
+
+impl WarmStartReport<'_> {
+    /// Returns the number of tokens whose previous price was kept.
+    pub fn transferred(&self) -> usize {
+        self.tokens
+            .iter()
+            .filter(|token| matches!(token.decision, WarmStartDecision::Transferred { .. }))
+            .count()
+    }
+}
+
+/// A new state built off the live router by [`Router::prepare_replacement`], to be swapped in
+/// with [`Router::replace`].
+#[derive(Debug)]
+pub struct PreparedReplacement<'l> {
+    router: Router<'l>,
+    report: WarmStartReport<'l>,
+}
+
+impl<'l> PreparedReplacement<'l> {
+    pub fn report(&self) -> &WarmStartReport<'l> {
+        &self.report
+    }
+}
+
+impl<'l> Router<'l> {
+    /// Builds a state over `pools`, of any type, see [`Pool::to_uni_v2`], with the configuration
+    /// of this router, whose prices are warm-started from the current ones according to
+    /// [`RouterConfig::warm_start`](crate::router::RouterConfig::warm_start).
+    ///
+    /// The router itself is untouched until [`Router::replace`], so the preparation can run
+    /// while quotes are still served. Fails if the configuration names a token absent from
+    /// `pools`.
+    pub fn prepare_replacement(
+        &self,
+        pools: Vec<impl Pool>,
+    ) -> Result<PreparedReplacement<'l>, RouterError> {
+        let pools = pools.iter().map(Pool::to_uni_v2).collect();
+        let mut config = self.config.clone();
+        config.capture_session = false;
+        config.publish_deltas = false;
+        let mut router = Router::from_config(pools, config)?;
+        let report = router.transfer_warm_start(&self.token_graph, &self.tokens);
+        Ok(PreparedReplacement { router, report })
+    }
+
+    /// Swaps in a prepared state. Pools without an identifier of their own get fresh ones and
+    /// token ids are invalidated, as by a [`Router::compact`]; the trade history and the event
+    /// journal are kept.
+    pub fn replace(&mut self, prepared: PreparedReplacement<'l>) -> WarmStartReport<'l> {
+        let PreparedReplacement { router, report } = prepared;
+
+        self.pool_ids = router
+            .pools
+            .iter()
+            .zip(self.next_pool_id..)
+            .map(|(pool, n)| pool_id(pool, n))
+            .collect();
+        self.next_pool_id += router.pools.len() as u64;
+        if let Some(capture) = &mut self.capture {
+            capture.push(CapturedOperation::Replace {
+                pools: self
+                    .pool_ids
+                    .iter()
+                    .zip(&router.pools)
+                    .map(Into::into)
+                    .collect(),
+            });
+        }
+        self.tokens = router.tokens;
+        self.token_index = router.token_index;
+        self.pools = router.pools;
+        self.token_graph = router.token_graph;
+        self.latest_generation += 1;
+        self.generation = self.latest_generation;
+        self.publish_version();
+
+        report
+    }
+
+    /// Adds `pools`, of any type, see [`Pool::to_uni_v2`], to the router at once, warm-starting
+    /// the prices of the tokens whose neighborhood changed according to
+    /// [`RouterConfig::warm_start`](crate::router::RouterConfig::warm_start). Token ids stay
+    /// valid. Fails with [`RouterError::InvalidPool`], adding none of the pools, if one of them
+    /// is invalid, see [`UniV2Pool::validate`](crate::UniV2Pool::validate), or with
+    /// [`RouterError::DuplicatePool`] if one of them has the identifier of another pool.
+    pub fn merge(&mut self, pools: Vec<impl Pool>) -> Result<WarmStartReport<'l>, RouterError> {
+        let pools = pools.iter().map(Pool::to_uni_v2).collect::<Vec<_>>();
+        check_pools(&pools, &self.pool_ids, self.next_pool_id)?;
+        let previous_graph = self.token_graph.clone();
+        let previous_tokens = self.tokens.clone();
+
+        let ids = pools
+            .into_iter()
+            .map(|pool| self.register_pool(pool))
+            .collect::<Vec<_>>();
+        if let Some(capture) = &mut self.capture {
+            let first = self.pools.len() - ids.len();
+            capture.push(CapturedOperation::Merge {
+                pools: ids
+                    .iter()
+                    .zip(&self.pools[first..])
+                    .map(Into::into)
+                    .collect(),
+            });
+        }
+        self.rebuild_token_graph();
+
+        let report = self.transfer_warm_start(&previous_graph, &previous_tokens);
+        self.publish_version();
+        Ok(report)
+    }
+
+    /// Initializes the prices of the token graph from those of a previous state.
+    ///
+    /// A token keeps its previous price when its total liquidity changed by less than the
+    /// configured factor and its paired tokens mostly stayed the same. The other tokens are
+    /// seeded breadth-first from the kept ones, each from an already seeded neighbor through the
+    /// spot price of their aggregated pools, `q_v = q_u · √(r_v / r_u)`; components without any
+    /// kept price are seeded from their smallest token.
+    ///
+    /// Connected components are processed independently, in parallel with the `parallel`
+    /// feature.
+    fn transfer_warm_start(
+        &mut self,
+        previous: &TokenGraph,
+        previous_tokens: &[&'l str],
+    ) -> WarmStartReport<'l> {
+        let previous = PreviousState {
+            graph: previous,
+            tokens: previous_tokens,
+            index: previous_tokens
+                .iter()
+                .enumerate()
+                .map(|(index, &token)| (token, index))
+                .collect(),
+        };
+        let policy = self.config.warm_start;
+        let graph = &self.token_graph;
+        let tokens = &self.tokens;
+        let pairs = self.aggregated_pairs();
+
+        let warm_starts = map_ordered(graph.connected_components(), |component| {
+            let mut prices = HashMap::with_capacity(component.len());
+            let mut queue = VecDeque::new();
+            let decisions = component
+                .iter()
+                .map(|&token| {
+                    let (decision, price) = previous.decision(graph, tokens, token, policy);
+                    if let Some(q) = price {
+                        prices.insert(token, q);
+                        queue.push_back(token);
+                    }
+                    decision
+                })
+                .collect::<Vec<_>>();
+
+            // The kept prices are spread first, then the component from its root if left unseeded
+            for root in std::iter::once(None).chain(component.iter().copied().map(Some)) {
+                if let Some(root) = root {
+                    if prices.contains_key(&root) {
+                        continue;
+                    }
+                    let q = previous
+                        .index
+                        .get(tokens[root])
+                        .map_or(1.0, |&previous_token| previous.graph.price(previous_token));
+                    prices.insert(root, q);
+                    queue.push_back(root);
+                }
+                while let Some(token) = queue.pop_front() {
+                    let q = prices[&token];
+                    for &(paired_token, reserve, paired_reserve) in &pairs[token] {
+                        prices.entry(paired_token).or_insert_with(|| {
+                            queue.push_back(paired_token);
+                            q * (paired_reserve / reserve).sqrt()
+                        });
+                    }
+                }
+            }
+
+            component
+                .into_iter()
+                .zip(decisions)
+                .map(|(token, decision)| (token, decision, prices[&token]))
+                .collect::<Vec<_>>()
+        });
+
+        let mut decisions = vec![WarmStartDecision::New; self.tokens.len()];
+        for (token, decision, q) in warm_starts.into_iter().flatten() {
+            self.token_graph.set_price(token, q);
+            decisions[token] = decision;
+        }
+        WarmStartReport {
+            tokens: self
+                .tokens
+                .iter()
+                .zip(decisions)
+                .map(|(&token, decision)| TokenWarmStart { token, decision })
+                .collect(),
+        }
+    }
+}
+
+/// Prices and pairs of the state a warm start is transferred from.
+struct PreviousState<'a, 'l> {
+    graph: &'a TokenGraph,
+    tokens: &'a [&'l str],
+    /// Index of every token in the previous state
+    index: HashMap<&'l str, usize>,
+}
+
+impl PreviousState<'_, '_> {
+    /// Decides whether the previous price of `token`, a token of `graph`, can be kept, returning
+    /// that price if so.
+    fn decision(
+        &self,
+        graph: &TokenGraph,
+        tokens: &[&str],
+        token: usize,
+        policy: WarmStartPolicy,
+    ) -> (WarmStartDecision, Option<f64>) {
+        let Some(&previous_token) = self.index.get(tokens[token]) else {
+            return (WarmStartDecision::New, None);
+        };
+        let liquidity_ratio = liquidity_ratio(
+            graph.total_liquidity(token),
+            self.graph.total_liquidity(previous_token),
+        );
+        let neighbors = graph
+            .neighbors(token)
+            .map(|(paired_token, _)| tokens[paired_token])
+            .collect::<HashSet<_>>();
+        let previous_neighbors = self
+            .graph
+            .neighbors(previous_token)
+            .map(|(paired_token, _)| self.tokens[paired_token])
+            .collect::<HashSet<_>>();
+        let union = neighbors.union(&previous_neighbors).count();
+        let neighbor_overlap = match union {
+            0 => 1.0,
+            _ => neighbors.intersection(&previous_neighbors).count() as f64 / union as f64,
+        };
+
+        match liquidity_ratio.max(1.0 / liquidity_ratio) <= policy.max_liquidity_change
+            && neighbor_overlap >= policy.min_neighbor_overlap
+        {
+            true => (
+                WarmStartDecision::Transferred {
+                    liquidity_ratio,
+                    neighbor_overlap,
+                },
+                Some(self.graph.price(previous_token)),
+            ),
+            false => (
+                WarmStartDecision::Reseeded {
+                    liquidity_ratio,
+                    neighbor_overlap,
+                },
+                None,
+            ),
+        }
+    }
+}
+
+/// New over previous liquidity, 1 when both are zero.
+fn liquidity_ratio(liquidity: f64, previous_liquidity: f64) -> f64 {
+    match liquidity == 0.0 && previous_liquidity == 0.0 {
+        true => 1.0,
+        false => liquidity / previous_liquidity,
+    }
+}
@@ -0,0 +1,485 @@
+//! Builder-style scenarios for routing tests, behind the `test-utils` feature.
+//!
+//! A [`Scenario`] lists pools, operations and expectations, then [`Scenario::run`] builds the
+//! router, executes the steps in order and panics at the first failed step with the whole
+//! script, each executed step annotated with what it observed:
+//!
+//! ```text
+//! scenario failed at step 4: output 9920.546077802079 is not within 1 of 19500
+//!   1. pool ETH/USDC 2000 2000000
+//!   2. pool ETH/DAI 1000 900000
+//!   3. trade ETH->USDC 10                  => 9920.546077802079
+//! > 4. expect output near 19500 ± 1
+//!   5. expect conservation
+//! ```
+//!
+//! Pools listed before the first other step form the initial pool set, later ones are added to
+//! the running router. Token names are leaked, as scenarios are meant for tests.
+
+use crate::{
+    error::RouterError,
+    router::{Router, RouterConfig},
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+use std::{collections::HashMap, fmt};
+
+/// Script of a routing test, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct Scenario {
+    config: RouterConfig,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Pool {
+        name: Option<String>,
+        pair: String,
+        reserve0: f64,
+        reserve1: f64,
+    },
+    Settle,
+    Trade {
+        route: String,
+        amount: f64,
+    },
+    Quote {
+        route: String,
+        amount: f64,
+    },
+    ExpectOutputNear {
+        expected: f64,
+        tolerance: f64,
+    },
+    ExpectFailure,
+    ExpectSpotPriceNear {
+        pool: String,
+        input_token: String,
+        expected: f64,
+        relative_tolerance: f64,
+    },
+    ExpectReservesNear {
+        pool: String,
+        reserve0: f64,
+        reserve1: f64,
+        relative_tolerance: f64,
+    },
+    ExpectConservation,
+}
+
+/// Relative tolerance of [`Scenario::expect_conservation`].
+const CONSERVATION_TOLERANCE: f64 = 1e-9;
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures the router the scenario builds.
+    pub fn config(mut self, config: RouterConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Adds a pool between the two tokens of `pair`, written `"TOKEN0/TOKEN1"`.
+    pub fn pool(self, pair: &str, reserve0: f64, reserve1: f64) -> Self {
+        self.push_pool(None, pair, reserve0, reserve1)
+    }
+
+    /// Adds a pool that later steps refer to as `name`, like a pool address.
+    pub fn named_pool(self, name: &str, pair: &str, reserve0: f64, reserve1: f64) -> Self {
+        self.push_pool(Some(name.to_string()), pair, reserve0, reserve1)
+    }
+
+    /// Settles the router, see [`Router::settle`].
+    pub fn settle(self) -> Self {
+        self.push(Step::Settle)
+    }
+
+    /// Commits a trade written `"INPUT->OUTPUT"`, see [`Router::solve`]. Its output is the one
+    /// output expectations check.
+    pub fn trade(self, route: &str, amount: f64) -> Self {
+        self.push(Step::Trade {
+            route: route.to_string(),
+            amount,
+        })
+    }
+
+    /// Quotes a trade written `"INPUT->OUTPUT"` without committing it, see [`Router::quote`].
+    /// Its output is the one output expectations check.
+    pub fn quote(self, route: &str, amount: f64) -> Self {
+        self.push(Step::Quote {
+            route: route.to_string(),
+            amount,
+        })
+    }
+
+    /// Expects the last trade or quote to have succeeded with an output within `tolerance` of
+    /// `expected`.
+    pub fn expect_output_near(self, expected: f64, tolerance: f64) -> Self {
+        self.push(Step::ExpectOutputNear {
+            expected,
+            tolerance,
+        })
+    }
+
+    /// Expects the last trade or quote to have failed.
+    pub fn expect_failure(self) -> Self {
+        self.push(Step::ExpectFailure)
+    }
+
+    /// Expects the spot price of `pool` for `input_token`, in units of the input token per unit
+    /// of the other, to be within `relative_tolerance` of `expected`.
+    pub fn expect_spot_price_near(
+        self,
+        pool: &str,
+        input_token: &str,
+        expected: f64,
+        relative_tolerance: f64,
+    ) -> Self {
+        self.push(Step::ExpectSpotPriceNear {
+            pool: pool.to_string(),
+            input_token: input_token.to_string(),
+            expected,
+            relative_tolerance,
+        })
+    }
+
+    /// Expects the reserves of `pool` to be within `relative_tolerance` of the given ones.
+    pub fn expect_reserves_near(
+        self,
+        pool: &str,
+        reserve0: f64,
+        reserve1: f64,
+        relative_tolerance: f64,
+    ) -> Self {
+        self.push(Step::ExpectReservesNear {
+            pool: pool.to_string(),
+            reserve0,
+            reserve1,
+            relative_tolerance,
+        })
+    }
+
+    /// Expects the pools to hold, for every token, what the scenario put in: the initial and
+    /// added reserves plus the trade inputs, minus the trade outputs and the settled arbitrage.
+    pub fn expect_conservation(self) -> Self {
+        self.push(Step::ExpectConservation)
+    }
+
+    /// Builds the router, executes the steps and returns the router in its final state.
+    ///
+    /// # Panics
+    ///
+    /// At the first failed step, with the annotated script of the scenario.
+    pub fn run(self) -> Router<'static> {
+        let mut run = Run::default();
+        let initial_pools = self
+            .steps
+            .iter()
+            .take_while(|step| matches!(step, Step::Pool { .. }))
+            .count();
+        let mut router = match run.build(&self.steps[..initial_pools], self.config.clone()) {
+            Ok(router) => router,
+            Err(message) => self.fail(&run, initial_pools.saturating_sub(1), &message),
+        };
+        for (position, step) in self.steps.iter().enumerate().skip(initial_pools) {
+            if let Err(message) = run.execute(&mut router, step) {
+                self.fail(&run, position, &message);
+            }
+        }
+        router
+    }
+
+    fn push_pool(self, name: Option<String>, pair: &str, reserve0: f64, reserve1: f64) -> Self {
+        self.push(Step::Pool {
+            name,
+            pair: pair.to_string(),
+            reserve0,
+            reserve1,
+        })
+    }
+
+    fn push(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    fn fail(&self, run: &Run, failed: usize, message: &str) -> ! {
+        let mut transcript = format!("scenario failed at step {}: {message}\n", failed + 1);
+        for (position, step) in self.steps.iter().enumerate() {
+            let marker = if position == failed { '>' } else { ' ' };
+            let line = format!("{marker} {}. {step}", position + 1);
+            match run.observations.get(position) {
+                Some(observation) if !observation.is_empty() => {
+                    transcript += &format!("{line:<40} => {observation}\n")
+                }
+                _ => transcript += &format!("{line}\n"),
+            }
+        }
+        panic!("{transcript}");
+    }
+}
+
+/// State of a scenario being executed.
+#[derive(Default)]
+struct Run {
+    /// What each executed step observed, empty for the steps with nothing to report
+    observations: Vec<String>,
+    pool_names: HashMap<String, PoolId>,
+    last_output: Option<Result<f64, RouterError>>,
+    /// Amount of every token the pools should hold
+    ledger: HashMap<&'static str, f64>,
+    /// Leaked token names, which pools borrow for `'static`
+    tokens: HashMap<String, &'static str>,
+}
+
+impl Run {
+    fn build(&mut self, pools: &[Step], config: RouterConfig) -> Result<Router<'static>, String> {
+        let mut names = Vec::new();
+        let mut initial = Vec::new();
+        for step in pools {
+            let (name, pool) = self.pool(step)?;
+            names.push(name);
+            initial.push(pool);
+            self.observations.push(String::new());
+        }
+        let router = Router::builder(initial)
+            .config(config)
+            .build()
+            .map_err(|error| format!("cannot build the router: {error}"))?;
+        for (name, id) in names.into_iter().zip(router.pool_ids()) {
+            if let Some(name) = name {
+                self.pool_names.insert(name, id.clone());
+            }
+        }
+        Ok(router)
+    }
+
+    fn execute(&mut self, router: &mut Router<'static>, step: &Step) -> Result<(), String> {
+        let observation = match step {
+            Step::Pool { .. } => {
+                let (name, pool) = self.pool(step)?;
+                let id = router.add_pool(pool).map_err(|error| error.to_string())?;
+                if let Some(name) = name {
+                    self.pool_names.insert(name, id.clone());
+                }
+                id.to_string()
+            }
+            Step::Settle => {
+                let settlement = router.settle();
+                for component in &settlement.components {
+                    self.credit(component.sink_token, -component.extracted_amount);
+                }
+                settlement
+                    .components
+                    .iter()
+                    .map(|component| {
+                        format!("{} {}", component.extracted_amount, component.sink_token)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+            Step::Trade { route, amount } => {
+                let (input_token, output_token) = split(route, "->")?;
+                let result = router.solve(input_token, output_token, *amount);
+                if let Ok(output_amount) = result {
+                    self.credit(input_token, *amount);
+                    self.credit(output_token, -output_amount);
+                }
+                self.record_output(result)
+            }
+            Step::Quote { route, amount } => {
+                let (input_token, output_token) = split(route, "->")?;
+                let result = router.quote(input_token, output_token, *amount);
+                self.record_output(result)
+            }
+            Step::ExpectOutputNear {
+                expected,
+                tolerance,
+            } => match self.last_output.clone() {
+                Some(Ok(output)) if (output - expected).abs() <= *tolerance => String::new(),
+                Some(Ok(output)) => {
+                    return Err(format!(
+                        "output {output} is not within {tolerance} of {expected}"
+                    ));
+                }
+                Some(Err(error)) => return Err(format!("the last trade failed: {error}")),
+                None => return Err("no trade or quote before the expectation".to_string()),
+            },
+            Step::ExpectFailure => match &self.last_output {
+                Some(Err(_)) => String::new(),
+                Some(Ok(output)) => return Err(format!("the last trade succeeded with {output}")),
+                None => return Err("no trade or quote before the expectation".to_string()),
+            },
+            Step::ExpectSpotPriceNear {
+                pool,
+                input_token,
+                expected,
+                relative_tolerance,
+            } => {
+                let pool = self.find_pool(router, pool)?;
+                if input_token != pool.token0 && input_token != pool.token1 {
+                    return Err(format!("{input_token} is not a token of the pool"));
+                }
+                let price = pool.get_spot_price(input_token);
+                check_near("spot price", price, *expected, *relative_tolerance)?;
+                price.to_string()
+            }
+            Step::ExpectReservesNear {
+                pool,
+                reserve0,
+                reserve1,
+                relative_tolerance,
+            } => {
+                let pool = self.find_pool(router, pool)?;
+                check_near("reserve0", pool.reserve0, *reserve0, *relative_tolerance)?;
+                check_near("reserve1", pool.reserve1, *reserve1, *relative_tolerance)?;
+                format!("{} {}", pool.reserve0, pool.reserve1)
+            }
+            Step::ExpectConservation => {
+                let mut held = HashMap::<&str, f64>::new();
+                for pool in router.pools() {
+                    *held.entry(pool.token0).or_default() += pool.reserve0;
+                    *held.entry(pool.token1).or_default() += pool.reserve1;
+                }
+                for (&token, &expected) in &self.ledger {
+                    let amount = held.get(token).copied().unwrap_or(0.0);
+                    check_near(token, amount, expected, CONSERVATION_TOLERANCE)?;
+                }
+                String::new()
+            }
+        };
+        self.observations.push(observation);
+        Ok(())
+    }
+
+    /// Builds the pool of a pool step and credits its reserves to the ledger.
+    fn pool(&mut self, step: &Step) -> Result<(Option<String>, UniV2Pool), String> {
+        let Step::Pool {
+            name,
+            pair,
+            reserve0,
+            reserve1,
+        } = step
+        else {
+            unreachable!("only pool steps describe pools");
+        };
+        let (token0, token1) = split(pair, "/")?;
+        let (token0, token1) = (self.token(token0), self.token(token1));
+        self.credit(token0, *reserve0);
+        self.credit(token1, *reserve1);
+        Ok((
+            name.clone(),
+            UniV2Pool::new(token0, token1, *reserve0, *reserve1),
+        ))
+    }
+
+    /// Adds `amount` to what the pools should hold of `token`.
+    fn credit(&mut self, token: &str, amount: f64) {
+        let token = self.token(token);
+        *self.ledger.entry(token).or_default() += amount;
+    }
+
+    fn token(&mut self, name: &str) -> &'static str {
+        self.tokens
+            .entry(name.to_string())
+            .or_insert_with(|| Box::leak(name.to_string().into_boxed_str()))
+    }
+
+    fn record_output(&mut self, result: Result<f64, RouterError>) -> String {
+        let observation = match &result {
+            Ok(output) => output.to_string(),
+            Err(error) => format!("error: {error}"),
+        };
+        self.last_output = Some(result);
+        observation
+    }
+
+    /// Finds a pool by scenario name, or by router identifier for the unnamed ones.
+    fn find_pool<'r>(
+        &self,
+        router: &'r Router<'static>,
+        name: &str,
+    ) -> Result<&'r UniV2Pool, String> {
+        let id = self
+            .pool_names
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| PoolId::from(name));
+        router
+            .pool_ids()
+            .iter()
+            .position(|pool_id| *pool_id == id)
+            .map(|position| &router.pools()[position])
+            .ok_or_else(|| format!("no pool named {name}"))
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pool {
+                name: Some(name),
+                pair,
+                reserve0,
+                reserve1,
+            } => write!(f, "pool {name} {pair} {reserve0} {reserve1}"),
+            Self::Pool {
+                name: None,
+                pair,
+                reserve0,
+                reserve1,
+            } => write!(f, "pool {pair} {reserve0} {reserve1}"),
+            Self::Settle => write!(f, "settle"),
+            Self::Trade { route, amount } => write!(f, "trade {route} {amount}"),
+            Self::Quote { route, amount } => write!(f, "quote {route} {amount}"),
+            Self::ExpectOutputNear {
+                expected,
+                tolerance,
+            } => write!(f, "expect output near {expected} ± {tolerance}"),
+            Self::ExpectFailure => write!(f, "expect failure"),
+            Self::ExpectSpotPriceNear {
+                pool,
+                input_token,
+                expected,
+                relative_tolerance,
+            } => write!(
+                f,
+                "expect spot price of {pool} for {input_token} near {expected} ± {relative_tolerance} relative"
+            ),
+            Self::ExpectReservesNear {
+                pool,
+                reserve0,
+                reserve1,
+                relative_tolerance,
+            } => write!(
+                f,
+                "expect reserves of {pool} near {reserve0} {reserve1} ± {relative_tolerance} relative"
+            ),
+            Self::ExpectConservation => write!(f, "expect conservation"),
+        }
+    }
+}
+
+fn split<'s>(text: &'s str, separator: &str) -> Result<(&'s str, &'s str), String> {
+    text.split_once(separator)
+        .map(|(left, right)| (left.trim(), right.trim()))
+        .ok_or_else(|| format!("{text} is not of the form A{separator}B"))
+}
+
+fn check_near(
+    what: &str,
+    actual: f64,
+    expected: f64,
+    relative_tolerance: f64,
+) -> Result<(), String> {
+    let difference = (actual - expected).abs();
+    if difference <= relative_tolerance * expected.abs() {
+        return Ok(());
+    }
+    Err(format!(
+        "{what} {actual} is not within {relative_tolerance} relative of {expected}"
+    ))
+}
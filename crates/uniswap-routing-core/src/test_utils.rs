@@ -0,0 +1,109 @@
+//! Pool sets for benchmarks and tests, behind the `test-utils` feature: the example of the
+//! challenge, see [`example_pools`], and seeded synthetic graphs of any size, see
+//! [`synthetic_pools`].
+//!
+//! The synthetic graphs are drawn by a generator of their own, so that a seed yields the same
+//! pools whatever the versions of the dependencies. Token names are leaked, as the pools are
+//! meant for tests.
+
+use crate::uni_v2_pool::UniV2Pool;
+
+/// Number of decades spanned by the hidden prices of the tokens of a synthetic graph.
+const PRICE_DECADES: f64 = 2.0;
+
+/// Number of decades spanned by the liquidities `√k` of the pools of a synthetic graph, from
+/// `10³` on.
+const LIQUIDITY_DECADES: f64 = 3.0;
+
+/// Largest relative deviation of the price quoted by a synthetic pool from the ratio of the
+/// hidden prices of its tokens.
+const PRICE_NOISE: f64 = 0.01;
+
+/// Returns the 11 pools of the example of the challenge, run by the `routing-challenge-rs`
+/// binary, between ETH, USDC, DAI and USDT.
+pub fn example_pools() -> Vec<UniV2Pool> {
+    vec![
+        UniV2Pool::new("ETH", "USDC", 2_000., 2_000_000.),
+        UniV2Pool::new("ETH", "USDC", 1_000., 1_000_000.),
+        UniV2Pool::new("ETH", "DAI", 1_000., 900_000.),
+        UniV2Pool::new("ETH", "DAI", 3_000., 2_800_000.),
+        UniV2Pool::new("ETH", "DAI", 3_000., 3_100_000.),
+        UniV2Pool::new("DAI", "USDC", 1_000_000., 1_000_000.),
+        UniV2Pool::new("DAI", "USDC", 2_000_000., 2_000_000.),
+        UniV2Pool::new("DAI", "USDT", 1_000_000., 900_000.),
+        UniV2Pool::new("DAI", "USDT", 900_000., 1_000_000.),
+        UniV2Pool::new("ETH", "USDT", 2_000., 2_000_000.),
+        UniV2Pool::new("ETH", "USDT", 10_000., 10_000_000.),
+    ]
+}
+
+/// Returns `edges` pools between `tokens` tokens named `T0`, `T1`, …, drawn from `seed`.
+///
+/// The first `tokens − 1` pools link every token but `T0` to one drawn among those before it,
+/// which connects the graph; the others link two distinct tokens drawn at random, a pair drawn
+/// twice getting parallel pools. Every token has a hidden price, log-uniform over two decades,
+/// and every pool a liquidity `√k` log-uniform between 10³ and 10⁶; its reserves quote the ratio
+/// of the prices of its tokens off by up to 1%, leaving some arbitrage for the first solve to
+/// absorb.
+///
+/// Panics if there are fewer than two tokens, or fewer than `tokens − 1` edges to connect them.
+pub fn synthetic_pools(tokens: usize, edges: usize, seed: u64) -> Vec<UniV2Pool> {
+    assert!(tokens >= 2, "a synthetic graph needs two tokens");
+    assert!(
+        edges + 1 >= tokens,
+        "{edges} edges cannot connect {tokens} tokens"
+    );
+    let mut random = SplitMix64(seed);
+    let names = (0..tokens)
+        .map(|token| &*Box::leak(format!("T{token}").into_boxed_str()))
+        .collect::<Vec<&'static str>>();
+    let prices = (0..tokens)
+        .map(|_| 10f64.powf(PRICE_DECADES * (random.unit() - 0.5)))
+        .collect::<Vec<_>>();
+
+    (0..edges)
+        .map(|edge| {
+            let (u, v) = match edge + 1 < tokens {
+                true => (random.below(edge + 1), edge + 1),
+                false => {
+                    let u = random.below(tokens);
+                    (u, (u + 1 + random.below(tokens - 1)) % tokens)
+                }
+            };
+            // Reserves of value `√k · √(p_u · p_v)` on each side, at the ratio of the prices
+            let liquidity = 1e3 * 10f64.powf(LIQUIDITY_DECADES * random.unit());
+            let ratio = (prices[v] / prices[u]).sqrt();
+            let noise = 1.0 + PRICE_NOISE * (2.0 * random.unit() - 1.0);
+            UniV2Pool::new(
+                names[u],
+                names[v],
+                liquidity * ratio,
+                liquidity / ratio * noise,
+            )
+        })
+        .collect()
+}
+
+/// SplitMix64 generator, see Steele, Lea and Flood, "Fast splittable pseudorandom number
+/// generators" (2014).
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number drawn uniformly in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns an integer drawn in `0..bound`, uniformly up to a bias of `bound / 2⁶⁴`.
+    fn below(&mut self, bound: usize) -> usize {
+        ((self.next() as u128 * bound as u128) >> 64) as usize
+    }
+}
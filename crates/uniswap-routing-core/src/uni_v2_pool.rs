@@ -0,0 +1,621 @@
+use crate::error::{PoolError, RouterError};
+
+use std::fmt;
+
+/// Identifier of a pool within a router.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoolId(pub String);
+
+impl PoolId {
+    /// Identifier assigned by the router to the `n`-th pool it registers.
+    pub(crate) fn synthetic(n: u64) -> Self {
+        Self(format!("pool-{n}"))
+    }
+}
+
+impl fmt::Display for PoolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for PoolId {
+    fn from(id: &str) -> Self {
+        Self(id.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UniV2Pool {
+    pub token0: &'static str,
+    pub token1: &'static str,
+    pub reserve0: f64,
+    pub reserve1: f64,
+    /// Fraction of the input of every swap kept by the pool, [`UniV2Pool::FEE`] unless set by
+    /// [`UniV2Pool::with_fee`]
+    pub fee: f64,
+    /// Reserves in the smallest units of the tokens, as held by the pair contract, for the exact
+    /// math of [`UniV2Pool::get_output_amount_exact`]; `None` unless set by
+    /// [`UniV2Pool::with_raw_reserves`]. The trades of the router keep them in step with the float
+    /// reserves, any other change of which drops them.
+    pub raw_reserves: Option<(u128, u128)>,
+    /// Decimals of `token0` and `token1`, the float reserves counting whole tokens of
+    /// `10^decimals` smallest units; `None` unless built by [`UniV2Pool::from_raw_reserves`]
+    pub decimals: Option<(u8, u8)>,
+    /// Identifier of the pool in the routers it enters, such as the address of its pair
+    /// contract; `None` unless set by [`UniV2Pool::with_id`], the router then assigning a
+    /// synthetic one
+    pub id: Option<PoolId>,
+    /// Liquidity shares issued by the pool, `√(reserve0 · reserve1)` at construction as minted by
+    /// the first deposit of a pair, then moved by [`UniV2Pool::add_liquidity`] and
+    /// [`UniV2Pool::remove_liquidity`]
+    pub total_shares: f64,
+    /// Amounts of `token0` and `token1` the curve of the pool holds on top of its reserves,
+    /// without any of them being withdrawable, such as the depth of
+    /// [`ConstantSumPool::to_uni_v2`](crate::ConstantSumPool); zero unless set by
+    /// [`UniV2Pool::with_virtual_reserves`]. Swaps are priced on the
+    /// [`UniV2Pool::curve_reserves`], and take at most the reserves.
+    pub virtual_reserves: (f64, f64),
+}
+
+/// Liquidity shares minted by [`UniV2Pool::add_liquidity`], and the deposit they stand for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LpShares {
+    pub shares: f64,
+    /// Amount of `token0` deposited, the proportional part of the amount offered
+    pub amount0: f64,
+    /// Amount of `token1` deposited, the proportional part of the amount offered
+    pub amount1: f64,
+}
+
+/// Denominator of the fee factor of the exact math, in basis points.
+const EXACT_FEE_DENOMINATOR: u128 = 10_000;
+
+impl UniV2Pool {
+    /// Fee of the pools built by [`UniV2Pool::new`] and [`UniV2Pool::try_new`], 0.3% as on
+    /// Uniswap V2.
+    pub const FEE: f64 = 0.003;
+
+    /// Builds a pool without checking its reserves, for tests and trusted data; prefer
+    /// [`UniV2Pool::try_new`]. Panics if `token0` and `token1` are the same token.
+    pub fn new(token0: &'static str, token1: &'static str, reserve0: f64, reserve1: f64) -> Self {
+        assert_ne!(token0, token1, "a pool needs two distinct tokens");
+        Self {
+            token0,
+            token1,
+            reserve0,
+            reserve1,
+            fee: Self::FEE,
+            raw_reserves: None,
+            decimals: None,
+            id: None,
+            total_shares: reserve0.sqrt() * reserve1.sqrt(),
+            virtual_reserves: (0.0, 0.0),
+        }
+    }
+
+    /// Sets the fraction of the input of every swap kept by the pool, such as `0.0025` for the
+    /// 25 bps of some forks; checked by [`UniV2Pool::validate`].
+    pub fn with_fee(mut self, fee: f64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Sets the identifier of the pool, by which routers refer to it instead of a synthetic
+    /// `pool-<n>` one, a form it should not take. Routers reject pools sharing an identifier.
+    pub fn with_id(mut self, id: impl Into<PoolId>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the reserves of the pool in the smallest units of its tokens, as read from the pair
+    /// contract, enabling [`UniV2Pool::get_output_amount_exact`]. They should hold the same
+    /// amounts as the float reserves, the router converting between the two at the decimals of the
+    /// tokens if known, at their ratio otherwise.
+    pub fn with_raw_reserves(mut self, raw0: u128, raw1: u128) -> Self {
+        self.raw_reserves = Some((raw0, raw1));
+        self
+    }
+
+    /// Sets the amounts the curve of the pool holds on top of its reserves, see
+    /// [`UniV2Pool::virtual_reserves`]; checked by [`UniV2Pool::validate`].
+    pub fn with_virtual_reserves(mut self, virtual0: f64, virtual1: f64) -> Self {
+        self.virtual_reserves = (virtual0, virtual1);
+        self
+    }
+
+    /// Returns the reserves of the constant-product curve of the pool, its reserves and its
+    /// virtual ones, see [`UniV2Pool::virtual_reserves`].
+    pub fn curve_reserves(&self) -> (f64, f64) {
+        (
+            self.reserve0 + self.virtual_reserves.0,
+            self.reserve1 + self.virtual_reserves.1,
+        )
+    }
+
+    /// Returns whether the curve of the pool holds virtual reserves, see
+    /// [`UniV2Pool::virtual_reserves`].
+    pub fn has_virtual_reserves(&self) -> bool {
+        self.virtual_reserves != (0.0, 0.0)
+    }
+
+    /// Builds a pool after checking it, see [`UniV2Pool::validate`].
+    pub fn try_new(
+        token0: &'static str,
+        token1: &'static str,
+        reserve0: f64,
+        reserve1: f64,
+    ) -> Result<Self, PoolError> {
+        let pool = Self {
+            token0,
+            token1,
+            reserve0,
+            reserve1,
+            fee: Self::FEE,
+            raw_reserves: None,
+            decimals: None,
+            id: None,
+            total_shares: reserve0.sqrt() * reserve1.sqrt(),
+            virtual_reserves: (0.0, 0.0),
+        };
+        pool.validate()?;
+        Ok(pool)
+    }
+
+    /// Builds a pool from its on-chain reserves `raw0` and `raw1`, in the smallest units of tokens
+    /// of `decimals0` and `decimals1` decimals, such as 18 for ETH and 6 for USDC. The float
+    /// reserves count whole tokens, the raw ones and the decimals being kept for the exact math
+    /// and [`UniV2Pool::to_raw_amount`]. Checks the pool, see [`UniV2Pool::validate`].
+    pub fn from_raw_reserves(
+        token0: &'static str,
+        token1: &'static str,
+        raw0: u128,
+        raw1: u128,
+        decimals0: u8,
+        decimals1: u8,
+    ) -> Result<Self, PoolError> {
+        let (reserve0, reserve1) = (
+            raw0 as f64 / 10f64.powi(decimals0.into()),
+            raw1 as f64 / 10f64.powi(decimals1.into()),
+        );
+        let pool = Self {
+            token0,
+            token1,
+            reserve0,
+            reserve1,
+            fee: Self::FEE,
+            raw_reserves: Some((raw0, raw1)),
+            decimals: Some((decimals0, decimals1)),
+            id: None,
+            total_shares: reserve0.sqrt() * reserve1.sqrt(),
+            virtual_reserves: (0.0, 0.0),
+        };
+        pool.validate()?;
+        Ok(pool)
+    }
+
+    /// Converts an amount of `token` in whole tokens, such as an output of the router, to its
+    /// smallest units, rounded to the nearest unit and saturating; `None` if the pool does not
+    /// know the decimals of its tokens. Panics if `token` is not a token of the pool.
+    pub fn to_raw_amount(&self, token: &str, amount: f64) -> Option<u128> {
+        let scale = 10f64.powi(self.token_decimals(token)?.into());
+        Some((amount * scale).round() as u128)
+    }
+
+    /// Converts an amount of `token` in its smallest units to whole tokens, the inverse of
+    /// [`UniV2Pool::to_raw_amount`]; `None` if the pool does not know the decimals of its tokens.
+    /// Panics if `token` is not a token of the pool.
+    pub fn from_raw_amount(&self, token: &str, raw_amount: u128) -> Option<f64> {
+        let scale = 10f64.powi(self.token_decimals(token)?.into());
+        Some(raw_amount as f64 / scale)
+    }
+
+    fn token_decimals(&self, token: &str) -> Option<u8> {
+        self.require_owned_token(token);
+        let (decimals0, decimals1) = self.decimals?;
+        Some(match token == self.token0 {
+            true => decimals0,
+            false => decimals1,
+        })
+    }
+
+    /// Checks that the tokens are named and distinct, that both reserves are finite and
+    /// positive, the conditions under which `√(reserve0 · reserve1)` is a valid liquidity, as
+    /// well as the raw reserves if any, that the virtual reserves are finite and not negative,
+    /// and that the fee lies in `[0, 1)`.
+    pub fn validate(&self) -> Result<(), PoolError> {
+        if self.token0.is_empty() || self.token1.is_empty() {
+            return Err(PoolError::EmptyToken);
+        }
+        if self.token0 == self.token1 {
+            return Err(PoolError::IdenticalTokens(self.token0.to_string()));
+        }
+        for (token, reserve) in [(self.token0, self.reserve0), (self.token1, self.reserve1)] {
+            if !(reserve.is_finite() && reserve > 0.0) {
+                return Err(PoolError::InvalidReserve {
+                    token: token.to_string(),
+                    reserve,
+                });
+            }
+        }
+        for (token, reserve) in [
+            (self.token0, self.virtual_reserves.0),
+            (self.token1, self.virtual_reserves.1),
+        ] {
+            if !(reserve.is_finite() && reserve >= 0.0) {
+                return Err(PoolError::InvalidReserve {
+                    token: token.to_string(),
+                    reserve,
+                });
+            }
+        }
+        if let Some((raw0, raw1)) = self.raw_reserves {
+            for (token, raw) in [(self.token0, raw0), (self.token1, raw1)] {
+                if raw == 0 {
+                    return Err(PoolError::InvalidReserve {
+                        token: token.to_string(),
+                        reserve: 0.0,
+                    });
+                }
+            }
+        }
+        if !(0.0..1.0).contains(&self.fee) {
+            return Err(PoolError::InvalidFee(self.fee));
+        }
+        Ok(())
+    }
+
+    // Returns how many output tokens will be returned if a given amount of input token are added to
+    // the pool, after its fee: `(1 − fee) · x · reserve_out / (reserve_in + (1 − fee) · x)`,
+    // `997 · x · reserve_out / (1000 · reserve_in + 997 · x)` at the default fee, over the curve
+    // reserves. Fails if the amount is negative or not finite, and with
+    // [`RouterError::PoolOperation`] if the output reaches the whole reserve, which only a pool of
+    // virtual reserves allows, the pool being named by its pair if it has no identifier.
+    #[allow(unused)]
+    pub fn get_output_amount(
+        &self,
+        input_token: &str,
+        input_amount: f64,
+    ) -> Result<f64, RouterError> {
+        self.output_amount_with_fee(input_token, input_amount, self.fee)
+            .map_err(|error| match error {
+                PoolError::InvalidAmount(amount) => RouterError::InvalidAmount(amount),
+                error => RouterError::PoolOperation {
+                    pool: self
+                        .id
+                        .clone()
+                        .unwrap_or_else(|| PoolId(format!("{}/{}", self.token0, self.token1))),
+                    error,
+                },
+            })
+    }
+
+    /// Returns the output of a swap like [`UniV2Pool::get_output_amount`], with the pool
+    /// keeping the fraction `fee` of the input.
+    pub(crate) fn output_amount_with_fee(
+        &self,
+        input_token: &str,
+        input_amount: f64,
+        fee: f64,
+    ) -> Result<f64, PoolError> {
+        self.require_owned_token(input_token);
+        if !(input_amount.is_finite() && input_amount >= 0.0) {
+            return Err(PoolError::InvalidAmount(input_amount));
+        }
+
+        let is_token0 = input_token == self.token0;
+        let ((reserve_in, reserve_out), withdrawable) = match (is_token0, self.curve_reserves()) {
+            (true, (curve0, curve1)) => ((curve0, curve1), self.reserve1),
+            (false, (curve0, curve1)) => ((curve1, curve0), self.reserve0),
+        };
+
+        let effective_input = input_amount * (1.0 - fee);
+        let output_amount = (effective_input * reserve_out) / (reserve_in + effective_input);
+        if self.has_virtual_reserves() && output_amount >= withdrawable {
+            return Err(PoolError::InsufficientLiquidity {
+                output_amount,
+                reserve: withdrawable,
+            });
+        }
+        Ok(output_amount)
+    }
+
+    /// Returns how many input tokens must be added to the pool to receive `output_amount` of
+    /// `output_token`, the inverse of [`UniV2Pool::get_output_amount`]:
+    /// `output_amount · reserve_in / ((1 − fee) · (reserve_out − output_amount))`. Fails if the
+    /// amount is negative or not finite, or if it reaches the whole reserve, which no input can
+    /// buy.
+    pub fn get_input_amount(
+        &self,
+        output_token: &str,
+        output_amount: f64,
+    ) -> Result<f64, PoolError> {
+        self.require_owned_token(output_token);
+        if !(output_amount.is_finite() && output_amount >= 0.0) {
+            return Err(PoolError::InvalidAmount(output_amount));
+        }
+
+        let (curve0, curve1) = self.curve_reserves();
+        let ((reserve_in, reserve_out), withdrawable) = match output_token == self.token0 {
+            true => ((curve1, curve0), self.reserve0),
+            false => ((curve0, curve1), self.reserve1),
+        };
+        if output_amount >= withdrawable {
+            return Err(PoolError::InsufficientLiquidity {
+                output_amount,
+                reserve: withdrawable,
+            });
+        }
+
+        Ok((output_amount * reserve_in) / ((reserve_out - output_amount) * (1.0 - self.fee)))
+    }
+
+    /// Swaps `input_amount` of `input_token` against the pool, after its fee, moving its reserves,
+    /// and returns the output, as computed by [`UniV2Pool::get_output_amount`]. The input
+    /// reserve grows by the whole input, fee included, so that `reserve0 · reserve1` grows by
+    /// the fee.
+    ///
+    /// Fails, leaving the pool untouched, if `input_token` is not a token of the pool, if the
+    /// amount is negative or not finite, or if the output reaches the whole reserve, see
+    /// [`UniV2Pool::virtual_reserves`].
+    pub fn apply_swap(&mut self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        let is_token0 = match input_token {
+            token if token == self.token0 => true,
+            token if token == self.token1 => false,
+            _ => return Err(PoolError::UnknownToken(input_token.to_string())),
+        };
+        let output_amount = self.output_amount_with_fee(input_token, input_amount, self.fee)?;
+
+        let (reserve_in, reserve_out) = match is_token0 {
+            true => (&mut self.reserve0, &mut self.reserve1),
+            false => (&mut self.reserve1, &mut self.reserve0),
+        };
+        *reserve_in += input_amount;
+        *reserve_out -= output_amount;
+        self.raw_reserves = None;
+        Ok(output_amount)
+    }
+
+    /// Deposits liquidity and returns the shares minted for it. Of the amounts offered, only the
+    /// largest part in the ratio of the reserves is deposited, as by the Uniswap V2 router, the
+    /// rest staying with the caller: the price of the pool is unchanged, and its shares grow in
+    /// proportion of its reserves. Drops the raw reserves.
+    ///
+    /// Fails if an amount is negative or not finite.
+    pub fn add_liquidity(&mut self, amount0: f64, amount1: f64) -> Result<LpShares, PoolError> {
+        for amount in [amount0, amount1] {
+            if !(amount.is_finite() && amount >= 0.0) {
+                return Err(PoolError::InvalidAmount(amount));
+            }
+        }
+
+        let ratio = (amount0 / self.reserve0).min(amount1 / self.reserve1);
+        let deposit = LpShares {
+            shares: ratio * self.total_shares,
+            amount0: ratio * self.reserve0,
+            amount1: ratio * self.reserve1,
+        };
+        self.reserve0 += deposit.amount0;
+        self.reserve1 += deposit.amount1;
+        self.virtual_reserves.0 *= 1.0 + ratio;
+        self.virtual_reserves.1 *= 1.0 + ratio;
+        self.total_shares += deposit.shares;
+        self.raw_reserves = None;
+        Ok(deposit)
+    }
+
+    /// Burns `shares` and returns the amounts of `token0` and `token1` withdrawn for them, their
+    /// share of the reserves. Drops the raw reserves.
+    ///
+    /// Fails if the amount is negative or not finite, or if it reaches the total shares: a pool
+    /// keeps some liquidity, as the pair contracts lock a minimum one.
+    pub fn remove_liquidity(&mut self, shares: f64) -> Result<(f64, f64), PoolError> {
+        if !(shares.is_finite() && shares >= 0.0) {
+            return Err(PoolError::InvalidAmount(shares));
+        }
+        if shares >= self.total_shares {
+            return Err(PoolError::InsufficientShares {
+                shares,
+                total_shares: self.total_shares,
+            });
+        }
+
+        let fraction = shares / self.total_shares;
+        let amounts = (fraction * self.reserve0, fraction * self.reserve1);
+        self.reserve0 -= amounts.0;
+        self.reserve1 -= amounts.1;
+        self.virtual_reserves.0 -= fraction * self.virtual_reserves.0;
+        self.virtual_reserves.1 -= fraction * self.virtual_reserves.1;
+        self.total_shares -= shares;
+        self.raw_reserves = None;
+        Ok(amounts)
+    }
+
+    /// Returns the output of swapping `amount_in` of `input_token` in the smallest units of the
+    /// tokens, over the raw reserves, exactly as `getAmountOut` of the Uniswap V2 library:
+    /// `⌊997 · amount_in · reserve_out / (1000 · reserve_in + 997 · amount_in)⌋` at the default
+    /// fee.
+    ///
+    /// The product of the numerator is taken over 256 bits, as the `uint256` of the contract. Fails
+    /// if the pool has no raw reserves, if its fee is not a whole number of basis points, or if
+    /// another intermediate value overflows `u128`, which reserves beyond the `uint112` of the
+    /// pair contracts, about `5 · 10³³`, can.
+    pub fn get_output_amount_exact(
+        &self,
+        input_token: &str,
+        amount_in: u128,
+    ) -> Result<u128, PoolError> {
+        self.require_owned_token(input_token);
+        let (reserve_in, reserve_out) = self.exact_reserves(input_token == self.token0)?;
+        let fee_factor = self.exact_fee_factor()?;
+
+        let amount_in_with_fee = amount_in
+            .checked_mul(fee_factor)
+            .ok_or(PoolError::ExactOverflow)?;
+        let denominator = reserve_in
+            .checked_mul(EXACT_FEE_DENOMINATOR)
+            .and_then(|reserve| reserve.checked_add(amount_in_with_fee))
+            .ok_or(PoolError::ExactOverflow)?;
+        match denominator {
+            0 => Ok(0),
+            _ => mul_div(amount_in_with_fee, reserve_out, denominator)
+                .ok_or(PoolError::ExactOverflow),
+        }
+    }
+
+    /// Returns the input needed to receive `amount_out` of `output_token` in the smallest units
+    /// of the tokens, over the raw reserves, exactly as `getAmountIn` of the Uniswap V2 library:
+    /// `⌊1000 · reserve_in · amount_out / (997 · (reserve_out − amount_out))⌋ + 1` at the default
+    /// fee, whose `+ 1` makes [`UniV2Pool::get_output_amount_exact`] of the result at least
+    /// `amount_out`. Returns zero for a zero output, which the library rejects.
+    ///
+    /// Fails like [`UniV2Pool::get_output_amount_exact`], and if the output reaches the whole
+    /// reserve.
+    pub fn get_input_amount_exact(
+        &self,
+        output_token: &str,
+        amount_out: u128,
+    ) -> Result<u128, PoolError> {
+        self.require_owned_token(output_token);
+        let (reserve_out, reserve_in) = self.exact_reserves(output_token == self.token0)?;
+        let fee_factor = self.exact_fee_factor()?;
+        if amount_out == 0 {
+            return Ok(0);
+        }
+        if amount_out >= reserve_out {
+            return Err(PoolError::InsufficientLiquidity {
+                output_amount: amount_out as f64,
+                reserve: reserve_out as f64,
+            });
+        }
+
+        let denominator = (reserve_out - amount_out)
+            .checked_mul(fee_factor)
+            .ok_or(PoolError::ExactOverflow)?;
+        reserve_in
+            .checked_mul(EXACT_FEE_DENOMINATOR)
+            .and_then(|reserve| mul_div(reserve, amount_out, denominator))
+            .and_then(|amount_in| amount_in.checked_add(1))
+            .ok_or(PoolError::ExactOverflow)
+    }
+
+    /// Returns the raw reserves of the side `is_token0` first, then of the other one.
+    fn exact_reserves(&self, is_token0: bool) -> Result<(u128, u128), PoolError> {
+        let (raw0, raw1) = self.raw_reserves.ok_or(PoolError::MissingRawReserves)?;
+        Ok(match is_token0 {
+            true => (raw0, raw1),
+            false => (raw1, raw0),
+        })
+    }
+
+    /// Returns the fraction `1 − fee` of the input the pool trades on, in basis points, `9970`
+    /// at the default fee, which scales `997 / 1000` without changing any rounding.
+    fn exact_fee_factor(&self) -> Result<u128, PoolError> {
+        let fee = self.fee * EXACT_FEE_DENOMINATOR as f64;
+        if !(0.0..EXACT_FEE_DENOMINATOR as f64).contains(&fee) || (fee - fee.round()).abs() > 1e-6 {
+            return Err(PoolError::InexactFee(self.fee));
+        }
+        Ok(EXACT_FEE_DENOMINATOR - fee.round() as u128)
+    }
+
+    // Returns the instataneous price. This is given mostly for information purpose.
+    #[allow(unused)]
+    pub fn get_spot_price(&self, input_token: &str) -> f64 {
+        self.require_owned_token(input_token);
+
+        let (curve0, curve1) = self.curve_reserves();
+        match input_token == self.token0 {
+            true => curve0 / curve1,
+            false => curve1 / curve0,
+        }
+    }
+
+    /// Returns the invariant `reserve0 · reserve1` of the curve of the pool, see
+    /// [`UniV2Pool::curve_reserves`], which swaps grow by their fee.
+    pub fn k(&self) -> f64 {
+        let (curve0, curve1) = self.curve_reserves();
+        curve0 * curve1
+    }
+
+    /// Returns the spot price of the pool for `input_token`, see [`UniV2Pool::get_spot_price`],
+    /// once `input_amount` of it is swapped as by [`UniV2Pool::apply_swap`], leaving the pool
+    /// untouched. Returns NaN if the amount is negative or not finite.
+    pub fn spot_price_after(&self, input_token: &str, input_amount: f64) -> f64 {
+        self.require_owned_token(input_token);
+        let mut pool = self.clone();
+        match pool.apply_swap(input_token, input_amount) {
+            Ok(_) => pool.get_spot_price(input_token),
+            Err(_) => f64::NAN,
+        }
+    }
+
+    /// Returns the relative increase of the spot price of the pool for `input_token` caused by
+    /// swapping `input_amount` of it, see [`UniV2Pool::spot_price_after`]: zero for a zero
+    /// amount, growing with the amount. Returns NaN if the amount is negative or not finite.
+    pub fn price_impact(&self, input_token: &str, input_amount: f64) -> f64 {
+        self.spot_price_after(input_token, input_amount) / self.get_spot_price(input_token) - 1.0
+    }
+
+    /// Returns the largest input of `input_token` whose average rate, after the fee, falls short
+    /// of the spot rate by at most the fraction `max_impact`, the price impact of
+    /// [`Router::depth`](crate::router::Router::depth). The output of an input `x` being
+    /// `(1 − fee) · x · r_out / (r_in + (1 − fee) · x)`, the shortfall
+    /// `1 − output / (x · r_out / r_in)` reaches `max_impact` at
+    /// `x = r_in · (1 / (1 − max_impact) − 1 / (1 − fee))`.
+    ///
+    /// Zero if the fee alone exceeds the tolerance, infinite for a tolerance of 1 or more, NaN for
+    /// a negative one. Panics if `input_token` is not a token of the pool.
+    pub fn max_input_for_impact(&self, input_token: &str, max_impact: f64) -> f64 {
+        self.require_owned_token(input_token);
+        if max_impact.is_nan() || max_impact < 0.0 {
+            return f64::NAN;
+        }
+        if max_impact >= 1.0 {
+            return f64::INFINITY;
+        }
+
+        let reserve_in = match input_token == self.token0 {
+            true => self.curve_reserves().0,
+            false => self.curve_reserves().1,
+        };
+        (reserve_in * (1.0 / (1.0 - max_impact) - 1.0 / (1.0 - self.fee))).max(0.0)
+    }
+
+    #[allow(unused)]
+    fn require_owned_token(&self, token: &str) {
+        let is_owned = token == self.token0 || token == self.token1;
+
+        if !is_owned {
+            panic!("unsupported token");
+        }
+    }
+}
+
+/// Returns `⌊a · b / divisor⌋`, the product being taken over 256 bits, or `None` if the quotient
+/// exceeds `u128`. Panics if `divisor` is zero.
+fn mul_div(a: u128, b: u128, divisor: u128) -> Option<u128> {
+    const LOW: u128 = u64::MAX as u128;
+    let (a_high, a_low, b_high, b_low) = (a >> 64, a & LOW, b >> 64, b & LOW);
+    let (low_low, low_high, high_low) = (a_low * b_low, a_low * b_high, a_high * b_low);
+    let middle = (low_low >> 64) + (low_high & LOW) + (high_low & LOW);
+    let low = (low_low & LOW) | (middle << 64);
+    let high = a_high * b_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+    if high >= divisor {
+        return None;
+    }
+
+    // Long division of the low half, the remainder staying below the divisor
+    let (mut remainder, mut quotient) = (high, 0u128);
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1 << bit;
+        }
+    }
+    Some(quotient)
+}
@@ -0,0 +1,154 @@
+use crate::{
+    error::PoolError,
+    pool::Pool,
+    uni_v2_pool::{PoolId, UniV2Pool},
+};
+
+/// A Balancer-style pool of invariant `reserve0^weight0 · reserve1^weight1`, such as the 80/20
+/// pools, a 50/50 one trading as a [`UniV2Pool`] of the same reserves and fee.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WeightedPool {
+    pub token0: &'static str,
+    pub token1: &'static str,
+    pub reserve0: f64,
+    pub reserve1: f64,
+    /// Weight of `token0`, only its ratio to `weight1` mattering
+    pub weight0: f64,
+    pub weight1: f64,
+    /// Fraction of the input of every swap kept by the pool, [`UniV2Pool::FEE`] unless set by
+    /// [`WeightedPool::with_fee`]
+    pub fee: f64,
+    /// Identifier of the pool in the routers it enters, see [`UniV2Pool::id`]
+    pub id: Option<PoolId>,
+}
+
+impl WeightedPool {
+    /// Builds a pool without checking its reserves nor its weights. Panics if `token0` and
+    /// `token1` are the same token.
+    pub fn new(
+        token0: &'static str,
+        token1: &'static str,
+        reserve0: f64,
+        reserve1: f64,
+        weight0: f64,
+        weight1: f64,
+    ) -> Self {
+        assert_ne!(token0, token1, "a pool needs two distinct tokens");
+        Self {
+            token0,
+            token1,
+            reserve0,
+            reserve1,
+            weight0,
+            weight1,
+            fee: UniV2Pool::FEE,
+            id: None,
+        }
+    }
+
+    /// Sets the fraction of the input of every swap kept by the pool.
+    pub fn with_fee(mut self, fee: f64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Sets the identifier of the pool, see [`UniV2Pool::with_id`].
+    pub fn with_id(mut self, id: impl Into<PoolId>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    // Returns the instantaneous price, in `input_token` per other token:
+    // `(reserve0 / weight0) / (reserve1 / weight1)` for `token0`. Panics if `input_token` is not a
+    // token of the pool.
+    pub fn get_spot_price(&self, input_token: &str) -> f64 {
+        let (reserve_in, weight_in, reserve_out, weight_out) = self
+            .sides_from(input_token)
+            .unwrap_or_else(|_| panic!("unsupported token"));
+        (reserve_in / weight_in) / (reserve_out / weight_out)
+    }
+
+    /// Returns the reserve and weight of the input side `input_token`, then of the other one.
+    fn sides_from(&self, input_token: &str) -> Result<(f64, f64, f64, f64), PoolError> {
+        match input_token {
+            token if token == self.token0 => {
+                Ok((self.reserve0, self.weight0, self.reserve1, self.weight1))
+            }
+            token if token == self.token1 => {
+                Ok((self.reserve1, self.weight1, self.reserve0, self.weight0))
+            }
+            _ => Err(PoolError::UnknownToken(input_token.to_string())),
+        }
+    }
+}
+
+impl Pool for WeightedPool {
+    fn tokens(&self) -> (&'static str, &'static str) {
+        (self.token0, self.token1)
+    }
+
+    fn reserves(&self) -> (f64, f64) {
+        (self.reserve0, self.reserve1)
+    }
+
+    /// Returns the invariant of the constant-product equivalent of the pool, see
+    /// [`WeightedPool::to_uni_v2`].
+    fn sqrt_invariant(&self) -> f64 {
+        let total = self.weight0 + self.weight1;
+        2.0 * (self.weight0 / total * self.weight1 / total).sqrt()
+            * self.reserve0.sqrt()
+            * self.reserve1.sqrt()
+    }
+
+    /// Returns the out-given-in of the weighted invariant, after the fee:
+    /// `reserve_out · (1 − (reserve_in / (reserve_in + (1 − fee) · x))^(weight_in / weight_out))`,
+    /// computed as by [`UniV2Pool::get_output_amount`] for equal weights.
+    fn get_output_amount(&self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        let (reserve_in, weight_in, reserve_out, weight_out) = self.sides_from(input_token)?;
+        if !(input_amount.is_finite() && input_amount >= 0.0) {
+            return Err(PoolError::InvalidAmount(input_amount));
+        }
+
+        let effective_input = input_amount * (1.0 - self.fee);
+        Ok(match weight_in == weight_out {
+            true => (effective_input * reserve_out) / (reserve_in + effective_input),
+            false => {
+                let ratio = reserve_in / (reserve_in + effective_input);
+                reserve_out * (1.0 - ratio.powf(weight_in / weight_out))
+            }
+        })
+    }
+
+    fn apply_swap(&mut self, input_token: &str, input_amount: f64) -> Result<f64, PoolError> {
+        let output_amount = self.get_output_amount(input_token, input_amount)?;
+        let (reserve_in, reserve_out) = match input_token == self.token0 {
+            true => (&mut self.reserve0, &mut self.reserve1),
+            false => (&mut self.reserve1, &mut self.reserve0),
+        };
+        *reserve_in += input_amount;
+        *reserve_out -= output_amount;
+        Ok(output_amount)
+    }
+
+    /// Returns the constant-product pool of reserves `2 · w1 · reserve0` and `2 · w0 · reserve1`,
+    /// the weights summing to one, and of the same fee: it has the spot price of the pool, and
+    /// moving that price takes the same amounts of either token, to first order. This
+    /// approximation around the current price drifts with the size of the trades, the
+    /// equivalent following the constant-product curve rather than the weighted one; it is
+    /// exact for equal weights.
+    fn to_uni_v2(&self) -> UniV2Pool {
+        let total = self.weight0 + self.weight1;
+        let pool = UniV2Pool::new(
+            self.token0,
+            self.token1,
+            2.0 * self.weight1 / total * self.reserve0,
+            2.0 * self.weight0 / total * self.reserve1,
+        )
+        .with_fee(self.fee);
+        match &self.id {
+            Some(id) => pool.with_id(id.clone()),
+            None => pool,
+        }
+    }
+}
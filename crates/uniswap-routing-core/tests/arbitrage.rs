@@ -0,0 +1,45 @@
+//! Arbitrage cycles between the individual pools, see `Router::find_arbitrage_cycles`.
+
+use uniswap_routing_core::{Router, test_utils::example_pools};
+
+#[test]
+fn price_gap_between_pools_of_a_pair_is_reported() {
+    let router = Router::new(example_pools());
+    let ids = router.pool_ids().to_vec();
+    let cycles = router.find_arbitrage_cycles(0.0);
+
+    // Buying ETH at 900 DAI from the third pool, selling it at 1033 DAI to the fifth one
+    let expected = (3_100_000. / 3_000.) / (900_000. / 1_000.);
+    let cycle = cycles
+        .iter()
+        .find(|cycle| {
+            cycle.tokens == ["ETH", "DAI", "ETH"] || cycle.tokens == ["DAI", "ETH", "DAI"]
+        })
+        .expect("ETH/DAI cycle");
+    assert!((cycle.profit_factor - expected).abs() < 1e-12 * expected);
+    let mut pools = cycle.pools.clone();
+    pools.sort();
+    let mut gap = vec![ids[2].clone(), ids[4].clone()];
+    gap.sort();
+    assert_eq!(pools, gap);
+
+    // Same for DAI/USDT, the widest gap of the example
+    let expected = (1_000_000. / 900_000.) * (1_000_000. / 900_000.);
+    assert!(matches!(
+        cycles[0].tokens[..],
+        ["DAI", "USDT", "DAI"] | ["USDT", "DAI", "USDT"]
+    ));
+    assert!((cycles[0].profit_factor - expected).abs() < 1e-12 * expected);
+    assert!(
+        cycles
+            .windows(2)
+            .all(|pair| pair[0].profit_factor >= pair[1].profit_factor)
+    );
+}
+
+#[test]
+fn settled_pools_leave_no_cycle() {
+    let mut router = Router::new(example_pools());
+    router.settle();
+    assert!(router.find_arbitrage_cycles(1e-6).is_empty());
+}
@@ -0,0 +1,107 @@
+//! Compaction of the tokens left without pools, see `Router::compact`.
+
+use uniswap_routing_core::{Router, RouterError, UniV2Pool, test_utils::example_pools};
+
+/// The example pools behind pools of three tokens sorted before them, which the compaction
+/// removes, shifting every other token.
+fn pools() -> Vec<UniV2Pool> {
+    let mut pools = vec![
+        UniV2Pool::new("FOO", "ETH", 50_000., 100.),
+        UniV2Pool::new("BAR", "USDC", 1_000., 2_000.),
+        UniV2Pool::new("BAR", "FOO", 1_000., 1_000.),
+        UniV2Pool::new("BAZ", "DAI", 300., 300.),
+    ];
+    pools.extend(example_pools());
+    pools
+}
+
+/// Removes the pools put before the example ones.
+fn remove_extra_pools(router: &mut Router<'_>) {
+    let extra = router.pool_ids()[..4].to_vec();
+    for id in &extra {
+        router.remove_pool(id).unwrap();
+    }
+}
+
+/// Returns what the router stores of every token, pool and pair, in its index layout.
+fn stores(router: &Router<'_>) -> String {
+    let tokens = router
+        .reserve_by_token()
+        .map(|(token, reserve)| {
+            let index = router.token_id(token).expect("the token is known").index();
+            format!("{index} {token} {}", reserve.to_bits())
+        })
+        .collect::<Vec<_>>();
+    let edges = router
+        .edges()
+        .map(|(u, v, liquidity)| format!("{u}/{v} {}", liquidity.to_bits()))
+        .collect::<Vec<_>>();
+    let pools = router
+        .pools()
+        .iter()
+        .map(|pool| {
+            format!(
+                "{}/{} {} {}",
+                pool.token0,
+                pool.token1,
+                pool.reserve0.to_bits(),
+                pool.reserve1.to_bits()
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("{tokens:?}\n{edges:?}\n{pools:?}\n{router}")
+}
+
+#[test]
+fn compacted_router_agrees_with_a_fresh_one() {
+    let mut router = Router::new(pools());
+    remove_extra_pools(&mut router);
+    let map = router.compact();
+    assert_eq!(map.removed, ["FOO", "BAR", "BAZ"]);
+    assert_eq!(map.generation, map.previous_generation + 1);
+
+    let mut fresh = Router::new(example_pools());
+    assert_eq!(stores(&router), stores(&fresh));
+    for (input_token, output_token, amount) in [("ETH", "USDC", 10.0), ("USDT", "DAI", 5e4)] {
+        let compacted = router.solve(input_token, output_token, amount).unwrap();
+        let expected = fresh.solve(input_token, output_token, amount).unwrap();
+        assert_eq!(compacted.to_bits(), expected.to_bits());
+    }
+    assert_eq!(stores(&router), stores(&fresh));
+}
+
+#[test]
+fn map_translates_the_ids_of_the_previous_generation() {
+    let mut router = Router::new(pools());
+    let ids = ["FOO", "ETH", "DAI"].map(|token| router.token_id(token).unwrap());
+    remove_extra_pools(&mut router);
+    let map = router.compact();
+
+    assert_eq!(map.remap(ids[0]).unwrap(), None);
+    for (id, token) in ids[1..].iter().zip(["ETH", "DAI"]) {
+        let new_id = map.remap(*id).unwrap().expect("the token is kept");
+        assert_eq!(new_id, router.token_id(token).unwrap());
+        assert_eq!(router.token_name(new_id).unwrap(), token);
+    }
+    assert_eq!(map.iter().count(), router.reserve_by_token().count());
+}
+
+#[test]
+fn pre_compaction_id_is_rejected() {
+    let mut router = Router::new(pools());
+    let eth = router.token_id("ETH").unwrap();
+    assert_eq!(router.token_name(eth).unwrap(), "ETH");
+    remove_extra_pools(&mut router);
+    let map = router.compact();
+
+    assert!(matches!(
+        router.token_name(eth),
+        Err(RouterError::StaleTokenId { id, generation })
+            if id == eth && generation == map.generation
+    ));
+    let new_id = map.remap(eth).unwrap().unwrap();
+    assert!(matches!(
+        map.remap(new_id),
+        Err(RouterError::StaleTokenId { .. })
+    ));
+}
@@ -0,0 +1,105 @@
+//! Independent settlement of connected components, sequentially or in parallel with the
+//! `parallel` feature, see `Router::settle`.
+
+use uniswap_routing_core::{Router, UniV2Pool, test_utils::synthetic_pools};
+
+/// Number of connected components of the graph, and tokens and pools of each.
+const COMPONENTS: usize = 10;
+const TOKENS: usize = 30;
+const EDGES: usize = 120;
+
+/// Returns the pools of the `component`-th component, whose tokens are prefixed by its number.
+fn component(component: usize) -> Vec<UniV2Pool> {
+    let rename = |token: &str| &*Box::leak(format!("C{component}{token}").into_boxed_str());
+    synthetic_pools(TOKENS, EDGES, 284 + component as u64)
+        .into_iter()
+        .map(|pool| {
+            UniV2Pool::new(
+                rename(pool.token0),
+                rename(pool.token1),
+                pool.reserve0,
+                pool.reserve1,
+            )
+        })
+        .collect()
+}
+
+/// Returns the pools of every component, interleaved so that no component is contiguous.
+fn graph() -> Vec<UniV2Pool> {
+    let components = (0..COMPONENTS).map(component).collect::<Vec<_>>();
+    (0..EDGES)
+        .flat_map(|edge| components.iter().map(move |pools| pools[edge].clone()))
+        .collect()
+}
+
+/// Returns the tokens and the bits of the reserves of every pool of `router`, sorted.
+fn reserves(router: &Router<'_>) -> Vec<(&'static str, &'static str, u64, u64)> {
+    let mut reserves = router
+        .pools()
+        .iter()
+        .map(|pool| {
+            (
+                pool.token0,
+                pool.token1,
+                pool.reserve0.to_bits(),
+                pool.reserve1.to_bits(),
+            )
+        })
+        .collect::<Vec<_>>();
+    reserves.sort_unstable();
+    reserves
+}
+
+#[test]
+fn settling_the_components_together_matches_settling_them_apart() {
+    let mut router = Router::new(graph());
+    let settlement = router.settle();
+    assert_eq!(settlement.components.len(), COMPONENTS);
+
+    let mut expected_reserves = Vec::new();
+    for number in 0..COMPONENTS {
+        let mut alone = Router::new(component(number));
+        let alone_settlement = alone.settle();
+        let [settled] = &alone_settlement.components[..] else {
+            panic!("component {number} is connected");
+        };
+        let together = settlement
+            .components
+            .iter()
+            .find(|settled| settled.sink_token.starts_with(&format!("C{number}T")))
+            .expect("every component settles");
+        assert_eq!(together.sink_token, settled.sink_token);
+        assert_eq!(
+            together.extracted_amount.to_bits(),
+            settled.extracted_amount.to_bits()
+        );
+        expected_reserves.extend(reserves(&alone));
+    }
+    expected_reserves.sort_unstable();
+    assert_eq!(reserves(&router), expected_reserves);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_settlement_is_bitwise_equal_to_the_sequential_one() {
+    let settle = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("the thread pool builds");
+        pool.install(|| {
+            let mut router = Router::new(graph());
+            let settlement = router
+                .settle()
+                .components
+                .iter()
+                .map(|settled| (settled.sink_token, settled.extracted_amount.to_bits()))
+                .collect::<Vec<_>>();
+            (settlement, reserves(&router), router.content_hash())
+        })
+    };
+    let sequential = settle(1);
+    for threads in [2, 4, COMPONENTS] {
+        assert_eq!(settle(threads), sequential, "{threads} threads");
+    }
+}
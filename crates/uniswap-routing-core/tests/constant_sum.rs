@@ -0,0 +1,89 @@
+//! Constant-sum pools, whose constant-product equivalent holds virtual reserves.
+
+use uniswap_routing_core::{
+    ConstantSumPool, Pool, Router, UniV2Pool,
+    error::{PoolError, RouterError},
+    router::FeeModel,
+};
+
+fn stable_pools() -> Vec<Box<dyn Pool>> {
+    vec![
+        Box::new(ConstantSumPool::new("USDC", "USDT", 1_000., 400.).with_id("stable")),
+        Box::new(UniV2Pool::new("ETH", "USDT", 10., 20_000.)),
+    ]
+}
+
+fn reserve_of(router: &Router, token: &str) -> f64 {
+    router
+        .reserve_by_token()
+        .find(|&(name, _)| name == token)
+        .map(|(_, reserve)| reserve)
+        .unwrap()
+}
+
+#[test]
+fn pools_report_their_reserves() {
+    let router = Router::new(stable_pools());
+    let pool = &router.pools()[0];
+    assert_eq!((pool.reserve0, pool.reserve1), (1_000., 400.));
+    assert_eq!(reserve_of(&router, "USDC"), 1_000.);
+    assert_eq!(reserve_of(&router, "USDT"), 20_400.);
+
+    // The pool prices USDC at one USDT
+    let tvl = router.tvl();
+    let value = |token| tvl.iter().find(|&&(name, _)| name == token).unwrap().1;
+    let ratio = value("USDC") / value("USDT");
+    assert!((ratio / (1_000. / 20_400.) - 1.).abs() < 1e-9, "{ratio}");
+}
+
+#[test]
+fn no_trade_takes_more_than_the_reserve() {
+    for fee_model in [FeeModel::FeeFree, FeeModel::Charged] {
+        for amount in [1., 100., 399., 400., 401., 1_000., 10_000.] {
+            let mut router = Router::builder(stable_pools())
+                .fee_model(fee_model)
+                .build()
+                .unwrap();
+            let hash = router.content_hash();
+            match router.solve("USDC", "USDT", amount) {
+                Ok(output_amount) => {
+                    assert!(
+                        amount < 1_000. && output_amount < 400.,
+                        "{amount}: {output_amount}"
+                    );
+                    let pool = &router.pools()[0];
+                    assert!(
+                        pool.reserve0 > 0. && pool.reserve1 > 0.,
+                        "{amount}: {pool:?}"
+                    );
+                    assert!((reserve_of(&router, "USDT") - 20_400. + output_amount).abs() < 1e-6);
+                }
+                Err(RouterError::PoolOperation {
+                    pool,
+                    error: PoolError::InsufficientLiquidity { reserve, .. },
+                }) => {
+                    assert!(amount > 401., "{amount} {fee_model:?}");
+                    assert_eq!((pool.0.as_str(), reserve), ("stable", 400.));
+                    assert_eq!(router.content_hash(), hash);
+                }
+                Err(error) => panic!("{amount} {fee_model:?}: {error}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn pool_output_stops_at_the_reserve() {
+    let pool = ConstantSumPool::new("USDC", "USDT", 1_000., 400.).to_uni_v2();
+    assert!(pool.get_output_amount("USDC", 399.).unwrap() < 399.);
+    assert!(matches!(
+        pool.get_output_amount("USDC", 410.),
+        Err(RouterError::PoolOperation {
+            error: PoolError::InsufficientLiquidity { reserve: 400., .. },
+            ..
+        })
+    ));
+    let mut swapped = pool.clone();
+    assert!(swapped.apply_swap("USDC", 410.).is_err());
+    assert_eq!((swapped.reserve0, swapped.reserve1), (1_000., 400.));
+}
@@ -0,0 +1,90 @@
+//! The core crate stays free of the dependencies of the integrations: front-ends, servers,
+//! fetchers and bindings live in `uniswap-routing-integrations`.
+
+use std::process::Command;
+
+/// Crates only the integrations may pull in, directly or not.
+const INTEGRATION_CRATES: &[&str] = &[
+    "uniswap-routing-integrations",
+    "routing-challenge-rs",
+    "serde_json",
+    "clap",
+    "tokio",
+    "hyper",
+    "axum",
+    "reqwest",
+    "ethers",
+    "alloy",
+    "pyo3",
+    "wasm-bindgen",
+];
+
+/// Crates the core may depend on, with every feature enabled.
+const CORE_DEPENDENCIES: &[&str] = &["itertools", "log", "rayon", "serde"];
+
+/// Returns the names of the crates in the normal dependency tree of the core, itself included,
+/// with every feature enabled.
+fn dependency_tree() -> Vec<String> {
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "tree",
+            "--offline",
+            "--package",
+            "uniswap-routing-core",
+            "--all-features",
+            "--edges",
+            "normal",
+            "--prefix",
+            "none",
+            "--format",
+            "{p}",
+        ])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("cargo runs");
+    assert!(
+        output.status.success(),
+        "cargo tree failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .expect("cargo tree prints UTF-8")
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn core_tree_has_no_integration_crate() {
+    let tree = dependency_tree();
+    assert!(tree.iter().any(|name| name == "uniswap-routing-core"));
+    assert!(tree.iter().any(|name| name == "itertools"));
+    for name in &tree {
+        assert!(
+            !INTEGRATION_CRATES.contains(&name.as_str()),
+            "the core depends on {name}"
+        );
+    }
+}
+
+#[test]
+fn core_depends_directly_on_its_listed_crates_only() {
+    let manifest = include_str!("../Cargo.toml");
+    let dependencies = manifest
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "[dependencies]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split(['.', '=', ' ']).next())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    assert!(!dependencies.is_empty());
+    for name in dependencies {
+        assert!(
+            CORE_DEPENDENCIES.contains(&name),
+            "the core depends on {name}"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Dust handling of tiny trades, see `RouterConfig::dust_tolerance`.
+
+use uniswap_routing_core::{Router, router::FeeModel, test_utils::example_pools};
+
+use std::collections::HashMap;
+
+/// Returns the amount of every token held by the pools of `router`.
+fn holdings(router: &Router<'_>) -> HashMap<String, f64> {
+    let mut holdings = HashMap::new();
+    for pool in router.pools() {
+        *holdings.entry(pool.token0.to_string()).or_default() += pool.reserve0;
+        *holdings.entry(pool.token1.to_string()).or_default() += pool.reserve1;
+    }
+    holdings
+}
+
+#[test]
+fn ten_thousand_tiny_alternating_trades_keep_the_reserves_consistent() {
+    let mut router = Router::builder(example_pools())
+        .fee_model(FeeModel::FeeFree)
+        .build()
+        .unwrap();
+    router.settle();
+    let mut expected = router
+        .reserve_by_token()
+        .map(|(token, total)| (token.to_string(), total))
+        .collect::<HashMap<_, _>>();
+    let (mut clamped, mut paid) = (0, 0);
+
+    for trade in 0..10_000 {
+        // Amounts around the dust tolerance of the output token, about 1e-9 of its reserve
+        let scale = 1.0 + (trade % 7) as f64;
+        let (input_token, output_token, amount) = match trade % 2 {
+            0 => ("ETH", "USDC", 1e-7 * scale),
+            _ => ("USDC", "ETH", 1e-4 * scale),
+        };
+        let output = router.solve(input_token, output_token, amount).unwrap();
+        assert!(
+            output.is_finite() && output >= 0.0,
+            "trade {trade} output {output}"
+        );
+        match output == 0.0 {
+            true => clamped += 1,
+            false => paid += 1,
+        }
+        *expected.get_mut(input_token).unwrap() += amount;
+        *expected.get_mut(output_token).unwrap() -= output;
+
+        if trade % 1_000 == 999 {
+            assert_eq!(router.verify_invariants(), Ok(()), "after trade {trade}");
+        }
+    }
+
+    assert!(clamped > 0 && paid > 0, "{clamped} clamped, {paid} paid");
+    // The totals follow the trades exactly; the pool reserves, recomputed from the equilibrium
+    // prices after every trade, match them within the tolerance of the solver, without drifting
+    let holdings = holdings(&router);
+    for (token, total) in router.reserve_by_token() {
+        let expected = expected[token];
+        assert!(
+            (total - expected).abs() <= 1e-12 * expected,
+            "{token}: total {total}, {expected} expected"
+        );
+        let held = holdings[token];
+        assert!(
+            (held - total).abs() <= 1e-9 * total,
+            "{token}: {held} held, total {total}"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Residual of the equilibrium after fee-free solves, and idempotence of the equilibration, see
+//! `Router::equilibrium_residual`. Fee-charging trades leave the pools of a pair apart by up to
+//! their fees, off the fee-free equilibrium the residual measures.
+
+use uniswap_routing_core::{
+    Router,
+    router::FeeModel,
+    test_utils::{example_pools, synthetic_pools},
+};
+
+/// Relative tolerance of the default solver.
+const TOLERANCE: f64 = 1e-12;
+
+#[test]
+fn residual_is_below_the_tolerance_after_every_solve() {
+    for pools in [example_pools(), synthetic_pools(60, 180, 326)] {
+        let mut router = Router::builder(pools)
+            .fee_model(FeeModel::FeeFree)
+            .build()
+            .unwrap();
+        assert!(!router.is_equilibrated(TOLERANCE));
+        router.settle();
+        assert!(router.is_equilibrated(TOLERANCE));
+        let tokens = router.replica_snapshot().tokens;
+        for (input, output, amount) in [(1, 2, 1.), (0, 3, 100.), (2, 0, 0.1)] {
+            router
+                .solve(&tokens[input], &tokens[output], amount)
+                .unwrap();
+            assert!(
+                router.is_equilibrated(TOLERANCE),
+                "{} after {} -> {}",
+                router.equilibrium_residual(),
+                tokens[input],
+                tokens[output]
+            );
+        }
+    }
+}
+
+#[test]
+fn second_equilibration_moves_no_price() {
+    // Measured: prices moved by up to 3 ulps, residuals of 4 ulps
+    for pools in [example_pools(), synthetic_pools(60, 180, 326)] {
+        let mut router = Router::builder(pools)
+            .fee_model(FeeModel::FeeFree)
+            .tolerances(1e-15, 0.)
+            .build()
+            .unwrap();
+        router.settle();
+        assert!(router.is_equilibrated(16. * f64::EPSILON));
+        let prices = router.replica_snapshot().prices;
+        router.settle();
+        assert!(router.is_equilibrated(16. * f64::EPSILON));
+        for (price, settled_price) in prices.iter().zip(router.replica_snapshot().prices) {
+            assert!((settled_price / price - 1.).abs() <= 16. * f64::EPSILON);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Idempotent application of pool events and rollbacks of reorganized blocks, see
+//! `Router::apply_events`.
+
+use uniswap_routing_core::{
+    Router, UniV2Pool,
+    router::{EventKey, PoolEvent, PoolEventKind},
+    test_utils::example_pools,
+};
+
+fn new_pool(block: u64, log_index: u64) -> PoolEvent {
+    PoolEvent::new(PoolEventKind::NewPool(UniV2Pool::new(
+        "ETH", "USDC", 500., 500_000.,
+    )))
+    .with_key(EventKey::from_log(block, log_index))
+}
+
+/// Returns the tokens and the bits of the reserves of every pool of `router`.
+fn reserves(router: &Router<'_>) -> Vec<(String, String, u64, u64)> {
+    router
+        .pools()
+        .iter()
+        .map(|pool| {
+            (
+                pool.token0.to_string(),
+                pool.token1.to_string(),
+                pool.reserve0.to_bits(),
+                pool.reserve1.to_bits(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn redelivered_new_pool_is_applied_once() {
+    let mut router = Router::new(example_pools());
+    let pools = router.pools().len();
+    let key = EventKey::from_log(10, 0);
+
+    let first = router.apply_events([new_pool(10, 0)]).unwrap();
+    assert_eq!(first.applied, 1);
+    assert_eq!(first.new_pools.len(), 1);
+    let hash = router.content_hash();
+
+    let redelivered = router.apply_events([new_pool(10, 0)]).unwrap();
+    assert_eq!(redelivered.applied, 0);
+    assert_eq!(redelivered.duplicates, [key]);
+    assert!(redelivered.new_pools.is_empty());
+    assert_eq!(router.pools().len(), pools + 1);
+    assert_eq!(router.content_hash(), hash);
+}
+
+#[test]
+fn duplicate_within_a_batch_is_applied_once() {
+    let mut router = Router::new(example_pools());
+    let pools = router.pools().len();
+    let summary = router
+        .apply_events([new_pool(10, 0), new_pool(10, 0)])
+        .unwrap();
+    assert_eq!(summary.applied, 1);
+    assert_eq!(summary.duplicates, [EventKey::from_log(10, 0)]);
+    assert_eq!(router.pools().len(), pools + 1);
+}
+
+#[test]
+fn out_of_order_key_is_rejected_without_change() {
+    let mut router = Router::new(example_pools());
+    router.apply_events([new_pool(10, 1)]).unwrap();
+    let hash = router.content_hash();
+    assert!(router.apply_events([new_pool(10, 0)]).is_err());
+    assert_eq!(router.content_hash(), hash);
+    router.apply_events([new_pool(10, 0).reorg()]).unwrap();
+}
+
+#[test]
+fn rollback_restores_the_pre_fork_state_exactly() {
+    let mut router = Router::new(example_pools());
+    let eth_usdc = router.pool_ids()[0].clone();
+    let fork_point = EventKey::from_log(10, 0);
+    router
+        .apply_events([PoolEvent::new(PoolEventKind::Sync {
+            pool: eth_usdc.clone(),
+            reserve0: 2_100.,
+            reserve1: 1_950_000.,
+        })
+        .with_key(fork_point)])
+        .unwrap();
+    let hash = router.content_hash();
+    let pools = reserves(&router);
+    let quote = router.quote("ETH", "DAI", 3.0).unwrap();
+
+    // The fork adds a pool, moves another and trades on top
+    router
+        .apply_events([
+            new_pool(11, 0),
+            PoolEvent::new(PoolEventKind::Sync {
+                pool: eth_usdc,
+                reserve0: 1_900.,
+                reserve1: 2_100_000.,
+            })
+            .with_key(EventKey::from_log(11, 1)),
+        ])
+        .unwrap();
+    router.solve("USDC", "ETH", 1_000.0).unwrap();
+    assert_ne!(router.content_hash(), hash);
+
+    let summary = router
+        .apply_events([PoolEvent::new(PoolEventKind::Rollback {
+            to_key: fork_point,
+        })])
+        .unwrap();
+    assert_eq!(summary.rolled_back_to, Some(fork_point));
+    assert_eq!(router.content_hash(), hash);
+    assert_eq!(reserves(&router), pools);
+    assert_eq!(
+        router.quote("ETH", "DAI", 3.0).unwrap().to_bits(),
+        quote.to_bits()
+    );
+
+    // The canonical chain may now reuse the keys of the abandoned fork
+    let summary = router.apply_events([new_pool(11, 0).reorg()]).unwrap();
+    assert_eq!(summary.applied, 1);
+    assert!(summary.duplicates.is_empty());
+}
@@ -0,0 +1,233 @@
+//! Trades that fail, and must leave the router as it was.
+
+use uniswap_routing_core::{
+    Router, UniV2Pool,
+    error::RouterError,
+    router::{FeeModel, ReplicaRouter, SolverConfig},
+    test_utils::example_pools,
+};
+
+/// Returns the bits of the total reserve of every token of `router`, and its content hash.
+fn state(router: &Router<'_>) -> (Vec<(String, u64)>, u64) {
+    (
+        router
+            .reserve_by_token()
+            .map(|(token, reserve)| (token.to_string(), reserve.to_bits()))
+            .collect(),
+        router.content_hash(),
+    )
+}
+
+#[test]
+fn unconverged_solve_fails_without_changing_the_state() {
+    let mut router = Router::builder(example_pools())
+        .solver(SolverConfig {
+            max_iterations: 1,
+            ..SolverConfig::default()
+        })
+        .build()
+        .unwrap();
+    let before = state(&router);
+
+    let Err(RouterError::DidNotConverge {
+        iterations,
+        residual,
+    }) = router.solve("ETH", "USDC", 10.)
+    else {
+        panic!("a single sweep cannot converge on the example");
+    };
+    assert_eq!(iterations, 1);
+    assert!(residual.is_finite() && residual > SolverConfig::default().tolerance);
+    assert_eq!(state(&router), before);
+}
+
+#[test]
+fn token_left_without_pools_is_isolated() {
+    let mut pools = example_pools();
+    pools.push(UniV2Pool::new("USDT", "FOO", 1_000., 50.));
+    let mut router = Router::new(pools);
+    let foo_pool = router.pool_ids().last().unwrap().clone();
+    router.remove_pool(&foo_pool).unwrap();
+    let before = state(&router);
+
+    for (input, output) in [("ETH", "FOO"), ("FOO", "ETH")] {
+        assert_eq!(
+            router.solve(input, output, 1.),
+            Err(RouterError::IsolatedToken("FOO".to_string()))
+        );
+        assert_eq!(state(&router), before);
+    }
+
+    // The other tokens trade as if FOO was not there
+    let output = router.solve("ETH", "USDT", 1.).unwrap();
+    assert!(output.is_finite() && output > 0.0);
+    router.verify_invariants().unwrap();
+}
+
+/// Returns a router over the example pools whose trades are fee-free, solved over the totals of
+/// the token graph which the replicas below corrupt; fee-charging trades solve the pools.
+fn fee_free_example() -> Router<'static> {
+    Router::builder(example_pools())
+        .fee_model(FeeModel::FeeFree)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn non_finite_reserve_surfaces_an_error_rather_than_nan() {
+    // Pools entering a router are validated, a replica takes the state of its primary as it is
+    let primary = fee_free_example();
+    let eth = primary.token_id("ETH").unwrap().index();
+    for reserve in [f64::NAN, f64::INFINITY, 0.0] {
+        let mut snapshot = primary.replica_snapshot();
+        let pool = &mut snapshot.pools[2];
+        assert_eq!(pool.token0, "ETH");
+        snapshot.totals[eth] += reserve - pool.reserve0;
+        pool.reserve0 = reserve;
+        let replica = ReplicaRouter::new(snapshot).unwrap();
+        let hash = replica.content_hash();
+
+        for (input, output) in [("ETH", "DAI"), ("DAI", "ETH"), ("USDC", "USDT")] {
+            match replica.quote(input, output, 1.) {
+                Ok(output_amount) => panic!("{input} -> {output} quoted {output_amount}"),
+                Err(
+                    RouterError::InvalidPrice { .. }
+                    | RouterError::NumericalError { .. }
+                    | RouterError::NegativeOutput { .. },
+                ) => {}
+                Err(error) => panic!("{input} -> {output} failed with {error}"),
+            }
+        }
+        assert_eq!(replica.content_hash(), hash);
+    }
+}
+
+#[test]
+fn non_finite_output_is_an_error_without_strict_math() {
+    let primary = fee_free_example();
+    let dai = primary.token_id("DAI").unwrap().index();
+    for total in [f64::INFINITY, f64::NAN] {
+        let mut snapshot = primary.replica_snapshot();
+        snapshot.config.strict_math = false;
+        snapshot.totals[dai] = total;
+        let replica = ReplicaRouter::new(snapshot).unwrap();
+        let hash = replica.content_hash();
+        match replica.quote("ETH", "DAI", 1.) {
+            Err(RouterError::NumericalError { token, value }) => {
+                assert_eq!(token, "DAI");
+                assert!(!value.is_finite());
+            }
+            result => panic!("{total} DAI quoted {result:?}"),
+        }
+        assert_eq!(replica.content_hash(), hash);
+    }
+}
+
+#[test]
+fn trades_stay_within_their_component() {
+    let mut pools = example_pools();
+    pools.push(UniV2Pool::new("FOO", "BAR", 1_000., 2_000.));
+    pools.push(UniV2Pool::new("BAR", "FOO", 3_000., 1_000.));
+    let mut router = Router::new(pools);
+    // Prices and pools of FOO and BAR, bit for bit
+    let foo_bar = |router: &Router<'_>| {
+        let snapshot = router.replica_snapshot();
+        let prices = snapshot
+            .tokens
+            .iter()
+            .zip(&snapshot.prices)
+            .filter(|(token, _)| ["FOO", "BAR"].contains(&token.as_str()))
+            .map(|(token, q)| (token.clone(), q.to_bits()))
+            .collect::<Vec<_>>();
+        let pools = snapshot
+            .pools
+            .iter()
+            .filter(|pool| pool.token0 == "FOO" || pool.token0 == "BAR")
+            .map(|pool| (pool.reserve0.to_bits(), pool.reserve1.to_bits()))
+            .collect::<Vec<_>>();
+        (prices, pools)
+    };
+    let before = state(&router);
+    let untouched = foo_bar(&router);
+
+    assert_eq!(
+        router.solve("ETH", "BAR", 1.),
+        Err(RouterError::NotConnected {
+            input_token: "ETH".to_string(),
+            output_token: "BAR".to_string(),
+        })
+    );
+    assert_eq!(state(&router), before);
+
+    router.solve("ETH", "USDC", 10.).unwrap();
+    router.solve("DAI", "ETH", 5_000.).unwrap();
+    assert_eq!(foo_bar(&router), untouched);
+    assert_eq!(untouched.0.len(), 2);
+    assert_eq!(untouched.1.len(), 2);
+}
+
+#[test]
+fn invalid_amounts_are_rejected_before_any_change() {
+    let mut router = Router::new(example_pools());
+    let before = state(&router);
+    let pool = example_pools().remove(0);
+    let rejected = |result: Result<f64, RouterError>, amount: f64| matches!(result, Err(RouterError::InvalidAmount(rejected)) if rejected.to_bits() == amount.to_bits());
+
+    for amount in [
+        -5.,
+        -f64::MIN_POSITIVE,
+        f64::NAN,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    ] {
+        assert!(rejected(router.solve("ETH", "USDC", amount), amount));
+        assert!(rejected(router.quote("ETH", "USDC", amount), amount));
+        assert!(rejected(
+            router
+                .quote_curve("ETH", "USDC", &[1., amount])
+                .map(|outputs| outputs[0]),
+            amount
+        ));
+        assert!(rejected(pool.get_output_amount("ETH", amount), amount));
+        assert_eq!(state(&router), before);
+    }
+
+    // Zero is a no-op, even before the first trade captures the arbitrage
+    assert_eq!(router.solve("ETH", "USDC", 0.), Ok(0.));
+    assert_eq!(router.quote_curve("ETH", "USDC", &[0.]), Ok(vec![0.]));
+    assert_eq!(pool.get_output_amount("ETH", 0.), Ok(0.));
+    assert_eq!(state(&router), before);
+}
+
+/// Returns a replica of the settled example whose snapshot lacks 1% of the DAI the pools hold,
+/// with `dust_tolerance`: consistent pools always yield a non-negative output, the replica has to
+/// top DAI up to reach the equilibrium.
+fn short_of_dai(dust_tolerance: f64) -> (ReplicaRouter, f64) {
+    let mut primary = fee_free_example();
+    primary.settle();
+    let dai = primary.token_id("DAI").unwrap().index();
+    let mut snapshot = primary.replica_snapshot();
+    let deficit = 0.01 * snapshot.totals[dai];
+    snapshot.totals[dai] -= deficit;
+    snapshot.config.dust_tolerance = dust_tolerance;
+    (ReplicaRouter::new(snapshot).unwrap(), deficit)
+}
+
+#[test]
+fn output_topped_up_beyond_the_dust_is_an_error() {
+    let (replica, deficit) = short_of_dai(1e-9);
+    let hash = replica.content_hash();
+    let Err(RouterError::NegativeOutput { amount }) = replica.quote("ETH", "DAI", 1.) else {
+        panic!("the missing DAI must come out of the output");
+    };
+    assert!(amount < -0.9 * deficit);
+    assert_eq!(replica.content_hash(), hash);
+}
+
+// The trade commits, which paranoid builds audit, and the totals are not those of the pools
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn output_topped_up_within_the_dust_is_zero() {
+    let (replica, _) = short_of_dai(0.02);
+    assert_eq!(replica.quote("ETH", "DAI", 1e-6), Ok(0.));
+}
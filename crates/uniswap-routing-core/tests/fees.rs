@@ -0,0 +1,48 @@
+//! Swap fees charged by default, see `FeeModel`.
+
+use uniswap_routing_core::{Router, UniV2Pool, router::FeeModel};
+
+#[test]
+fn single_pool_route_matches_get_output_amount() {
+    for pool in [
+        UniV2Pool::new("A", "B", 10., 40.),
+        UniV2Pool::new("ETH", "USDC", 2_000., 2_000_000.),
+        UniV2Pool::new("ETH", "USDC", 2_000., 2_000_000.).with_fee(0.0025),
+    ] {
+        let router = Router::new(vec![pool.clone()]);
+        for (input_token, output_token) in [(pool.token0, pool.token1), (pool.token1, pool.token0)]
+        {
+            let reserve_in = match input_token == pool.token0 {
+                true => pool.reserve0,
+                false => pool.reserve1,
+            };
+            for size_ratio in [1e-6, 1e-3, 0.1, 1., 10.] {
+                let amount = size_ratio * reserve_in;
+                let expected = pool.get_output_amount(input_token, amount).unwrap();
+                let quoted = router.quote(input_token, output_token, amount).unwrap();
+                assert!(
+                    (quoted / expected - 1.).abs() < 1e-9,
+                    "{amount} {input_token}: quoted {quoted}, {expected} expected"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn default_fee_is_the_textbook_formula() {
+    // amountOut = 997 · x · R_out / (1000 · R_in + 997 · x)
+    let pool = UniV2Pool::new("ETH", "USDC", 2_000., 2_000_000.);
+    let router = Router::new(vec![pool]);
+    let textbook = 997. * 10. * 2_000_000. / (1_000. * 2_000. + 997. * 10.);
+    let quoted = router.quote("ETH", "USDC", 10.).unwrap();
+    assert!((quoted / textbook - 1.).abs() < 1e-9);
+
+    // The fee-free model quotes the bare curve, optimistic by about the fee
+    let fee_free = Router::builder(vec![UniV2Pool::new("ETH", "USDC", 2_000., 2_000_000.)])
+        .fee_model(FeeModel::FeeFree)
+        .build()
+        .unwrap();
+    let curve = 10. * 2_000_000. / (2_000. + 10.);
+    assert!((fee_free.quote("ETH", "USDC", 10.).unwrap() / curve - 1.).abs() < 1e-12);
+}
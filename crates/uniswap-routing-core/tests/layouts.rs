@@ -0,0 +1,95 @@
+//! Independence of the results from the layout of the pair liquidities, bit for bit, see
+//! `LiquidityStorage`, and from the summation of the dense rows in full.
+
+use uniswap_routing_core::{
+    Router, UniV2Pool,
+    router::{FeeModel, LiquidityStorage, SweepMode},
+    test_utils::{example_pools, synthetic_pools},
+};
+
+/// Settles a router over `pools` laying the liquidities out as `storage`, then returns the
+/// outputs of a sequence of trades, each solved from the state the previous ones left, with the
+/// prices of every token after them.
+fn results(
+    pools: Vec<UniV2Pool>,
+    storage: LiquidityStorage,
+    trades: &[(&str, &str, f64)],
+) -> Vec<f64> {
+    let mut router = Router::builder(pools)
+        .fee_model(FeeModel::FeeFree)
+        .liquidity_storage(storage)
+        .build()
+        .unwrap();
+    assert_eq!(router.liquidity_layout(), storage);
+    router.settle();
+    let mut results = trades
+        .iter()
+        .map(|&(input, output, amount)| router.solve(input, output, amount).unwrap())
+        .collect::<Vec<_>>();
+    results.extend(router.replica_snapshot().prices);
+    results
+}
+
+/// Asserts that the results of `trades` over `pools` are the same bit for bit in both layouts.
+fn assert_layouts_agree(pools: Vec<UniV2Pool>, trades: &[(&str, &str, f64)]) {
+    let dense = results(pools.clone(), LiquidityStorage::Dense, trades);
+    let sparse = results(pools, LiquidityStorage::Sparse, trades);
+    assert_eq!(dense.len(), sparse.len());
+    for (n, (dense, sparse)) in dense.iter().zip(&sparse).enumerate() {
+        assert_eq!(
+            dense.to_bits(),
+            sparse.to_bits(),
+            "{n}: {dense} against {sparse}"
+        );
+    }
+}
+
+#[test]
+fn example_pools_trade_the_same_in_both_layouts() {
+    let trades = [
+        ("ETH", "USDC", 1.),
+        ("USDC", "DAI", 2_500.),
+        ("DAI", "ETH", 10_000.),
+        ("USDC", "ETH", 1e-6),
+    ];
+    assert_layouts_agree(example_pools(), &trades);
+}
+
+#[test]
+fn synthetic_pools_trade_the_same_in_both_layouts() {
+    let trades = [
+        ("T0", "T1", 1.),
+        ("T42", "T3", 250.),
+        ("T7", "T0", 10.),
+        ("T1", "T42", 1e-3),
+    ];
+    assert_layouts_agree(synthetic_pools(120, 360, 349), &trades);
+}
+
+#[test]
+fn dense_rows_summed_in_full_match_the_neighbor_sums() {
+    // 200 tokens sharing pools with about 60 others each, beyond the density from which the
+    // dense rows are summed in full
+    let pools = synthetic_pools(200, 6_000, 352);
+    for sweep_mode in [SweepMode::GaussSeidel, SweepMode::Jacobi] {
+        for blacklisted in [None, Some("T17")] {
+            let mut builder = Router::builder(pools.clone())
+                .fee_model(FeeModel::FeeFree)
+                .liquidity_storage(LiquidityStorage::Dense)
+                .sweep_mode(sweep_mode);
+            if let Some(token) = blacklisted {
+                builder = builder.blacklist_token(token);
+            }
+            let router = builder.build().unwrap();
+            let full_rows = router.sweep_denominators(true);
+            let neighbors = router.sweep_denominators(false);
+            for (token, (full_row, neighbors)) in full_rows.iter().zip(&neighbors).enumerate() {
+                assert_eq!(
+                    full_row.to_bits(),
+                    neighbors.to_bits(),
+                    "{sweep_mode:?} {blacklisted:?} T{token}: {full_row} against {neighbors}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Budgeted best-first path search, see `Router::search_paths`.
+
+use uniswap_routing_core::{Router, router::PathSearchRequest, test_utils::synthetic_pools};
+
+fn request(
+    max_hops: usize,
+    budget: usize,
+    beam_width: Option<usize>,
+) -> PathSearchRequest<'static> {
+    PathSearchRequest {
+        input_token: "T0",
+        output_token: "T1",
+        input_amount: 10.0,
+        max_hops,
+        budget,
+        beam_width,
+    }
+}
+
+#[test]
+fn budgeted_search_finds_the_unbudgeted_best_path_on_a_small_dense_graph() {
+    let router = Router::new(synthetic_pools(8, 28, 277));
+    let unbudgeted = router.search_paths(&request(4, usize::MAX, None));
+    let budgeted = router.search_paths(&request(4, 10_000, None));
+
+    assert!(!unbudgeted.possibly_suboptimal);
+    assert!(!budgeted.possibly_suboptimal);
+    assert!(budgeted.paths_examined <= 10_000);
+    let best = budgeted.best.expect("T1 is reachable");
+    assert_eq!(Some(&best), unbudgeted.best.as_ref());
+    assert_eq!(best.tokens.first(), Some(&"T0"));
+    assert_eq!(best.tokens.last(), Some(&"T1"));
+}
+
+#[test]
+fn search_stops_within_its_budget_on_a_large_dense_graph() {
+    let router = Router::new(synthetic_pools(60, 1_500, 277));
+    let unbudgeted = router.search_paths(&request(5, usize::MAX, None));
+    let needed = unbudgeted.paths_examined;
+    assert!(needed > 1);
+    for budget in [1, needed / 2, needed - 1, needed, 2 * needed] {
+        let result = router.search_paths(&request(5, budget, None));
+        assert!(result.paths_examined <= budget);
+        assert_eq!(
+            result.possibly_suboptimal,
+            budget < needed,
+            "budget {budget}"
+        );
+        if budget >= needed {
+            assert_eq!(result.best, unbudgeted.best);
+        }
+    }
+}
+
+#[test]
+fn beam_width_truncation_flags_the_result() {
+    let router = Router::new(synthetic_pools(60, 1_500, 277));
+    let result = router.search_paths(&request(4, usize::MAX, Some(2)));
+    assert!(result.possibly_suboptimal);
+    assert!(result.best.is_some());
+}
+
+#[test]
+fn truncated_search_never_beats_the_optimal_path() {
+    let router = Router::new(synthetic_pools(30, 300, 277));
+    let optimal = router
+        .search_paths(&request(3, usize::MAX, None))
+        .best
+        .expect("T1 is reachable");
+    for budget in [5, 50, 500] {
+        if let Some(path) = router.search_paths(&request(3, budget, None)).best {
+            assert!(path.output_amount <= optimal.output_amount);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Pools and pool reserves refused wherever they enter the router, which is left untouched.
+
+use uniswap_routing_core::{
+    Router, UniV2Pool,
+    error::{PoolError, RouterError},
+    router::{LiquidityStorage, PoolEvent, PoolEventKind},
+    test_utils::{example_pools, synthetic_pools},
+};
+
+/// Returns a pool pairing ETH with itself, which [`UniV2Pool::new`] refuses to build.
+fn self_pool() -> UniV2Pool {
+    let mut pool = UniV2Pool::new("ETH", "DAI", 100., 100.);
+    pool.token1 = "ETH";
+    pool
+}
+
+fn is_identical_eth<T>(result: Result<T, RouterError>) -> bool {
+    matches!(
+        result,
+        Err(RouterError::InvalidPool { error: PoolError::IdenticalTokens(token), .. })
+            if token == "ETH"
+    )
+}
+
+#[test]
+#[should_panic(expected = "a pool needs two distinct tokens")]
+fn pool_of_identical_tokens_cannot_be_built() {
+    UniV2Pool::new("ETH", "ETH", 100., 100.);
+}
+
+#[test]
+#[should_panic(expected = "pairs ETH with itself")]
+fn router_panics_on_a_pool_of_identical_tokens() {
+    let mut pools = example_pools();
+    pools.push(self_pool());
+    Router::new(pools);
+}
+
+#[test]
+fn pools_of_identical_tokens_are_rejected() {
+    let mut pools = example_pools();
+    pools.push(self_pool());
+    assert!(is_identical_eth(Router::builder(pools).build()));
+
+    let mut router = Router::new(example_pools());
+    let hash = router.content_hash();
+    assert!(is_identical_eth(router.add_pool(self_pool())));
+    assert!(is_identical_eth(router.merge(vec![
+        UniV2Pool::new("ETH", "USDC", 500., 500_000.),
+        self_pool(),
+    ])));
+    assert!(is_identical_eth(router.apply_events([PoolEvent::new(
+        PoolEventKind::NewPool(self_pool()),
+    )])));
+    assert_eq!(router.content_hash(), hash);
+}
+
+#[test]
+fn invalid_reserves_are_rejected_on_update() {
+    let mut router = Router::new(example_pools());
+    let hash = router.content_hash();
+    let id = router.pool_ids()[2].clone();
+    let other = router.pool_ids()[0].clone();
+    let (token0, token1) = (router.pools()[2].token0, router.pools()[2].token1);
+    let is_invalid = |error: Option<RouterError>, token: &str, reserve: f64| {
+        matches!(
+            error,
+            Some(RouterError::InvalidPool {
+                pool,
+                error: PoolError::InvalidReserve { token: rejected, reserve: value },
+            }) if pool == id && rejected == token && value.to_bits() == reserve.to_bits()
+        )
+    };
+
+    for reserve in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -1., -0., 0.] {
+        for (token, reserve0, reserve1) in [(token0, reserve, 1.), (token1, 1., reserve)] {
+            let result = router.update_pool_reserves(&id, reserve0, reserve1);
+            assert!(
+                is_invalid(result.err(), token, reserve),
+                "{reserve} of {token}"
+            );
+            assert_eq!(router.content_hash(), hash);
+
+            // The batch fails as a whole, the valid event before the invalid one included
+            let result = router.apply_events([
+                PoolEvent::new(PoolEventKind::Sync {
+                    pool: other.clone(),
+                    reserve0: 2.,
+                    reserve1: 3.,
+                }),
+                PoolEvent::new(PoolEventKind::Sync {
+                    pool: id.clone(),
+                    reserve0,
+                    reserve1,
+                }),
+            ]);
+            assert!(
+                is_invalid(result.err(), token, reserve),
+                "{reserve} of {token}"
+            );
+            assert_eq!(router.content_hash(), hash);
+        }
+    }
+}
+
+#[test]
+fn liquidity_diagonal_is_empty() {
+    for storage in [LiquidityStorage::Dense, LiquidityStorage::Sparse] {
+        for pools in [
+            example_pools(),
+            synthetic_pools(20, 60, 331),
+            synthetic_pools(50, 49, 332),
+        ] {
+            let mut router = Router::builder(pools)
+                .liquidity_storage(storage)
+                .build()
+                .unwrap();
+            router.verify_invariants().unwrap();
+            router.add_pool(UniV2Pool::new("T1", "T2", 5., 7.)).ok();
+            router.settle();
+            router.verify_invariants().unwrap();
+        }
+    }
+}
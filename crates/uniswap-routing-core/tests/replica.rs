@@ -0,0 +1,75 @@
+//! Replicas following a primary router through its state deltas, see `ReplicaRouter`.
+
+use uniswap_routing_core::{
+    Router, UniV2Pool,
+    router::{ReplicaRouter, StateDelta},
+    test_utils::example_pools,
+};
+
+const QUOTES: [(&str, &str, f64); 4] = [
+    ("ETH", "USDC", 10.),
+    ("USDC", "DAI", 25_000.),
+    ("USDT", "ETH", 1_000.),
+    ("DAI", "USDT", 0.5),
+];
+
+fn assert_same_quotes(primary: &Router<'_>, replica: &ReplicaRouter) {
+    for (input, output, amount) in QUOTES {
+        assert_eq!(
+            primary.quote(input, output, amount).unwrap().to_bits(),
+            replica.quote(input, output, amount).unwrap().to_bits(),
+            "{input} -> {output} at version {}",
+            replica.version()
+        );
+    }
+}
+
+#[test]
+fn replicas_quote_as_the_primary() {
+    let mut primary = Router::builder(example_pools())
+        .publish_deltas()
+        .build()
+        .unwrap();
+    let mut following = ReplicaRouter::new(primary.replica_snapshot()).unwrap();
+    let mut lagging = ReplicaRouter::new(primary.replica_snapshot()).unwrap();
+    assert_same_quotes(&primary, &following);
+
+    let mut deltas = Vec::<StateDelta>::new();
+    let mut publish = |primary: &Router<'_>| {
+        deltas.push(primary.publish().unwrap().clone());
+        deltas.last().unwrap().clone()
+    };
+
+    primary.solve("ETH", "USDC", 5.).unwrap();
+    let first = publish(&primary);
+    primary.settle();
+    let second = publish(&primary);
+    let ids = primary.pool_ids().to_vec();
+    primary
+        .update_pool_reserves(&ids[3], 3_100., 2_900_000.)
+        .unwrap();
+    let third = publish(&primary);
+    primary
+        .add_pool(UniV2Pool::new("USDC", "USDT", 500_000., 499_000.))
+        .unwrap();
+    let fourth = publish(&primary);
+    primary.remove_pool(&ids[1]).unwrap();
+    let fifth = publish(&primary);
+    primary.solve("DAI", "ETH", 40_000.).unwrap();
+    let sixth = publish(&primary);
+
+    for delta in [&first, &second, &third, &fourth, &fifth, &sixth] {
+        following.apply_delta(delta).unwrap();
+    }
+    assert_eq!(following.version(), sixth.version);
+    assert_same_quotes(&primary, &following);
+
+    // The lagging replica misses the third delta, notices the gap and resyncs
+    lagging.apply_delta(&first).unwrap();
+    lagging.apply_delta(&second).unwrap();
+    assert!(lagging.apply_delta(&fourth).is_err());
+    assert_eq!(lagging.version(), second.version);
+    lagging.resync(primary.replica_snapshot()).unwrap();
+    assert_eq!(lagging.version(), sixth.version);
+    assert_same_quotes(&primary, &lagging);
+}
@@ -0,0 +1,108 @@
+//! Routing sessions written as scenarios, see `uniswap_routing_core::scenario`.
+
+use uniswap_routing_core::{
+    router::{FeeModel, RouterConfig},
+    scenario::Scenario,
+};
+
+/// Scenario of fee-free trades, whose outputs follow from the constant-product curves alone.
+fn fee_free() -> Scenario {
+    Scenario::new().config(RouterConfig {
+        fee_model: FeeModel::FeeFree,
+        ..RouterConfig::default()
+    })
+}
+
+/// Pools of the example of the binary, those of `test_utils::example_pools`.
+fn example() -> Scenario {
+    fee_free()
+        .pool("ETH/USDC", 2_000., 2_000_000.)
+        .pool("ETH/USDC", 1_000., 1_000_000.)
+        .named_pool("cheap ETH/DAI", "ETH/DAI", 1_000., 900_000.)
+        .pool("ETH/DAI", 3_000., 2_800_000.)
+        .named_pool("dear ETH/DAI", "ETH/DAI", 3_000., 3_100_000.)
+        .pool("DAI/USDC", 1_000_000., 1_000_000.)
+        .pool("DAI/USDC", 2_000_000., 2_000_000.)
+        .pool("DAI/USDT", 1_000_000., 900_000.)
+        .pool("DAI/USDT", 900_000., 1_000_000.)
+        .pool("ETH/USDT", 2_000., 2_000_000.)
+        .pool("ETH/USDT", 10_000., 10_000_000.)
+}
+
+#[test]
+fn parallel_pools_trade_as_one() {
+    // Two 10/40 pools hold 20 A and 80 B with an invariant of (2 · 20)², selling 20 A leaves
+    // 40 A and 1600 / 40 B
+    fee_free()
+        .named_pool("first", "A/B", 10., 40.)
+        .named_pool("second", "A/B", 10., 40.)
+        .trade("A->B", 20.)
+        .expect_output_near(40., 1e-9)
+        .expect_reserves_near("first", 20., 20., 1e-12)
+        .expect_reserves_near("second", 20., 20., 1e-12)
+        .expect_spot_price_near("first", "A", 1., 1e-12)
+        .expect_conservation()
+        .run();
+}
+
+#[test]
+fn first_trade_captures_the_arbitrage() {
+    example()
+        .trade("ETH->USDC", 10.)
+        .expect_output_near(21_004.367_456_4, 1e-6)
+        .expect_conservation()
+        .trade("USDC->ETH", 10_000.)
+        .expect_output_near(10.16, 5e-3)
+        .expect_conservation()
+        .run();
+}
+
+#[test]
+fn settlement_aligns_the_pools_of_a_pair() {
+    // Once settled, the cheap and the dear pools quote ETH alike
+    example()
+        .settle()
+        .expect_conservation()
+        .expect_spot_price_near("cheap ETH/DAI", "DAI", 981.44, 1e-5)
+        .expect_spot_price_near("dear ETH/DAI", "DAI", 981.44, 1e-5)
+        .quote("ETH->DAI", 1.)
+        .trade("ETH->DAI", 1.)
+        .expect_conservation()
+        .run();
+}
+
+#[test]
+fn added_pool_takes_part_in_the_next_trade() {
+    example()
+        .settle()
+        .quote("USDC->USDT", 1_000.)
+        .expect_output_near(1_006.97, 0.01)
+        // Quoting USDT dearer than the settled pools, the new pool adds its arbitrage to the trade
+        .named_pool("new USDC/USDT", "USDC/USDT", 500_000., 500_000.)
+        .trade("USDC->USDT", 1_000.)
+        .expect_output_near(1_011.96, 0.01)
+        .expect_conservation()
+        .run();
+}
+
+#[test]
+fn unknown_token_fails_the_trade() {
+    example()
+        .trade("ETH->WBTC", 1.)
+        .expect_failure()
+        .quote("ETH->USDC", -1.)
+        .expect_failure()
+        .expect_conservation()
+        .run();
+}
+
+#[test]
+#[should_panic(expected = "scenario failed at step 4: output")]
+fn failed_step_is_reported_with_the_script() {
+    Scenario::new()
+        .pool("A/B", 10., 40.)
+        .pool("A/B", 10., 40.)
+        .trade("A->B", 20.)
+        .expect_output_near(41., 1e-9)
+        .run();
+}
@@ -0,0 +1,125 @@
+//! Replays a captured router session through the current build and compares every output with
+//! the one recorded by the build that produced the capture.
+//!
+//! Usage: `compat-check <capture.json> [--tolerance <relative>]`
+//!
+//! Exit code: 0 when every output matches within tolerance, 1 when at least one diverges, 2 when
+//! the capture cannot be read or replayed.
+
+use uniswap_routing_core::router::{CAPTURE_FORMAT_VERSION, SessionCapture};
+
+use std::{fs, process::ExitCode};
+
+const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+struct Divergence {
+    id: u64,
+    recorded: f64,
+    replayed: f64,
+    relative_error: f64,
+}
+
+fn main() -> ExitCode {
+    let (path, tolerance) = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}");
+            eprintln!("Usage: compat-check <capture.json> [--tolerance <relative>]");
+            return ExitCode::from(2);
+        }
+    };
+    let capture = match load_capture(&path) {
+        Ok(capture) => capture,
+        Err(message) => {
+            eprintln!("Cannot load {path}: {message}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let replayed = match capture.replay() {
+        Ok(replayed) => replayed,
+        Err(error) => {
+            eprintln!("Cannot replay {path}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let divergences = capture
+        .solves()
+        .zip(replayed)
+        .filter_map(|(solve, replayed)| {
+            let relative_error = relative_error(solve.output_amount, replayed);
+            (relative_error > tolerance).then_some(Divergence {
+                id: solve.id,
+                recorded: solve.output_amount,
+                replayed,
+                relative_error,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    println!(
+        "Replayed {} solves captured with version {} (tolerance {tolerance:e})",
+        capture.solves().count(),
+        capture.crate_version,
+    );
+    for divergence in &divergences {
+        println!(
+            "  solve #{}: recorded {} replayed {} (absolute {:e}, relative {:e})",
+            divergence.id,
+            divergence.recorded,
+            divergence.replayed,
+            (divergence.replayed - divergence.recorded).abs(),
+            divergence.relative_error,
+        );
+    }
+
+    match divergences.is_empty() {
+        true => {
+            println!("PASS");
+            ExitCode::SUCCESS
+        }
+        false => {
+            println!("FAIL: {} divergent solves", divergences.len());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(String, f64), String> {
+    let mut path = None;
+    let mut tolerance = DEFAULT_TOLERANCE;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tolerance" => {
+                let value = args.next().ok_or("Missing value for --tolerance")?;
+                tolerance = value
+                    .parse()
+                    .map_err(|_| format!("Invalid tolerance: {value}"))?;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+    Ok((path.ok_or("Missing capture path")?, tolerance))
+}
+
+fn load_capture(path: &str) -> Result<SessionCapture, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let capture: SessionCapture = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    if capture.format_version != CAPTURE_FORMAT_VERSION {
+        return Err(format!(
+            "unsupported capture format {} (expected {CAPTURE_FORMAT_VERSION})",
+            capture.format_version
+        ));
+    }
+    Ok(capture)
+}
+
+/// Relative error of `replayed` against `recorded`, exact zero when both are bit-identical
+/// (including matching NaNs, which a faithful replay reproduces).
+fn relative_error(recorded: f64, replayed: f64) -> f64 {
+    if recorded.to_bits() == replayed.to_bits() {
+        return 0.0;
+    }
+    (replayed - recorded).abs() / recorded.abs().max(f64::MIN_POSITIVE)
+}
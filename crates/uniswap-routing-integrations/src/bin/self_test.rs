@@ -0,0 +1,81 @@
+//! Runs the health check of a router restored from a captured session, leaving the capture file
+//! untouched.
+//!
+//! Usage: `self-test <capture.json> [--skip <check>]...`, with `<check>` among `audit`,
+//! `conditioning`, `settle`, `probe-quotes`, `precision`, `quote-cache`, `concurrency` and
+//! `fill`.
+//!
+//! Exit code: 0 when every check passes, 1 when at least one fails, 2 when the capture cannot be
+//! read or restored.
+
+use uniswap_routing_core::router::{CAPTURE_FORMAT_VERSION, SelfTestConfig, SessionCapture};
+
+use std::{fs, process::ExitCode};
+
+fn main() -> ExitCode {
+    let (path, config) = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}");
+            eprintln!("Usage: self-test <capture.json> [--skip <check>]...");
+            return ExitCode::from(2);
+        }
+    };
+    let router = match load_capture(&path).and_then(|capture| {
+        capture
+            .restore()
+            .map_err(|error| format!("cannot restore the session: {error}"))
+    }) {
+        Ok(router) => router,
+        Err(message) => {
+            eprintln!("Cannot load {path}: {message}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let report = router.self_test(&config);
+    print!("{report}");
+    match report.passed() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(String, SelfTestConfig), String> {
+    let mut path = None;
+    let mut config = SelfTestConfig::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--skip" => {
+                let check = args.next().ok_or("Missing check for --skip")?;
+                let enabled = match check.as_str() {
+                    "audit" => &mut config.audit,
+                    "conditioning" => &mut config.conditioning,
+                    "settle" => &mut config.settle,
+                    "probe-quotes" => &mut config.probe_quotes,
+                    "precision" => &mut config.precision,
+                    "quote-cache" => &mut config.quote_cache,
+                    "concurrency" => &mut config.concurrency,
+                    "fill" => &mut config.fill,
+                    _ => return Err(format!("Unknown check: {check}")),
+                };
+                *enabled = false;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("Unexpected argument: {arg}")),
+        }
+    }
+    Ok((path.ok_or("Missing capture path")?, config))
+}
+
+fn load_capture(path: &str) -> Result<SessionCapture, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let capture: SessionCapture = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    if capture.format_version != CAPTURE_FORMAT_VERSION {
+        return Err(format!(
+            "unsupported capture format {} (expected {CAPTURE_FORMAT_VERSION})",
+            capture.format_version
+        ));
+    }
+    Ok(capture)
+}
@@ -1,9 +1,6 @@
-use crate::{router::Router, uni_v2_pool::UniV2Pool};
+use uniswap_routing_core::{error::RouterError, router::Router, uni_v2_pool::UniV2Pool};
 
-mod router;
-mod uni_v2_pool;
-
-fn main() {
+fn main() -> Result<(), RouterError> {
     let pools: Vec<UniV2Pool> = vec![
         UniV2Pool::new("A", "B", 10., 40.),
         UniV2Pool::new("A", "B", 10., 40.),
@@ -11,8 +8,9 @@ fn main() {
     let mut router = Router::new(pools);
     let a_sell_amount = 20.;
 
-    let b_output_amount = router.solve("A", "B", a_sell_amount);
+    let b_output_amount = router.solve("A", "B", a_sell_amount)?;
     println!("Solution for {a_sell_amount:.2} A to B: {b_output_amount:.2}");
+    println!("{router}");
 
     let pools = vec![
         UniV2Pool::new("ETH", "USDC", 2_000., 2_000_000.),
@@ -32,11 +30,13 @@ fn main() {
 
     // First trade before equilibrium, we'll win extra tokens from arbitrage
     let eth_sell_amount = 10.;
-    let usdc_output_amount = router.solve("ETH", "USDC", eth_sell_amount);
+    let usdc_output_amount = router.solve("ETH", "USDC", eth_sell_amount)?;
     println!("Solution for {eth_sell_amount:.2} ETH to USDC: {usdc_output_amount:.2}");
 
     // Second trade after equilibrium, now the conversions are fair
     let usdc_sell_amount = 10000.;
-    let eth_output_amount = router.solve("USDC", "ETH", usdc_sell_amount);
+    let eth_output_amount = router.solve("USDC", "ETH", usdc_sell_amount)?;
     println!("Solution for {usdc_sell_amount:.2} USDC to ETH: {eth_output_amount:.2}");
+    println!("{router}");
+    Ok(())
 }
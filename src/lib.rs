@@ -0,0 +1,6 @@
+//! Umbrella crate re-exporting the routing core under its historical module paths
+//! (`router::Router`, `uni_v2_pool::UniV2Pool`).
+
+pub use uniswap_routing_core::{
+    PoolId, Router, RouterError, UniV2Pool, conventions, router, uni_v2_pool,
+};